@@ -0,0 +1,24 @@
+#pragma once
+
+#include <windows.h>
+#include <string>
+
+// Maps Bluetooth devices to their Windows render endpoints and reads/changes the
+// system default output. Endpoints carry the same container ID as the Bluetooth
+// device node they belong to, which is how the two are matched.
+class AudioEndpoints {
+public:
+    // Empty if the device has no active render endpoint (yet)
+    static std::wstring FindRenderEndpoint(unsigned long long address);
+    // ID of the default console render endpoint, empty if there is none
+    static std::wstring GetDefaultRenderEndpoint();
+    // Bluetooth address behind the default console render endpoint, 0 if it isn't a Bluetooth device
+    static unsigned long long GetDefaultRenderAddress();
+    // Makes the endpoint the default for all roles (console, multimedia, communications)
+    static bool SetDefaultRenderEndpoint(const std::wstring& endpoint_id);
+    // Battery percentage Windows reports for the device (hands-free headsets), -1 if none
+    static int GetBatteryLevel(unsigned long long address);
+    // Master volume of the device's render endpoint, 0.0-1.0; -1 if it has none
+    static float GetVolume(unsigned long long address);
+    static bool SetVolume(unsigned long long address, float level);
+};
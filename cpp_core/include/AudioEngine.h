@@ -10,6 +10,10 @@
 #include <mutex>
 #include <functional>
 
+// Interleaved float test sequence: one beep on the left channel, two on the right,
+// then a final beep on all channels. Channels beyond the first two stay silent.
+std::vector<float> GenerateChannelTestTone(int channels, int sample_rate);
+
 class WASAPICapturer {
 public:
     WASAPICapturer();
@@ -39,6 +43,11 @@ public:
     void FeedData(const BYTE* data, UINT32 frames);
     int GetChannelCount() const { return channel_count_; }
 
+    // Diagnostics, updated on every FeedData
+    unsigned int GetBufferFillPercent() const { return fill_percent_; }
+    unsigned int GetUnderrunCount() const { return underruns_; }
+    unsigned int GetResyncCount() const { return resyncs_; }
+
 private:
     std::wstring device_id_;
     IMMDevice* device_;
@@ -46,4 +55,7 @@ private:
     IAudioRenderClient* render_client_;
     UINT32 buffer_size_;
     int channel_count_;
+    std::atomic<unsigned int> fill_percent_{0};
+    std::atomic<unsigned int> underruns_{0};
+    std::atomic<unsigned int> resyncs_{0};
 };
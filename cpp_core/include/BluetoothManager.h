@@ -14,6 +14,38 @@ extern "C" {
         unsigned long cod;
     } DiscoveredDevice;
 
+    // Bumped whenever a function signature or struct layout in this header changes
+    #define BT_API_VERSION 16
+
+    // An LE Audio (Auracast) broadcast source seen in extended advertising
+    typedef struct {
+        unsigned long long address;
+        // 0 public, 1 random
+        unsigned char address_type;
+        unsigned char advertising_sid;
+        // 24-bit Broadcast_ID
+        unsigned int broadcast_id;
+        // Broadcast Name (UTF-8), or NULL if none is advertised; valid during the callback
+        const char* name;
+        int rssi;
+        // A Broadcast_Code is needed to listen
+        bool encrypted;
+        // 0 unknown, 1 standard, 2 high quality
+        unsigned char quality;
+    } BroadcastSource;
+
+    // A bond from the stack's key store
+    typedef struct {
+        unsigned long long adapter;
+        unsigned long long address;
+        // LE keys (LTK, IRK, ...) rather than a BR/EDR link key
+        bool le;
+        // The registry values: records of name length (u8), UTF-8 name, type (u32),
+        // data length (u32), data; valid during the callback
+        const unsigned char* values;
+        unsigned int values_len;
+    } BondRecord;
+
     // Error codes for FFI operations
     typedef enum {
         FFI_SUCCESS = 0,
@@ -23,28 +55,269 @@ extern "C" {
         FFI_DEVICE_NOT_FOUND = 4,
         FFI_CONNECTION_FAILED = 5,
         FFI_AUDIO_INIT_FAILED = 6,
+        // The device is known but the link key was rejected or is missing (broken bond)
+        FFI_AUTH_FAILED = 7,
+        // The device never answered the page: switched off or out of range
+        FFI_PAGE_TIMEOUT = 8,
+        // The device answered a recent inquiry but not the page; usually connected to another host
+        FFI_PEER_BUSY = 9,
+        // The core can't do this on this system (yet); callers should hide the feature
+        FFI_NOT_SUPPORTED = 10,
         FFI_UNKNOWN_ERROR = 255,
     } FfiErrorCode;
 
+    // HFP call states reported through OnHfpStateCallback
+    typedef enum {
+        HFP_CALL_IDLE = 0,
+        HFP_CALL_RINGING = 1,
+        HFP_CALL_ACTIVE = 2,
+    } HfpCallState;
+
+    // OBEX Object Push transfer states reported through OnObexProgressCallback
+    typedef enum {
+        OBEX_PENDING = 0,
+        OBEX_IN_PROGRESS = 1,
+        OBEX_COMPLETE = 2,
+        OBEX_FAILED = 3,
+        OBEX_CANCELLED = 4,
+    } ObexTransferState;
+
+    // PAN link state; byte counters are since the link came up, read from the PAN network
+    // adapter, which Windows shares between all PAN links
+    typedef struct {
+        bool connected;
+        unsigned long long rx_bytes;
+        unsigned long long tx_bytes;
+    } PanLinkStatus;
+
+    // Phone Book Access Profile folders
+    typedef enum {
+        PBAP_PHONEBOOK = 0,     // telecom/pb.vcf
+        PBAP_CALL_HISTORY = 1,  // telecom/cch.vcf (combined incoming/outgoing/missed)
+    } PbapFolder;
+
+    // Resampler quality used when a sink's mix format differs from the capture format
+    typedef enum {
+        RESAMPLER_LOW = 0,
+        RESAMPLER_MEDIUM = 1,
+        RESAMPLER_HIGH = 2,
+    } ResamplerQuality;
+
+    // Local radio state reported by bt_get_radio_state
+    typedef enum {
+        RADIO_ABSENT = 0,
+        RADIO_OFF = 1,
+        RADIO_ON = 2,
+    } RadioState;
+
+    // Local adapter identity; versions and subversions come from the controller's HCI info
+    typedef struct {
+        unsigned long long address;
+        char name[248];             // UTF-8, NUL-terminated
+        unsigned short manufacturer; // Bluetooth SIG company identifier
+        unsigned char hci_version;
+        unsigned short hci_revision;
+        unsigned char lmp_version;
+        unsigned short lmp_subversion;
+    } AdapterInfo;
+
+    // Classic inquiry tuning, see bt_set_scan_tuning
+    typedef struct {
+        unsigned char inquiry_length;   // In 1.28 s units, 1-48
+        unsigned int cycle_gap_ms;      // Idle time between inquiries, 500-60000
+    } ScanTuning;
+
+    // Per-sink renderer diagnostics (counters are cumulative since audio_add_device)
+    typedef struct {
+        unsigned int buffer_fill_percent;
+        unsigned int underruns;
+        unsigned int resyncs;
+    } AudioSinkStats;
+
     // Callback types
     typedef void (*OnDeviceFoundCallback)(DiscoveredDevice device);
     typedef void (*OnErrorCallback)(FfiErrorCode error_code, const char* message);
+    typedef void (*OnRemoteNameCallback)(unsigned long long address, const char* name);
+    typedef void (*OnConnectionChangedCallback)(unsigned long long address, bool connected);
+    typedef void (*OnHfpStateCallback)(unsigned long long address, int call_state, bool mic_muted);
+    // GATT value for a 16-bit characteristic UUID, from a notification or a read
+    typedef void (*OnGattValueCallback)(unsigned long long address, unsigned short characteristic_uuid, const unsigned char* value, unsigned int length);
+    // One discovered characteristic; properties is the GATT property bitfield (0x02 read, 0x10 notify, 0x20 indicate)
+    typedef void (*OnGattCharacteristicCallback)(unsigned long long address, unsigned short service_uuid, unsigned short characteristic_uuid, unsigned char properties);
+    typedef void (*OnObexProgressCallback)(unsigned int transfer_id, int state, unsigned long long bytes_done, unsigned long long bytes_total);
+    // A remote device wants to push a file; answer with bt_obex_accept or bt_obex_reject
+    typedef void (*OnObexIncomingCallback)(unsigned int transfer_id, unsigned long long address, const char* file_name, unsigned long long size);
+    // Result of bt_pbap_pull: concatenated vCards (UTF-8), or NULL with result != FFI_SUCCESS
+    typedef void (*OnPbapResultCallback)(unsigned long long address, int folder, FfiErrorCode result, const char* vcards);
+    // Result of bt_pair_device
+    typedef void (*OnPairingResultCallback)(unsigned long long address, FfiErrorCode result);
+    // One HCI packet; packet_type is the H4 indicator (1 command, 2 ACL, 3 SCO, 4 event, 5 ISO)
+    // and data excludes it. Called from the capture thread
+    typedef void (*OnHciPacketCallback)(unsigned char packet_type, bool received, const unsigned char* data, unsigned int length);
+    // One bond per call, on the calling thread
+    typedef void (*OnBondCallback)(const BondRecord* bond);
+    // Signal strength of a device from one of its LE advertisements; called from the
+    // watcher's thread, at most once a second per device
+    typedef void (*OnSignalCallback)(unsigned long long address, int rssi);
+    // Every broadcast announcement, repeats included; called from the watcher's thread
+    typedef void (*OnBroadcastFoundCallback)(const BroadcastSource* source);
+    // Interleaved 16-bit PCM of the broadcast mix, called from the capture thread
+    typedef void (*OnAudioTapCallback)(const short* samples, unsigned int frames, unsigned short channels, unsigned int sample_rate);
 
     // Bluetooth functions
     FfiErrorCode bt_init(OnErrorCallback error_callback);
     FfiErrorCode bt_start_scan(OnDeviceFoundCallback callback, OnErrorCallback error_callback);
     FfiErrorCode bt_stop_scan();
+    // Reduced scan duty cycle for running on battery; takes effect on the next cycle
+    FfiErrorCode bt_set_low_power_scan(bool enabled);
+    // Inquiry length and gap between scan cycles; takes effect on the next cycle.
+    // Low-power mode still caps the inquiry and stretches the gap. Out-of-range values
+    // are rejected with FFI_INVALID_PARAMETER
+    FfiErrorCode bt_set_scan_tuning(const ScanTuning* tuning);
+    // Live RSSI from LE advertisements. Inquiry results (bt_start_scan) report rssi 0, since
+    // Windows doesn't expose it for classic devices. Needs an adapter with Bluetooth LE
+    bool bt_signal_monitor_supported();
+    FfiErrorCode bt_signal_monitor_start(OnSignalCallback callback);
+    FfiErrorCode bt_signal_monitor_stop();
     FfiErrorCode bt_connect_device(unsigned long long address);
+    // Connects through the local adapter with address adapter_address. Falls back to the
+    // system's choice when that adapter isn't present (e.g. the dongle is unplugged)
+    FfiErrorCode bt_connect_device_via(unsigned long long address, unsigned long long adapter_address);
     FfiErrorCode bt_disconnect_device(unsigned long long address);
+    // Asynchronously re-queries the device name; callback fires on a worker thread
+    FfiErrorCode bt_request_remote_name(unsigned long long address, OnRemoteNameCallback callback);
+    // Removes the bond (link key) so the device can be paired from scratch
+    FfiErrorCode bt_unpair_device(unsigned long long address);
+    // Pairs with a device in pairing mode on a worker thread; Windows may show its own PIN prompt
+    FfiErrorCode bt_pair_device(unsigned long long address, OnPairingResultCallback callback);
+    // Starts the link watchdog; callback fires when a pooled connection drops or recovers
+    FfiErrorCode bt_set_connection_callback(OnConnectionChangedCallback callback);
+
+    // Hands-free profile: call state / mic mute notifications and mute control
+    // False while no call-state source exists; bt_set_mic_mute then returns FFI_NOT_SUPPORTED
+    bool bt_hfp_supported();
+    FfiErrorCode bt_set_hfp_callback(OnHfpStateCallback callback);
+    FfiErrorCode bt_set_mic_mute(unsigned long long address, bool muted);
+    // Sends one AT command over RFCOMM and copies the device's reply, NUL-terminated, into reply.
+    // Channel 0 finds the Serial Port service through SDP. Blocks for a few seconds at most.
+    FfiErrorCode bt_send_at_command(unsigned long long address, unsigned char channel, const char* command,
+                                    char* reply, size_t reply_len);
+    // Sends a vendor protocol frame to the RFCOMM service service_uuid ("96CC203E-5068-...")
+    // and copies the first chunk of the answer into reply; *reply_len receives its length.
+    FfiErrorCode bt_rfcomm_exchange(unsigned long long address, const char* service_uuid,
+                                    const unsigned char* request, size_t request_len,
+                                    unsigned char* reply, size_t reply_cap, size_t* reply_len);
+    
+    // BLE GATT (standard 16-bit UUIDs). Values arrive through the value callback.
+    FfiErrorCode bt_gatt_set_value_callback(OnGattValueCallback callback);
+    // Enumerates services/characteristics on a worker thread, one callback per characteristic.
+    // A failure on the worker goes to the error callback.
+    FfiErrorCode bt_gatt_discover(unsigned long long address, OnGattCharacteristicCallback callback);
+    // Turns on notifications and forwards them to the value callback. Blocks for the GATT round trips.
+    FfiErrorCode bt_gatt_subscribe(unsigned long long address, unsigned short service_uuid, unsigned short characteristic_uuid);
+    FfiErrorCode bt_gatt_unsubscribe(unsigned long long address, unsigned short characteristic_uuid);
+    // Reads on a worker thread; the value arrives through the value callback, a failure through the error callback
+    FfiErrorCode bt_gatt_read(unsigned long long address, unsigned short service_uuid, unsigned short characteristic_uuid);
+    
+    // OBEX Object Push. Transfers run on worker threads and report through the progress callback.
+    FfiErrorCode bt_obex_set_callbacks(OnObexProgressCallback progress, OnObexIncomingCallback incoming);
+    FfiErrorCode bt_obex_send_file(unsigned long long address, const char* path, unsigned int* transfer_id);
+    // Receiving isn't implemented yet (no OPP server); both return FFI_NOT_SUPPORTED
+    FfiErrorCode bt_obex_accept(unsigned int transfer_id, const char* save_path);
+    FfiErrorCode bt_obex_reject(unsigned int transfer_id);
+    FfiErrorCode bt_obex_cancel(unsigned int transfer_id);
+    
+    // PAN tethering to a phone's Network Access Point
+    bool bt_pan_has_nap(unsigned long long address);
+    FfiErrorCode bt_pan_connect(unsigned long long address);
+    FfiErrorCode bt_pan_disconnect(unsigned long long address);
+    FfiErrorCode bt_pan_get_status(unsigned long long address, PanLinkStatus* out);
+
+    // PBAP client: read-only pull of a phone book folder on a worker thread.
+    // Not implemented yet; returns FFI_NOT_SUPPORTED
+    FfiErrorCode bt_pbap_pull(unsigned long long address, int folder, OnPbapResultCallback callback);
     
     // Audio functions
     FfiErrorCode audio_init(OnErrorCallback error_callback);
     FfiErrorCode audio_start();
     FfiErrorCode audio_stop();
+    // FFI_DEVICE_NOT_FOUND until Windows has created the device's render endpoint, which
+    // can take several seconds after connecting; poll audio_endpoint_ready first
     FfiErrorCode audio_add_device(unsigned long long address);
     FfiErrorCode audio_remove_device(unsigned long long address);
+    // Per-process routing: audio from process_name (e.g. "Spotify.exe", or "*" for
+    // every other process) is rendered only to sink_address. Not implemented yet:
+    // audio_app_routes_supported() is false and audio_set_app_route fails with FFI_NOT_SUPPORTED
+    bool audio_app_routes_supported();
+    FfiErrorCode audio_set_app_route(const char* process_name, unsigned long long sink_address);
+    FfiErrorCode audio_clear_app_routes();
+    // Taps the mixed output (pass NULL to remove the tap). Not implemented yet:
+    // audio_tap_supported() is false and installing a tap fails with FFI_NOT_SUPPORTED
+    bool audio_tap_supported();
+    FfiErrorCode audio_set_tap(OnAudioTapCallback callback);
+    // Plays the left/right channel test sequence on one sink; works without audio_start.
+    // Not implemented yet: fails with FFI_NOT_SUPPORTED
+    FfiErrorCode audio_play_test_tone(unsigned long long address);
+    // Silences one sink while it stays in the stream; FFI_DEVICE_NOT_FOUND if it isn't streaming.
+    // Not implemented yet: muting fails with FFI_NOT_SUPPORTED, unmuting succeeds
+    FfiErrorCode audio_set_sink_muted(unsigned long long address, bool muted);
+    // Ramps `from` down and `to` up over duration_ms (at most 10 s), then removes `from`.
+    // Returns at once; both must be sinks already. Not implemented yet:
+    // audio_crossfade_supported() is false and audio_crossfade fails with FFI_NOT_SUPPORTED
+    bool audio_crossfade_supported();
+    FfiErrorCode audio_crossfade(unsigned long long from, unsigned long long to, unsigned int duration_ms);
+    // Engine settings; read at audio_start, so changes apply to the next stream. Not implemented
+    // yet: audio_engine_settings_supported() is false and valid values fail with FFI_NOT_SUPPORTED
+    bool audio_engine_settings_supported();
+    FfiErrorCode audio_set_buffer_ms(unsigned int buffer_ms);
+    FfiErrorCode audio_set_resampler_quality(int quality);
+    FfiErrorCode audio_set_sample_rate(unsigned int sample_rate);
+    // Fills out with the current diagnostics for a sink; FFI_DEVICE_NOT_FOUND if it isn't streaming.
+    // Not implemented yet: fails with FFI_NOT_SUPPORTED for streaming sinks
+    FfiErrorCode audio_get_sink_stats(unsigned long long address, AudioSinkStats* out);
+    // System default output: address of the Bluetooth device behind it, or 0 if the
+    // default isn't a Bluetooth endpoint
+    FfiErrorCode audio_get_default_output(unsigned long long* address);
+    // FFI_DEVICE_NOT_FOUND if the device has no active render endpoint
+    FfiErrorCode audio_set_default_output(unsigned long long address);
+    // Opaque endpoint ID of the current default output ("" if none), for restoring it later.
+    // Valid until the next call.
+    const char* audio_get_default_output_id();
+    // True once the device has an active render endpoint
+    bool audio_endpoint_ready(unsigned long long address);
+    // Battery percentage of a headset as Windows reports it, or -1 if it doesn't report one
+    FfiErrorCode bt_get_battery_level(unsigned long long address, int* level);
+    FfiErrorCode audio_set_default_output_id(const char* endpoint_id);
+    // Master volume of the device's audio output, 0.0-1.0. FFI_DEVICE_NOT_FOUND if it
+    // has no render endpoint
+    FfiErrorCode audio_get_device_volume(unsigned long long address, float* level);
+    FfiErrorCode audio_set_device_volume(unsigned long long address, float level);
+    // Presses the Play/Pause media key; Windows passes it to the app owning the media session
+    FfiErrorCode audio_media_play_pause();
     
-    // Error handling
+    // LE Audio broadcasts (Auracast). Scanning needs an adapter with extended advertising
+    bool bt_le_audio_supported();
+    FfiErrorCode bt_broadcast_scan_start(OnBroadcastFoundCallback callback);
+    FfiErrorCode bt_broadcast_scan_stop();
+    // Asks the sink to join the broadcast through its Broadcast Audio Scan Service. Blocks
+    // for the GATT round trips. broadcast_code is 16 bytes, or NULL for an open broadcast
+    FfiErrorCode bt_broadcast_join(unsigned long long sink_address, const BroadcastSource* source,
+                                   const unsigned char* broadcast_code);
+
+    // Developer HCI capture from the stack's ETW tracing; needs administrator rights
+    FfiErrorCode bt_hci_capture_start(OnHciPacketCallback callback);
+    FfiErrorCode bt_hci_capture_stop();
+
+    // Bond backup. Reading and writing the key store needs administrator rights.
+    // Key material passes through the callback unencrypted; encrypting it is up to the caller
+    FfiErrorCode bt_bonds_export(OnBondCallback callback);
+    // Writes one bond, under bond->adapter; takes effect after bt_bonds_reload
+    FfiErrorCode bt_bond_import(const BondRecord* bond);
+    // Restarts the Bluetooth Support Service; every connection drops for a few seconds
+    FfiErrorCode bt_bonds_reload();
+    
+    // Error handling. Errors are kept per thread: these return the last error set by a call
+    // on the calling thread, valid until that thread's next call into the core
     const char* bt_get_last_error();
     const char* audio_get_last_error();
     
@@ -54,6 +327,13 @@ extern "C" {
     // Permission check
     bool bt_check_permission();
 
+    // Self-test; both work before bt_init
+    unsigned int bt_get_api_version();
+    int bt_get_radio_state();
+    FfiErrorCode bt_get_adapter_info(AdapterInfo* out);
+    // Every local adapter, first one first; *count receives how many were written (at most capacity)
+    FfiErrorCode bt_list_adapters(AdapterInfo* out, unsigned int capacity, unsigned int* count);
+
 #ifdef __cplusplus
 }
 #endif
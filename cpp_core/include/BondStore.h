@@ -0,0 +1,39 @@
+#pragma once
+
+#include <windows.h>
+#include <functional>
+#include <string>
+#include <vector>
+
+// One registry value of a bond: the BR/EDR link key, or one of the LE keys
+// (LTK, IRK, EDIV, ...)
+struct BondValue {
+    std::string name;
+    DWORD type = REG_BINARY;
+    std::vector<unsigned char> data;
+};
+
+struct Bond {
+    unsigned long long adapter = 0;
+    unsigned long long address = 0;
+    // LE bonds are a key per device holding several values; BR/EDR bonds a
+    // single value named after the device
+    bool le = false;
+    std::vector<BondValue> values;
+};
+
+// The stack's key store under BTHPORT\Parameters\Keys. Only SYSTEM can open it
+// normally, so reads and writes go through the backup and restore privileges,
+// which administrators hold but have to enable.
+class BondStore {
+public:
+    // On failure, error receives a message
+    static bool ReadAll(std::vector<Bond>& bonds, std::string& error);
+    static bool Write(const Bond& bond, std::string& error);
+    // Restarts the Bluetooth Support Service, which reads the key store at start
+    static bool Reload(std::string& error);
+
+    // Values as records of name length (u8), UTF-8 name, type (u32), data length (u32), data
+    static std::vector<unsigned char> Serialize(const std::vector<BondValue>& values);
+    static bool Deserialize(const unsigned char* data, unsigned int length, std::vector<BondValue>& values);
+};
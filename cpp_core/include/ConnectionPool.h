@@ -11,9 +11,12 @@ public:
     ConnectionPool();
     ~ConnectionPool();
 
-    bool ConnectDevice(BLUETOOTH_ADDRESS address);
+    // On failure, *error (if given) receives the Win32 error from the profile connect.
+    // radio is the local adapter to use, or NULL for the system's choice
+    bool ConnectDevice(BLUETOOTH_ADDRESS address, DWORD* error = nullptr, HANDLE radio = NULL);
     bool DisconnectDevice(BLUETOOTH_ADDRESS address);
     bool IsConnected(BLUETOOTH_ADDRESS address);
+    std::vector<unsigned long long> GetActiveAddresses();
 
 private:
     std::map<unsigned long long, HANDLE> active_connections_; // Address -> Socket/Handle
@@ -6,6 +6,7 @@
 #include <string>
 #include <mutex>
 #include <functional>
+#include <atomic>
 
 struct BluetoothDevice {
     std::wstring name;
@@ -30,6 +31,12 @@ public:
     // Check if Bluetooth radio is valid/connectable
     bool IsValidRadio();
 
+    // Low-power mode: shorter inquiries and a long idle gap between cycles
+    void SetLowPower(bool enabled);
+
+    // Inquiry length in 1.28 s units and idle time between cycles; validated by the caller
+    void SetTuning(unsigned char inquiry_length, unsigned int cycle_gap_ms);
+
     // Callback for new device found
     void SetOnDeviceFoundCallback(std::function<void(const BluetoothDevice&)> callback);
 
@@ -37,6 +44,9 @@ private:
     void ScanLoop();
 
     bool scanning_;
+    std::atomic<bool> low_power_{false};
+    std::atomic<unsigned char> inquiry_length_{4}; // ~5 seconds
+    std::atomic<unsigned int> cycle_gap_ms_{1000};
     std::mutex mutex_;
     std::vector<BluetoothDevice> cached_devices_;
     std::function<void(const BluetoothDevice&)> on_device_found_;
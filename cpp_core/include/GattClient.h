@@ -0,0 +1,39 @@
+#pragma once
+
+#include <windows.h>
+#include <functional>
+#include <map>
+#include <memory>
+#include <mutex>
+#include <string>
+#include <vector>
+
+// GATT client for standard 16-bit UUIDs, over WinRT: it looks devices up by
+// address, where the Win32 BluetoothGATT* API needs a device interface path
+// per service. Every call blocks for the GATT round trips, so callers run it
+// on a worker thread.
+class GattClient {
+public:
+    using CharacteristicHandler = std::function<void(unsigned short service, unsigned short characteristic, unsigned char properties)>;
+    // Called from a WinRT thread pool thread for every notification or indication
+    using ValueHandler = std::function<void(unsigned long long address, unsigned short characteristic, const unsigned char* value, unsigned int length)>;
+
+    // Reports every characteristic with a 16-bit UUID; properties is the GATT
+    // property bitfield. Services and characteristics with 128-bit UUIDs are skipped.
+    static bool Discover(unsigned long long address, CharacteristicHandler on_characteristic, std::string& error);
+    static bool Read(unsigned long long address, unsigned short service, unsigned short characteristic,
+                     std::vector<unsigned char>& value, std::string& error);
+
+    // Writes the CCCD (notify, or indicate if that's all it offers) and forwards
+    // every value to on_value until Unsubscribe. Subscribing twice is a no-op.
+    bool Subscribe(unsigned long long address, unsigned short service, unsigned short characteristic,
+                   ValueHandler on_value, std::string& error);
+    // Clears the CCCD; false if there was no such subscription
+    bool Unsubscribe(unsigned long long address, unsigned short characteristic);
+
+private:
+    std::mutex mutex_;
+    // The WinRT characteristic and event registration per (address, characteristic),
+    // kept type-erased so the header doesn't pull in WinRT
+    std::map<std::pair<unsigned long long, unsigned short>, std::shared_ptr<void>> subscriptions_;
+};
@@ -0,0 +1,35 @@
+#pragma once
+
+#include <windows.h>
+#include <evntrace.h>
+#include <evntcons.h>
+#include <atomic>
+#include <functional>
+#include <string>
+#include <thread>
+
+// Live HCI traffic from the Windows Bluetooth stack's ETW tracing. Needs
+// administrator rights (or the Performance Log Users group) to start the session.
+class HciCapture {
+public:
+    // packet(type, received, data, length): type is the H4 packet indicator
+    // (1 command, 2 ACL, 3 SCO, 4 event); called from the trace thread
+    using PacketHandler = std::function<void(unsigned char, bool, const unsigned char*, unsigned int)>;
+
+    HciCapture() = default;
+    ~HciCapture();
+
+    // On failure, *error (if given) receives the Win32 error from starting the trace
+    bool Start(PacketHandler on_packet, DWORD* error = nullptr);
+    void Stop();
+    bool IsRunning() const { return running_; }
+
+private:
+    static void WINAPI OnEvent(PEVENT_RECORD record);
+
+    PacketHandler on_packet_;
+    TRACEHANDLE session_ = 0;
+    TRACEHANDLE consumer_ = INVALID_PROCESSTRACE_HANDLE;
+    std::atomic<bool> running_{false};
+    std::thread thread_;
+};
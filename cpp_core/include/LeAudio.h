@@ -0,0 +1,63 @@
+#pragma once
+
+#include <windows.h>
+#include <atomic>
+#include <functional>
+#include <memory>
+#include <mutex>
+#include <string>
+#include <vector>
+
+// An Auracast broadcast as announced in extended advertising
+struct BroadcastAnnouncement {
+    unsigned long long address = 0;
+    unsigned char address_type = 0;
+    unsigned char advertising_sid = 0;
+    unsigned int broadcast_id = 0;
+    std::string name;
+    int rssi = 0;
+    bool encrypted = false;
+    // 0 unknown, 1 standard, 2 high quality (Public Broadcast Announcement features)
+    unsigned char quality = 0;
+};
+
+// One AD structure: type and payload without the length byte
+using AdSection = std::pair<unsigned char, std::vector<unsigned char>>;
+
+// Fills out from the Broadcast Audio Announcement, Public Broadcast Announcement
+// and Broadcast Name sections. False if there is no Broadcast Audio Announcement.
+bool ParseBroadcastAnnouncement(const std::vector<AdSection>& sections, BroadcastAnnouncement* out);
+
+// Watches extended advertising for broadcast sources. Uses the WinRT advertisement
+// watcher, since Win32 has no extended advertising API.
+class BroadcastScanner {
+public:
+    // Called from the watcher's thread for every announcement, repeats included
+    using Handler = std::function<void(const BroadcastAnnouncement&)>;
+
+    BroadcastScanner() = default;
+    ~BroadcastScanner();
+
+    // On failure, error receives a message
+    bool Start(Handler on_broadcast, std::string& error);
+    void Stop();
+    bool IsRunning() const { return running_; }
+
+    // The default adapter can scan extended advertising, which LE Audio broadcasts need
+    static bool Supported();
+
+private:
+    std::mutex mutex_;
+    // The WinRT watcher, kept type-erased so the header doesn't pull in WinRT
+    std::shared_ptr<void> watcher_;
+    std::atomic<bool> running_{false};
+};
+
+// Broadcast Assistant role: asks a sink to sync to a broadcast through its
+// Broadcast Audio Scan Service. Blocks for the GATT round trips.
+class BroadcastAssistant {
+public:
+    // broadcast_code is 16 bytes, or NULL for an unencrypted broadcast
+    static bool Join(unsigned long long sink, const BroadcastAnnouncement& source,
+                     const unsigned char* broadcast_code, std::string& error);
+};
@@ -2,12 +2,41 @@
 
 #include <windows.h>
 #include <bluetoothapis.h>
+#include <functional>
 #include <vector>
+#include <string>
 
 class ProfileManager {
 public:
-    static bool EnableAudioSink(const BLUETOOTH_ADDRESS& address);
+    // On failure, *error (if given) receives the Win32 error of the failing call.
+    // radio selects the local adapter to connect through; NULL lets Windows choose
+    static bool EnableAudioSink(const BLUETOOTH_ADDRESS& address, DWORD* error = nullptr, HANDLE radio = NULL);
     static bool DisableAudioSink(const BLUETOOTH_ADDRESS& address);
+    // False when Windows remembers the device but no longer holds a valid bond for it
+    static bool IsAuthenticated(const BLUETOOTH_ADDRESS& address);
+
+    // PAN: true if the (paired) device lists the NAP service, i.e. can share its internet
+    static bool HasNetworkAccessPoint(const BLUETOOTH_ADDRESS& address);
+    // Enabling NAP makes Windows bring up the BNEP link and the PAN network adapter
+    static bool EnableNetworkAccess(const BLUETOOTH_ADDRESS& address);
+    static bool DisableNetworkAccess(const BLUETOOTH_ADDRESS& address);
+
+    // One AT command over an RFCOMM socket (channel 0 = Serial Port service via SDP).
+    // Fails if the device can't be reached or answers ERROR; reply holds whatever arrived.
+    static bool SendAtCommand(const BLUETOOTH_ADDRESS& address, unsigned char channel, const std::string& command,
+                              std::string& reply, DWORD* error = nullptr);
+    // Sends request to the RFCOMM service with the given UUID and returns the first chunk the
+    // device answers with. For vendor protocols that frame their own messages.
+    static bool ExchangeRfcomm(const BLUETOOTH_ADDRESS& address, const GUID& service,
+                               const std::vector<unsigned char>& request, std::vector<unsigned char>& reply,
+                               DWORD* error = nullptr);
+
+    // OBEX Object Push of one file (path is UTF-8): CONNECT, PUT in packets of the size the
+    // device accepts, DISCONNECT. on_progress gets the bytes sent after each packet and
+    // returns false to abort. Blocks until the device has the file or refuses it.
+    static bool PushFile(const BLUETOOTH_ADDRESS& address, const std::string& path,
+                         const std::function<bool(unsigned long long sent, unsigned long long total)>& on_progress,
+                         std::string& error);
     
     // Future: HFP, AVRCP
 };
@@ -0,0 +1,35 @@
+#pragma once
+
+#include <windows.h>
+#include <atomic>
+#include <functional>
+#include <memory>
+#include <mutex>
+#include <string>
+
+// Signal strength of nearby devices from their LE advertisements. Classic inquiry
+// results carry no RSSI on Windows, so this is the only live reading there is.
+// Devices that don't advertise over LE, or advertise under a private address, are
+// not reported.
+class SignalMonitor {
+public:
+    // Called from the watcher's thread, at most once a second per address
+    using Handler = std::function<void(unsigned long long address, int rssi)>;
+
+    SignalMonitor() = default;
+    ~SignalMonitor();
+
+    // On failure, error receives a message
+    bool Start(Handler on_signal, std::string& error);
+    void Stop();
+    bool IsRunning() const { return running_; }
+
+    // The default adapter supports Bluetooth LE
+    static bool Supported();
+
+private:
+    std::mutex mutex_;
+    // The WinRT watcher, kept type-erased so the header doesn't pull in WinRT
+    std::shared_ptr<void> watcher_;
+    std::atomic<bool> running_{false};
+};
@@ -5,9 +5,12 @@
 #include <functional>
 #include "ConnectionPool.h"
 
+#include <map>
+
 class Watchdog {
 public:
-    Watchdog(ConnectionPool* pool);
+    // on_change(address, connected) fires from the watchdog thread when a pooled link changes state
+    Watchdog(ConnectionPool* pool, std::function<void(unsigned long long, bool)> on_change = nullptr);
     ~Watchdog();
 
     void Start();
@@ -17,6 +20,8 @@ private:
     void Loop();
 
     ConnectionPool* pool_;
+    std::function<void(unsigned long long, bool)> on_change_;
+    std::map<unsigned long long, bool> last_state_;
     std::atomic<bool> running_;
     std::thread thread_;
 };
@@ -0,0 +1,252 @@
+#include "AudioEndpoints.h"
+#include <mmdeviceapi.h>
+#include <endpointvolume.h>
+#include <functiondiscoverykeys_devpkey.h>
+#include <setupapi.h>
+#include <initguid.h>
+#include <devpkey.h>
+#include <vector>
+
+// DEVPKEY_Bluetooth_DeviceAddress: 12 hex digits on BTHENUM/BTHLE device nodes
+DEFINE_DEVPROPKEY(DEVPKEY_Bluetooth_DeviceAddress,
+    0x2bd67d8b, 0x8beb, 0x48d5, 0x87, 0xe0, 0x6c, 0xda, 0x34, 0x28, 0x04, 0x0a, 1);
+
+// DEVPKEY_Bluetooth_Battery: percentage the hands-free profile reports, on one of the device's nodes
+DEFINE_DEVPROPKEY(DEVPKEY_Bluetooth_Battery,
+    0x104ea319, 0x6ee2, 0x4701, 0xbd, 0x47, 0x8d, 0xdb, 0xf4, 0x25, 0xbb, 0xe5, 2);
+
+// Undocumented, but the interface the Sound control panel uses to change the default
+// endpoint; stable since Windows 7. Only SetDefaultEndpoint is called.
+interface DECLSPEC_UUID("f8679f50-850a-41cf-9c72-430f290290c8") IPolicyConfig : public IUnknown {
+    virtual HRESULT STDMETHODCALLTYPE GetMixFormat(PCWSTR, WAVEFORMATEX**) = 0;
+    virtual HRESULT STDMETHODCALLTYPE GetDeviceFormat(PCWSTR, INT, WAVEFORMATEX**) = 0;
+    virtual HRESULT STDMETHODCALLTYPE ResetDeviceFormat(PCWSTR) = 0;
+    virtual HRESULT STDMETHODCALLTYPE SetDeviceFormat(PCWSTR, WAVEFORMATEX*, WAVEFORMATEX*) = 0;
+    virtual HRESULT STDMETHODCALLTYPE GetProcessingPeriod(PCWSTR, INT, PINT64, PINT64) = 0;
+    virtual HRESULT STDMETHODCALLTYPE SetProcessingPeriod(PCWSTR, PINT64) = 0;
+    virtual HRESULT STDMETHODCALLTYPE GetShareMode(PCWSTR, void*) = 0;
+    virtual HRESULT STDMETHODCALLTYPE SetShareMode(PCWSTR, void*) = 0;
+    virtual HRESULT STDMETHODCALLTYPE GetPropertyValue(PCWSTR, const PROPERTYKEY&, PROPVARIANT*) = 0;
+    virtual HRESULT STDMETHODCALLTYPE SetPropertyValue(PCWSTR, const PROPERTYKEY&, PROPVARIANT*) = 0;
+    virtual HRESULT STDMETHODCALLTYPE SetDefaultEndpoint(PCWSTR, ERole) = 0;
+    virtual HRESULT STDMETHODCALLTYPE SetEndpointVisibility(PCWSTR, INT) = 0;
+};
+class DECLSPEC_UUID("870af99c-171d-4f9e-af0d-e63df40c2bc9") CPolicyConfigClient;
+
+// Container ID of the Bluetooth device node with this address
+static bool FindBluetoothContainer(unsigned long long address, GUID& container) {
+    HDEVINFO devs = SetupDiGetClassDevsW(NULL, NULL, NULL, DIGCF_ALLCLASSES | DIGCF_PRESENT);
+    if (devs == INVALID_HANDLE_VALUE) return false;
+
+    wchar_t wanted[13];
+    swprintf_s(wanted, L"%012llX", address);
+
+    bool found = false;
+    SP_DEVINFO_DATA info = { sizeof(SP_DEVINFO_DATA) };
+    for (DWORD i = 0; !found && SetupDiEnumDeviceInfo(devs, i, &info); i++) {
+        DEVPROPTYPE type;
+        wchar_t value[32] = {};
+        if (!SetupDiGetDevicePropertyW(devs, &info, &DEVPKEY_Bluetooth_DeviceAddress, &type,
+                                       (PBYTE)value, sizeof(value) - sizeof(wchar_t), NULL, 0)) {
+            continue;
+        }
+        if (_wcsicmp(value, wanted) != 0) continue;
+        found = SetupDiGetDevicePropertyW(devs, &info, &DEVPKEY_Device_ContainerId, &type,
+                                          (PBYTE)&container, sizeof(container), NULL, 0) != FALSE;
+    }
+    SetupDiDestroyDeviceInfoList(devs);
+    return found;
+}
+
+static bool EndpointContainer(IMMDevice* device, GUID& container) {
+    IPropertyStore* props = NULL;
+    if (FAILED(device->OpenPropertyStore(STGM_READ, &props))) return false;
+    PROPVARIANT value;
+    PropVariantInit(&value);
+    bool ok = SUCCEEDED(props->GetValue(PKEY_Device_ContainerId, &value)) && value.vt == VT_CLSID;
+    if (ok) container = *value.puuid;
+    PropVariantClear(&value);
+    props->Release();
+    return ok;
+}
+
+static std::wstring EndpointId(IMMDevice* device) {
+    LPWSTR id = NULL;
+    std::wstring result;
+    if (SUCCEEDED(device->GetId(&id))) {
+        result = id;
+        CoTaskMemFree(id);
+    }
+    return result;
+}
+
+std::wstring AudioEndpoints::FindRenderEndpoint(unsigned long long address) {
+    GUID container;
+    if (!FindBluetoothContainer(address, container)) return L"";
+
+    CoInitialize(NULL);
+    std::wstring result;
+    IMMDeviceEnumerator* enumerator = NULL;
+    IMMDeviceCollection* endpoints = NULL;
+    if (SUCCEEDED(CoCreateInstance(__uuidof(MMDeviceEnumerator), NULL, CLSCTX_ALL,
+                                   __uuidof(IMMDeviceEnumerator), (void**)&enumerator)) &&
+        SUCCEEDED(enumerator->EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE, &endpoints))) {
+        UINT count = 0;
+        endpoints->GetCount(&count);
+        for (UINT i = 0; i < count && result.empty(); i++) {
+            IMMDevice* device = NULL;
+            if (FAILED(endpoints->Item(i, &device))) continue;
+            GUID endpoint_container;
+            if (EndpointContainer(device, endpoint_container) && IsEqualGUID(endpoint_container, container)) {
+                result = EndpointId(device);
+            }
+            device->Release();
+        }
+    }
+    if (endpoints) endpoints->Release();
+    if (enumerator) enumerator->Release();
+    CoUninitialize();
+    return result;
+}
+
+std::wstring AudioEndpoints::GetDefaultRenderEndpoint() {
+    CoInitialize(NULL);
+    std::wstring result;
+    IMMDeviceEnumerator* enumerator = NULL;
+    if (SUCCEEDED(CoCreateInstance(__uuidof(MMDeviceEnumerator), NULL, CLSCTX_ALL,
+                                   __uuidof(IMMDeviceEnumerator), (void**)&enumerator))) {
+        IMMDevice* device = NULL;
+        if (SUCCEEDED(enumerator->GetDefaultAudioEndpoint(eRender, eConsole, &device))) {
+            result = EndpointId(device);
+            device->Release();
+        }
+        enumerator->Release();
+    }
+    CoUninitialize();
+    return result;
+}
+
+unsigned long long AudioEndpoints::GetDefaultRenderAddress() {
+    CoInitialize(NULL);
+    GUID container;
+    bool have_container = false;
+    IMMDeviceEnumerator* enumerator = NULL;
+    if (SUCCEEDED(CoCreateInstance(__uuidof(MMDeviceEnumerator), NULL, CLSCTX_ALL,
+                                   __uuidof(IMMDeviceEnumerator), (void**)&enumerator))) {
+        IMMDevice* device = NULL;
+        if (SUCCEEDED(enumerator->GetDefaultAudioEndpoint(eRender, eConsole, &device))) {
+            have_container = EndpointContainer(device, container);
+            device->Release();
+        }
+        enumerator->Release();
+    }
+    CoUninitialize();
+    if (!have_container) return 0;
+
+    // Walk the Bluetooth device nodes for the one sharing the endpoint's container
+    unsigned long long address = 0;
+    HDEVINFO devs = SetupDiGetClassDevsW(NULL, NULL, NULL, DIGCF_ALLCLASSES | DIGCF_PRESENT);
+    if (devs == INVALID_HANDLE_VALUE) return 0;
+    SP_DEVINFO_DATA info = { sizeof(SP_DEVINFO_DATA) };
+    for (DWORD i = 0; address == 0 && SetupDiEnumDeviceInfo(devs, i, &info); i++) {
+        DEVPROPTYPE type;
+        wchar_t value[32] = {};
+        GUID node_container;
+        if (!SetupDiGetDevicePropertyW(devs, &info, &DEVPKEY_Bluetooth_DeviceAddress, &type,
+                                       (PBYTE)value, sizeof(value) - sizeof(wchar_t), NULL, 0) ||
+            !SetupDiGetDevicePropertyW(devs, &info, &DEVPKEY_Device_ContainerId, &type,
+                                       (PBYTE)&node_container, sizeof(node_container), NULL, 0)) {
+            continue;
+        }
+        if (IsEqualGUID(node_container, container)) {
+            address = wcstoull(value, NULL, 16);
+        }
+    }
+    SetupDiDestroyDeviceInfoList(devs);
+    return address;
+}
+
+int AudioEndpoints::GetBatteryLevel(unsigned long long address) {
+    GUID container;
+    if (!FindBluetoothContainer(address, container)) return -1;
+
+    // The level sits on whichever node of the device handles hands-free, not the device node itself
+    int level = -1;
+    HDEVINFO devs = SetupDiGetClassDevsW(NULL, NULL, NULL, DIGCF_ALLCLASSES | DIGCF_PRESENT);
+    if (devs == INVALID_HANDLE_VALUE) return -1;
+    SP_DEVINFO_DATA info = { sizeof(SP_DEVINFO_DATA) };
+    for (DWORD i = 0; level < 0 && SetupDiEnumDeviceInfo(devs, i, &info); i++) {
+        DEVPROPTYPE type;
+        GUID node_container;
+        BYTE value = 0;
+        if (!SetupDiGetDevicePropertyW(devs, &info, &DEVPKEY_Device_ContainerId, &type,
+                                       (PBYTE)&node_container, sizeof(node_container), NULL, 0) ||
+            !IsEqualGUID(node_container, container)) {
+            continue;
+        }
+        if (SetupDiGetDevicePropertyW(devs, &info, &DEVPKEY_Bluetooth_Battery, &type,
+                                      &value, sizeof(value), NULL, 0) && type == DEVPROP_TYPE_BYTE) {
+            level = value <= 100 ? value : -1;
+        }
+    }
+    SetupDiDestroyDeviceInfoList(devs);
+    return level;
+}
+
+bool AudioEndpoints::SetDefaultRenderEndpoint(const std::wstring& endpoint_id) {
+    CoInitialize(NULL);
+    bool ok = false;
+    IPolicyConfig* policy = NULL;
+    if (SUCCEEDED(CoCreateInstance(__uuidof(CPolicyConfigClient), NULL, CLSCTX_ALL,
+                                   __uuidof(IPolicyConfig), (void**)&policy))) {
+        ok = SUCCEEDED(policy->SetDefaultEndpoint(endpoint_id.c_str(), eConsole)) &&
+             SUCCEEDED(policy->SetDefaultEndpoint(endpoint_id.c_str(), eMultimedia)) &&
+             SUCCEEDED(policy->SetDefaultEndpoint(endpoint_id.c_str(), eCommunications));
+        policy->Release();
+    }
+    CoUninitialize();
+    return ok;
+}
+
+// Volume control of the device's render endpoint; the caller releases it and uninitializes COM
+static IAudioEndpointVolume* OpenEndpointVolume(unsigned long long address) {
+    std::wstring endpoint_id = AudioEndpoints::FindRenderEndpoint(address);
+    if (endpoint_id.empty()) return NULL;
+
+    IAudioEndpointVolume* volume = NULL;
+    IMMDeviceEnumerator* enumerator = NULL;
+    if (SUCCEEDED(CoCreateInstance(__uuidof(MMDeviceEnumerator), NULL, CLSCTX_ALL,
+                                   __uuidof(IMMDeviceEnumerator), (void**)&enumerator))) {
+        IMMDevice* device = NULL;
+        if (SUCCEEDED(enumerator->GetDevice(endpoint_id.c_str(), &device))) {
+            device->Activate(__uuidof(IAudioEndpointVolume), CLSCTX_ALL, NULL, (void**)&volume);
+            device->Release();
+        }
+        enumerator->Release();
+    }
+    return volume;
+}
+
+float AudioEndpoints::GetVolume(unsigned long long address) {
+    CoInitialize(NULL);
+    float level = -1.0f;
+    IAudioEndpointVolume* volume = OpenEndpointVolume(address);
+    if (volume) {
+        if (FAILED(volume->GetMasterVolumeLevelScalar(&level))) level = -1.0f;
+        volume->Release();
+    }
+    CoUninitialize();
+    return level;
+}
+
+bool AudioEndpoints::SetVolume(unsigned long long address, float level) {
+    CoInitialize(NULL);
+    bool ok = false;
+    IAudioEndpointVolume* volume = OpenEndpointVolume(address);
+    if (volume) {
+        ok = SUCCEEDED(volume->SetMasterVolumeLevelScalar(level, NULL));
+        volume->Release();
+    }
+    CoUninitialize();
+    return ok;
+}
@@ -1,6 +1,8 @@
 #include "AudioEngine.h"
 #include <iostream>
 #include <functiondiscoverykeys_devpkey.h>
+#include <algorithm>
+#include <cmath>
 
 const CLSID CLSID_MMDeviceEnumerator = __uuidof(MMDeviceEnumerator);
 const IID IID_IMMDeviceEnumerator = __uuidof(IMMDeviceEnumerator);
@@ -8,6 +10,35 @@ const IID IID_IAudioClient = __uuidof(IAudioClient);
 const IID IID_IAudioCaptureClient = __uuidof(IAudioCaptureClient);
 const IID IID_IAudioRenderClient = __uuidof(IAudioRenderClient);
 
+// ================= TEST TONE =================
+
+std::vector<float> GenerateChannelTestTone(int channels, int sample_rate) {
+    const double kPi = 3.14159265358979323846;
+    const int beep = sample_rate * 3 / 10;  // 300 ms
+    const int gap = sample_rate / 5;        // 200 ms
+
+    // (channel mask, frequency) per beep; mask -1 plays on every channel
+    const struct { int mask; double freq; } steps[] = {
+        { 1, 440.0 }, { 2, 660.0 }, { 2, 660.0 }, { -1, 550.0 },
+    };
+
+    std::vector<float> out;
+    out.reserve((beep + gap) * 4 * channels);
+    for (const auto& step : steps) {
+        for (int i = 0; i < beep; i++) {
+            // Short linear fade in/out so the beeps don't click
+            double env = std::min(1.0, std::min(i, beep - i) / (sample_rate * 0.01));
+            float value = (float)(0.3 * env * sin(2.0 * kPi * step.freq * i / sample_rate));
+            for (int c = 0; c < channels; c++) {
+                bool on = step.mask == -1 || (c < 2 && (step.mask & (1 << c)));
+                out.push_back(on ? value : 0.0f);
+            }
+        }
+        out.insert(out.end(), (size_t)gap * channels, 0.0f);
+    }
+    return out;
+}
+
 // ================= CAPTURER =================
 
 WASAPICapturer::WASAPICapturer() : enumerator_(NULL), device_(NULL), audio_client_(NULL), capture_client_(NULL), running_(false) {
@@ -122,6 +153,9 @@ bool WASAPIRenderer::Initialize(WAVEFORMATEX* format) {
     hr = audio_client_->Initialize(AUDCLNT_SHAREMODE_SHARED, 0, 10000000 /* 1 sec */, 0, format, NULL);
     if (FAILED(hr)) return false;
 
+    hr = audio_client_->GetBufferSize(&buffer_size_);
+    if (FAILED(hr)) return false;
+
     hr = audio_client_->GetService(IID_IAudioRenderClient, (void**)&render_client_);
     if (FAILED(hr)) return false;
 
@@ -130,9 +164,21 @@ bool WASAPIRenderer::Initialize(WAVEFORMATEX* format) {
 
 void WASAPIRenderer::FeedData(const BYTE* data, UINT32 frames) {
     if (!render_client_) return;
+
+    // Padding is what's still queued; an empty queue means the device ran dry
+    UINT32 padding = 0;
+    if (SUCCEEDED(audio_client_->GetCurrentPadding(&padding)) && buffer_size_ > 0) {
+        fill_percent_ = padding * 100 / buffer_size_;
+        if (padding == 0) underruns_++;
+    }
     
     BYTE* pBuffer;
     HRESULT hr = render_client_->GetBuffer(frames, &pBuffer);
+    if (hr == AUDCLNT_E_BUFFER_TOO_LARGE) {
+        // Renderer fell behind the capture clock; drop this packet to catch up
+        resyncs_++;
+        return;
+    }
     if (SUCCEEDED(hr)) {
         // Assume format matches for MVP. Real code needs resampling/conversion.
         // Copy data. size = frames * frame_size. 
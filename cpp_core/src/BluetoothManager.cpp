@@ -1,19 +1,47 @@
 #include "BluetoothManager.h"
 #include "DeviceScanner.h"
 #include "ConnectionPool.h"
+#include "Watchdog.h"
+#include "ProfileManager.h"
+#include "AudioEndpoints.h"
+#include "HciCapture.h"
+#include "BondStore.h"
+#include "LeAudio.h"
+#include "SignalMonitor.h"
+#include "GattClient.h"
 #include <memory>
 #include <string>
 #include <vector>
 #include <mutex>
 #include <sstream>
+#include <thread>
+#include <map>
+#include <set>
+#include <atomic>
+#include <chrono>
+#include <climits>
+#include <winioctl.h>
+#include <bthioctl.h>
+#include <rpc.h>
+#include <iphlpapi.h>
+#include <optional>
+
+#pragma comment(lib, "Rpcrt4.lib")
+#pragma comment(lib, "Iphlpapi.lib")
 
 // Global singleton instances for simplicity in this FFI layer
 static std::unique_ptr<DeviceScanner> g_scanner;
 static std::unique_ptr<ConnectionPool> g_pool;
+static std::unique_ptr<Watchdog> g_watchdog;
+static HciCapture g_hci_capture;
+static BroadcastScanner g_broadcast_scanner;
+static SignalMonitor g_signal_monitor;
+static OnConnectionChangedCallback g_connection_callback = nullptr;
 
-// Error handling
-static std::string g_last_bt_error;
-static std::string g_last_audio_error;
+// Error handling. The last errors are per thread: connects run on several threads at once
+// and the worker threads below fail on their own, so each caller reads back only its own error
+static thread_local std::string g_last_bt_error;
+static thread_local std::string g_last_audio_error;
 static std::mutex g_error_mutex;
 static OnErrorCallback g_error_callback = nullptr;
 
@@ -66,16 +94,22 @@ FfiErrorCode bt_init(OnErrorCallback error_callback) {
 // Need a global callback to bridge C to C++ raw pointer callback
 static OnDeviceFoundCallback g_c_callback = nullptr;
 
+// Convert wstring to string (UTF-8)
+static std::string WideToUtf8(const std::wstring& wide) {
+    if (wide.empty()) return std::string();
+    int size_needed = WideCharToMultiByte(CP_UTF8, 0, &wide[0], (int)wide.size(), NULL, 0, NULL, NULL);
+    std::string strTo( size_needed, 0 );
+    WideCharToMultiByte(CP_UTF8, 0, &wide[0], (int)wide.size(), &strTo[0], size_needed, NULL, NULL);
+    return strTo;
+}
+
 void DeviceFoundTrampoline(const BluetoothDevice& dev) {
     if (!g_c_callback) return;
     
     DiscoveredDevice c_dev;
     c_dev.address = dev.address.ullLong;
     
-    // Convert wstring to string (UTF-8)
-    int size_needed = WideCharToMultiByte(CP_UTF8, 0, &dev.name[0], (int)dev.name.size(), NULL, 0, NULL, NULL);
-    std::string strTo( size_needed, 0 );
-    WideCharToMultiByte(CP_UTF8, 0, &dev.name[0], (int)dev.name.size(), &strTo[0], size_needed, NULL, NULL);
+    std::string strTo = WideToUtf8(dev.name);
     
     c_dev.name = strTo.c_str(); 
     c_dev.connected = dev.connected;
@@ -143,7 +177,124 @@ FfiErrorCode bt_stop_scan() {
     }
 }
 
-FfiErrorCode bt_connect_device(unsigned long long address) {
+FfiErrorCode bt_set_low_power_scan(bool enabled) {
+    if (!g_scanner) {
+        set_error("Scanner not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
+        return FFI_NOT_INITIALIZED;
+    }
+    g_scanner->SetLowPower(enabled);
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_set_scan_tuning(const ScanTuning* tuning) {
+    if (!tuning) {
+        set_error("bt_set_scan_tuning: null tuning", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    if (tuning->inquiry_length < 1 || tuning->inquiry_length > 48) {
+        set_error("Inquiry length must be between 1 and 48 (1.28 s units)", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    if (tuning->cycle_gap_ms < 500 || tuning->cycle_gap_ms > 60000) {
+        set_error("Gap between scan cycles must be between 500 and 60000 ms", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    if (!g_scanner) {
+        set_error("Scanner not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
+        return FFI_NOT_INITIALIZED;
+    }
+    g_scanner->SetTuning(tuning->inquiry_length, tuning->cycle_gap_ms);
+    return FFI_SUCCESS;
+}
+
+bool bt_signal_monitor_supported() {
+    return SignalMonitor::Supported();
+}
+
+FfiErrorCode bt_signal_monitor_start(OnSignalCallback callback) {
+    if (!callback) {
+        set_error("bt_signal_monitor_start: null callback", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    std::string error;
+    if (!g_signal_monitor.Start([callback](unsigned long long address, int rssi) { callback(address, rssi); }, error)) {
+        set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_signal_monitor_stop() {
+    g_signal_monitor.Stop();
+    return FFI_SUCCESS;
+}
+
+// A page that takes this long has run into the controller's page timeout (5.12 s by default)
+static const long long PAGE_TIMEOUT_MS = 5000;
+// An inquiry response this recent means the device is in range
+static const unsigned long long RECENT_SIGHTING_SECS = 120;
+
+// Seconds since the device last answered an inquiry, or ULLONG_MAX if unknown
+static unsigned long long seconds_since_seen(const BLUETOOTH_ADDRESS& addr) {
+    BLUETOOTH_DEVICE_INFO info = { sizeof(BLUETOOTH_DEVICE_INFO) };
+    info.Address = addr;
+    FILETIME seen, now;
+    SYSTEMTIME utc;
+    if (BluetoothGetDeviceInfo(NULL, &info) != ERROR_SUCCESS || !SystemTimeToFileTime(&info.stLastSeen, &seen)) {
+        return ULLONG_MAX;
+    }
+    GetSystemTime(&utc);
+    SystemTimeToFileTime(&utc, &now);
+    ULARGE_INTEGER a, b;
+    a.LowPart = seen.dwLowDateTime; a.HighPart = seen.dwHighDateTime;
+    b.LowPart = now.dwLowDateTime; b.HighPart = now.dwHighDateTime;
+    if (b.QuadPart < a.QuadPart) return 0;
+    return (b.QuadPart - a.QuadPart) / 10000000ULL;
+}
+
+// Tells a device that is busy with another host apart from one that is simply gone.
+// Both fail the page; only the busy one still shows up in inquiries.
+static FfiErrorCode classify_connect_failure(const BLUETOOTH_ADDRESS& addr, DWORD error, long long elapsed_ms) {
+    bool page_timeout = error == ERROR_TIMEOUT || error == ERROR_SEM_TIMEOUT || error == WAIT_TIMEOUT
+        || error == ERROR_DEVICE_NOT_CONNECTED || elapsed_ms >= PAGE_TIMEOUT_MS;
+    if (!page_timeout) {
+        set_error("Failed to connect to device (error " + std::to_string(error) + ")", g_last_bt_error, FFI_CONNECTION_FAILED);
+        return FFI_CONNECTION_FAILED;
+    }
+    if (seconds_since_seen(addr) <= RECENT_SIGHTING_SECS) {
+        set_error("Device is nearby but refused the connection. It is probably connected to another computer or phone; "
+                  "disconnect it there and try again.", g_last_bt_error, FFI_PEER_BUSY);
+        return FFI_PEER_BUSY;
+    }
+    set_error("Device did not respond. It may be switched off or out of range.", g_last_bt_error, FFI_PAGE_TIMEOUT);
+    return FFI_PAGE_TIMEOUT;
+}
+
+// Opens the local radio with the given address; NULL if it isn't present.
+static HANDLE open_radio(unsigned long long adapter_address) {
+    BLUETOOTH_FIND_RADIO_PARAMS params;
+    params.dwSize = sizeof(BLUETOOTH_FIND_RADIO_PARAMS);
+    HANDLE hRadio = NULL;
+    HBLUETOOTH_RADIO_FIND hFind = BluetoothFindFirstRadio(&params, &hRadio);
+    if (!hFind) {
+        return NULL;
+    }
+    do {
+        BLUETOOTH_RADIO_INFO radio = {};
+        radio.dwSize = sizeof(BLUETOOTH_RADIO_INFO);
+        if (BluetoothGetRadioInfo(hRadio, &radio) == ERROR_SUCCESS && radio.address.ullLong == adapter_address) {
+            BluetoothFindRadioClose(hFind);
+            return hRadio;
+        }
+        CloseHandle(hRadio);
+        hRadio = NULL;
+    } while (BluetoothFindNextRadio(hFind, &hRadio));
+    BluetoothFindRadioClose(hFind);
+    return NULL;
+}
+
+// Connects through radio, or wherever Windows chooses if it is NULL.
+static FfiErrorCode connect_through(unsigned long long address, HANDLE radio) {
     if (!g_pool) {
         set_error("Connection pool not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
         return FFI_NOT_INITIALIZED;
@@ -153,9 +304,15 @@ FfiErrorCode bt_connect_device(unsigned long long address) {
     addr.ullLong = address;
     
     try {
-        if (!g_pool->ConnectDevice(addr)) {
-            set_error("Failed to connect to device", g_last_bt_error, FFI_CONNECTION_FAILED);
-            return FFI_CONNECTION_FAILED;
+        DWORD error = ERROR_SUCCESS;
+        auto started = std::chrono::steady_clock::now();
+        if (!g_pool->ConnectDevice(addr, &error, radio)) {
+            if (!ProfileManager::IsAuthenticated(addr)) {
+                set_error("Device rejected authentication; the pairing may be broken", g_last_bt_error, FFI_AUTH_FAILED);
+                return FFI_AUTH_FAILED;
+            }
+            auto elapsed = std::chrono::duration_cast<std::chrono::milliseconds>(std::chrono::steady_clock::now() - started);
+            return classify_connect_failure(addr, error, elapsed.count());
         }
         
         FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
@@ -174,6 +331,24 @@ FfiErrorCode bt_connect_device(unsigned long long address) {
     }
 }
 
+FfiErrorCode bt_connect_device(unsigned long long address) {
+    return connect_through(address, NULL);
+}
+
+FfiErrorCode bt_connect_device_via(unsigned long long address, unsigned long long adapter_address) {
+    HANDLE radio = open_radio(adapter_address);
+    if (!radio) {
+        FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+        if (log) {
+            fprintf(log, "[WARN] bt_connect_device_via: adapter %llu not present, using the default\n", adapter_address);
+            fclose(log);
+        }
+    }
+    FfiErrorCode result = connect_through(address, radio);
+    if (radio) CloseHandle(radio);
+    return result;
+}
+
 FfiErrorCode bt_disconnect_device(unsigned long long address) {
     if (!g_pool) {
         set_error("Connection pool not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
@@ -205,124 +380,1201 @@ FfiErrorCode bt_disconnect_device(unsigned long long address) {
     }
 }
 
-const char* bt_get_last_error() {
-    std::lock_guard<std::mutex> lock(g_error_mutex);
-    return g_last_bt_error.c_str();
-}
+FfiErrorCode bt_unpair_device(unsigned long long address) {
+    if (address == 0) {
+        set_error("Invalid device address", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    BLUETOOTH_ADDRESS addr;
+    addr.ullLong = address;
+    if (g_pool) {
+        g_pool->DisconnectDevice(addr);
+    }
+
+    DWORD ret = BluetoothRemoveDevice(&addr);
+    if (ret != ERROR_SUCCESS && ret != ERROR_NOT_FOUND) {
+        set_error("Failed to remove device pairing (error " + std::to_string(ret) + ")", g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
 
-// Audio functions (stubs for now)
-FfiErrorCode audio_init(OnErrorCallback error_callback) {
-    g_error_callback = error_callback;
-    
     FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
     if (log) { 
-        fprintf(log, "[INFO] audio_init called\n"); 
+        fprintf(log, "[INFO] Unpaired device: %llu\n", address); 
         fclose(log); 
     }
-    
-    // TODO: Implement actual audio initialization
     return FFI_SUCCESS;
 }
 
-FfiErrorCode audio_start() {
+FfiErrorCode bt_pair_device(unsigned long long address, OnPairingResultCallback callback) {
+    if (address == 0 || !callback) {
+        set_error("Invalid pairing parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    std::thread([address, callback]() {
+        BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
+        deviceInfo.Address.ullLong = address;
+        if (BluetoothGetDeviceInfo(NULL, &deviceInfo) != ERROR_SUCCESS) {
+            callback(address, FFI_DEVICE_NOT_FOUND);
+            return;
+        }
+        // NULL parent/radio/OOB: Windows picks the radio and shows its own UI if a PIN is needed
+        DWORD ret = BluetoothAuthenticateDeviceEx(NULL, NULL, &deviceInfo, NULL, MITMProtectionNotRequired);
+        callback(address, ret == ERROR_SUCCESS ? FFI_SUCCESS : FFI_AUTH_FAILED);
+    }).detach();
+
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_request_remote_name(unsigned long long address, OnRemoteNameCallback callback) {
+    if (!g_scanner) {
+        set_error("Bluetooth not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
+        return FFI_NOT_INITIALIZED;
+    }
+    if (!callback) {
+        set_error("Remote name callback is null", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
     FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
     if (log) { 
-        fprintf(log, "[INFO] audio_start called\n"); 
+        fprintf(log, "[INFO] bt_request_remote_name called for address: %llu\n", address); 
         fclose(log); 
     }
-    
-    // TODO: Implement actual audio start
+
+    // The stack resolves names lazily after inquiry; poll the device record a few
+    // times on a worker thread so the caller isn't blocked.
+    std::thread([address, callback]() {
+        BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
+        deviceInfo.Address.ullLong = address;
+
+        std::string name;
+        for (int attempt = 0; attempt < 5 && name.empty(); ++attempt) {
+            if (BluetoothGetDeviceInfo(NULL, &deviceInfo) == ERROR_SUCCESS) {
+                name = WideToUtf8(deviceInfo.szName);
+            }
+            if (name.empty()) {
+                std::this_thread::sleep_for(std::chrono::milliseconds(1000));
+            }
+        }
+
+        // Empty string signals that the name could not be resolved
+        callback(address, name.c_str());
+    }).detach();
+
     return FFI_SUCCESS;
 }
 
-FfiErrorCode audio_stop() {
+FfiErrorCode bt_set_connection_callback(OnConnectionChangedCallback callback) {
+    if (!g_pool) {
+        set_error("Connection pool not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
+        return FFI_NOT_INITIALIZED;
+    }
+
+    g_connection_callback = callback;
+    if (!g_watchdog) {
+        g_watchdog = std::make_unique<Watchdog>(g_pool.get(), [](unsigned long long address, bool connected) {
+            FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+            if (log) { 
+                fprintf(log, "[INFO] Link state changed: %llu -> %s\n", address, connected ? "connected" : "lost"); 
+                fclose(log); 
+            }
+            if (g_connection_callback) {
+                g_connection_callback(address, connected);
+            }
+        });
+        g_watchdog->Start();
+    }
+
+    return FFI_SUCCESS;
+}
+
+// HFP call state needs the audio gateway side of the hands-free profile, which
+// Windows keeps to itself; there is no API that reports a headset's call state
+// or forwards its mute to us. Until there is, the hooks are reported as
+// unsupported so the UI can hide them instead of showing a state that never changes.
+static OnHfpStateCallback g_hfp_callback = nullptr;
+static std::mutex g_hfp_mutex;
+
+bool bt_hfp_supported() {
+    return false;
+}
+
+FfiErrorCode bt_set_hfp_callback(OnHfpStateCallback callback) {
+    std::lock_guard<std::mutex> lock(g_hfp_mutex);
+    g_hfp_callback = callback;
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_set_mic_mute(unsigned long long address, bool muted) {
     FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
     if (log) { 
-        fprintf(log, "[INFO] audio_stop called\n"); 
+        fprintf(log, "[WARN] bt_set_mic_mute: %llu -> %s not supported\n", address, muted ? "muted" : "unmuted"); 
         fclose(log); 
     }
-    
-    // TODO: Implement actual audio stop
+    set_error("Headset call control is not supported on this system", g_last_bt_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode bt_send_at_command(unsigned long long address, unsigned char channel, const char* command,
+                                char* reply, size_t reply_len) {
+    if (!command || !reply || reply_len == 0) {
+        set_error("Invalid AT command parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    BLUETOOTH_ADDRESS addr;
+    addr.ullLong = address;
+    std::string response;
+    DWORD error = ERROR_SUCCESS;
+    bool ok = ProfileManager::SendAtCommand(addr, channel, command, response, &error);
+    strncpy_s(reply, reply_len, response.c_str(), _TRUNCATE);
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] bt_send_at_command: %llu ch %u '%s' -> %s\n", address, channel, command, ok ? "ok" : "failed");
+        fclose(log);
+    }
+
+    if (!ok) {
+        if (error == ERROR_INVALID_FUNCTION) {
+            set_error(std::string("Device answered ERROR to ") + command, g_last_bt_error, FFI_OPERATION_FAILED);
+        } else {
+            set_error("Could not reach the device over RFCOMM (error " + std::to_string(error) + ")", g_last_bt_error, FFI_OPERATION_FAILED);
+        }
+        return FFI_OPERATION_FAILED;
+    }
     return FFI_SUCCESS;
 }
 
-FfiErrorCode audio_add_device(unsigned long long address) {
+FfiErrorCode bt_rfcomm_exchange(unsigned long long address, const char* service_uuid,
+                                const unsigned char* request, size_t request_len,
+                                unsigned char* reply, size_t reply_cap, size_t* reply_len) {
+    GUID service;
+    if (!service_uuid || !request || request_len == 0 || !reply || !reply_len
+        || UuidFromStringA((RPC_CSTR)service_uuid, &service) != RPC_S_OK) {
+        set_error("Invalid RFCOMM exchange parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    BLUETOOTH_ADDRESS addr;
+    addr.ullLong = address;
+    std::vector<unsigned char> out(request, request + request_len);
+    std::vector<unsigned char> in;
+    DWORD error = ERROR_SUCCESS;
+    bool ok = ProfileManager::ExchangeRfcomm(addr, service, out, in, &error);
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] bt_rfcomm_exchange: %llu %s, %zu bytes out, %zu in -> %s\n",
+                address, service_uuid, request_len, in.size(), ok ? "ok" : "failed");
+        fclose(log);
+    }
+
+    if (!ok) {
+        set_error("No answer from the device's " + std::string(service_uuid) + " service (error " + std::to_string(error) + ")",
+                  g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    *reply_len = in.size() < reply_cap ? in.size() : reply_cap;
+    memcpy(reply, in.data(), *reply_len);
+    return FFI_SUCCESS;
+}
+
+static GattClient g_gatt_client;
+static std::mutex g_gatt_mutex;
+static OnGattValueCallback g_gatt_callback = nullptr;
+
+FfiErrorCode bt_gatt_set_value_callback(OnGattValueCallback callback) {
+    std::lock_guard<std::mutex> lock(g_gatt_mutex);
+    g_gatt_callback = callback;
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_gatt_discover(unsigned long long address, OnGattCharacteristicCallback callback) {
+    if (address == 0 || !callback) {
+        set_error("Invalid GATT discovery parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
     FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
     if (log) { 
-        fprintf(log, "[INFO] audio_add_device called for address: %llu\n", address); 
+        fprintf(log, "[INFO] bt_gatt_discover: %llu\n", address); 
         fclose(log); 
     }
-    
-    // TODO: Implement actual audio device addition
+
+    std::thread([address, callback]() {
+        std::string error;
+        bool ok = GattClient::Discover(address, [address, callback](unsigned short service, unsigned short characteristic, unsigned char properties) {
+            callback(address, service, characteristic, properties);
+        }, error);
+        if (!ok) {
+            set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        }
+    }).detach();
+
     return FFI_SUCCESS;
 }
 
-FfiErrorCode audio_remove_device(unsigned long long address) {
+FfiErrorCode bt_gatt_subscribe(unsigned long long address, unsigned short service_uuid, unsigned short characteristic_uuid) {
+    if (address == 0 || characteristic_uuid == 0) {
+        set_error("Invalid GATT subscription parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
     FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
     if (log) { 
-        fprintf(log, "[INFO] audio_remove_device called for address: %llu\n", address); 
+        fprintf(log, "[INFO] bt_gatt_subscribe: %llu service 0x%04X char 0x%04X\n", address, service_uuid, characteristic_uuid); 
         fclose(log); 
     }
-    
-    // TODO: Implement actual audio device removal
+
+    std::string error;
+    bool ok = g_gatt_client.Subscribe(address, service_uuid, characteristic_uuid,
+        [](unsigned long long address, unsigned short characteristic, const unsigned char* value, unsigned int length) {
+            OnGattValueCallback callback;
+            {
+                std::lock_guard<std::mutex> lock(g_gatt_mutex);
+                callback = g_gatt_callback;
+            }
+            if (callback) {
+                callback(address, characteristic, value, length);
+            }
+        }, error);
+    if (!ok) {
+        set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
     return FFI_SUCCESS;
 }
 
-const char* audio_get_last_error() {
-    std::lock_guard<std::mutex> lock(g_error_mutex);
-    return g_last_audio_error.c_str();
+FfiErrorCode bt_gatt_unsubscribe(unsigned long long address, unsigned short characteristic_uuid) {
+    if (!g_gatt_client.Unsubscribe(address, characteristic_uuid)) {
+        return FFI_DEVICE_NOT_FOUND;
+    }
+    return FFI_SUCCESS;
 }
 
-int audio_get_channel_count(unsigned long long address) {
-    // TODO: Implement actual channel count lookup
-    // For now, return a mock value
+FfiErrorCode bt_gatt_read(unsigned long long address, unsigned short service_uuid, unsigned short characteristic_uuid) {
+    if (address == 0 || characteristic_uuid == 0) {
+        set_error("Invalid GATT read parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
     FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
     if (log) { 
-        fprintf(log, "[INFO] audio_get_channel_count called for address: %llu\n", address); 
+        fprintf(log, "[INFO] bt_gatt_read: %llu service 0x%04X char 0x%04X\n", address, service_uuid, characteristic_uuid); 
         fclose(log); 
     }
-    
-    // Mock: Return 2 channels for even addresses, 1 for odd
-    return (address % 2 == 0) ? 2 : 1;
-}
 
-bool bt_check_permission() {
-    BLUETOOTH_FIND_RADIO_PARAMS params;
-    params.dwSize = sizeof(BLUETOOTH_FIND_RADIO_PARAMS);
-    
-    HANDLE hRadio = NULL;
-    HBLUETOOTH_RADIO_FIND hFind = BluetoothFindFirstRadio(&params, &hRadio);
-    
-    bool hasPermission = true;
-    
-    if (hFind) {
-        // Found at least one radio, so we have permission
-        BluetoothFindRadioClose(hFind);
-        if (hRadio) CloseHandle(hRadio);
-        
-        FILE* log = fopen("bt_debug_mgr.txt", "a");
-        if (log) { 
-            fprintf(log, "[INFO] bt_check_permission: Radio found, permission granted\n"); 
-            fclose(log); 
+    std::thread([address, service_uuid, characteristic_uuid]() {
+        std::vector<unsigned char> value;
+        std::string error;
+        if (!GattClient::Read(address, service_uuid, characteristic_uuid, value, error)) {
+            set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+            return;
         }
-    } else {
-        DWORD error = GetLastError();
-        if (error == ERROR_ACCESS_DENIED) {
-            hasPermission = false;
-            FILE* log = fopen("bt_debug_mgr.txt", "a");
-            if (log) { 
-                fprintf(log, "[ERROR] bt_check_permission: Access Denied (Error %lu)\n", error); 
-                fclose(log); 
-            }
+        OnGattValueCallback callback;
+        {
+            std::lock_guard<std::mutex> lock(g_gatt_mutex);
+            callback = g_gatt_callback;
+        }
+        if (callback) {
+            callback(address, characteristic_uuid, value.data(), (unsigned int)value.size());
+        }
+    }).detach();
+
+    return FFI_SUCCESS;
+}
+
+// OBEX Object Push. Each transfer gets an id; cancellation is cooperative via the flag.
+struct ObexTransfer {
+    unsigned long long address = 0;
+    std::string path;
+    bool incoming = false;
+    std::shared_ptr<std::atomic<bool>> cancelled = std::make_shared<std::atomic<bool>>(false);
+};
+static std::map<unsigned int, ObexTransfer> g_obex_transfers;
+static std::mutex g_obex_mutex;
+static unsigned int g_next_transfer_id = 1;
+static OnObexProgressCallback g_obex_progress = nullptr;
+static OnObexIncomingCallback g_obex_incoming = nullptr;
+
+FfiErrorCode bt_obex_set_callbacks(OnObexProgressCallback progress, OnObexIncomingCallback incoming) {
+    std::lock_guard<std::mutex> lock(g_obex_mutex);
+    g_obex_progress = progress;
+    g_obex_incoming = incoming;
+    // Receiving isn't implemented: no OPP server (SDP record and RFCOMM listener) is
+    // registered, so incoming stays unused and accept/reject report FFI_NOT_SUPPORTED.
+    // Windows' own "Receive files" dialog still works alongside the app.
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_obex_send_file(unsigned long long address, const char* path, unsigned int* transfer_id) {
+    if (address == 0 || !path || !*path || !transfer_id) {
+        set_error("Invalid OBEX send parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    FILE* file = fopen(path, "rb");
+    if (!file) {
+        set_error("Cannot open file for sending", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    fseek(file, 0, SEEK_END);
+    unsigned long long size = (unsigned long long)_ftelli64(file);
+    fclose(file);
+
+    unsigned int id;
+    std::shared_ptr<std::atomic<bool>> cancelled;
+    OnObexProgressCallback progress;
+    {
+        std::lock_guard<std::mutex> lock(g_obex_mutex);
+        id = g_next_transfer_id++;
+        ObexTransfer transfer;
+        transfer.address = address;
+        transfer.path = path;
+        cancelled = transfer.cancelled;
+        g_obex_transfers[id] = transfer;
+        progress = g_obex_progress;
+    }
+    *transfer_id = id;
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] bt_obex_send_file: %llu <- %s (%llu bytes, id %u)\n", address, path, size, id); 
+        fclose(log); 
+    }
+
+    std::thread([id, address, file_path = std::string(path), size, cancelled, progress]() {
+        if (progress) progress(id, OBEX_PENDING, 0, size);
+        BLUETOOTH_ADDRESS addr;
+        addr.ullLong = address;
+        unsigned long long done = 0;
+        std::string error;
+        bool ok = ProfileManager::PushFile(addr, file_path, [&](unsigned long long sent, unsigned long long total) {
+            done = sent;
+            if (progress) progress(id, OBEX_IN_PROGRESS, sent, total);
+            return !cancelled->load();
+        }, error);
+
+        int state = OBEX_COMPLETE;
+        if (ok) {
+            done = size;
+        } else if (cancelled->load()) {
+            state = OBEX_CANCELLED;
         } else {
-            // Other errors (e.g. no radio found) usually imply we had permission to look
-            FILE* log = fopen("bt_debug_mgr.txt", "a");
-            if (log) { 
-                fprintf(log, "[INFO] bt_check_permission: No radio found but access allowed (Error %lu)\n", error); 
-                fclose(log); 
-            }
+            state = OBEX_FAILED;
+            set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
         }
+        if (progress) progress(id, state, done, size);
+        std::lock_guard<std::mutex> lock(g_obex_mutex);
+        g_obex_transfers.erase(id);
+    }).detach();
+
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_obex_accept(unsigned int transfer_id, const char* save_path) {
+    set_error("Receiving files is not supported yet", g_last_bt_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode bt_obex_reject(unsigned int transfer_id) {
+    set_error("Receiving files is not supported yet", g_last_bt_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode bt_obex_cancel(unsigned int transfer_id) {
+    std::lock_guard<std::mutex> lock(g_obex_mutex);
+    auto it = g_obex_transfers.find(transfer_id);
+    if (it == g_obex_transfers.end()) {
+        return FFI_DEVICE_NOT_FOUND;
     }
-    
-    return hasPermission;
+    it->second.cancelled->store(true);
+    return FFI_SUCCESS;
+}
+
+struct PanOctets {
+    unsigned long long rx;
+    unsigned long long tx;
+};
+
+// PAN links brought up through bt_pan_connect, with the adapter's counters when they came
+// up; unset until the adapter shows up, which can lag the link
+static std::map<unsigned long long, std::optional<PanOctets>> g_pan_links;
+static std::mutex g_pan_mutex;
+
+// Reads the counters of the "Bluetooth Device (Personal Area Network)" adapter. Windows
+// has one such adapter for all PAN links, so links up at the same time share the counts.
+static std::optional<PanOctets> pan_adapter_octets() {
+    PMIB_IF_TABLE2 table = nullptr;
+    if (GetIfTable2(&table) != NO_ERROR) {
+        return std::nullopt;
+    }
+    std::optional<PanOctets> octets;
+    for (ULONG i = 0; i < table->NumEntries; ++i) {
+        const MIB_IF_ROW2& row = table->Table[i];
+        // The filter drivers stacked on the adapter repeat its description
+        if (row.InterfaceAndOperStatusFlags.HardwareInterface && wcsstr(row.Description, L"Personal Area Network")) {
+            octets = PanOctets{ row.InOctets, row.OutOctets };
+            break;
+        }
+    }
+    FreeMibTable(table);
+    return octets;
+}
+
+bool bt_pan_has_nap(unsigned long long address) {
+    BLUETOOTH_ADDRESS addr;
+    addr.ullLong = address;
+    return ProfileManager::HasNetworkAccessPoint(addr);
+}
+
+FfiErrorCode bt_pan_connect(unsigned long long address) {
+    BLUETOOTH_ADDRESS addr;
+    addr.ullLong = address;
+
+    if (!ProfileManager::EnableNetworkAccess(addr)) {
+        set_error("Failed to bring up the PAN link (is tethering enabled on the phone?)", g_last_bt_error, FFI_CONNECTION_FAILED);
+        return FFI_CONNECTION_FAILED;
+    }
+
+    {
+        std::lock_guard<std::mutex> lock(g_pan_mutex);
+        g_pan_links[address] = pan_adapter_octets();
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] PAN connected: %llu\n", address); 
+        fclose(log); 
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_pan_disconnect(unsigned long long address) {
+    BLUETOOTH_ADDRESS addr;
+    addr.ullLong = address;
+
+    {
+        std::lock_guard<std::mutex> lock(g_pan_mutex);
+        g_pan_links.erase(address);
+    }
+
+    if (!ProfileManager::DisableNetworkAccess(addr)) {
+        set_error("Failed to tear down the PAN link", g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_pan_get_status(unsigned long long address, PanLinkStatus* out) {
+    if (!out) {
+        set_error("Status output pointer is null", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    std::lock_guard<std::mutex> lock(g_pan_mutex);
+    auto link = g_pan_links.find(address);
+    out->connected = link != g_pan_links.end();
+    out->rx_bytes = 0;
+    out->tx_bytes = 0;
+    if (!out->connected) {
+        return FFI_SUCCESS;
+    }
+
+    std::optional<PanOctets> now = pan_adapter_octets();
+    if (!now) {
+        return FFI_SUCCESS;
+    }
+    std::optional<PanOctets>& start = link->second;
+    // First sighting of the adapter, or its counters were reset (e.g. it was re-enabled)
+    if (!start || now->rx < start->rx || now->tx < start->tx) {
+        start = now;
+    }
+    out->rx_bytes = now->rx - start->rx;
+    out->tx_bytes = now->tx - start->tx;
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_pbap_pull(unsigned long long address, int folder, OnPbapResultCallback callback) {
+    if (address == 0 || !callback || (folder != PBAP_PHONEBOOK && folder != PBAP_CALL_HISTORY)) {
+        set_error("Invalid PBAP pull parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    if (!g_pool) {
+        set_error("Connection pool not initialized", g_last_bt_error, FFI_NOT_INITIALIZED);
+        return FFI_NOT_INITIALIZED;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] bt_pbap_pull: %llu folder %d\n", address, folder); 
+        fclose(log); 
+    }
+
+    // Not implemented yet: needs an RFCOMM/L2CAP connection to the PSE from SDP (UUID 0x112F),
+    // OBEX CONNECT with the PBAP target header, then PullPhoneBook for the folder as vCard 3.0
+    set_error("Phone book download is not supported yet", g_last_bt_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+const char* bt_get_last_error() {
+    return g_last_bt_error.c_str();
+}
+
+// Sinks added via audio_add_device. The stats stay empty until the renderers report them
+static std::map<unsigned long long, AudioSinkStats> g_sink_stats;
+static std::mutex g_stats_mutex;
+
+// Audio functions (stubs for now)
+FfiErrorCode audio_init(OnErrorCallback error_callback) {
+    g_error_callback = error_callback;
+    
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_init called\n"); 
+        fclose(log); 
+    }
+    
+    // TODO: Implement actual audio initialization
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_start() {
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_start called\n"); 
+        fclose(log); 
+    }
+    
+    // TODO: Implement actual audio start
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_stop() {
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_stop called\n"); 
+        fclose(log); 
+    }
+    
+    // TODO: Implement actual audio stop
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_add_device(unsigned long long address) {
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_add_device called for address: %llu\n", address); 
+        fclose(log); 
+    }
+
+    if (AudioEndpoints::FindRenderEndpoint(address).empty()) {
+        set_error("Audio output for this device is not available yet", g_last_audio_error, FFI_DEVICE_NOT_FOUND);
+        return FFI_DEVICE_NOT_FOUND;
+    }
+    
+    {
+        std::lock_guard<std::mutex> lock(g_stats_mutex);
+        g_sink_stats[address] = AudioSinkStats{};
+    }
+
+    // TODO: Implement actual audio device addition
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_remove_device(unsigned long long address) {
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_remove_device called for address: %llu\n", address); 
+        fclose(log); 
+    }
+    
+    {
+        std::lock_guard<std::mutex> lock(g_stats_mutex);
+        g_sink_stats.erase(address);
+    }
+
+    // TODO: Implement actual audio device removal
+    return FFI_SUCCESS;
+}
+
+bool audio_app_routes_supported() {
+    // Needs a process-loopback capturer (AUDIOCLIENT_ACTIVATION_TYPE_PROCESS_LOOPBACK) per
+    // routed process, mixed only into its sink
+    return false;
+}
+
+FfiErrorCode audio_set_app_route(const char* process_name, unsigned long long sink_address) {
+    if (!process_name || !*process_name) {
+        set_error("Process name is empty", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_set_app_route: %s -> %llu\n", process_name, sink_address); 
+        fclose(log); 
+    }
+
+    set_error("Per-application audio routing is not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode audio_clear_app_routes() {
+    // No route is ever applied, so there is nothing to clear
+    return FFI_SUCCESS;
+}
+
+bool audio_tap_supported() {
+    // Needs AudioManager::OnAudioCaptured to convert the mix format to PCM16 and hand
+    // each packet to the tap
+    return false;
+}
+
+FfiErrorCode audio_set_tap(OnAudioTapCallback callback) {
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_set_tap: %s\n", callback ? "installed" : "removed"); 
+        fclose(log); 
+    }
+
+    // No tap is ever installed, so removing it is already done
+    if (!callback) {
+        return FFI_SUCCESS;
+    }
+    set_error("Recording the broadcast is not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode audio_play_test_tone(unsigned long long address) {
+    if (address == 0) {
+        set_error("Invalid sink address", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_play_test_tone called for address: %llu\n", address); 
+        fclose(log); 
+    }
+
+    // Not implemented yet: needs a WASAPIRenderer on the sink's endpoint fed
+    // GenerateChannelTestTone(renderer.GetChannelCount(), 48000) on a worker thread
+    set_error("The channel test tone is not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode audio_set_sink_muted(unsigned long long address, bool muted) {
+    {
+        std::lock_guard<std::mutex> lock(g_stats_mutex);
+        if (g_sink_stats.find(address) == g_sink_stats.end()) {
+            set_error("Sink is not part of the stream", g_last_audio_error, FFI_DEVICE_NOT_FOUND);
+            return FFI_DEVICE_NOT_FOUND;
+        }
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_set_sink_muted: %llu -> %s\n", address, muted ? "muted" : "unmuted"); 
+        fclose(log); 
+    }
+
+    // Nothing is ever muted, so unmuting is already done
+    if (!muted) {
+        return FFI_SUCCESS;
+    }
+    // Not implemented yet: needs silence fed to muted renderers instead of the mix,
+    // keeping them clocked so unmuting doesn't need a resync
+    set_error("Muting a single sink is not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+bool audio_crossfade_supported() {
+    // Needs complementary equal-power gain ramps in the two renderers' FeedData, releasing
+    // the `from` renderer when its ramp reaches zero
+    return false;
+}
+
+FfiErrorCode audio_crossfade(unsigned long long from, unsigned long long to, unsigned int duration_ms) {
+    if (from == to || duration_ms > 10000) {
+        set_error("Invalid cross-fade", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    {
+        std::lock_guard<std::mutex> lock(g_stats_mutex);
+        if (g_sink_stats.find(from) == g_sink_stats.end() || g_sink_stats.find(to) == g_sink_stats.end()) {
+            set_error("Both sinks must be part of the stream", g_last_audio_error, FFI_DEVICE_NOT_FOUND);
+            return FFI_DEVICE_NOT_FOUND;
+        }
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_crossfade: %llu -> %llu over %u ms\n", from, to, duration_ms); 
+        fclose(log); 
+    }
+
+    set_error("Cross-fading between sinks is not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+bool audio_engine_settings_supported() {
+    // Needs audio_start to pass the buffer as the WASAPI buffer duration
+    // (hnsBufferDuration = ms * 10000) and the sample rate and quality to the resampler
+    return false;
+}
+
+static FfiErrorCode engine_setting_not_supported() {
+    set_error("Audio engine settings are not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode audio_set_buffer_ms(unsigned int buffer_ms) {
+    if (buffer_ms < 10 || buffer_ms > 1000) {
+        set_error("Buffer size must be between 10 and 1000 ms", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    return engine_setting_not_supported();
+}
+
+FfiErrorCode audio_set_resampler_quality(int quality) {
+    if (quality < RESAMPLER_LOW || quality > RESAMPLER_HIGH) {
+        set_error("Unknown resampler quality", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    return engine_setting_not_supported();
+}
+
+FfiErrorCode audio_set_sample_rate(unsigned int sample_rate) {
+    switch (sample_rate) {
+        case 44100:
+        case 48000:
+        case 96000:
+            return engine_setting_not_supported();
+        default:
+            set_error("Unsupported sample rate", g_last_audio_error, FFI_INVALID_PARAMETER);
+            return FFI_INVALID_PARAMETER;
+    }
+}
+
+FfiErrorCode audio_get_sink_stats(unsigned long long address, AudioSinkStats* out) {
+    if (!out) {
+        set_error("Stats output pointer is null", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+
+    std::lock_guard<std::mutex> lock(g_stats_mutex);
+    if (g_sink_stats.find(address) == g_sink_stats.end()) {
+        return FFI_DEVICE_NOT_FOUND;
+    }
+
+    // Not implemented yet: needs WASAPIRenderer::GetBufferFillPercent/GetUnderrunCount/GetResyncCount,
+    // once the renderers are owned here instead of by AudioManager
+    set_error("Buffer statistics are not supported yet", g_last_audio_error, FFI_NOT_SUPPORTED);
+    return FFI_NOT_SUPPORTED;
+}
+
+FfiErrorCode audio_get_default_output(unsigned long long* address) {
+    if (!address) {
+        set_error("Address output pointer is null", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    *address = AudioEndpoints::GetDefaultRenderAddress();
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_set_default_output(unsigned long long address) {
+    std::wstring endpoint = AudioEndpoints::FindRenderEndpoint(address);
+    if (endpoint.empty()) {
+        set_error("No audio output found for this device", g_last_audio_error, FFI_DEVICE_NOT_FOUND);
+        return FFI_DEVICE_NOT_FOUND;
+    }
+    if (!AudioEndpoints::SetDefaultRenderEndpoint(endpoint)) {
+        set_error("Windows refused to change the default output", g_last_audio_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_set_default_output: %llu\n", address); 
+        fclose(log); 
+    }
+    return FFI_SUCCESS;
+}
+
+const char* audio_get_default_output_id() {
+    static std::string id;
+    id = WideToUtf8(AudioEndpoints::GetDefaultRenderEndpoint());
+    return id.c_str();
+}
+
+bool audio_endpoint_ready(unsigned long long address) {
+    return !AudioEndpoints::FindRenderEndpoint(address).empty();
+}
+
+FfiErrorCode bt_get_battery_level(unsigned long long address, int* level) {
+    if (!level) {
+        set_error("Level output pointer is null", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    *level = AudioEndpoints::GetBatteryLevel(address);
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_set_default_output_id(const char* endpoint_id) {
+    if (!endpoint_id || !*endpoint_id) {
+        set_error("Endpoint ID is empty", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    int length = MultiByteToWideChar(CP_UTF8, 0, endpoint_id, -1, NULL, 0);
+    std::wstring wide(length > 0 ? length - 1 : 0, L'\0');
+    MultiByteToWideChar(CP_UTF8, 0, endpoint_id, -1, wide.data(), length);
+    if (!AudioEndpoints::SetDefaultRenderEndpoint(wide)) {
+        set_error("Could not restore the previous default output", g_last_audio_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_get_device_volume(unsigned long long address, float* level) {
+    if (!level) {
+        set_error("Level output pointer is null", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    *level = AudioEndpoints::GetVolume(address);
+    if (*level < 0.0f) {
+        set_error("No audio output found for this device", g_last_audio_error, FFI_DEVICE_NOT_FOUND);
+        return FFI_DEVICE_NOT_FOUND;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_set_device_volume(unsigned long long address, float level) {
+    if (level < 0.0f || level > 1.0f) {
+        set_error("Volume must be between 0 and 1", g_last_audio_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    if (!AudioEndpoints::SetVolume(address, level)) {
+        set_error("No audio output found for this device", g_last_audio_error, FFI_DEVICE_NOT_FOUND);
+        return FFI_DEVICE_NOT_FOUND;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode audio_media_play_pause() {
+    INPUT inputs[2] = {};
+    inputs[0].type = INPUT_KEYBOARD;
+    inputs[0].ki.wVk = VK_MEDIA_PLAY_PAUSE;
+    inputs[1] = inputs[0];
+    inputs[1].ki.dwFlags = KEYEVENTF_KEYUP;
+    if (SendInput(2, inputs, sizeof(INPUT)) != 2) {
+        set_error("Windows blocked the media key", g_last_audio_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+const char* audio_get_last_error() {
+    return g_last_audio_error.c_str();
+}
+
+int audio_get_channel_count(unsigned long long address) {
+    // TODO: Implement actual channel count lookup
+    // For now, return a mock value
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) { 
+        fprintf(log, "[INFO] audio_get_channel_count called for address: %llu\n", address); 
+        fclose(log); 
+    }
+    
+    // Mock: Return 2 channels for even addresses, 1 for odd
+    return (address % 2 == 0) ? 2 : 1;
+}
+
+bool bt_check_permission() {
+    BLUETOOTH_FIND_RADIO_PARAMS params;
+    params.dwSize = sizeof(BLUETOOTH_FIND_RADIO_PARAMS);
+    
+    HANDLE hRadio = NULL;
+    HBLUETOOTH_RADIO_FIND hFind = BluetoothFindFirstRadio(&params, &hRadio);
+    
+    bool hasPermission = true;
+    
+    if (hFind) {
+        // Found at least one radio, so we have permission
+        BluetoothFindRadioClose(hFind);
+        if (hRadio) CloseHandle(hRadio);
+        
+        FILE* log = fopen("bt_debug_mgr.txt", "a");
+        if (log) { 
+            fprintf(log, "[INFO] bt_check_permission: Radio found, permission granted\n"); 
+            fclose(log); 
+        }
+    } else {
+        DWORD error = GetLastError();
+        if (error == ERROR_ACCESS_DENIED) {
+            hasPermission = false;
+            FILE* log = fopen("bt_debug_mgr.txt", "a");
+            if (log) { 
+                fprintf(log, "[ERROR] bt_check_permission: Access Denied (Error %lu)\n", error); 
+                fclose(log); 
+            }
+        } else {
+            // Other errors (e.g. no radio found) usually imply we had permission to look
+            FILE* log = fopen("bt_debug_mgr.txt", "a");
+            if (log) { 
+                fprintf(log, "[INFO] bt_check_permission: No radio found but access allowed (Error %lu)\n", error); 
+                fclose(log); 
+            }
+        }
+    }
+    
+    return hasPermission;
+}
+
+unsigned int bt_get_api_version() {
+    return BT_API_VERSION;
+}
+
+int bt_get_radio_state() {
+    BLUETOOTH_FIND_RADIO_PARAMS params;
+    params.dwSize = sizeof(BLUETOOTH_FIND_RADIO_PARAMS);
+
+    HANDLE hRadio = NULL;
+    HBLUETOOTH_RADIO_FIND hFind = BluetoothFindFirstRadio(&params, &hRadio);
+    if (!hFind) {
+        return RADIO_ABSENT;
+    }
+    BluetoothFindRadioClose(hFind);
+
+    // A radio switched off in Settings is still enumerated but accepts no connections
+    int state = BluetoothIsConnectable(hRadio) ? RADIO_ON : RADIO_OFF;
+    CloseHandle(hRadio);
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] bt_get_radio_state: %d\n", state);
+        fclose(log);
+    }
+    return state;
+}
+
+// Fills out from an open radio handle; false if Windows can't describe the radio.
+static bool read_adapter_info(HANDLE hRadio, AdapterInfo* out) {
+    BLUETOOTH_RADIO_INFO radio = {};
+    radio.dwSize = sizeof(BLUETOOTH_RADIO_INFO);
+    if (BluetoothGetRadioInfo(hRadio, &radio) != ERROR_SUCCESS) {
+        return false;
+    }
+    out->address = radio.address.ullLong;
+    out->manufacturer = radio.manufacturer;
+    out->lmp_subversion = radio.lmpSubversion;
+    WideCharToMultiByte(CP_UTF8, 0, radio.szName, -1, out->name, sizeof(out->name), NULL, NULL);
+
+    // HCI and LMP versions are only available from the driver
+    BTH_LOCAL_RADIO_INFO local = {};
+    DWORD returned = 0;
+    if (DeviceIoControl(hRadio, IOCTL_BTH_GET_LOCAL_INFO, NULL, 0, &local, sizeof(local), &returned, NULL)) {
+        out->hci_version = local.hciVersion;
+        out->hci_revision = local.hciRevision;
+        out->lmp_version = local.radioInfo.lmpVersion;
+    } else {
+        FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+        if (log) {
+            fprintf(log, "[WARN] read_adapter_info: IOCTL_BTH_GET_LOCAL_INFO failed (%lu)\n", GetLastError());
+            fclose(log);
+        }
+    }
+    return true;
+}
+
+FfiErrorCode bt_get_adapter_info(AdapterInfo* out) {
+    if (!out) {
+        set_error("bt_get_adapter_info: null output", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    *out = AdapterInfo{};
+
+    BLUETOOTH_FIND_RADIO_PARAMS params;
+    params.dwSize = sizeof(BLUETOOTH_FIND_RADIO_PARAMS);
+    HANDLE hRadio = NULL;
+    HBLUETOOTH_RADIO_FIND hFind = BluetoothFindFirstRadio(&params, &hRadio);
+    if (!hFind) {
+        set_error("No Bluetooth adapter found", g_last_bt_error, FFI_DEVICE_NOT_FOUND);
+        return FFI_DEVICE_NOT_FOUND;
+    }
+    BluetoothFindRadioClose(hFind);
+
+    bool read = read_adapter_info(hRadio, out);
+    CloseHandle(hRadio);
+    if (!read) {
+        set_error("Failed to read adapter info", g_last_bt_error);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_list_adapters(AdapterInfo* out, unsigned int capacity, unsigned int* count) {
+    if (!out || !count) {
+        set_error("bt_list_adapters: null output", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    *count = 0;
+    if (capacity == 0) {
+        return FFI_SUCCESS;
+    }
+
+    BLUETOOTH_FIND_RADIO_PARAMS params;
+    params.dwSize = sizeof(BLUETOOTH_FIND_RADIO_PARAMS);
+    HANDLE hRadio = NULL;
+    HBLUETOOTH_RADIO_FIND hFind = BluetoothFindFirstRadio(&params, &hRadio);
+    if (!hFind) {
+        // No adapter is an empty list, not an error
+        return FFI_SUCCESS;
+    }
+    do {
+        out[*count] = AdapterInfo{};
+        if (read_adapter_info(hRadio, &out[*count])) {
+            (*count)++;
+        }
+        CloseHandle(hRadio);
+        hRadio = NULL;
+    } while (*count < capacity && BluetoothFindNextRadio(hFind, &hRadio));
+    BluetoothFindRadioClose(hFind);
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_hci_capture_start(OnHciPacketCallback callback) {
+    if (!callback) {
+        set_error("bt_hci_capture_start: null callback", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    DWORD error = ERROR_SUCCESS;
+    auto forward = [callback](unsigned char type, bool received, const unsigned char* data, unsigned int length) {
+        callback(type, received, data, length);
+    };
+    if (!g_hci_capture.Start(forward, &error)) {
+        if (error == ERROR_ACCESS_DENIED) {
+            set_error("HCI capture needs RedTooth to run as administrator", g_last_bt_error, FFI_OPERATION_FAILED);
+        } else {
+            set_error("Failed to start HCI capture (error " + std::to_string(error) + ")", g_last_bt_error, FFI_OPERATION_FAILED);
+        }
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_hci_capture_stop() {
+    g_hci_capture.Stop();
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_bonds_export(OnBondCallback callback) {
+    if (!callback) {
+        set_error("bt_bonds_export: null callback", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    std::vector<Bond> bonds;
+    std::string error;
+    if (!BondStore::ReadAll(bonds, error)) {
+        set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    for (const auto& bond : bonds) {
+        std::vector<unsigned char> values = BondStore::Serialize(bond.values);
+        BondRecord record = { bond.adapter, bond.address, bond.le, values.data(), (unsigned int)values.size() };
+        callback(&record);
+        SecureZeroMemory(values.data(), values.size());
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_bond_import(const BondRecord* bond) {
+    Bond parsed;
+    if (!bond || !bond->values || !BondStore::Deserialize(bond->values, bond->values_len, parsed.values) ||
+        parsed.values.empty()) {
+        set_error("bt_bond_import: malformed bond", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    parsed.adapter = bond->adapter;
+    parsed.address = bond->address;
+    parsed.le = bond->le;
+    std::string error;
+    if (!BondStore::Write(parsed, error)) {
+        set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Restored bond for %llu\n", bond->address);
+        fclose(log);
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_bonds_reload() {
+    std::string error;
+    if (!BondStore::Reload(error)) {
+        set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+bool bt_le_audio_supported() {
+    return BroadcastScanner::Supported();
+}
+
+FfiErrorCode bt_broadcast_scan_start(OnBroadcastFoundCallback callback) {
+    if (!callback) {
+        set_error("bt_broadcast_scan_start: null callback", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    auto forward = [callback](const BroadcastAnnouncement& announcement) {
+        BroadcastSource source = {};
+        source.address = announcement.address;
+        source.address_type = announcement.address_type;
+        source.advertising_sid = announcement.advertising_sid;
+        source.broadcast_id = announcement.broadcast_id;
+        source.name = announcement.name.empty() ? NULL : announcement.name.c_str();
+        source.rssi = announcement.rssi;
+        source.encrypted = announcement.encrypted;
+        source.quality = announcement.quality;
+        callback(&source);
+    };
+    std::string error;
+    if (!g_broadcast_scanner.Start(forward, error)) {
+        set_error(error, g_last_bt_error, FFI_OPERATION_FAILED);
+        return FFI_OPERATION_FAILED;
+    }
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_broadcast_scan_stop() {
+    g_broadcast_scanner.Stop();
+    return FFI_SUCCESS;
+}
+
+FfiErrorCode bt_broadcast_join(unsigned long long sink_address, const BroadcastSource* source,
+                               const unsigned char* broadcast_code) {
+    if (sink_address == 0 || !source) {
+        set_error("Invalid broadcast join parameters", g_last_bt_error, FFI_INVALID_PARAMETER);
+        return FFI_INVALID_PARAMETER;
+    }
+    BroadcastAnnouncement announcement;
+    announcement.address = source->address;
+    announcement.address_type = source->address_type;
+    announcement.advertising_sid = source->advertising_sid;
+    announcement.broadcast_id = source->broadcast_id;
+    std::string error;
+    if (!BroadcastAssistant::Join(sink_address, announcement, broadcast_code, error)) {
+        set_error(error, g_last_bt_error, FFI_CONNECTION_FAILED);
+        return FFI_CONNECTION_FAILED;
+    }
+    return FFI_SUCCESS;
 }
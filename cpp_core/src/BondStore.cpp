@@ -0,0 +1,204 @@
+#include "BondStore.h"
+#include <chrono>
+#include <cstdio>
+#include <thread>
+
+static const wchar_t* KEYS_PATH = L"SYSTEM\\CurrentControlSet\\Services\\BTHPORT\\Parameters\\Keys";
+static const wchar_t* BLUETOOTH_SERVICE = L"bthserv";
+
+static bool enable_privilege(const wchar_t* privilege) {
+    HANDLE token = NULL;
+    if (!OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &token)) return false;
+    TOKEN_PRIVILEGES tp = {};
+    tp.PrivilegeCount = 1;
+    tp.Privileges[0].Attributes = SE_PRIVILEGE_ENABLED;
+    bool ok = LookupPrivilegeValueW(NULL, privilege, &tp.Privileges[0].Luid) &&
+              AdjustTokenPrivileges(token, FALSE, &tp, 0, NULL, NULL) &&
+              GetLastError() == ERROR_SUCCESS;
+    CloseHandle(token);
+    return ok;
+}
+
+static std::string narrow(const std::wstring& wide) {
+    return std::string(wide.begin(), wide.end());
+}
+
+static std::wstring widen(const std::string& text) {
+    return std::wstring(text.begin(), text.end());
+}
+
+static std::wstring address_key(unsigned long long address) {
+    wchar_t key[13];
+    swprintf_s(key, L"%012llx", address);
+    return key;
+}
+
+static bool parse_address(const wchar_t* text, unsigned long long& address) {
+    if (wcslen(text) != 12) return false;
+    wchar_t* end = NULL;
+    address = wcstoull(text, &end, 16);
+    return end && *end == L'\0';
+}
+
+static std::vector<BondValue> read_values(HKEY key) {
+    std::vector<BondValue> values;
+    for (DWORD i = 0;; i++) {
+        wchar_t name[256];
+        DWORD name_len = 256, type = 0, size = 0;
+        if (RegEnumValueW(key, i, name, &name_len, NULL, &type, NULL, &size) != ERROR_SUCCESS) break;
+        BondValue value;
+        value.name = narrow(name);
+        value.type = type;
+        value.data.resize(size);
+        name_len = 256;
+        if (RegEnumValueW(key, i, name, &name_len, NULL, &type, value.data.data(), &size) == ERROR_SUCCESS) {
+            values.push_back(value);
+        }
+    }
+    return values;
+}
+
+bool BondStore::ReadAll(std::vector<Bond>& bonds, std::string& error) {
+    if (!enable_privilege(SE_BACKUP_NAME)) {
+        error = "Reading pairings needs RedTooth to run as administrator";
+        return false;
+    }
+    HKEY keys = NULL;
+    LSTATUS status = RegOpenKeyExW(HKEY_LOCAL_MACHINE, KEYS_PATH, REG_OPTION_BACKUP_RESTORE, KEY_READ, &keys);
+    if (status != ERROR_SUCCESS) {
+        error = "Could not open the Bluetooth key store (error " + std::to_string(status) + ")";
+        return false;
+    }
+
+    wchar_t adapter_name[64];
+    for (DWORD a = 0;; a++) {
+        DWORD adapter_len = 64;
+        if (RegEnumKeyExW(keys, a, adapter_name, &adapter_len, NULL, NULL, NULL, NULL) != ERROR_SUCCESS) break;
+        unsigned long long adapter = 0;
+        HKEY adapter_key = NULL;
+        if (!parse_address(adapter_name, adapter) ||
+            RegOpenKeyExW(keys, adapter_name, REG_OPTION_BACKUP_RESTORE, KEY_READ, &adapter_key) != ERROR_SUCCESS) {
+            continue;
+        }
+
+        // BR/EDR: one 16-byte value per device
+        for (const auto& value : read_values(adapter_key)) {
+            unsigned long long address = 0;
+            if (value.type != REG_BINARY || !parse_address(widen(value.name).c_str(), address)) continue;
+            bonds.push_back({ adapter, address, false, { value } });
+        }
+
+        // LE: a key per device
+        wchar_t device_name[64];
+        for (DWORD d = 0;; d++) {
+            DWORD device_len = 64;
+            if (RegEnumKeyExW(adapter_key, d, device_name, &device_len, NULL, NULL, NULL, NULL) != ERROR_SUCCESS) break;
+            unsigned long long address = 0;
+            HKEY device_key = NULL;
+            if (!parse_address(device_name, address) ||
+                RegOpenKeyExW(adapter_key, device_name, REG_OPTION_BACKUP_RESTORE, KEY_READ, &device_key) != ERROR_SUCCESS) {
+                continue;
+            }
+            bonds.push_back({ adapter, address, true, read_values(device_key) });
+            RegCloseKey(device_key);
+        }
+        RegCloseKey(adapter_key);
+    }
+    RegCloseKey(keys);
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Read %zu bonds from the key store\n", bonds.size());
+        fclose(log);
+    }
+    return true;
+}
+
+bool BondStore::Write(const Bond& bond, std::string& error) {
+    if (!enable_privilege(SE_RESTORE_NAME) || !enable_privilege(SE_BACKUP_NAME)) {
+        error = "Restoring pairings needs RedTooth to run as administrator";
+        return false;
+    }
+    std::wstring path = std::wstring(KEYS_PATH) + L"\\" + address_key(bond.adapter);
+    if (bond.le) path += L"\\" + address_key(bond.address);
+
+    HKEY key = NULL;
+    LSTATUS status = RegCreateKeyExW(HKEY_LOCAL_MACHINE, path.c_str(), 0, NULL, REG_OPTION_BACKUP_RESTORE,
+                                     KEY_WRITE, NULL, &key, NULL);
+    if (status != ERROR_SUCCESS) {
+        error = "Could not open the Bluetooth key store (error " + std::to_string(status) + ")";
+        return false;
+    }
+    for (const auto& value : bond.values) {
+        // A BR/EDR link key is always stored under its device, whatever the backup said
+        std::wstring name = bond.le ? widen(value.name) : address_key(bond.address);
+        status = RegSetValueExW(key, name.c_str(), 0, value.type, value.data.data(), (DWORD)value.data.size());
+        if (status != ERROR_SUCCESS) {
+            error = "Could not write the key (error " + std::to_string(status) + ")";
+            break;
+        }
+    }
+    RegCloseKey(key);
+    return status == ERROR_SUCCESS;
+}
+
+bool BondStore::Reload(std::string& error) {
+    SC_HANDLE manager = OpenSCManagerW(NULL, NULL, SC_MANAGER_CONNECT);
+    SC_HANDLE service = manager ? OpenServiceW(manager, BLUETOOTH_SERVICE, SERVICE_STOP | SERVICE_START | SERVICE_QUERY_STATUS) : NULL;
+    if (!service) {
+        error = "Could not open the Bluetooth Support Service";
+        if (manager) CloseServiceHandle(manager);
+        return false;
+    }
+
+    SERVICE_STATUS status = {};
+    ControlService(service, SERVICE_CONTROL_STOP, &status);
+    for (int i = 0; i < 50 && QueryServiceStatus(service, &status) && status.dwCurrentState != SERVICE_STOPPED; i++) {
+        std::this_thread::sleep_for(std::chrono::milliseconds(200));
+    }
+    bool ok = StartServiceW(service, 0, NULL) || GetLastError() == ERROR_SERVICE_ALREADY_RUNNING;
+    if (!ok) error = "Could not restart the Bluetooth Support Service (error " + std::to_string(GetLastError()) + ")";
+    CloseServiceHandle(service);
+    CloseServiceHandle(manager);
+    return ok;
+}
+
+static void put_u32(std::vector<unsigned char>& out, unsigned int value) {
+    for (int i = 0; i < 4; i++) out.push_back((unsigned char)(value >> (8 * i)));
+}
+
+static unsigned int get_u32(const unsigned char* data) {
+    return data[0] | (data[1] << 8) | (data[2] << 16) | ((unsigned int)data[3] << 24);
+}
+
+std::vector<unsigned char> BondStore::Serialize(const std::vector<BondValue>& values) {
+    std::vector<unsigned char> out;
+    for (const auto& value : values) {
+        std::string name = value.name.substr(0, 255);
+        out.push_back((unsigned char)name.size());
+        out.insert(out.end(), name.begin(), name.end());
+        put_u32(out, value.type);
+        put_u32(out, (unsigned int)value.data.size());
+        out.insert(out.end(), value.data.begin(), value.data.end());
+    }
+    return out;
+}
+
+bool BondStore::Deserialize(const unsigned char* data, unsigned int length, std::vector<BondValue>& values) {
+    unsigned int pos = 0;
+    while (pos < length) {
+        unsigned int name_len = data[pos++];
+        if (length - pos < name_len + 8) return false;
+        BondValue value;
+        value.name.assign((const char*)data + pos, name_len);
+        pos += name_len;
+        value.type = get_u32(data + pos);
+        unsigned int size = get_u32(data + pos + 4);
+        pos += 8;
+        if (length - pos < size) return false;
+        value.data.assign(data + pos, data + pos + size);
+        pos += size;
+        values.push_back(value);
+    }
+    return true;
+}
@@ -10,10 +10,10 @@ ConnectionPool::ConnectionPool() {
 ConnectionPool::~ConnectionPool() {
 }
 
-bool ConnectionPool::ConnectDevice(BLUETOOTH_ADDRESS address) {
+bool ConnectionPool::ConnectDevice(BLUETOOTH_ADDRESS address, DWORD* error, HANDLE radio) {
     if (IsConnected(address)) return true;
 
-    bool success = ProfileManager::EnableAudioSink(address);
+    bool success = ProfileManager::EnableAudioSink(address, error, radio);
     if (success) {
         std::lock_guard<std::mutex> lock(mutex_);
         // In a real app we'd get a handle or some token, but for high-level link manager:
@@ -49,3 +49,12 @@ bool ConnectionPool::IsConnected(BLUETOOTH_ADDRESS address) {
     }
     return false;
 }
+
+std::vector<unsigned long long> ConnectionPool::GetActiveAddresses() {
+    std::lock_guard<std::mutex> lock(mutex_);
+    std::vector<unsigned long long> addresses;
+    for (const auto& pair : active_connections_) {
+        addresses.push_back(pair.first);
+    }
+    return addresses;
+}
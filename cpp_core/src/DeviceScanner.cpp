@@ -116,6 +116,17 @@ bool DeviceScanner::IsValidRadio() {
     return false; // No radio handle means no access or no hardware
 }
 
+void DeviceScanner::SetLowPower(bool enabled) {
+    low_power_ = enabled;
+    LogCLI("[INFO] Low-power scanning %s", enabled ? "enabled" : "disabled");
+}
+
+void DeviceScanner::SetTuning(unsigned char inquiry_length, unsigned int cycle_gap_ms) {
+    inquiry_length_ = inquiry_length;
+    cycle_gap_ms_ = cycle_gap_ms;
+    LogCLI("[INFO] Scan tuning: inquiry length %u, %u ms between cycles", inquiry_length, cycle_gap_ms);
+}
+
 void DeviceScanner::ScanLoop() {
     BLUETOOTH_DEVICE_SEARCH_PARAMS searchParams;
     ZeroMemory(&searchParams, sizeof(BLUETOOTH_DEVICE_SEARCH_PARAMS));
@@ -125,7 +136,7 @@ void DeviceScanner::ScanLoop() {
     searchParams.fReturnUnknown = TRUE;
     searchParams.fReturnConnected = TRUE;
     searchParams.fIssueInquiry = TRUE;
-    searchParams.cTimeoutMultiplier = 4; // ~5 seconds
+    searchParams.cTimeoutMultiplier = inquiry_length_;
     searchParams.hRadio = NULL;
 
     BLUETOOTH_DEVICE_INFO deviceInfo;
@@ -147,6 +158,11 @@ void DeviceScanner::ScanLoop() {
 
         LogCLI("[INFO] Scanning cycle starting...");
 
+        // Inquiry is the expensive part; on battery keep it short (~2.5 s at most)
+        bool low_power = low_power_;
+        unsigned char inquiry_length = inquiry_length_;
+        searchParams.cTimeoutMultiplier = low_power ? (std::min)(inquiry_length, (unsigned char)2) : inquiry_length;
+
         HBLUETOOTH_DEVICE_FIND hFind = BluetoothFindFirstDevice(&searchParams, &deviceInfo);
         
         if (hFind) {
@@ -161,7 +177,7 @@ void DeviceScanner::ScanLoop() {
                 dev.connected = deviceInfo.fConnected;
                 dev.authenticated = deviceInfo.fAuthenticated;
                 dev.cod = deviceInfo.ulClassofDevice;
-                dev.rssi = 0; // Windows API doesn't give RSSI easily in this struct without Winsock
+                dev.rssi = 0; // Not available for inquiry results; see SignalMonitor
 
                 {
                     std::lock_guard<std::mutex> lock(mutex_);
@@ -177,12 +193,13 @@ void DeviceScanner::ScanLoop() {
                     }
                     if (!exists) {
                         cached_devices_.push_back(dev);
-                        // LogCLI("[CLI] Device Found: %S", dev.name.c_str()); // C++ CLI Echo
-                        if (on_device_found_) {
-                            on_device_found_(dev);
-                        }
                         device_count++;
                     }
+                    // Reported every cycle, not only the first time, so callers can tell
+                    // a device that is still around from one that has left
+                    if (on_device_found_) {
+                        on_device_found_(dev);
+                    }
                 }
             } while (BluetoothFindNextDevice(hFind, &deviceInfo));
             
@@ -205,9 +222,19 @@ void DeviceScanner::ScanLoop() {
             }
         }
         
+        // Repeated failures back off beyond the configured gap; on battery, idle
+        // for at least 15 s between cycles
+        int idle_ms = static_cast<int>(cycle_gap_ms_.load());
+        if (consecutive_errors > 2) {
+            idle_ms = (std::max)(idle_ms, current_backoff_ms);
+        }
+        if (low_power) {
+            idle_ms = (std::max)(idle_ms, 15000);
+        }
+
         // Sleep interruptible? 
         // For simplicity, sleep in chunks
-        for (int i = 0; i < current_backoff_ms; i += 100) {
+        for (int i = 0; i < idle_ms; i += 100) {
              std::lock_guard<std::mutex> lock(mutex_);
              if (!scanning_) break;
              std::this_thread::sleep_for(std::chrono::milliseconds(100)); // Use std::this_thread::sleep_for
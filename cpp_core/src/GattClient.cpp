@@ -0,0 +1,234 @@
+#include "GattClient.h"
+#include <cstdio>
+
+#include <winrt/Windows.Foundation.h>
+#include <winrt/Windows.Foundation.Collections.h>
+#include <winrt/Windows.Devices.Bluetooth.h>
+#include <winrt/Windows.Devices.Bluetooth.GenericAttributeProfile.h>
+#include <winrt/Windows.Storage.Streams.h>
+
+#pragma comment(lib, "WindowsApp.lib")
+
+using namespace winrt::Windows::Devices::Bluetooth;
+using namespace winrt::Windows::Devices::Bluetooth::GenericAttributeProfile;
+using winrt::Windows::Storage::Streams::DataReader;
+using winrt::Windows::Storage::Streams::IBuffer;
+
+// WinRT calls need the calling thread in an apartment; FFI calls arrive on whatever thread
+static void ensure_apartment() {
+    thread_local bool initialized = false;
+    if (!initialized) {
+        try {
+            winrt::init_apartment(winrt::apartment_type::multi_threaded);
+        } catch (const winrt::hresult_error&) {
+            // Already initialized by someone else, possibly single-threaded; WinRT copes
+        }
+        initialized = true;
+    }
+}
+
+static std::vector<unsigned char> to_bytes(const IBuffer& buffer) {
+    std::vector<unsigned char> bytes(buffer.Length());
+    DataReader::FromBuffer(buffer).ReadBytes(bytes);
+    return bytes;
+}
+
+// 0 for UUIDs outside the Bluetooth base UUID or above 16 bits
+static unsigned short short_uuid(const winrt::guid& uuid) {
+    auto id = BluetoothUuidHelper::TryGetShortId(uuid);
+    if (!id || id.Value() > 0xFFFF) return 0;
+    return (unsigned short)id.Value();
+}
+
+// Held for as long as a subscription lasts; closing the device would drop it
+struct Subscription {
+    BluetoothLEDevice device{ nullptr };
+    GattDeviceService service{ nullptr };
+    GattCharacteristic characteristic{ nullptr };
+    winrt::event_token token{};
+};
+
+static BluetoothLEDevice open_device(unsigned long long address, std::string& error) {
+    auto device = BluetoothLEDevice::FromBluetoothAddressAsync(address).get();
+    if (!device) {
+        error = "The device isn't reachable over Bluetooth LE";
+    }
+    return device;
+}
+
+bool GattClient::Discover(unsigned long long address, CharacteristicHandler on_characteristic, std::string& error) {
+    ensure_apartment();
+    int reported = 0;
+    try {
+        auto device = open_device(address, error);
+        if (!device) return false;
+        auto services = device.GetGattServicesAsync(BluetoothCacheMode::Uncached).get();
+        if (services.Status() != GattCommunicationStatus::Success) {
+            error = "The device didn't answer service discovery";
+            device.Close();
+            return false;
+        }
+        for (const auto& service : services.Services()) {
+            unsigned short service_uuid = short_uuid(service.Uuid());
+            if (service_uuid == 0) {
+                service.Close();
+                continue;
+            }
+            auto characteristics = service.GetCharacteristicsAsync(BluetoothCacheMode::Uncached).get();
+            if (characteristics.Status() == GattCommunicationStatus::Success) {
+                for (const auto& characteristic : characteristics.Characteristics()) {
+                    unsigned short uuid = short_uuid(characteristic.Uuid());
+                    if (uuid == 0) continue;
+                    // The WinRT flags use the GATT property bit positions for the low byte
+                    auto properties = (unsigned char)((unsigned int)characteristic.CharacteristicProperties() & 0xFF);
+                    on_characteristic(service_uuid, uuid, properties);
+                    ++reported;
+                }
+            }
+            service.Close();
+        }
+        device.Close();
+    } catch (const winrt::hresult_error& e) {
+        error = "GATT discovery failed: " + winrt::to_string(e.message());
+        return false;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] GATT discovery on %llu: %d characteristics\n", address, reported);
+        fclose(log);
+    }
+    if (reported == 0) {
+        error = "The device has no standard GATT characteristics";
+        return false;
+    }
+    return true;
+}
+
+bool GattClient::Read(unsigned long long address, unsigned short service, unsigned short characteristic,
+                      std::vector<unsigned char>& value, std::string& error) {
+    ensure_apartment();
+    try {
+        auto device = open_device(address, error);
+        if (!device) return false;
+        auto services = device.GetGattServicesForUuidAsync(BluetoothUuidHelper::FromShortId(service), BluetoothCacheMode::Uncached).get();
+        if (services.Status() != GattCommunicationStatus::Success || services.Services().Size() == 0) {
+            error = "The device doesn't offer that service";
+            device.Close();
+            return false;
+        }
+        auto gatt_service = services.Services().GetAt(0);
+        auto found = gatt_service.GetCharacteristicsForUuidAsync(BluetoothUuidHelper::FromShortId(characteristic), BluetoothCacheMode::Uncached).get();
+        bool ok = false;
+        if (found.Status() != GattCommunicationStatus::Success || found.Characteristics().Size() == 0) {
+            error = "The service doesn't have that characteristic";
+        } else {
+            auto read = found.Characteristics().GetAt(0).ReadValueAsync(BluetoothCacheMode::Uncached).get();
+            if (read.Status() != GattCommunicationStatus::Success) {
+                error = "The device refused the read";
+            } else {
+                value = to_bytes(read.Value());
+                ok = true;
+            }
+        }
+        gatt_service.Close();
+        device.Close();
+        return ok;
+    } catch (const winrt::hresult_error& e) {
+        error = "GATT read failed: " + winrt::to_string(e.message());
+        return false;
+    }
+}
+
+bool GattClient::Subscribe(unsigned long long address, unsigned short service, unsigned short characteristic,
+                           ValueHandler on_value, std::string& error) {
+    auto key = std::make_pair(address, characteristic);
+    {
+        std::lock_guard<std::mutex> lock(mutex_);
+        if (subscriptions_.count(key)) return true;
+    }
+    ensure_apartment();
+    auto subscription = std::make_shared<Subscription>();
+    try {
+        subscription->device = open_device(address, error);
+        if (!subscription->device) return false;
+        auto services = subscription->device.GetGattServicesForUuidAsync(BluetoothUuidHelper::FromShortId(service), BluetoothCacheMode::Uncached).get();
+        if (services.Status() != GattCommunicationStatus::Success || services.Services().Size() == 0) {
+            error = "The device doesn't offer that service";
+            subscription->device.Close();
+            return false;
+        }
+        subscription->service = services.Services().GetAt(0);
+        auto found = subscription->service.GetCharacteristicsForUuidAsync(BluetoothUuidHelper::FromShortId(characteristic), BluetoothCacheMode::Uncached).get();
+        if (found.Status() != GattCommunicationStatus::Success || found.Characteristics().Size() == 0) {
+            error = "The service doesn't have that characteristic";
+            subscription->service.Close();
+            subscription->device.Close();
+            return false;
+        }
+        subscription->characteristic = found.Characteristics().GetAt(0);
+
+        auto properties = subscription->characteristic.CharacteristicProperties();
+        GattClientCharacteristicConfigurationDescriptorValue cccd;
+        if ((properties & GattCharacteristicProperties::Notify) == GattCharacteristicProperties::Notify) {
+            cccd = GattClientCharacteristicConfigurationDescriptorValue::Notify;
+        } else if ((properties & GattCharacteristicProperties::Indicate) == GattCharacteristicProperties::Indicate) {
+            cccd = GattClientCharacteristicConfigurationDescriptorValue::Indicate;
+        } else {
+            error = "The characteristic doesn't send notifications";
+            subscription->service.Close();
+            subscription->device.Close();
+            return false;
+        }
+
+        subscription->token = subscription->characteristic.ValueChanged([address, characteristic, on_value](const GattCharacteristic&, const GattValueChangedEventArgs& args) {
+            auto value = to_bytes(args.CharacteristicValue());
+            on_value(address, characteristic, value.data(), (unsigned int)value.size());
+        });
+        auto status = subscription->characteristic.WriteClientCharacteristicConfigurationDescriptorAsync(cccd).get();
+        if (status != GattCommunicationStatus::Success) {
+            error = "The device refused the subscription";
+            subscription->characteristic.ValueChanged(subscription->token);
+            subscription->service.Close();
+            subscription->device.Close();
+            return false;
+        }
+    } catch (const winrt::hresult_error& e) {
+        error = "GATT subscribe failed: " + winrt::to_string(e.message());
+        return false;
+    }
+
+    {
+        std::lock_guard<std::mutex> lock(mutex_);
+        subscriptions_[key] = subscription;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] GATT subscribed: %llu service 0x%04X char 0x%04X\n", address, service, characteristic);
+        fclose(log);
+    }
+    return true;
+}
+
+bool GattClient::Unsubscribe(unsigned long long address, unsigned short characteristic) {
+    std::shared_ptr<Subscription> subscription;
+    {
+        std::lock_guard<std::mutex> lock(mutex_);
+        auto found = subscriptions_.find(std::make_pair(address, characteristic));
+        if (found == subscriptions_.end()) return false;
+        subscription = std::static_pointer_cast<Subscription>(found->second);
+        subscriptions_.erase(found);
+    }
+    ensure_apartment();
+    try {
+        subscription->characteristic.ValueChanged(subscription->token);
+        subscription->characteristic.WriteClientCharacteristicConfigurationDescriptorAsync(
+            GattClientCharacteristicConfigurationDescriptorValue::None).get();
+    } catch (const winrt::hresult_error&) {
+        // Out of range or switched off; the device forgets the CCCD when the link drops
+    }
+    subscription->service.Close();
+    subscription->device.Close();
+    return true;
+}
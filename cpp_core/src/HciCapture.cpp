@@ -0,0 +1,112 @@
+#include "HciCapture.h"
+#include <vector>
+#include <cstdio>
+
+#pragma comment(lib, "Advapi32.lib")
+
+// BthMini, the provider the Bluetooth stack logs HCI packets to
+static const GUID BTHMINI_PROVIDER = { 0x8a1f9517, 0x3a8c, 0x4a9e, { 0xa0, 0x18, 0x4f, 0x17, 0xa2, 0x00, 0xf2, 0x77 } };
+static const wchar_t SESSION_NAME[] = L"RedToothHciCapture";
+
+// H4 packet indicators
+static const unsigned char H4_COMMAND = 1;
+static const unsigned char H4_ISO = 5;
+
+HciCapture::~HciCapture() {
+    Stop();
+}
+
+// EVENT_TRACE_PROPERTIES followed by room for the session name, as ETW expects
+static std::vector<unsigned char> session_properties() {
+    std::vector<unsigned char> buffer(sizeof(EVENT_TRACE_PROPERTIES) + sizeof(SESSION_NAME), 0);
+    auto* props = reinterpret_cast<EVENT_TRACE_PROPERTIES*>(buffer.data());
+    props->Wnode.BufferSize = static_cast<ULONG>(buffer.size());
+    props->Wnode.Flags = WNODE_FLAG_TRACED_GUID;
+    props->Wnode.ClientContext = 1; // QPC timestamps
+    props->LogFileMode = EVENT_TRACE_REAL_TIME_MODE;
+    props->LoggerNameOffset = sizeof(EVENT_TRACE_PROPERTIES);
+    return buffer;
+}
+
+bool HciCapture::Start(PacketHandler on_packet, DWORD* error) {
+    if (running_) return true;
+    on_packet_ = on_packet;
+
+    // A session left over from a crashed run would make StartTrace fail
+    auto stale = session_properties();
+    ControlTraceW(0, SESSION_NAME, reinterpret_cast<EVENT_TRACE_PROPERTIES*>(stale.data()), EVENT_TRACE_CONTROL_STOP);
+
+    auto props = session_properties();
+    ULONG ret = StartTraceW(&session_, SESSION_NAME, reinterpret_cast<EVENT_TRACE_PROPERTIES*>(props.data()));
+    if (ret != ERROR_SUCCESS) {
+        if (error) *error = ret;
+        return false;
+    }
+    ret = EnableTraceEx2(session_, &BTHMINI_PROVIDER, EVENT_CONTROL_CODE_ENABLE_PROVIDER,
+                         TRACE_LEVEL_VERBOSE, 0xFFFFFFFFFFFFFFFFULL, 0, 0, NULL);
+    if (ret != ERROR_SUCCESS) {
+        ControlTraceW(session_, NULL, reinterpret_cast<EVENT_TRACE_PROPERTIES*>(props.data()), EVENT_TRACE_CONTROL_STOP);
+        session_ = 0;
+        if (error) *error = ret;
+        return false;
+    }
+
+    EVENT_TRACE_LOGFILEW logfile = {};
+    logfile.LoggerName = const_cast<LPWSTR>(SESSION_NAME);
+    logfile.ProcessTraceMode = PROCESS_TRACE_MODE_REAL_TIME | PROCESS_TRACE_MODE_EVENT_RECORD;
+    logfile.EventRecordCallback = &HciCapture::OnEvent;
+    logfile.Context = this;
+    consumer_ = OpenTraceW(&logfile);
+    if (consumer_ == INVALID_PROCESSTRACE_HANDLE) {
+        DWORD open_error = GetLastError();
+        ControlTraceW(session_, NULL, reinterpret_cast<EVENT_TRACE_PROPERTIES*>(props.data()), EVENT_TRACE_CONTROL_STOP);
+        session_ = 0;
+        if (error) *error = open_error;
+        return false;
+    }
+
+    running_ = true;
+    // ProcessTrace blocks until the session is stopped
+    thread_ = std::thread([this]() { ProcessTrace(&consumer_, 1, NULL, NULL); });
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] HCI capture started\n");
+        fclose(log);
+    }
+    return true;
+}
+
+void HciCapture::Stop() {
+    if (!running_) return;
+    running_ = false;
+
+    auto props = session_properties();
+    ControlTraceW(session_, NULL, reinterpret_cast<EVENT_TRACE_PROPERTIES*>(props.data()), EVENT_TRACE_CONTROL_STOP);
+    session_ = 0;
+    CloseTrace(consumer_);
+    consumer_ = INVALID_PROCESSTRACE_HANDLE;
+    if (thread_.joinable()) thread_.join();
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] HCI capture stopped\n");
+        fclose(log);
+    }
+}
+
+// Packet events carry the H4 indicator followed by the HCI packet. Anything else
+// the provider logs (state changes, driver messages) is skipped.
+void WINAPI HciCapture::OnEvent(PEVENT_RECORD record) {
+    auto* self = static_cast<HciCapture*>(record->UserContext);
+    if (!self || !self->running_ || !self->on_packet_) return;
+    if (record->UserDataLength < 2) return;
+
+    auto* data = static_cast<const unsigned char*>(record->UserData);
+    unsigned char type = data[0];
+    if (type < H4_COMMAND || type > H4_ISO) return;
+
+    // The payload doesn't say which way data packets went; only commands are outgoing for sure
+    bool received = type != H4_COMMAND;
+    self->on_packet_(type, received, data + 1, record->UserDataLength - 1);
+}
@@ -0,0 +1,249 @@
+#include "LeAudio.h"
+#include <chrono>
+#include <cstdio>
+#include <thread>
+
+#include <winrt/Windows.Foundation.h>
+#include <winrt/Windows.Foundation.Collections.h>
+#include <winrt/Windows.Devices.Bluetooth.h>
+#include <winrt/Windows.Devices.Bluetooth.Advertisement.h>
+#include <winrt/Windows.Devices.Bluetooth.GenericAttributeProfile.h>
+#include <winrt/Windows.Storage.Streams.h>
+
+#pragma comment(lib, "WindowsApp.lib")
+
+using namespace winrt::Windows::Devices::Bluetooth;
+using namespace winrt::Windows::Devices::Bluetooth::Advertisement;
+using namespace winrt::Windows::Devices::Bluetooth::GenericAttributeProfile;
+using winrt::Windows::Storage::Streams::DataReader;
+using winrt::Windows::Storage::Streams::DataWriter;
+using winrt::Windows::Storage::Streams::IBuffer;
+
+// AD types
+static const unsigned char AD_SERVICE_DATA_16 = 0x16;
+static const unsigned char AD_BROADCAST_NAME = 0x30;
+
+// Assigned numbers
+static const unsigned short UUID_BASS = 0x184F;
+static const unsigned short UUID_BROADCAST_AUDIO_ANNOUNCEMENT = 0x1852;
+static const unsigned short UUID_PUBLIC_BROADCAST_ANNOUNCEMENT = 0x1856;
+static const unsigned short UUID_BASS_CONTROL_POINT = 0x2BC7;
+static const unsigned short UUID_BASS_RECEIVE_STATE = 0x2BC8;
+
+// Broadcast Audio Scan Control Point opcodes
+static const unsigned char BASS_ADD_SOURCE = 0x02;
+static const unsigned char BASS_SET_BROADCAST_CODE = 0x04;
+
+// WinRT calls need the calling thread in an apartment; FFI calls arrive on whatever thread
+static void ensure_apartment() {
+    thread_local bool initialized = false;
+    if (!initialized) {
+        try {
+            winrt::init_apartment(winrt::apartment_type::multi_threaded);
+        } catch (const winrt::hresult_error&) {
+            // Already initialized by someone else, possibly single-threaded; WinRT copes
+        }
+        initialized = true;
+    }
+}
+
+static std::vector<unsigned char> to_bytes(const IBuffer& buffer) {
+    std::vector<unsigned char> bytes(buffer.Length());
+    DataReader::FromBuffer(buffer).ReadBytes(bytes);
+    return bytes;
+}
+
+static unsigned short read_u16(const unsigned char* data) {
+    return (unsigned short)(data[0] | (data[1] << 8));
+}
+
+bool ParseBroadcastAnnouncement(const std::vector<AdSection>& sections, BroadcastAnnouncement* out) {
+    bool found = false;
+    for (const auto& [type, data] : sections) {
+        if (type == AD_SERVICE_DATA_16 && data.size() >= 2) {
+            unsigned short uuid = read_u16(data.data());
+            if (uuid == UUID_BROADCAST_AUDIO_ANNOUNCEMENT && data.size() >= 5) {
+                out->broadcast_id = data[2] | (data[3] << 8) | (data[4] << 16);
+                found = true;
+            } else if (uuid == UUID_PUBLIC_BROADCAST_ANNOUNCEMENT && data.size() >= 3) {
+                unsigned char features = data[2];
+                out->encrypted = (features & 0x01) != 0;
+                out->quality = (features & 0x04) ? 2 : (features & 0x02) ? 1 : 0;
+            }
+        } else if (type == AD_BROADCAST_NAME) {
+            out->name.assign(data.begin(), data.end());
+        }
+    }
+    return found;
+}
+
+BroadcastScanner::~BroadcastScanner() {
+    Stop();
+}
+
+bool BroadcastScanner::Supported() {
+    ensure_apartment();
+    try {
+        auto adapter = BluetoothAdapter::GetDefaultAsync().get();
+        return adapter && adapter.IsLowEnergySupported() && adapter.IsExtendedAdvertisingSupported();
+    } catch (const winrt::hresult_error&) {
+        return false;
+    }
+}
+
+bool BroadcastScanner::Start(Handler on_broadcast, std::string& error) {
+    std::lock_guard<std::mutex> lock(mutex_);
+    if (running_) return true;
+    ensure_apartment();
+    try {
+        auto watcher = std::make_shared<BluetoothLEAdvertisementWatcher>();
+        watcher->ScanningMode(BluetoothLEScanningMode::Passive);
+        watcher->AllowExtendedAdvertisements(true);
+        watcher->Received([on_broadcast](const BluetoothLEAdvertisementWatcher&,
+                                         const BluetoothLEAdvertisementReceivedEventArgs& args) {
+            std::vector<AdSection> sections;
+            for (const auto& section : args.Advertisement().DataSections()) {
+                sections.emplace_back(section.DataType(), to_bytes(section.Data()));
+            }
+            BroadcastAnnouncement announcement;
+            if (!ParseBroadcastAnnouncement(sections, &announcement)) return;
+            announcement.address = args.BluetoothAddress();
+            announcement.address_type = args.BluetoothAddressType() == BluetoothAddressType::Random ? 1 : 0;
+            // Windows doesn't report the advertising SID; sinks match on the broadcast id
+            announcement.advertising_sid = 0;
+            announcement.rssi = args.RawSignalStrengthInDBm();
+            on_broadcast(announcement);
+        });
+        watcher->Start();
+        watcher_ = watcher;
+    } catch (const winrt::hresult_error& e) {
+        error = "Failed to start the broadcast scan: " + winrt::to_string(e.message());
+        return false;
+    }
+    running_ = true;
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Broadcast scan started\n");
+        fclose(log);
+    }
+    return true;
+}
+
+void BroadcastScanner::Stop() {
+    std::lock_guard<std::mutex> lock(mutex_);
+    if (!running_) return;
+    running_ = false;
+    auto watcher = std::static_pointer_cast<BluetoothLEAdvertisementWatcher>(watcher_);
+    try {
+        watcher->Stop();
+    } catch (const winrt::hresult_error&) {
+        // Already stopped, e.g. the radio went off
+    }
+    watcher_.reset();
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Broadcast scan stopped\n");
+        fclose(log);
+    }
+}
+
+static GattCharacteristic find_characteristic(const GattDeviceService& service, unsigned short uuid) {
+    auto result = service.GetCharacteristicsForUuidAsync(BluetoothUuidHelper::FromShortId(uuid), BluetoothCacheMode::Uncached).get();
+    if (result.Status() != GattCommunicationStatus::Success || result.Characteristics().Size() == 0) {
+        return nullptr;
+    }
+    return result.Characteristics().GetAt(0);
+}
+
+static bool write_control_point(const GattCharacteristic& control, const std::vector<unsigned char>& op, std::string& error) {
+    DataWriter writer;
+    writer.WriteBytes(op);
+    auto result = control.WriteValueWithResultAsync(writer.DetachBuffer(), GattWriteOption::WriteWithResponse).get();
+    if (result.Status() != GattCommunicationStatus::Success) {
+        error = "The sink rejected the request";
+        if (auto code = result.ProtocolError()) {
+            error += " (ATT error " + std::to_string(code.Value()) + ")";
+        }
+        return false;
+    }
+    return true;
+}
+
+// Source_ID the sink gave the broadcast, from its Broadcast Receive State characteristics
+static int find_source_id(const GattDeviceService& service, unsigned int broadcast_id) {
+    auto result = service.GetCharacteristicsForUuidAsync(BluetoothUuidHelper::FromShortId(UUID_BASS_RECEIVE_STATE), BluetoothCacheMode::Uncached).get();
+    if (result.Status() != GattCommunicationStatus::Success) return -1;
+    for (const auto& characteristic : result.Characteristics()) {
+        auto read = characteristic.ReadValueAsync(BluetoothCacheMode::Uncached).get();
+        if (read.Status() != GattCommunicationStatus::Success) continue;
+        auto state = to_bytes(read.Value());
+        // Source_ID, address type, address (6), SID, Broadcast_ID (3), ...
+        if (state.size() >= 12 && (unsigned int)(state[9] | (state[10] << 8) | (state[11] << 16)) == broadcast_id) {
+            return state[0];
+        }
+    }
+    return -1;
+}
+
+bool BroadcastAssistant::Join(unsigned long long sink, const BroadcastAnnouncement& source,
+                              const unsigned char* broadcast_code, std::string& error) {
+    ensure_apartment();
+    try {
+        auto device = BluetoothLEDevice::FromBluetoothAddressAsync(sink).get();
+        if (!device) {
+            error = "The sink isn't reachable over Bluetooth LE";
+            return false;
+        }
+        auto services = device.GetGattServicesForUuidAsync(BluetoothUuidHelper::FromShortId(UUID_BASS), BluetoothCacheMode::Uncached).get();
+        if (services.Status() != GattCommunicationStatus::Success || services.Services().Size() == 0) {
+            error = "The sink can't join broadcasts (no Broadcast Audio Scan Service)";
+            return false;
+        }
+        auto service = services.Services().GetAt(0);
+        auto control = find_characteristic(service, UUID_BASS_CONTROL_POINT);
+        if (!control) {
+            error = "The sink's Broadcast Audio Scan Service has no control point";
+            return false;
+        }
+
+        // Add Source: sync to the periodic advertising and any BIS of one subgroup
+        std::vector<unsigned char> add = { BASS_ADD_SOURCE, source.address_type };
+        for (int i = 0; i < 6; i++) add.push_back((unsigned char)(source.address >> (8 * i)));
+        add.push_back(source.advertising_sid);
+        for (int i = 0; i < 3; i++) add.push_back((unsigned char)(source.broadcast_id >> (8 * i)));
+        add.push_back(0x02);                                 // PA_Sync: synchronize, PAST not available
+        add.push_back(0xFF); add.push_back(0xFF);            // PA_Interval unknown
+        add.push_back(1);                                    // Num_Subgroups
+        for (int i = 0; i < 4; i++) add.push_back(0xFF);     // BIS_Sync: no preference
+        add.push_back(0);                                    // Metadata_Length
+        if (!write_control_point(control, add, error)) return false;
+
+        if (broadcast_code) {
+            // The sink reports the source once it has picked it up
+            int source_id = -1;
+            for (int attempt = 0; attempt < 10 && source_id < 0; attempt++) {
+                std::this_thread::sleep_for(std::chrono::milliseconds(500));
+                source_id = find_source_id(service, source.broadcast_id);
+            }
+            if (source_id < 0) {
+                error = "The sink didn't pick up the broadcast";
+                return false;
+            }
+            std::vector<unsigned char> code = { BASS_SET_BROADCAST_CODE, (unsigned char)source_id };
+            code.insert(code.end(), broadcast_code, broadcast_code + 16);
+            if (!write_control_point(control, code, error)) return false;
+        }
+    } catch (const winrt::hresult_error& e) {
+        error = winrt::to_string(e.message());
+        return false;
+    }
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Sink %llu asked to join broadcast %06X\n", sink, source.broadcast_id);
+        fclose(log);
+    }
+    return true;
+}
@@ -1,24 +1,28 @@
 #include "ProfileManager.h"
+#include <cstdio>
 #include <iostream>
 #include <ws2bth.h>
 
 // GUIDs for profiles
 static GUID A2DP_SINK_GUID = { 0x0000110B, 0x0000, 0x1000, { 0x80, 0x00, 0x00, 0x80, 0x5F, 0x9B, 0x34, 0xFB } };
+static GUID PAN_NAP_GUID = { 0x00001116, 0x0000, 0x1000, { 0x80, 0x00, 0x00, 0x80, 0x5F, 0x9B, 0x34, 0xFB } };
 
-bool ProfileManager::EnableAudioSink(const BLUETOOTH_ADDRESS& address) {
+bool ProfileManager::EnableAudioSink(const BLUETOOTH_ADDRESS& address, DWORD* error, HANDLE radio) {
     BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
     deviceInfo.Address = address;
     
-    DWORD ret = BluetoothGetDeviceInfo(NULL, &deviceInfo);
+    DWORD ret = BluetoothGetDeviceInfo(radio, &deviceInfo);
     if (ret != ERROR_SUCCESS) {
         std::cerr << "Failed to get device info: " << ret << std::endl;
+        if (error) *error = ret;
         return false;
     }
 
     // This call triggers the Windows Bluetooth stack to connect the profile
-    ret = BluetoothSetServiceState(NULL, &deviceInfo, &A2DP_SINK_GUID, BLUETOOTH_SERVICE_ENABLE);
+    ret = BluetoothSetServiceState(radio, &deviceInfo, &A2DP_SINK_GUID, BLUETOOTH_SERVICE_ENABLE);
     if (ret != ERROR_SUCCESS) {
         std::cerr << "Failed to enable A2DP: " << ret << std::endl;
+        if (error) *error = ret;
         return false;
     }
     
@@ -35,3 +39,288 @@ bool ProfileManager::DisableAudioSink(const BLUETOOTH_ADDRESS& address) {
     ret = BluetoothSetServiceState(NULL, &deviceInfo, &A2DP_SINK_GUID, BLUETOOTH_SERVICE_DISABLE);
     return (ret == ERROR_SUCCESS);
 }
+
+bool ProfileManager::IsAuthenticated(const BLUETOOTH_ADDRESS& address) {
+    BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
+    deviceInfo.Address = address;
+
+    DWORD ret = BluetoothGetDeviceInfo(NULL, &deviceInfo);
+    if (ret != ERROR_SUCCESS) return false;
+    return deviceInfo.fAuthenticated != FALSE;
+}
+
+bool ProfileManager::HasNetworkAccessPoint(const BLUETOOTH_ADDRESS& address) {
+    BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
+    deviceInfo.Address = address;
+
+    DWORD ret = BluetoothGetDeviceInfo(NULL, &deviceInfo);
+    if (ret != ERROR_SUCCESS) return false;
+
+    GUID services[32];
+    DWORD count = 32;
+    ret = BluetoothEnumerateInstalledServices(NULL, &deviceInfo, &count, services);
+    if (ret != ERROR_SUCCESS && ret != ERROR_MORE_DATA) return false;
+
+    for (DWORD i = 0; i < count && i < 32; i++) {
+        if (IsEqualGUID(services[i], PAN_NAP_GUID)) return true;
+    }
+    return false;
+}
+
+bool ProfileManager::EnableNetworkAccess(const BLUETOOTH_ADDRESS& address) {
+    BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
+    deviceInfo.Address = address;
+
+    DWORD ret = BluetoothGetDeviceInfo(NULL, &deviceInfo);
+    if (ret != ERROR_SUCCESS) return false;
+
+    ret = BluetoothSetServiceState(NULL, &deviceInfo, &PAN_NAP_GUID, BLUETOOTH_SERVICE_ENABLE);
+    if (ret != ERROR_SUCCESS) {
+        std::cerr << "Failed to enable PAN NAP: " << ret << std::endl;
+        return false;
+    }
+    return true;
+}
+
+bool ProfileManager::DisableNetworkAccess(const BLUETOOTH_ADDRESS& address) {
+    BLUETOOTH_DEVICE_INFO deviceInfo = { sizeof(BLUETOOTH_DEVICE_INFO) };
+    deviceInfo.Address = address;
+
+    DWORD ret = BluetoothGetDeviceInfo(NULL, &deviceInfo);
+    if (ret != ERROR_SUCCESS) return false;
+
+    ret = BluetoothSetServiceState(NULL, &deviceInfo, &PAN_NAP_GUID, BLUETOOTH_SERVICE_DISABLE);
+    return (ret == ERROR_SUCCESS);
+}
+
+// How long to wait for a reply on an RFCOMM socket
+static const DWORD RFCOMM_REPLY_TIMEOUT_MS = 3000;
+
+// Starts Winsock and connects an RFCOMM socket, to a fixed channel or, with channel 0, to
+// whichever channel SDP lists for service. On success the caller closes the socket and
+// calls WSACleanup; on failure both are already done.
+static SOCKET open_rfcomm(const BLUETOOTH_ADDRESS& address, const GUID& service, unsigned char channel, DWORD* error) {
+    WSADATA wsa;
+    if (WSAStartup(MAKEWORD(2, 2), &wsa) != 0) {
+        if (error) *error = WSAGetLastError();
+        return INVALID_SOCKET;
+    }
+
+    SOCKET s = socket(AF_BTH, SOCK_STREAM, BTHPROTO_RFCOMM);
+    if (s != INVALID_SOCKET) {
+        SOCKADDR_BTH target = {};
+        target.addressFamily = AF_BTH;
+        target.btAddr = address.ullLong;
+        if (channel == 0) {
+            target.serviceClassId = service;
+        } else {
+            target.port = channel;
+        }
+        setsockopt(s, SOL_SOCKET, SO_RCVTIMEO, (const char*)&RFCOMM_REPLY_TIMEOUT_MS, sizeof(RFCOMM_REPLY_TIMEOUT_MS));
+        if (connect(s, (SOCKADDR*)&target, sizeof(target)) == 0) {
+            return s;
+        }
+        closesocket(s);
+    }
+
+    std::cerr << "RFCOMM connect failed: " << WSAGetLastError() << std::endl;
+    if (error) *error = WSAGetLastError();
+    WSACleanup();
+    return INVALID_SOCKET;
+}
+
+bool ProfileManager::SendAtCommand(const BLUETOOTH_ADDRESS& address, unsigned char channel, const std::string& command,
+                                   std::string& reply, DWORD* error) {
+    SOCKET s = open_rfcomm(address, SerialPortServiceClass_UUID, channel, error);
+    if (s == INVALID_SOCKET) return false;
+
+    bool ok = false;
+    std::string line = command + "\r";
+    if (send(s, line.c_str(), (int)line.size(), 0) != SOCKET_ERROR) {
+        char buf[128];
+        // Some devices act on the command without answering; a timeout isn't a failure
+        while (reply.find("OK") == std::string::npos && reply.find("ERROR") == std::string::npos) {
+            int n = recv(s, buf, sizeof(buf), 0);
+            if (n <= 0) break;
+            reply.append(buf, n);
+        }
+        ok = reply.find("ERROR") == std::string::npos;
+        if (!ok && error) *error = ERROR_INVALID_FUNCTION;
+    } else if (error) {
+        *error = WSAGetLastError();
+    }
+
+    closesocket(s);
+    WSACleanup();
+    return ok;
+}
+
+bool ProfileManager::ExchangeRfcomm(const BLUETOOTH_ADDRESS& address, const GUID& service,
+                                    const std::vector<unsigned char>& request, std::vector<unsigned char>& reply,
+                                    DWORD* error) {
+    SOCKET s = open_rfcomm(address, service, 0, error);
+    if (s == INVALID_SOCKET) return false;
+
+    bool ok = false;
+    if (send(s, (const char*)request.data(), (int)request.size(), 0) != SOCKET_ERROR) {
+        unsigned char buf[512];
+        int n = recv(s, (char*)buf, sizeof(buf), 0);
+        if (n > 0) {
+            reply.assign(buf, buf + n);
+            ok = true;
+        } else if (error) {
+            *error = n == 0 ? ERROR_GRACEFUL_DISCONNECT : WSAGetLastError();
+        }
+    } else if (error) {
+        *error = WSAGetLastError();
+    }
+
+    closesocket(s);
+    WSACleanup();
+    return ok;
+}
+
+// OBEX opcodes, response codes and header ids used by Object Push
+static const unsigned char OBEX_CONNECT = 0x80;
+static const unsigned char OBEX_DISCONNECT = 0x81;
+static const unsigned char OBEX_PUT = 0x02;
+static const unsigned char OBEX_PUT_FINAL = 0x82;
+static const unsigned char OBEX_ABORT = 0xFF;
+static const unsigned char OBEX_CONTINUE = 0x90;
+static const unsigned char OBEX_OK = 0xA0;
+static const unsigned char OBEX_HI_NAME = 0x01;
+static const unsigned char OBEX_HI_LENGTH = 0xC3;
+static const unsigned char OBEX_HI_BODY = 0x48;
+static const unsigned char OBEX_HI_END_OF_BODY = 0x49;
+// Packet size we offer; the device may ask for smaller, but not below the OBEX minimum
+static const size_t OBEX_MAX_PACKET = 0x2000;
+static const size_t OBEX_MIN_PACKET = 255;
+// Phones ask their user before answering the first PUT
+static const DWORD OBEX_ANSWER_TIMEOUT_MS = 60000;
+
+static void put_u16(std::vector<unsigned char>& out, size_t value) {
+    out.push_back((unsigned char)((value >> 8) & 0xFF));
+    out.push_back((unsigned char)(value & 0xFF));
+}
+
+static bool recv_exact(SOCKET s, unsigned char* buf, size_t len) {
+    size_t got = 0;
+    while (got < len) {
+        int n = recv(s, (char*)buf + got, (int)(len - got), 0);
+        if (n <= 0) return false;
+        got += n;
+    }
+    return true;
+}
+
+// Sends packet (its length field is filled in here) and returns the response code,
+// or 0 if the link failed. The response's fields and headers go to reply.
+static unsigned char obex_request(SOCKET s, std::vector<unsigned char> packet, std::vector<unsigned char>* reply = nullptr) {
+    packet[1] = (unsigned char)((packet.size() >> 8) & 0xFF);
+    packet[2] = (unsigned char)(packet.size() & 0xFF);
+    size_t sent = 0;
+    while (sent < packet.size()) {
+        int n = send(s, (const char*)packet.data() + sent, (int)(packet.size() - sent), 0);
+        if (n == SOCKET_ERROR) return 0;
+        sent += n;
+    }
+    unsigned char head[3];
+    if (!recv_exact(s, head, sizeof(head))) return 0;
+    size_t length = ((size_t)head[1] << 8) | head[2];
+    if (length < sizeof(head)) return 0;
+    std::vector<unsigned char> rest(length - sizeof(head));
+    if (!rest.empty() && !recv_exact(s, rest.data(), rest.size())) return 0;
+    if (reply) *reply = std::move(rest);
+    return head[0];
+}
+
+bool ProfileManager::PushFile(const BLUETOOTH_ADDRESS& address, const std::string& path,
+                              const std::function<bool(unsigned long long sent, unsigned long long total)>& on_progress,
+                              std::string& error) {
+    FILE* file = fopen(path.c_str(), "rb");
+    if (!file) {
+        error = "Cannot open file for sending";
+        return false;
+    }
+    _fseeki64(file, 0, SEEK_END);
+    unsigned long long size = (unsigned long long)_ftelli64(file);
+    _fseeki64(file, 0, SEEK_SET);
+
+    // The Name header is the file's own name in UTF-16BE, NUL-terminated
+    size_t slash = path.find_last_of("/\\");
+    std::string base = slash == std::string::npos ? path : path.substr(slash + 1);
+    std::wstring wide(MultiByteToWideChar(CP_UTF8, 0, base.c_str(), (int)base.size(), nullptr, 0), L'\0');
+    MultiByteToWideChar(CP_UTF8, 0, base.c_str(), (int)base.size(), wide.data(), (int)wide.size());
+
+    DWORD win_error = ERROR_SUCCESS;
+    SOCKET s = open_rfcomm(address, OBEXObjectPushServiceClass_UUID, 0, &win_error);
+    if (s == INVALID_SOCKET) {
+        fclose(file);
+        error = "The device has no Object Push service or didn't answer (error " + std::to_string(win_error) + ")";
+        return false;
+    }
+    setsockopt(s, SOL_SOCKET, SO_RCVTIMEO, (const char*)&OBEX_ANSWER_TIMEOUT_MS, sizeof(OBEX_ANSWER_TIMEOUT_MS));
+
+    bool ok = false;
+    std::vector<unsigned char> reply;
+    // Version 1.0, no flags, our maximum packet size
+    std::vector<unsigned char> connect_request = { OBEX_CONNECT, 0, 0, 0x10, 0x00 };
+    put_u16(connect_request, OBEX_MAX_PACKET);
+    if (obex_request(s, connect_request, &reply) != OBEX_OK || reply.size() < 4) {
+        error = "The device didn't accept an OBEX session";
+    } else {
+        size_t peer_max = ((size_t)reply[2] << 8) | reply[3];
+        size_t packet_size = peer_max < OBEX_MAX_PACKET ? peer_max : OBEX_MAX_PACKET;
+        if (packet_size < OBEX_MIN_PACKET) packet_size = OBEX_MIN_PACKET;
+
+        std::vector<unsigned char> chunk(packet_size);
+        unsigned long long sent = 0;
+        bool first = true;
+        for (;;) {
+            std::vector<unsigned char> request = { OBEX_PUT, 0, 0 };
+            if (first) {
+                request.push_back(OBEX_HI_NAME);
+                put_u16(request, 3 + (wide.size() + 1) * 2);
+                for (wchar_t c : wide) put_u16(request, (unsigned short)c);
+                put_u16(request, 0);
+                // The Length header is 32-bit; larger files go without it
+                if (size <= 0xFFFFFFFFull) {
+                    request.push_back(OBEX_HI_LENGTH);
+                    for (int shift = 24; shift >= 0; shift -= 8) request.push_back((unsigned char)((size >> shift) & 0xFF));
+                }
+            }
+            size_t room = packet_size - request.size() - 3;
+            size_t n = fread(chunk.data(), 1, room, file);
+            bool last = n < room || sent + n >= size;
+            request[0] = last ? OBEX_PUT_FINAL : OBEX_PUT;
+            request.push_back(last ? OBEX_HI_END_OF_BODY : OBEX_HI_BODY);
+            put_u16(request, n + 3);
+            request.insert(request.end(), chunk.begin(), chunk.begin() + n);
+
+            unsigned char code = obex_request(s, request);
+            if (code != (last ? OBEX_OK : OBEX_CONTINUE)) {
+                char text[64];
+                snprintf(text, sizeof(text), "The device refused the file (OBEX 0x%02X)", code);
+                error = code == 0 ? "The link dropped during the transfer" : text;
+                break;
+            }
+            sent += n;
+            first = false;
+            if (last) {
+                ok = true;
+                break;
+            }
+            if (!on_progress(sent, size)) {
+                obex_request(s, { OBEX_ABORT, 0, 0 });
+                error = "Transfer cancelled";
+                break;
+            }
+        }
+        obex_request(s, { OBEX_DISCONNECT, 0, 0 });
+    }
+
+    fclose(file);
+    closesocket(s);
+    WSACleanup();
+    return ok;
+}
@@ -0,0 +1,101 @@
+#include "SignalMonitor.h"
+#include <chrono>
+#include <cstdio>
+#include <map>
+
+#include <winrt/Windows.Foundation.h>
+#include <winrt/Windows.Devices.Bluetooth.h>
+#include <winrt/Windows.Devices.Bluetooth.Advertisement.h>
+
+#pragma comment(lib, "WindowsApp.lib")
+
+using namespace winrt::Windows::Devices::Bluetooth;
+using namespace winrt::Windows::Devices::Bluetooth::Advertisement;
+
+// Readings of one device closer together than this are dropped
+static const auto REPORT_INTERVAL = std::chrono::seconds(1);
+
+// WinRT calls need the calling thread in an apartment; FFI calls arrive on whatever thread
+static void ensure_apartment() {
+    thread_local bool initialized = false;
+    if (!initialized) {
+        try {
+            winrt::init_apartment(winrt::apartment_type::multi_threaded);
+        } catch (const winrt::hresult_error&) {
+            // Already initialized by someone else, possibly single-threaded; WinRT copes
+        }
+        initialized = true;
+    }
+}
+
+SignalMonitor::~SignalMonitor() {
+    Stop();
+}
+
+bool SignalMonitor::Supported() {
+    ensure_apartment();
+    try {
+        auto adapter = BluetoothAdapter::GetDefaultAsync().get();
+        return adapter && adapter.IsLowEnergySupported();
+    } catch (const winrt::hresult_error&) {
+        return false;
+    }
+}
+
+bool SignalMonitor::Start(Handler on_signal, std::string& error) {
+    std::lock_guard<std::mutex> lock(mutex_);
+    if (running_) return true;
+    ensure_apartment();
+    // Last report per address, for throttling; advertisements come many times a second
+    auto reported = std::make_shared<std::map<unsigned long long, std::chrono::steady_clock::time_point>>();
+    auto reported_mutex = std::make_shared<std::mutex>();
+    try {
+        auto watcher = std::make_shared<BluetoothLEAdvertisementWatcher>();
+        // Passive: only listens, so it costs little and doesn't wake devices with scan requests
+        watcher->ScanningMode(BluetoothLEScanningMode::Passive);
+        watcher->Received([on_signal, reported, reported_mutex](const BluetoothLEAdvertisementWatcher&,
+                                                                 const BluetoothLEAdvertisementReceivedEventArgs& args) {
+            unsigned long long address = args.BluetoothAddress();
+            auto now = std::chrono::steady_clock::now();
+            {
+                std::lock_guard<std::mutex> lock(*reported_mutex);
+                auto last = reported->find(address);
+                if (last != reported->end() && now - last->second < REPORT_INTERVAL) return;
+                (*reported)[address] = now;
+            }
+            on_signal(address, args.RawSignalStrengthInDBm());
+        });
+        watcher->Start();
+        watcher_ = watcher;
+    } catch (const winrt::hresult_error& e) {
+        error = "Failed to start the signal monitor: " + winrt::to_string(e.message());
+        return false;
+    }
+    running_ = true;
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Signal monitor started\n");
+        fclose(log);
+    }
+    return true;
+}
+
+void SignalMonitor::Stop() {
+    std::lock_guard<std::mutex> lock(mutex_);
+    if (!running_) return;
+    running_ = false;
+    auto watcher = std::static_pointer_cast<BluetoothLEAdvertisementWatcher>(watcher_);
+    try {
+        watcher->Stop();
+    } catch (const winrt::hresult_error&) {
+        // Already stopped, e.g. the radio went off
+    }
+    watcher_.reset();
+
+    FILE* log = fopen("bt_debug_mgr_v2.txt", "a");
+    if (log) {
+        fprintf(log, "[INFO] Signal monitor stopped\n");
+        fclose(log);
+    }
+}
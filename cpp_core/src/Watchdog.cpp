@@ -2,7 +2,8 @@
 #include <chrono>
 #include <iostream>
 
-Watchdog::Watchdog(ConnectionPool* pool) : pool_(pool), running_(false) {}
+Watchdog::Watchdog(ConnectionPool* pool, std::function<void(unsigned long long, bool)> on_change)
+    : pool_(pool), on_change_(on_change), running_(false) {}
 
 Watchdog::~Watchdog() {
     Stop();
@@ -21,10 +22,23 @@ void Watchdog::Stop() {
 
 void Watchdog::Loop() {
     while (running_) {
-        // Here we would iterate over desired connections in the pool and check their status
-        // For demonstration, we just log. 
-        // Real implementation would access pool_->GetTargetDevices() and check if IsConnected() is true.
-        // If not, trigger pool_->ConnectDevice().
+        // Report links the OS has dropped (or restored) since the last pass.
+        // Reconnection policy is left to the caller.
+        if (pool_) {
+            for (unsigned long long address : pool_->GetActiveAddresses()) {
+                BLUETOOTH_ADDRESS addr;
+                addr.ullLong = address;
+                bool connected = pool_->IsConnected(addr);
+
+                auto it = last_state_.find(address);
+                bool changed = (it == last_state_.end()) ? !connected : (it->second != connected);
+                last_state_[address] = connected;
+
+                if (changed && on_change_) {
+                    on_change_(address, connected);
+                }
+            }
+        }
         
         // Sleep for 500ms (recovery requirement)
         std::this_thread::sleep_for(std::chrono::milliseconds(500));
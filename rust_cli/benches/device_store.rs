@@ -0,0 +1,107 @@
+//! Device store under heavy discovery traffic: 1,000 devices each reporting
+//! 50 times a second, drained once per 60 Hz frame like the GUI does.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use std::sync::mpsc;
+
+// The app is a binary crate, so pull in the modules under test directly
+#[allow(dead_code)]
+#[path = "../src/device.rs"]
+mod device;
+#[allow(dead_code)]
+#[path = "../src/device_store.rs"]
+mod device_store;
+
+use device::{BluetoothDevice, ConnectionState};
+use device_store::DeviceStore;
+
+const DEVICES: u64 = 1_000;
+const EVENTS_PER_DEVICE_PER_SEC: u64 = 50;
+const FRAMES_PER_SEC: u64 = 60;
+const EVENTS_PER_FRAME: u64 = DEVICES * EVENTS_PER_DEVICE_PER_SEC / FRAMES_PER_SEC;
+
+fn device(address: u64, rssi: i32) -> BluetoothDevice {
+    BluetoothDevice {
+        address,
+        name: format!("Device {}", address),
+        state: ConnectionState::Disconnected,
+        authenticated: false,
+        rssi,
+        cod: 0x240404,
+    }
+}
+
+fn populated_store() -> DeviceStore {
+    let mut store = DeviceStore::default();
+    for address in 0..DEVICES {
+        store.upsert(device(address, -60));
+    }
+    store
+}
+
+/// One frame's worth of reports. `jitter` makes RSSI change (a real update);
+/// without it every report repeats what the store already has.
+fn frame_events(frame: u64, jitter: bool) -> Vec<BluetoothDevice> {
+    (0..EVENTS_PER_FRAME)
+        .map(|i| {
+            let address = (frame * EVENTS_PER_FRAME + i) % DEVICES;
+            let rssi = if jitter { -60 - ((frame + i) % 10) as i32 } else { -60 };
+            device(address, rssi)
+        })
+        .collect()
+}
+
+fn bench_frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("discovery_frame");
+
+    for (label, jitter) in [("changing_rssi", true), ("repeated_reports", false)] {
+        group.bench_function(label, |b| {
+            let mut frame = 0;
+            b.iter_batched(
+                || {
+                    frame += 1;
+                    (populated_store(), frame_events(frame, jitter))
+                },
+                |(mut store, events)| {
+                    // Same path as the scanner callback: send over a channel, drain, upsert, snapshot
+                    let (tx, rx) = mpsc::channel();
+                    for event in events {
+                        tx.send(event).unwrap();
+                    }
+                    for event in rx.try_iter() {
+                        store.upsert(event);
+                    }
+                    black_box(store.snapshot());
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot");
+
+    group.bench_function("clean", |b| {
+        let mut store = populated_store();
+        store.snapshot();
+        b.iter(|| black_box(store.snapshot()));
+    });
+
+    group.bench_function("after_one_update", |b| {
+        let mut store = populated_store();
+        let mut rssi = -60;
+        b.iter(|| {
+            rssi = if rssi == -60 { -61 } else { -60 };
+            store.update(0, |d| d.rssi = rssi);
+            black_box(store.snapshot())
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_frames, bench_snapshot);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+//! The activity feed: what happened this session, in words, newest first.
+//! Filled from the event bus as the GUI drains it, so it shows the same
+//! connects, failures and discoveries the handlers act on.
+
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+
+/// Entries kept; older ones are dropped
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub at: DateTime<Local>,
+    pub text: String,
+    /// The device it is about, for click-through to its details
+    pub address: Option<u64>,
+    pub failed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ActivityFeed {
+    entries: VecDeque<Entry>,
+}
+
+impl ActivityFeed {
+    pub fn push(&mut self, address: Option<u64>, text: impl Into<String>) {
+        self.add(address, text.into(), false);
+    }
+
+    pub fn push_failure(&mut self, address: Option<u64>, text: impl Into<String>) {
+        self.add(address, text.into(), true);
+    }
+
+    fn add(&mut self, address: Option<u64>, text: String, failed: bool) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_back();
+        }
+        self.entries.push_front(Entry { at: Local::now(), text, address, failed });
+    }
+
+    /// Newest first.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
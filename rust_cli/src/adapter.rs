@@ -0,0 +1,65 @@
+/// The local Bluetooth adapter as reported by the core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adapter {
+    pub address: u64,
+    pub name: String,
+    /// Bluetooth SIG company identifier
+    pub manufacturer: u16,
+    pub hci_version: u8,
+    pub hci_revision: u16,
+    pub lmp_version: u8,
+    /// Vendor-specific; changes with firmware updates
+    pub lmp_subversion: u16,
+}
+
+impl Adapter {
+    /// Core specification version matching the LMP version, e.g. "5.1".
+    pub fn core_version(&self) -> &'static str {
+        match self.lmp_version {
+            0 => "1.0b",
+            1 => "1.1",
+            2 => "1.2",
+            3 => "2.0 + EDR",
+            4 => "2.1 + EDR",
+            5 => "3.0 + HS",
+            6 => "4.0",
+            7 => "4.1",
+            8 => "4.2",
+            9 => "5.0",
+            10 => "5.1",
+            11 => "5.2",
+            12 => "5.3",
+            13 => "5.4",
+            _ => "newer than 5.4",
+        }
+    }
+
+    /// Vendor name for the chipsets commonly found in PCs and dongles.
+    pub fn manufacturer_name(&self) -> Option<&'static str> {
+        Some(match self.manufacturer {
+            2 => "Intel",
+            6 => "Microsoft",
+            10 => "Qualcomm (CSR)",
+            13 => "Texas Instruments",
+            15 => "Broadcom",
+            29 => "Qualcomm",
+            70 => "MediaTek",
+            72 => "Marvell",
+            93 => "Realtek",
+            _ => return None,
+        })
+    }
+
+    /// Manufacturer name, falling back to the raw company identifier.
+    pub fn manufacturer_label(&self) -> String {
+        match self.manufacturer_name() {
+            Some(name) => name.to_string(),
+            None => format!("Company ID {}", self.manufacturer),
+        }
+    }
+
+    /// Firmware identification: HCI revision and LMP subversion are what vendors bump.
+    pub fn firmware(&self) -> String {
+        format!("HCI rev {:#06x}, LMP subversion {:#06x}", self.hci_revision, self.lmp_subversion)
+    }
+}
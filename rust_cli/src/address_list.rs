@@ -0,0 +1,60 @@
+use crate::error::Result;
+use std::fs;
+use std::path::Path;
+
+/// `AA:BB:CC:DD:EE:FF`, the form most exported lists use.
+pub fn format_mac(address: u64) -> String {
+    let hex = format!("{:012X}", address);
+    (0..6).map(|i| &hex[i * 2..i * 2 + 2]).collect::<Vec<_>>().join(":")
+}
+
+/// A full 12-digit address with `:`/`-` separators or none. Stricter than
+/// `config::parse_address` so CSV columns like counts aren't mistaken for one.
+fn parse_mac(field: &str) -> Option<u64> {
+    let hex: String = field
+        .trim()
+        .trim_matches('"')
+        .chars()
+        .filter(|c| *c != ':' && *c != '-')
+        .collect();
+    if hex.len() != 12 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u64::from_str_radix(&hex, 16).ok()
+}
+
+/// Reads one address per line, or CSV rows where the first field that is an
+/// address is taken. Headers, comments and other lines without an address are
+/// skipped and counted. Duplicates are dropped, keeping the first.
+pub fn parse(text: &str) -> (Vec<u64>, usize) {
+    let mut addresses = Vec::new();
+    let mut skipped = 0;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split([',', ';', '\t']).find_map(parse_mac) {
+            Some(address) if !addresses.contains(&address) => addresses.push(address),
+            Some(_) => {}
+            None => skipped += 1,
+        }
+    }
+    (addresses, skipped)
+}
+
+pub fn read(path: &Path) -> Result<(Vec<u64>, usize)> {
+    Ok(parse(&fs::read_to_string(path)?))
+}
+
+/// Writes CSV with an `address` header if the file name ends in `.csv`,
+/// otherwise one address per line.
+pub fn write(path: &Path, addresses: &[u64]) -> Result<()> {
+    let csv = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    let mut text = String::from(if csv { "address\n" } else { "" });
+    for address in addresses {
+        text.push_str(&format_mac(*address));
+        text.push('\n');
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
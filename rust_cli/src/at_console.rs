@@ -0,0 +1,106 @@
+use crate::bluetooth::{self, BluetoothEvent};
+use chrono::{DateTime, Local};
+use std::thread;
+
+/// Commands offered from the console's menu, with what they ask for
+pub const CANNED: &[(&str, &str)] = &[
+    ("AT", "Is anyone listening"),
+    ("ATI", "Identification"),
+    ("AT+CGMI", "Manufacturer"),
+    ("AT+CGMM", "Model"),
+    ("AT+CGMR", "Firmware revision"),
+    ("AT+CIND=?", "Supported indicators (HFP)"),
+    ("AT+CIND?", "Indicator values (HFP)"),
+    ("AT+BRSF=0", "Supported features (HFP)"),
+    ("AT+CSQ", "Signal quality (modems)"),
+];
+
+/// Commands remembered for recall with the arrow keys
+const RECALL_LEN: usize = 50;
+
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub at: DateTime<Local>,
+    pub command: String,
+    pub reply: Result<String, String>,
+}
+
+/// Raw AT command session with one device over RFCOMM.
+///
+/// Meant for poking at vendor extensions; nothing here knows what a command does.
+#[derive(Debug)]
+pub struct AtConsole {
+    pub address: u64,
+    /// 0 finds the Serial Port service through SDP
+    pub channel: u8,
+    pub input: String,
+    /// Sent and not yet answered; one command at a time
+    pub pending: Option<String>,
+    pub transcript: Vec<Exchange>,
+    recall: Vec<String>,
+    recall_pos: Option<usize>,
+}
+
+impl AtConsole {
+    pub fn new(address: u64) -> Self {
+        AtConsole {
+            address,
+            channel: 0,
+            input: String::new(),
+            pending: None,
+            transcript: Vec::new(),
+            recall: Vec::new(),
+            recall_pos: None,
+        }
+    }
+
+    /// Sends `command` on a worker thread; the reply comes back as `BluetoothEvent::AtReply`.
+    pub fn send(&mut self, command: &str) {
+        let command = command.trim().to_string();
+        if command.is_empty() || self.pending.is_some() {
+            return;
+        }
+        self.recall.retain(|c| *c != command);
+        self.recall.push(command.clone());
+        if self.recall.len() > RECALL_LEN {
+            self.recall.remove(0);
+        }
+        self.recall_pos = None;
+        self.input.clear();
+        self.pending = Some(command.clone());
+
+        let (address, channel) = (self.address, self.channel);
+        thread::spawn(move || {
+            let reply = bluetooth::send_at_command(address, channel, &command).map_err(|e| e.to_string());
+            bluetooth::send_event(BluetoothEvent::AtReply { address, command, reply });
+        });
+    }
+
+    pub fn answered(&mut self, command: String, reply: Result<String, String>) {
+        self.pending = None;
+        self.transcript.push(Exchange { at: Local::now(), command, reply });
+    }
+
+    /// Steps back through sent commands, like a shell's up arrow.
+    pub fn recall_previous(&mut self) {
+        if self.recall.is_empty() {
+            return;
+        }
+        let pos = self.recall_pos.map_or(self.recall.len() - 1, |p| p.saturating_sub(1));
+        self.recall_pos = Some(pos);
+        self.input = self.recall[pos].clone();
+    }
+
+    pub fn recall_next(&mut self) {
+        let Some(pos) = self.recall_pos else {
+            return;
+        };
+        if pos + 1 < self.recall.len() {
+            self.recall_pos = Some(pos + 1);
+            self.input = self.recall[pos + 1].clone();
+        } else {
+            self.recall_pos = None;
+            self.input.clear();
+        }
+    }
+}
@@ -0,0 +1,545 @@
+use crate::bluetooth;
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+#[cfg(feature = "audio-routing")]
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::time::Instant;
+
+// ---- Configuration ----
+
+/// A named set of sinks to broadcast to. Entries are saved device names or `tag:<name>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AudioProfile {
+    pub name: String,
+    pub sinks: Vec<String>,
+    /// Sink whose loss triggers failover. Must be one of `sinks`.
+    #[serde(default)]
+    pub primary: Option<String>,
+    /// Sink brought in automatically when the primary drops mid-stream.
+    #[serde(default)]
+    pub backup: Option<String>,
+    /// Sinks that start muted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub muted: Vec<String>,
+    /// Sink that starts soloed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solo: Option<String>,
+    /// Used instead of the global app routes while this profile plays
+    #[cfg(feature = "audio-routing")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub app_routes: Vec<AppRoute>,
+    /// Starts the profile while the window has focus, e.g. `Ctrl+Alt+1`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub hotkey: String,
+    /// Favours latency over robustness, e.g. for gaming headsets: see `EngineSettings::low_latency`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_latency: bool,
+}
+
+/// Process name used for the catch-all routing rule.
+#[cfg(feature = "audio-routing")]
+pub const ROUTE_ALL_OTHERS: &str = "*";
+
+/// Sends audio from one application to one sink, e.g. `Spotify.exe` -> "Speaker".
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AppRoute {
+    /// Executable name, or `*` for every process without its own rule
+    pub process: String,
+    /// Saved device name or `tag:<name>`
+    pub sink: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResamplerQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl ResamplerQuality {
+    fn to_raw(self) -> c_int {
+        match self {
+            ResamplerQuality::Low => ffi::RESAMPLER_LOW,
+            ResamplerQuality::Medium => ffi::RESAMPLER_MEDIUM,
+            ResamplerQuality::High => ffi::RESAMPLER_HIGH,
+        }
+    }
+}
+
+pub const SAMPLE_RATES: [u32; 3] = [44100, 48000, 96000];
+
+/// Engine tuning pushed to the core before each `audio_start`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct EngineSettings {
+    /// Render buffer length; larger survives radio hiccups at the cost of latency
+    pub buffer_ms: u32,
+    pub resampler_quality: ResamplerQuality,
+    pub sample_rate: u32,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            buffer_ms: 100,
+            resampler_quality: ResamplerQuality::Medium,
+            sample_rate: 48000,
+        }
+    }
+}
+
+/// Render buffer used by low-latency profiles, unless the configured one is shorter
+pub const LOW_LATENCY_BUFFER_MS: u32 = 20;
+
+impl EngineSettings {
+    /// These settings with the shortest buffer and the cheapest resampler, whose
+    /// shorter filter adds less delay. The sample rate is kept so the sinks don't
+    /// have to renegotiate.
+    pub fn low_latency(&self) -> EngineSettings {
+        EngineSettings {
+            buffer_ms: self.buffer_ms.min(LOW_LATENCY_BUFFER_MS),
+            resampler_quality: ResamplerQuality::Low,
+            sample_rate: self.sample_rate,
+        }
+    }
+}
+
+// ---- FFI Wrappers ----
+
+pub fn init() -> Result<()> {
+    println!("CLI: Initializing Audio Engine...");
+    match ffi_call!(audio_init(bluetooth::on_error)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Whether the core's engine honours the buffer, resampler and sample rate settings.
+pub fn engine_settings_supported() -> bool {
+    ffi_call!(audio_engine_settings_supported())
+}
+
+/// Pushes `settings` to the core; a no-op while the engine can't use them.
+pub fn apply_engine_settings(settings: &EngineSettings) -> Result<()> {
+    if !engine_settings_supported() {
+        return Ok(());
+    }
+    let results = [
+        ffi_call!(audio_set_buffer_ms(settings.buffer_ms)),
+        ffi_call!(audio_set_resampler_quality(settings.resampler_quality.to_raw())),
+        ffi_call!(audio_set_sample_rate(settings.sample_rate)),
+    ];
+    match results.iter().find(|r| **r != ffi::FfiErrorCode::Success) {
+        None => Ok(()),
+        Some(ffi::FfiErrorCode::NotSupported) => Err(AppError::NotSupported(last_error())),
+        Some(_) => Err(AppError::audio(&last_error())),
+    }
+}
+
+pub fn start() -> Result<()> {
+    println!("CLI: Action -> Audio Start");
+    match ffi_call!(audio_start()) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+pub fn stop() -> Result<()> {
+    println!("CLI: Action -> Audio Stop");
+    match ffi_call!(audio_stop()) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+pub fn add_sink(address: u64) -> Result<()> {
+    println!("CLI: Action -> Add Audio Sink {:X}", address);
+    match ffi_call!(audio_add_device(address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+pub fn remove_sink(address: u64) -> Result<()> {
+    println!("CLI: Action -> Remove Audio Sink {:X}", address);
+    match ffi_call!(audio_remove_device(address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Silences one sink without removing it from the stream.
+pub fn set_sink_muted(address: u64, muted: bool) -> Result<()> {
+    println!("CLI: Action -> {} Audio Sink {:X}", if muted { "Mute" } else { "Unmute" }, address);
+    match ffi_call!(audio_set_sink_muted(address, muted)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(last_error())),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Whether the core can cross-fade between sinks at all.
+pub fn crossfade_supported() -> bool {
+    ffi_call!(audio_crossfade_supported())
+}
+
+/// Fades `from` out and `to` in over `duration_ms`, then drops `from` from the stream.
+/// Both must be sinks already.
+pub fn crossfade(from: u64, to: u64, duration_ms: u32) -> Result<()> {
+    println!("CLI: Action -> Cross-fade {:X} -> {:X} ({} ms)", from, to, duration_ms);
+    match ffi_call!(audio_crossfade(from, to, duration_ms)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(last_error())),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Plays a left-then-right beep sequence on one sink to check channel mapping.
+pub fn play_test_tone(address: u64) -> Result<()> {
+    println!("CLI: Action -> Test Tone {:X}", address);
+    match ffi_call!(audio_play_test_tone(address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(last_error())),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Whether the core can route audio per application at all.
+#[cfg(feature = "audio-routing")]
+pub fn app_routes_supported() -> bool {
+    ffi_call!(audio_app_routes_supported())
+}
+
+#[cfg(feature = "audio-routing")]
+pub fn set_app_route(process: &str, address: u64) -> Result<()> {
+    println!("CLI: Action -> Route {} to {:X}", process, address);
+    let name = CString::new(process).map_err(|_| AppError::audio("Process name contains a NUL byte"))?;
+    match ffi_call!(audio_set_app_route(name.as_ptr(), address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(last_error())),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+#[cfg(feature = "audio-routing")]
+pub fn clear_app_routes() -> Result<()> {
+    match ffi_call!(audio_clear_app_routes()) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Current diagnostics for a sink, or `None` if the engine isn't streaming to it.
+pub fn sink_stats(address: u64) -> Result<Option<ffi::AudioSinkStats>> {
+    let mut stats = ffi::AudioSinkStats::default();
+    match ffi_call!(audio_get_sink_stats(address, &mut stats)) {
+        ffi::FfiErrorCode::Success => Ok(Some(stats)),
+        ffi::FfiErrorCode::DeviceNotFound => Ok(None),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(last_error())),
+        _ => Err(AppError::audio(&last_error())),
+    }
+}
+
+/// Why the last failed call on this thread failed. The core keeps errors per thread, so
+/// read it on the thread that made the call, before calling into the core again.
+pub fn last_error() -> String {
+    unsafe {
+        let ptr = ffi::audio_get_last_error();
+        if ptr.is_null() {
+            String::from("Unknown audio error")
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    }
+}
+
+// ---- Session State ----
+
+/// The profile currently being broadcast, resolved to addresses.
+#[derive(Debug, Clone)]
+pub struct AudioSession {
+    pub profile: String,
+    pub sinks: Vec<u64>,
+    pub primary: Option<u64>,
+    pub backup: Option<u64>,
+    /// Streaming halted temporarily (e.g. during a call) with sinks kept attached
+    pub paused: bool,
+    /// Sinks silenced from the mixer; they stay attached
+    pub muted: Vec<u64>,
+    /// The one sink left audible, overriding `muted` until unsoloed
+    pub solo: Option<u64>,
+    /// Routes applied at start, the profile's own or the global ones
+    #[cfg(feature = "audio-routing")]
+    pub app_routes: Vec<AppRoute>,
+    /// Started from a low-latency profile: short buffer, no cross-fades
+    pub low_latency: bool,
+    pub started: Instant,
+}
+
+impl AudioSession {
+    pub fn start(profile: &AudioProfile, config: &Config) -> Result<Self> {
+        let sinks = config.resolve_targets(&profile.sinks);
+        if sinks.is_empty() {
+            return Err(AppError::audio("Profile has no reachable sinks"));
+        }
+        let resolve_one = |target: &Option<String>| {
+            target
+                .as_ref()
+                .and_then(|t| config.resolve_targets(std::slice::from_ref(t)).first().copied())
+        };
+
+        info!("Starting audio profile '{}' with {} sinks", profile.name, sinks.len());
+        // The mode only changes engine settings, so it isn't claimed while the engine ignores them
+        let low_latency = profile.low_latency && engine_settings_supported();
+        if low_latency {
+            info!("Low-latency mode: {} ms buffer, no cross-fades", config.audio.engine.low_latency().buffer_ms);
+            apply_engine_settings(&config.audio.engine.low_latency())?;
+        } else {
+            if profile.low_latency {
+                warn!("Low-latency mode is not supported by the audio engine yet; starting normally");
+            }
+            apply_engine_settings(&config.audio.engine)?;
+        }
+        for address in &sinks {
+            add_sink(*address)?;
+        }
+        start()?;
+
+        let muted = config.resolve_targets(&profile.muted).into_iter().filter(|a| sinks.contains(a)).collect();
+        let mut session = AudioSession {
+            profile: profile.name.clone(),
+            primary: resolve_one(&profile.primary),
+            backup: resolve_one(&profile.backup),
+            paused: false,
+            muted,
+            solo: resolve_one(&profile.solo).filter(|a| sinks.contains(a)),
+            sinks,
+            #[cfg(feature = "audio-routing")]
+            app_routes: if profile.app_routes.is_empty() { config.app_routes.clone() } else { profile.app_routes.clone() },
+            low_latency,
+            started: Instant::now(),
+        };
+        if !session.muted.is_empty() || session.solo.is_some() {
+            match session.apply_mutes() {
+                Err(AppError::NotSupported(reason)) => {
+                    // Everything plays; the session shouldn't claim otherwise
+                    warn!("Ignoring the profile's mutes: {}", reason);
+                    session.muted.clear();
+                    session.solo = None;
+                }
+                result => result?,
+            }
+        }
+        #[cfg(feature = "audio-routing")]
+        session.apply_routes(&session.app_routes, config)?;
+        Ok(session)
+    }
+
+    #[cfg(feature = "audio-routing")]
+    /// Replaces the core's routing table with the rules whose sink is part of this session.
+    /// Where the core can't route, the rules are kept but nothing is applied.
+    pub fn apply_routes(&self, routes: &[AppRoute], config: &Config) -> Result<()> {
+        if !app_routes_supported() {
+            if !routes.is_empty() {
+                warn!("Not applying {} audio route(s): not supported by the audio engine", routes.len());
+            }
+            return Ok(());
+        }
+        clear_app_routes()?;
+        for route in routes {
+            let target = config
+                .resolve_targets(std::slice::from_ref(&route.sink))
+                .into_iter()
+                .find(|a| self.sinks.contains(a));
+            match target {
+                Some(address) => set_app_route(&route.process, address)?,
+                None => warn!("Skipping route {} -> {}: sink not in session", route.process, route.sink),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self) -> Result<()> {
+        if !self.paused {
+            stop()?;
+            self.paused = true;
+        }
+        Ok(())
+    }
+
+    pub fn resume(&mut self) -> Result<()> {
+        if self.paused {
+            start()?;
+            self.paused = false;
+        }
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        info!("Stopping audio profile '{}'", self.profile);
+        stop()?;
+        for address in self.sinks.drain(..) {
+            if let Err(e) = remove_sink(address) {
+                warn!("Failed to remove sink {:X}: {}", address, e);
+            }
+        }
+        self.muted.clear();
+        self.solo = None;
+        Ok(())
+    }
+
+    /// The session as it stands, mutes and routes included, as a profile called `name`.
+    /// Sinks are saved under their device names.
+    pub fn to_profile(&self, name: &str, config: &Config) -> AudioProfile {
+        let names = |addresses: &[u64]| -> Vec<String> {
+            addresses
+                .iter()
+                .filter_map(|a| config.devices.iter().find(|(_, addr)| *addr == a).map(|(n, _)| n.clone()))
+                .collect()
+        };
+        let one = |address: Option<u64>| names(address.as_slice()).pop();
+        AudioProfile {
+            name: name.to_string(),
+            sinks: names(&self.sinks),
+            primary: one(self.primary),
+            backup: one(self.backup),
+            muted: names(&self.muted),
+            solo: one(self.solo),
+            #[cfg(feature = "audio-routing")]
+            app_routes: self.app_routes.clone(),
+            hotkey: config.audio_profiles.iter().find(|p| p.name == name).map(|p| p.hotkey.clone()).unwrap_or_default(),
+            low_latency: self.low_latency,
+        }
+    }
+
+    pub fn is_audible(&self, address: u64) -> bool {
+        self.solo.map_or(!self.muted.contains(&address), |s| s == address)
+    }
+
+    /// On failure the mutes are left as they were.
+    pub fn toggle_mute(&mut self, address: u64) -> Result<()> {
+        let before = self.muted.clone();
+        if self.muted.contains(&address) {
+            self.muted.retain(|a| *a != address);
+        } else {
+            self.muted.push(address);
+        }
+        self.apply_mutes().inspect_err(|_| {
+            self.muted = before;
+            let _ = self.apply_mutes();
+        })
+    }
+
+    /// Soloing a sink silences all others; soloing it again brings back the mutes set before.
+    pub fn toggle_solo(&mut self, address: u64) -> Result<()> {
+        let before = self.solo;
+        self.solo = (self.solo != Some(address)).then_some(address);
+        self.apply_mutes().inspect_err(|_| {
+            self.solo = before;
+            let _ = self.apply_mutes();
+        })
+    }
+
+    fn apply_mutes(&self) -> Result<()> {
+        for address in &self.sinks {
+            set_sink_muted(*address, !self.is_audible(*address))?;
+        }
+        Ok(())
+    }
+
+    /// Drops a sink that left the stream, along with its mixer state.
+    fn forget_sink(&mut self, address: u64) {
+        self.sinks.retain(|a| *a != address);
+        self.muted.retain(|a| *a != address);
+        if self.solo == Some(address) {
+            self.solo = None;
+            if let Err(e) = self.apply_mutes() {
+                warn!("Failed to unmute sinks after solo sink {:X} left: {}", address, e);
+            }
+        }
+    }
+
+    /// Starts connecting the backup sink if `address` was the primary and dropped
+    /// without being asked to, and makes it the new primary. Returns it so the
+    /// caller can `attach_sink` once its endpoint is ready; the connect itself
+    /// finishes in the background.
+    pub fn handle_disconnect(&mut self, address: u64, requested: bool) -> Result<Option<u64>> {
+        if self.primary != Some(address) {
+            self.forget_sink(address);
+            return Ok(None);
+        }
+        if requested {
+            info!("Primary sink {:X} disconnected on request; not failing over", address);
+            let _ = remove_sink(address);
+            self.forget_sink(address);
+            self.primary = None;
+            return Ok(None);
+        }
+        let Some(backup) = self.backup.take() else {
+            warn!("Primary sink {:X} dropped with no backup configured", address);
+            self.forget_sink(address);
+            return Ok(None);
+        };
+
+        println!("CLI: Audio Failover {:X} -> {:X}", address, backup);
+        let _ = remove_sink(address);
+        self.forget_sink(address);
+
+        bluetooth::start_connect(backup)?;
+        self.primary = Some(backup);
+        Ok(Some(backup))
+    }
+
+    /// Moves the stream from `from` to `to`, whose endpoint must be ready. With
+    /// `fade_ms` above 0 the core cross-fades and drops `from` at the end; 0 cuts
+    /// over, as low-latency sessions and cores that can't fade always do.
+    pub fn move_sink(&mut self, from: u64, to: u64, fade_ms: u32) -> Result<()> {
+        self.attach_sink(to)?;
+        if fade_ms > 0 && !self.low_latency && crossfade_supported() {
+            crossfade(from, to, fade_ms)?;
+        } else {
+            remove_sink(from)?;
+        }
+        self.forget_sink(from);
+        if self.primary == Some(from) {
+            self.primary = Some(to);
+        }
+        if self.backup == Some(to) {
+            self.backup = None;
+        }
+        Ok(())
+    }
+
+    /// Takes a sink out of the stream before it is disconnected on purpose, so
+    /// the disconnect doesn't fail over to the backup.
+    pub fn drop_sink(&mut self, address: u64) -> Result<()> {
+        remove_sink(address)?;
+        self.forget_sink(address);
+        if self.primary == Some(address) {
+            self.primary = None;
+        }
+        Ok(())
+    }
+
+    /// Adds a sink to the running stream.
+    pub fn attach_sink(&mut self, address: u64) -> Result<()> {
+        add_sink(address)?;
+        if !self.sinks.contains(&address) {
+            self.sinks.push(address);
+        }
+        // A sink joining during a solo stays quiet until the solo ends
+        if !self.is_audible(address) {
+            set_sink_muted(address, true)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+//! Scheduled backups: config.toml and a snapshot of registry.db in a
+//! timestamped zip, written to a folder of the user's choosing (a synced
+//! drive works), with only the newest few kept.
+
+use crate::config;
+use crate::error::{AppError, Result};
+use crate::paths;
+use crate::registry::Registry;
+use crate::settings::BackupSettings;
+use log::{info, warn};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const FILE_PREFIX: &str = "redtooth-";
+const CONFIG_ENTRY: &str = "config.toml";
+const REGISTRY_ENTRY: &str = "registry.db";
+
+/// Where backups go: the configured folder, or `backups` in the data folder.
+pub fn folder(settings: &BackupSettings) -> PathBuf {
+    if settings.folder.trim().is_empty() {
+        paths::user_file("backups")
+    } else {
+        PathBuf::from(settings.folder.trim())
+    }
+}
+
+/// Backups in `dir`, newest first.
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut backups: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.extension().is_some_and(|ext| ext == "zip")
+                    && p.file_name().is_some_and(|n| n.to_string_lossy().starts_with(FILE_PREFIX))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // Names embed a sortable timestamp
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// The newest backup in `dir` is older than `interval`, or there is none.
+pub fn due(dir: &Path, interval: Duration) -> bool {
+    list(dir)
+        .first()
+        .and_then(|p| fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_none_or(|age| age >= interval)
+}
+
+/// Writes a backup into `dir` and deletes all but the newest `keep`. The
+/// registry is copied through its own connection, so this can run on a
+/// background thread while the GUI keeps using the database.
+pub fn create(dir: &Path, keep: usize, busy_timeout: Duration) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let path = dir.join(format!("{}{}.zip", FILE_PREFIX, stamp));
+    // Written under another name first, so a sync client never uploads half a zip
+    let partial = path.with_extension("zip.part");
+
+    let snapshot = std::env::temp_dir().join(format!("{}{}.db", FILE_PREFIX, stamp));
+    let registry = if Registry::path().exists() {
+        Registry::open(busy_timeout)?.snapshot(&snapshot)?;
+        Some(snapshot.as_path())
+    } else {
+        None
+    };
+    let config = Some(config::config_path()).filter(|p| p.exists());
+
+    let written = write_zip(&partial, config.as_deref(), registry).and_then(|()| fs::rename(&partial, &path).map_err(AppError::from));
+    let _ = fs::remove_file(&snapshot);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    info!("Backed up config and registry to {}", path.display());
+
+    for old in list(dir).into_iter().skip(keep) {
+        if let Err(e) = fs::remove_file(&old) {
+            warn!("Failed to remove old backup {:?}: {}", old, e);
+        }
+    }
+    Ok(path)
+}
+
+fn write_zip(path: &Path, config: Option<&Path>, registry: Option<&Path>) -> Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default();
+    for (entry, source) in [(CONFIG_ENTRY, config), (REGISTRY_ENTRY, registry)] {
+        let Some(source) = source else {
+            continue;
+        };
+        zip.start_file(entry, options).map_err(|e| AppError::Unknown(e.to_string()))?;
+        io::copy(&mut File::open(source)?, &mut zip)?;
+    }
+    zip.finish().map_err(|e| AppError::Unknown(e.to_string()))?.flush()?;
+    Ok(())
+}
+
+/// The files of a backup, unpacked to a temporary folder for restoring.
+pub struct Extracted {
+    pub config: Option<PathBuf>,
+    pub registry: Option<PathBuf>,
+}
+
+pub fn extract(path: &Path) -> Result<Extracted> {
+    let mut zip = ZipArchive::new(File::open(path)?).map_err(|e| AppError::Parse(format!("Not a backup zip: {}", e)))?;
+    let dir = std::env::temp_dir().join("redtooth-restore");
+    fs::create_dir_all(&dir)?;
+    let mut unpack = |entry: &str| -> Result<Option<PathBuf>> {
+        let Ok(mut file) = zip.by_name(entry) else {
+            return Ok(None);
+        };
+        let target = dir.join(entry);
+        io::copy(&mut file, &mut File::create(&target)?)?;
+        Ok(Some(target))
+    };
+    let extracted = Extracted { config: unpack(CONFIG_ENTRY)?, registry: unpack(REGISTRY_ENTRY)? };
+    if extracted.config.is_none() && extracted.registry.is_none() {
+        return Err(AppError::Parse(String::from("The zip holds neither config.toml nor registry.db")));
+    }
+    Ok(extracted)
+}
+
+/// Puts an extracted registry in place of registry.db. Every connection to it
+/// must be closed first; the replaced file is kept as `registry.db.bak`.
+pub fn restore_registry(snapshot: &Path) -> Result<()> {
+    let current = Registry::path();
+    if current.exists() {
+        fs::copy(&current, current.with_extension("db.bak"))?;
+    }
+    fs::copy(snapshot, &current)?;
+    info!("Restored registry from {}", snapshot.display());
+    Ok(())
+}
@@ -1,20 +1,52 @@
+pub use crate::device::{BluetoothDevice, ConnectionState, MajorClass, QuickAction};
+use crate::adapter::Adapter;
 use crate::error::{AppError, Result};
 use crate::ffi;
-use std::ffi::CStr;
+use crate::trace::ffi_call;
+#[cfg(feature = "gatt")]
+use crate::gatt;
+#[cfg(feature = "obex")]
+use crate::obex;
+use crate::pbap;
+#[cfg(feature = "scripting")]
+use crate::scripting;
+use crate::le_audio;
+use crate::plugins::NoiseMode;
+use crate::settings::ScanTuning;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{self, Receiver, Sender};
-use log::{error, info};
+use log::{error, info, warn};
+
+/// More local adapters than anyone plugs in
+const MAX_ADAPTERS: usize = 8;
 
 // ---- Data Structures ----
 
-#[derive(Clone, Debug)]
-pub struct BluetoothDevice {
-    pub address: u64,
-    pub name: String,
-    pub connected: bool,
-    pub authenticated: bool,
-    pub rssi: i32,
-    pub cod: u32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallState {
+    Idle,
+    Ringing,
+    Active,
+}
+
+impl CallState {
+    fn from_raw(value: std::os::raw::c_int) -> Self {
+        match value {
+            ffi::HFP_CALL_RINGING => CallState::Ringing,
+            ffi::HFP_CALL_ACTIVE => CallState::Active,
+            ffi::HFP_CALL_IDLE => CallState::Idle,
+            other => {
+                error!("Unknown HFP call state {}, treating as idle", other);
+                CallState::Idle
+            }
+        }
+    }
+
+    pub fn in_call(self) -> bool {
+        self != CallState::Idle
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +56,43 @@ pub enum BluetoothEvent {
     ScanStopped,
     Connected(u64),
     Disconnected(u64),
+    /// Any other link state change: in-flight transitions, failures and rollbacks
+    LinkState { address: u64, state: ConnectionState },
+    /// Outcome of `start_connect`; `bond_rejected` when the device refused the link key
+    ConnectFinished { address: u64, result: std::result::Result<(), String>, bond_rejected: bool },
+    DisconnectFinished { address: u64, result: std::result::Result<(), String> },
+    NameResolved(u64, String),
+    /// Signal strength from an LE advertisement, for devices in the list or not
+    Signal { address: u64, rssi: i32 },
+    CallStateChanged { address: u64, state: CallState, mic_muted: bool },
+    #[cfg(feature = "gatt")]
+    GattCharacteristicFound { address: u64, characteristic: gatt::Characteristic },
+    #[cfg(feature = "gatt")]
+    GattValue { address: u64, characteristic: u16, value: Vec<u8> },
+    #[cfg(feature = "obex")]
+    ObexIncoming(obex::Transfer),
+    #[cfg(feature = "obex")]
+    ObexProgress { id: u32, state: obex::TransferState, bytes_done: u64, bytes_total: u64 },
+    PhoneBookPulled { address: u64, folder: pbap::Folder, data: std::result::Result<String, String> },
+    /// `Ok` with why the saved recording ended, `Err` if no file was saved
+    RecordingFinished { path: String, result: std::result::Result<String, String> },
+    HciCaptureFinished { path: String, packets: u64, reason: String },
+    BroadcastFound(le_audio::BroadcastSource),
+    BroadcastJoinFinished { sink: u64, broadcast_id: u32, result: std::result::Result<(), String> },
+    PairingFinished { address: u64, result: std::result::Result<(), String> },
+    HandoverFinished { address: u64, result: std::result::Result<(), String> },
+    AtReply { address: u64, command: String, reply: std::result::Result<String, String> },
+    PluginFinished { address: u64, plugin: String, action: String, result: std::result::Result<String, String> },
+    NoiseControlSet { address: u64, mode: NoiseMode, result: std::result::Result<(), String> },
+    /// VACUUM on a second registry connection finished
+    RegistryCompacted { result: std::result::Result<(), String> },
+    /// A scheduled or manual backup zip was written, or failed
+    BackupFinished { result: std::result::Result<std::path::PathBuf, String> },
+    #[cfg(feature = "scripting")]
+    Script(scripting::ScriptEvent),
+    /// The system-wide quick connect hotkey was pressed
+    #[cfg_attr(not(windows), allow(dead_code))]
+    QuickConnectRequested,
     Error(String),
 }
 
@@ -32,27 +101,44 @@ pub enum BluetoothEvent {
 // This Mutex is ONLY for the Sender, not the data. It is locked extremely briefly.
 lazy_static::lazy_static! {
     static ref EVENT_SENDER: Mutex<Option<Sender<BluetoothEvent>>> = Mutex::new(None);
+    /// Devices the managed policy forbids connecting to
+    static ref BLOCKED_DEVICES: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+    /// Link state per device; absent means disconnected
+    static ref LINK_STATES: Mutex<HashMap<u64, ConnectionState>> = Mutex::new(HashMap::new());
+    /// Device -> Local adapter it should connect through
+    static ref PREFERRED_ADAPTERS: Mutex<HashMap<u64, u64>> = Mutex::new(HashMap::new());
+    /// Device -> Local adapter its last connect went through, when one was chosen
+    static ref CONNECTED_VIA: Mutex<HashMap<u64, u64>> = Mutex::new(HashMap::new());
 }
 
 // ---- FFI Callbacks ----
 
-extern "C" fn on_device_found(device: ffi::DiscoveredDevice) {
-    let name = unsafe {
-        if device.name.is_null() {
-            String::new()
-        } else {
-            CStr::from_ptr(device.name).to_string_lossy().into_owned()
-        }
+/// Copies a device reported by the core into an owned `BluetoothDevice`.
+/// Names that aren't valid UTF-8 are converted lossily.
+///
+/// # Safety
+/// `device.name` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call.
+unsafe fn device_from_ffi(device: &ffi::DiscoveredDevice) -> BluetoothDevice {
+    let name = if device.name.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(device.name).to_string_lossy().into_owned()
     };
 
-    let dev = BluetoothDevice {
+    BluetoothDevice {
         address: device.address,
-        name: name.clone(),
-        connected: device.connected,
+        name,
+        state: if device.connected { ConnectionState::Connected } else { ConnectionState::Disconnected },
         authenticated: device.authenticated,
         rssi: device.rssi,
         cod: device.cod,
-    };
+    }
+}
+
+extern "C" fn on_device_found(device: ffi::DiscoveredDevice) {
+    let mut dev = unsafe { device_from_ffi(&device) };
+    dev.state = report_link(dev.address, dev.state.is_connected());
 
     // CLI ECHO
     println!("CLI: Device Found: {} ({:X})", dev.name, dev.address);
@@ -60,7 +146,11 @@ extern "C" fn on_device_found(device: ffi::DiscoveredDevice) {
     send_event(BluetoothEvent::DeviceFound(dev));
 }
 
-extern "C" fn on_error(error_code: ffi::FfiErrorCode, message: *const std::os::raw::c_char) {
+extern "C" fn on_signal(address: u64, rssi: std::os::raw::c_int) {
+    send_event(BluetoothEvent::Signal { address, rssi });
+}
+
+pub(crate) extern "C" fn on_error(error_code: ffi::FfiErrorCode, message: *const std::os::raw::c_char) {
     let error_msg = unsafe {
         if message.is_null() {
             format!("Error code: {:?}", error_code)
@@ -76,7 +166,106 @@ extern "C" fn on_error(error_code: ffi::FfiErrorCode, message: *const std::os::r
     }
 }
 
-fn send_event(event: BluetoothEvent) {
+extern "C" fn on_remote_name(address: u64, name: *const std::os::raw::c_char) {
+    let name = unsafe {
+        if name.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(name).to_string_lossy().into_owned()
+        }
+    };
+
+    if name.is_empty() {
+        println!("CLI: Name request for {:X} returned nothing", address);
+        send_event(BluetoothEvent::Error(format!("Could not resolve name for {:X}", address)));
+    } else {
+        println!("CLI: Name Resolved: {} ({:X})", name, address);
+        send_event(BluetoothEvent::NameResolved(address, name));
+    }
+}
+
+extern "C" fn on_connection_changed(address: u64, connected: bool) {
+    // CLI ECHO
+    println!("CLI: Link {} for {:X}", if connected { "restored" } else { "lost" }, address);
+
+    set_link_state(address, if connected { ConnectionState::Connected } else { ConnectionState::Disconnected });
+    if connected {
+        send_event(BluetoothEvent::Connected(address));
+    } else {
+        send_event(BluetoothEvent::Disconnected(address));
+    }
+}
+
+extern "C" fn on_pairing_result(address: u64, result: ffi::FfiErrorCode) {
+    // CLI ECHO
+    println!("CLI: Pairing with {:X}: {:?}", address, result);
+
+    let result = match result {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::DeviceNotFound => Err(String::from("Device not found; is it still in pairing mode?")),
+        _ => Err(String::from("Pairing was refused or timed out")),
+    };
+    send_event(BluetoothEvent::PairingFinished { address, result });
+}
+
+extern "C" fn on_hfp_state(address: u64, call_state: std::os::raw::c_int, mic_muted: bool) {
+    let state = CallState::from_raw(call_state);
+
+    // CLI ECHO
+    println!("CLI: HFP {:X}: {:?} (mic {})", address, state, if mic_muted { "muted" } else { "live" });
+
+    send_event(BluetoothEvent::CallStateChanged { address, state, mic_muted });
+}
+
+#[cfg(feature = "gatt")]
+extern "C" fn on_gatt_value(address: u64, characteristic_uuid: u16, value: *const u8, length: u32) {
+    let value = if value.is_null() {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(value, length as usize) }.to_vec()
+    };
+
+    send_event(BluetoothEvent::GattValue { address, characteristic: characteristic_uuid, value });
+}
+
+fn set_link_state(address: u64, state: ConnectionState) {
+    let mut states = LINK_STATES.lock().unwrap();
+    if state == ConnectionState::Disconnected {
+        states.remove(&address);
+    } else {
+        states.insert(address, state);
+    }
+}
+
+fn report_link(address: u64, connected: bool) -> ConnectionState {
+    let mut states = LINK_STATES.lock().unwrap();
+    let state = states.get(&address).cloned().unwrap_or_default().with_report(connected);
+    if state == ConnectionState::Disconnected {
+        states.remove(&address);
+    } else {
+        states.insert(address, state.clone());
+    }
+    state
+}
+
+/// Moves a device into `next` unless a connect or disconnect is already in flight.
+fn begin_transition(address: u64, action: &'static str, next: ConnectionState) -> Result<()> {
+    {
+        let mut states = LINK_STATES.lock().unwrap();
+        if let Some(state) = states.get(&address).filter(|s| s.in_transition()) {
+            return Err(AppError::InvalidState { address, action, state: state.clone() });
+        }
+        states.insert(address, next.clone());
+    }
+    send_event(BluetoothEvent::LinkState { address, state: next });
+    Ok(())
+}
+
+pub fn link_state(address: u64) -> ConnectionState {
+    LINK_STATES.lock().unwrap().get(&address).cloned().unwrap_or_default()
+}
+
+pub(crate) fn send_event(event: BluetoothEvent) {
     if let Ok(guard) = EVENT_SENDER.lock() {
         if let Some(sender) = &*guard {
             let _ = sender.send(event);
@@ -100,12 +289,40 @@ pub fn init() -> Result<Receiver<BluetoothEvent>> {
         *guard = Some(tx);
     }
 
-    let result = unsafe { ffi::bt_init(on_error) };
+    init_core()?;
+    Ok(rx)
+}
+
+/// Re-creates the core's scanner and connection pool, e.g. when scanning has
+/// stalled. Events keep arriving on the receiver returned by `init`.
+pub fn reinit() -> Result<()> {
+    println!("CLI: Action -> Reinitialize Bluetooth");
+    warn!("Reinitializing the Bluetooth core");
+    let _ = ffi_call!(bt_stop_scan());
+    init_core()
+}
+
+fn init_core() -> Result<()> {
+    let result = ffi_call!(bt_init(on_error));
     
     match result {
         ffi::FfiErrorCode::Success => {
             println!("CLI: Bluetooth Initialized Successfully.");
-            Ok(rx)
+            if ffi_call!(bt_set_connection_callback(on_connection_changed)) != ffi::FfiErrorCode::Success {
+                error!("Failed to register connection callback; link drops won't be reported");
+            }
+            if ffi_call!(bt_set_hfp_callback(on_hfp_state)) != ffi::FfiErrorCode::Success {
+                error!("Failed to register HFP callback; call state won't be reported");
+            }
+            #[cfg(feature = "gatt")]
+            if ffi_call!(bt_gatt_set_value_callback(on_gatt_value)) != ffi::FfiErrorCode::Success {
+                error!("Failed to register GATT callback; sensor values won't be reported");
+            }
+            #[cfg(feature = "obex")]
+            if ffi_call!(bt_obex_set_callbacks(obex::on_progress, obex::on_incoming)) != ffi::FfiErrorCode::Success {
+                error!("Failed to register OBEX callbacks; file transfers won't be reported");
+            }
+            Ok(())
         }
         _ => {
             println!("CLI: Failed to Initialize Bluetooth.");
@@ -116,7 +333,7 @@ pub fn init() -> Result<Receiver<BluetoothEvent>> {
 
 pub fn start_scan() -> Result<()> {
     println!("CLI: Action -> Start Scan");
-    let result = unsafe { ffi::bt_start_scan(on_device_found, on_error) };
+    let result = ffi_call!(bt_start_scan(on_device_found, on_error));
     if result == ffi::FfiErrorCode::Success {
         send_event(BluetoothEvent::ScanStarted);
         Ok(())
@@ -125,9 +342,29 @@ pub fn start_scan() -> Result<()> {
     }
 }
 
+/// Whether live signal readings are available: inquiry results carry no RSSI,
+/// so they come from LE advertisements, which need an adapter with Bluetooth LE.
+pub fn signal_supported() -> bool {
+    ffi_call!(bt_signal_monitor_supported())
+}
+
+/// Readings arrive as `BluetoothEvent::Signal`, about once a second per device.
+pub fn start_signal_monitor() -> Result<()> {
+    println!("CLI: Action -> Start Signal Monitor");
+    match ffi_call!(bt_signal_monitor_start(on_signal as ffi::OnSignalCallback)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&last_error())),
+    }
+}
+
+pub fn stop_signal_monitor() {
+    println!("CLI: Action -> Stop Signal Monitor");
+    ffi_call!(bt_signal_monitor_stop());
+}
+
 pub fn stop_scan() -> Result<()> {
     println!("CLI: Action -> Stop Scan");
-    let result = unsafe { ffi::bt_stop_scan() };
+    let result = ffi_call!(bt_stop_scan());
     if result == ffi::FfiErrorCode::Success {
         send_event(BluetoothEvent::ScanStopped);
         Ok(())
@@ -136,34 +373,405 @@ pub fn stop_scan() -> Result<()> {
     }
 }
 
+/// Takes effect on the next scan cycle; values are clamped to what the core accepts.
+pub fn set_scan_tuning(tuning: &ScanTuning) -> Result<()> {
+    let tuning = tuning.clamped();
+    println!("CLI: Action -> Scan Tuning (inquiry {}, gap {} ms)", tuning.inquiry_length, tuning.cycle_gap_ms);
+    let raw = ffi::ScanTuning { inquiry_length: tuning.inquiry_length, cycle_gap_ms: tuning.cycle_gap_ms };
+    match ffi_call!(bt_set_scan_tuning(&raw as *const ffi::ScanTuning)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&last_error())),
+    }
+}
+
+pub fn set_low_power_scan(enabled: bool) -> Result<()> {
+    println!("CLI: Action -> Low-Power Scan {}", if enabled { "On" } else { "Off" });
+    let result = ffi_call!(bt_set_low_power_scan(enabled));
+    if result == ffi::FfiErrorCode::Success {
+        Ok(())
+    } else {
+        Err(AppError::bluetooth("Failed to change scan power mode"))
+    }
+}
+
+pub fn set_blocked_devices(addresses: Vec<u64>) {
+    *BLOCKED_DEVICES.lock().unwrap() = addresses;
+}
+
+pub fn is_blocked(address: u64) -> bool {
+    BLOCKED_DEVICES.lock().unwrap().contains(&address)
+}
+
+pub fn set_preferred_adapters(preferences: HashMap<u64, u64>) {
+    *PREFERRED_ADAPTERS.lock().unwrap() = preferences;
+}
+
+/// The local adapter the device's last connect went through, if it had a preference
+/// and that adapter was present.
+pub fn connected_via(address: u64) -> Option<u64> {
+    CONNECTED_VIA.lock().unwrap().get(&address).copied()
+}
+
+/// The device's preferred adapter, if it is plugged in.
+fn present_preferred_adapter(address: u64) -> Option<u64> {
+    let preferred = PREFERRED_ADAPTERS.lock().unwrap().get(&address).copied()?;
+    match adapters() {
+        Ok(present) if present.iter().any(|a| a.address == preferred) => Some(preferred),
+        Ok(_) => {
+            warn!("Preferred adapter {:X} of {:X} is not present, using the default", preferred, address);
+            None
+        }
+        Err(e) => {
+            warn!("Failed to list adapters: {}", e);
+            None
+        }
+    }
+}
+
 pub fn connect(address: u64) -> Result<()> {
+    begin_connect(address)?;
+    finish_connect(address)
+}
+
+/// `connect` for the GUI thread. Refusals come back at once; the page itself, which
+/// can take the full page timeout, runs on a worker thread and reports its outcome
+/// as `BluetoothEvent::ConnectFinished` after the link state events.
+pub fn start_connect(address: u64) -> Result<()> {
+    begin_connect(address)?;
+    std::thread::spawn(move || {
+        let result = finish_connect(address);
+        let bond_rejected = matches!(result, Err(AppError::Pairing(_)));
+        send_event(BluetoothEvent::ConnectFinished { address, result: result.map_err(|e| e.to_string()), bond_rejected });
+    });
+    Ok(())
+}
+
+fn begin_connect(address: u64) -> Result<()> {
     println!("CLI: Action -> Connect to {:X}", address);
-    let result = unsafe { ffi::bt_connect_device(address) };
-    match result {
+    if is_blocked(address) {
+        return Err(AppError::bluetooth("This device is blocked by your organization's policy"));
+    }
+    begin_transition(address, "connect", ConnectionState::Connecting)
+}
+
+fn finish_connect(address: u64) -> Result<()> {
+    let via = present_preferred_adapter(address);
+    match via {
+        Some(adapter) => CONNECTED_VIA.lock().unwrap().insert(address, adapter),
+        None => CONNECTED_VIA.lock().unwrap().remove(&address),
+    };
+    let result = match via {
+        Some(adapter) => ffi_call!(bt_connect_device_via(address, adapter)),
+        None => ffi_call!(bt_connect_device(address)),
+    };
+    let error = match result {
         ffi::FfiErrorCode::Success => {
              // We don't get an async callback for connection in this simple FFI yet,
              // so we speculate/send event here or wait for next scan update.
              // For now, let's assume success triggers an event.
+             set_link_state(address, ConnectionState::Connected);
              send_event(BluetoothEvent::Connected(address));
-             Ok(())
+             return Ok(());
         }
-        _ => Err(AppError::bluetooth("Connection failed"))
+        ffi::FfiErrorCode::AuthFailed => AppError::Pairing(last_error()),
+        ffi::FfiErrorCode::PageTimeout => AppError::Unreachable(last_error()),
+        ffi::FfiErrorCode::PeerBusy => AppError::ConnectedElsewhere(last_error()),
+        _ => AppError::bluetooth(&last_error()),
+    };
+    let state = ConnectionState::Failed(error.to_string());
+    set_link_state(address, state.clone());
+    send_event(BluetoothEvent::LinkState { address, state });
+    Err(error)
+}
+
+/// Removes the stored pairing. The device has to be paired again before it can connect.
+pub fn unpair(address: u64) -> Result<()> {
+    println!("CLI: Action -> Unpair {:X}", address);
+    match ffi_call!(bt_unpair_device(address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&last_error())),
+    }
+}
+
+/// Starts pairing with a device in pairing mode; the outcome arrives as
+/// `BluetoothEvent::PairingFinished`.
+pub fn pair(address: u64) -> Result<()> {
+    println!("CLI: Action -> Pair {:X}", address);
+    match ffi_call!(bt_pair_device(address, on_pairing_result)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&last_error())),
     }
 }
 
 pub fn disconnect(address: u64) -> Result<()> {
+    let before = begin_disconnect(address)?;
+    finish_disconnect(address, before)
+}
+
+/// `disconnect` for the GUI thread, like `start_connect`; the outcome arrives as
+/// `BluetoothEvent::DisconnectFinished`.
+pub fn start_disconnect(address: u64) -> Result<()> {
+    let before = begin_disconnect(address)?;
+    std::thread::spawn(move || {
+        let result = finish_disconnect(address, before);
+        send_event(BluetoothEvent::DisconnectFinished { address, result: result.map_err(|e| e.to_string()) });
+    });
+    Ok(())
+}
+
+/// Returns the state to go back to if the disconnect fails.
+fn begin_disconnect(address: u64) -> Result<ConnectionState> {
     println!("CLI: Action -> Disconnect from {:X}", address);
-    let result = unsafe { ffi::bt_disconnect_device(address) };
+    let before = link_state(address);
+    begin_transition(address, "disconnect", ConnectionState::Disconnecting)?;
+    Ok(before)
+}
+
+fn finish_disconnect(address: u64, before: ConnectionState) -> Result<()> {
+    let result = ffi_call!(bt_disconnect_device(address));
     match result {
         ffi::FfiErrorCode::Success => {
+             set_link_state(address, ConnectionState::Disconnected);
              send_event(BluetoothEvent::Disconnected(address));
              Ok(())
         }
-        _ => Err(AppError::bluetooth("Disconnect failed"))
+        _ => {
+            // The link is presumably still in whatever state it was
+            set_link_state(address, before.clone());
+            send_event(BluetoothEvent::LinkState { address, state: before });
+            Err(AppError::bluetooth("Disconnect failed"))
+        }
+    }
+}
+
+/// Asks the core to re-query a device's name. The result arrives later as
+/// `BluetoothEvent::NameResolved`.
+pub fn request_remote_name(address: u64) -> Result<()> {
+    println!("CLI: Action -> Refresh Name for {:X}", address);
+    let result = ffi_call!(bt_request_remote_name(address, on_remote_name));
+    if result == ffi::FfiErrorCode::Success {
+        Ok(())
+    } else {
+        Err(AppError::bluetooth("Failed to request remote name"))
+    }
+}
+
+/// Whether headset call state is reported at all; without it the call row and
+/// the pause-during-calls setting have nothing to act on.
+pub fn hfp_supported() -> bool {
+    ffi_call!(bt_hfp_supported())
+}
+
+pub fn set_mic_mute(address: u64, muted: bool) -> Result<()> {
+    println!("CLI: Action -> Mic {} on {:X}", if muted { "Mute" } else { "Unmute" }, address);
+    match ffi_call!(bt_set_mic_mute(address, muted)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(last_error())),
+        _ => Err(AppError::bluetooth("Failed to change microphone mute")),
+    }
+}
+
+/// Sends one AT command over RFCOMM, e.g. a vendor command that makes a multipoint
+/// headset switch hosts, and returns the reply. Channel 0 uses the Serial Port service.
+/// Blocks for a few seconds at most.
+pub fn send_at_command(address: u64, channel: u8, command: &str) -> Result<String> {
+    println!("CLI: Action -> AT '{}' to {:X}", command, address);
+    let c_command = CString::new(command).map_err(|_| AppError::bluetooth("AT command contains a NUL byte"))?;
+    let mut reply = vec![0 as std::os::raw::c_char; 256];
+    match ffi_call!(bt_send_at_command(address, channel, c_command.as_ptr(), reply.as_mut_ptr(), reply.len())) {
+        ffi::FfiErrorCode::Success => {
+            let reply = unsafe { CStr::from_ptr(reply.as_ptr()) };
+            Ok(reply.to_string_lossy().trim().to_string())
+        }
+        _ => Err(AppError::bluetooth(&last_error())),
+    }
+}
+
+/// Sends one frame of a vendor protocol to the RFCOMM service `service_uuid` and returns
+/// the first chunk of the answer. Framing and escaping are up to the caller.
+pub fn rfcomm_exchange(address: u64, service_uuid: &str, request: &[u8]) -> Result<Vec<u8>> {
+    println!("CLI: Action -> RFCOMM {} bytes to {} on {:X}", request.len(), service_uuid, address);
+    let c_uuid = CString::new(service_uuid).map_err(|_| AppError::bluetooth("Service UUID contains a NUL byte"))?;
+    let mut reply = vec![0u8; 512];
+    let mut reply_len = 0usize;
+    match ffi_call!(bt_rfcomm_exchange(
+        address,
+        c_uuid.as_ptr(),
+        request.as_ptr(),
+        request.len(),
+        reply.as_mut_ptr(),
+        reply.len(),
+        &mut reply_len
+    )) {
+        ffi::FfiErrorCode::Success => {
+            reply.truncate(reply_len);
+            Ok(reply)
+        }
+        _ => Err(AppError::bluetooth(&last_error())),
     }
 }
 
 pub fn check_permission() -> bool {
     println!("CLI: Action -> Check Permissions");
-    unsafe { ffi::bt_check_permission() }
+    ffi_call!(bt_check_permission())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadioState {
+    Absent,
+    Off,
+    On,
+}
+
+pub fn radio_state() -> RadioState {
+    match ffi_call!(bt_get_radio_state()) {
+        ffi::RADIO_ON => RadioState::On,
+        ffi::RADIO_OFF => RadioState::Off,
+        ffi::RADIO_ABSENT => RadioState::Absent,
+        other => {
+            error!("Unknown radio state {}, treating as absent", other);
+            RadioState::Absent
+        }
+    }
+}
+
+/// Identity of the first local adapter.
+pub fn adapter_info() -> Result<Adapter> {
+    println!("CLI: Action -> Read Adapter Info");
+    let mut info = ffi::AdapterInfo::default();
+    match ffi_call!(bt_get_adapter_info(&mut info as *mut ffi::AdapterInfo)) {
+        ffi::FfiErrorCode::Success => Ok(adapter_from(&info)),
+        _ => Err(AppError::bluetooth(&last_error())),
+    }
+}
+
+/// Every local adapter, e.g. the built-in radio and a USB dongle.
+pub fn adapters() -> Result<Vec<Adapter>> {
+    let mut infos = [ffi::AdapterInfo::default(); MAX_ADAPTERS];
+    let mut count = 0u32;
+    match ffi_call!(bt_list_adapters(infos.as_mut_ptr(), MAX_ADAPTERS as u32, &mut count as *mut u32)) {
+        ffi::FfiErrorCode::Success => Ok(infos.iter().take(count as usize).map(adapter_from).collect()),
+        _ => Err(AppError::bluetooth(&last_error())),
+    }
+}
+
+fn adapter_from(info: &ffi::AdapterInfo) -> Adapter {
+    let name: Vec<u8> = info.name.iter().take_while(|&&c| c != 0).map(|&c| c as u8).collect();
+    Adapter {
+        address: info.address,
+        name: String::from_utf8_lossy(&name).into_owned(),
+        manufacturer: info.manufacturer,
+        hci_version: info.hci_version,
+        hci_revision: info.hci_revision,
+        lmp_version: info.lmp_version,
+        lmp_subversion: info.lmp_subversion,
+    }
+}
+
+/// Interface version the linked core was built with; see `ffi::BT_API_VERSION`.
+pub fn core_api_version() -> u32 {
+    ffi_call!(bt_get_api_version())
+}
+
+/// Why the last failed call on this thread failed. The core keeps errors per thread, so
+/// read it on the thread that made the call, before calling into the core again.
+pub fn last_error() -> String {
+    unsafe {
+        let ptr = ffi::bt_get_last_error();
+        if ptr.is_null() {
+            String::from("Unknown Bluetooth error")
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::COD_SERVICE_AUDIO;
+    use proptest::prelude::*;
+    use std::ffi::CString;
+
+    /// Arbitrary bytes without interior NULs, so invalid UTF-8 is included.
+    fn name_bytes() -> impl Strategy<Value = Option<Vec<u8>>> {
+        proptest::option::of(proptest::collection::vec(1u8..=255, 0..300))
+    }
+
+    fn raw_device(address: u64, name: Option<&CString>, rssi: i32, cod: u32, flags: (bool, bool)) -> ffi::DiscoveredDevice {
+        ffi::DiscoveredDevice {
+            address,
+            name: name.map_or(std::ptr::null(), |n| n.as_ptr()),
+            connected: flags.0,
+            authenticated: flags.1,
+            rssi,
+            cod,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn conversion_preserves_fields(
+            address: u64,
+            name in name_bytes(),
+            rssi: i32,
+            cod: u32,
+            flags: (bool, bool),
+        ) {
+            let name = name.map(|bytes| CString::new(bytes).unwrap());
+            let raw = raw_device(address, name.as_ref(), rssi, cod, flags);
+            let device = unsafe { device_from_ffi(&raw) };
+
+            prop_assert_eq!(device.address, address);
+            prop_assert_eq!(device.rssi, rssi);
+            prop_assert_eq!(device.cod, cod);
+            prop_assert_eq!((device.state.is_connected(), device.authenticated), flags);
+            match &name {
+                None => prop_assert!(device.name.is_empty()),
+                Some(n) => prop_assert_eq!(&device.name, &String::from_utf8_lossy(n.as_bytes())),
+            }
+        }
+
+        #[test]
+        fn callback_accepts_any_device(
+            address: u64,
+            name in name_bytes(),
+            rssi: i32,
+            cod: u32,
+            flags: (bool, bool),
+        ) {
+            let name = name.map(|bytes| CString::new(bytes).unwrap());
+            on_device_found(raw_device(address, name.as_ref(), rssi, cod, flags));
+        }
+
+        #[test]
+        fn no_link_operation_while_in_transition(address in 0xF000_0000_0000u64..0xF000_FFFF_FFFF) {
+            begin_transition(address, "connect", ConnectionState::Connecting).unwrap();
+            let rejected = begin_transition(address, "disconnect", ConnectionState::Disconnecting);
+            let still_connecting = matches!(rejected, Err(AppError::InvalidState { state: ConnectionState::Connecting, .. }));
+            prop_assert!(still_connecting);
+            prop_assert_eq!(report_link(address, false), ConnectionState::Connecting);
+            set_link_state(address, ConnectionState::Disconnected);
+        }
+
+        #[test]
+        fn class_decoder_handles_any_cod(cod: u32) {
+            let device = BluetoothDevice { address: 0, name: String::new(), state: ConnectionState::Disconnected, authenticated: false, rssi: 0, cod };
+            if let MajorClass::Reserved(value) = device.major_class() {
+                prop_assert!((0x0A..0x1F).contains(&value));
+            }
+            prop_assert_eq!(device.has_audio_service(), cod & COD_SERVICE_AUDIO != 0);
+        }
+
+        #[test]
+        fn quick_actions_follow_the_class(cod: u32) {
+            let device = BluetoothDevice { address: 0, name: String::new(), state: ConnectionState::Disconnected, authenticated: false, rssi: 0, cod };
+            let actions = device.quick_actions();
+            prop_assert_eq!(actions.contains(&QuickAction::Volume), device.is_audio_output());
+            prop_assert_eq!(actions.contains(&QuickAction::Reconnect), device.is_input_device());
+            if device.major_class() == MajorClass::Phone {
+                prop_assert!(actions.contains(&QuickAction::SendFile) && actions.contains(&QuickAction::Tether));
+            }
+        }
+    }
 }
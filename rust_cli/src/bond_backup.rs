@@ -0,0 +1,267 @@
+//! Bond backup: the stack's pairing keys exported to a passphrase-encrypted
+//! file and written back after reinstalling Windows, so devices don't have to
+//! be paired again. Whoever has the file and its passphrase can pose as this
+//! PC to every device in it.
+
+use crate::bluetooth;
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use zeroize::{Zeroize, Zeroizing};
+
+/// File signature and format version
+const MAGIC: &[u8; 8] = b"RTBONDS\x01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// One pairing as the stack stores it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bond {
+    /// Adapter the pairing belongs to
+    pub adapter: u64,
+    pub address: u64,
+    /// LE keys rather than a BR/EDR link key
+    pub le: bool,
+    /// The key store's values, in the core's serialization
+    pub values: Vec<u8>,
+}
+
+impl Drop for Bond {
+    fn drop(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBond {
+    adapter: u64,
+    address: u64,
+    le: bool,
+    /// Base64 of `Bond::values`, so just as secret
+    values: String,
+}
+
+impl Drop for SavedBond {
+    fn drop(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    bonds: Vec<SavedBond>,
+}
+
+static EXPORTED: Mutex<Vec<Bond>> = Mutex::new(Vec::new());
+
+/// `EXPORTED` is poisoned: a panic while it was held, not a concurrent export,
+/// since those wait for the lock.
+fn poisoned<T>(_: T) -> AppError {
+    AppError::Unknown(String::from("Internal error: the bond export state was left inconsistent by an earlier failure"))
+}
+
+extern "C" fn on_bond(record: *const ffi::BondRecord) {
+    let Some(record) = (unsafe { record.as_ref() }) else {
+        return;
+    };
+    let values = if record.values.is_null() {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(record.values, record.values_len as usize) }.to_vec()
+    };
+    if let Ok(mut bonds) = EXPORTED.lock() {
+        bonds.push(Bond { adapter: record.adapter, address: record.address, le: record.le, values });
+    }
+}
+
+/// Every pairing in the stack's key store. Needs administrator rights.
+pub fn read_bonds() -> Result<Vec<Bond>> {
+    println!("CLI: Action -> Export Bonds");
+    let mut exported = EXPORTED.lock().map_err(poisoned)?;
+    exported.clear();
+    // The core calls back on this thread, so the lock is released for it
+    drop(exported);
+    let result = ffi_call!(bt_bonds_export(on_bond as ffi::OnBondCallback));
+    let bonds = std::mem::take(&mut *EXPORTED.lock().map_err(poisoned)?);
+    match result {
+        ffi::FfiErrorCode::Success => Ok(bonds),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+/// Writes `bonds` into the key store, under `adapter` if given (e.g. when the
+/// PC's adapter was replaced), then restarts the Bluetooth service so they take
+/// effect. Needs administrator rights.
+pub fn restore(bonds: &[Bond], adapter: Option<u64>) -> Result<usize> {
+    println!("CLI: Action -> Restore {} Bonds", bonds.len());
+    for bond in bonds {
+        let record = ffi::BondRecord {
+            adapter: adapter.unwrap_or(bond.adapter),
+            address: bond.address,
+            le: bond.le,
+            values: bond.values.as_ptr(),
+            values_len: bond.values.len() as u32,
+        };
+        if ffi_call!(bt_bond_import(&record)) != ffi::FfiErrorCode::Success {
+            return Err(AppError::bluetooth(&format!("{:X}: {}", bond.address, bluetooth::last_error())));
+        }
+    }
+    match ffi_call!(bt_bonds_reload()) {
+        ffi::FfiErrorCode::Success => {
+            info!("Restored {} bond(s)", bonds.len());
+            Ok(bonds.len())
+        }
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| AppError::Unknown(format!("Key derivation failed: {}", e)))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref())))
+}
+
+/// The backup file: signature, salt, nonce, then the bonds as TOML sealed with
+/// AES-256-GCM under a key derived from `passphrase` with Argon2id.
+pub fn encrypt(bonds: &[Bond], passphrase: &str) -> Result<Vec<u8>> {
+    let saved = Saved {
+        bonds: bonds
+            .iter()
+            .map(|b| SavedBond {
+                adapter: b.adapter,
+                address: b.address,
+                le: b.le,
+                values: STANDARD.encode(&b.values),
+            })
+            .collect(),
+    };
+    let plain = Zeroizing::new(
+        toml::to_string(&saved).map_err(|e| AppError::config(&format!("Serialization failed: {}", e)))?,
+    );
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let sealed = cipher(passphrase, &salt)?
+        .encrypt(&nonce, plain.as_bytes())
+        .map_err(|_| AppError::Unknown(String::from("Encryption failed")))?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + sealed.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<Bond>> {
+    let rest = data
+        .strip_prefix(MAGIC.as_slice())
+        .filter(|rest| rest.len() > SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| AppError::Parse(String::from("Not a RedTooth bond backup")))?;
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let plain = Zeroizing::new(
+        cipher(passphrase, salt)?
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| AppError::Parse(String::from("Wrong passphrase, or the file is damaged")))?,
+    );
+
+    let text = std::str::from_utf8(&plain).map_err(|e| AppError::Parse(e.to_string()))?;
+    let saved: Saved = toml::from_str(text).map_err(|e| AppError::Parse(e.to_string()))?;
+    saved
+        .bonds
+        .iter()
+        .map(|b| {
+            let values = STANDARD
+                .decode(&b.values)
+                .map_err(|e| AppError::Parse(format!("{:X}: {}", b.address, e)))?;
+            Ok(Bond { adapter: b.adapter, address: b.address, le: b.le, values })
+        })
+        .collect()
+}
+
+pub fn save(path: &Path, bonds: &[Bond], passphrase: &str) -> Result<()> {
+    fs::write(path, encrypt(bonds, passphrase)?)?;
+    info!("Backed up {} bond(s) to {}", bonds.len(), path.display());
+    Ok(())
+}
+
+pub fn load(path: &Path, passphrase: &str) -> Result<Vec<Bond>> {
+    decrypt(&fs::read(path)?, passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn bonds() -> Vec<Bond> {
+        vec![
+            Bond { adapter: 0x1A2B3C4D5E6F, address: 0xA1, le: false, values: vec![0x10; 16] },
+            Bond { adapter: 0x1A2B3C4D5E6F, address: 0xB2, le: true, values: (0..=255).collect() },
+        ]
+    }
+
+    fn is_parse_error(result: Result<Vec<Bond>>) -> bool {
+        matches!(result, Err(AppError::Parse(_)))
+    }
+
+    #[test]
+    fn round_trip() {
+        let sealed = encrypt(&bonds(), PASSPHRASE).unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert_eq!(decrypt(&sealed, PASSPHRASE).unwrap(), bonds());
+    }
+
+    #[test]
+    fn same_bonds_seal_differently() {
+        assert_ne!(encrypt(&bonds(), PASSPHRASE).unwrap(), encrypt(&bonds(), PASSPHRASE).unwrap());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_refused() {
+        let sealed = encrypt(&bonds(), PASSPHRASE).unwrap();
+        assert!(is_parse_error(decrypt(&sealed, "correct horse battery!")));
+    }
+
+    #[test]
+    fn damaged_file_is_refused() {
+        let mut sealed = encrypt(&bonds(), PASSPHRASE).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(is_parse_error(decrypt(&sealed, PASSPHRASE)));
+    }
+
+    #[test]
+    fn truncated_file_is_refused() {
+        let sealed = encrypt(&bonds(), PASSPHRASE).unwrap();
+        for len in [0, MAGIC.len() - 1, MAGIC.len(), MAGIC.len() + SALT_LEN + NONCE_LEN, sealed.len() - 1] {
+            assert!(is_parse_error(decrypt(&sealed[..len], PASSPHRASE)), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn other_signature_is_refused() {
+        let mut sealed = encrypt(&bonds(), PASSPHRASE).unwrap();
+        // A future format version
+        sealed[MAGIC.len() - 1] = 2;
+        assert!(is_parse_error(decrypt(&sealed, PASSPHRASE)));
+        assert!(is_parse_error(decrypt(b"PK\x03\x04 not a backup at all", PASSPHRASE)));
+    }
+}
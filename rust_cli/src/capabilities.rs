@@ -0,0 +1,45 @@
+/// An optional subsystem selected with a cargo feature at build time.
+pub struct Capability {
+    pub feature: &'static str,
+    pub description: &'static str,
+    pub enabled: bool,
+}
+
+/// Every optional subsystem and whether this binary includes it.
+pub const CAPABILITIES: &[Capability] = &[
+    Capability {
+        feature: "gatt",
+        description: "BLE GATT browser and sensor readouts",
+        enabled: cfg!(feature = "gatt"),
+    },
+    Capability {
+        feature: "obex",
+        description: "File transfer (OBEX Object Push)",
+        enabled: cfg!(feature = "obex"),
+    },
+    Capability {
+        feature: "audio-routing",
+        description: "Per-application audio routing",
+        enabled: cfg!(feature = "audio-routing"),
+    },
+    Capability {
+        feature: "scripting",
+        description: "Rhai automation scripts",
+        enabled: cfg!(feature = "scripting"),
+    },
+    Capability {
+        feature: "tui",
+        description: "Terminal interface (--tui)",
+        enabled: cfg!(feature = "tui"),
+    },
+];
+
+/// Comma-separated list of the features compiled in, for logs and bug reports.
+pub fn summary() -> String {
+    let enabled: Vec<&str> = CAPABILITIES.iter().filter(|c| c.enabled).map(|c| c.feature).collect();
+    if enabled.is_empty() {
+        String::from("none")
+    } else {
+        enabled.join(", ")
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Height assumed for a card that hasn't been drawn yet
+const ESTIMATED_CARD_HEIGHT: f32 = 110.0;
+
+/// Cards to draw for the current viewport, and where they go.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    /// Indices into the list passed to `CardLayout::window`
+    pub range: Range<usize>,
+    /// Height of the cards above `range`
+    pub offset: f32,
+    /// Height of the whole list
+    pub total: f32,
+}
+
+/// Remembered card heights so the device list only lays out the cards in view.
+///
+/// Cards differ in height (notes, tags, recent results), which rules out
+/// fixed-height rows. Each card's height is measured whenever it is drawn and
+/// used to place the cards that aren't.
+#[derive(Debug, Default)]
+pub struct CardLayout {
+    heights: HashMap<u64, f32>,
+}
+
+impl CardLayout {
+    fn height(&self, address: u64) -> f32 {
+        self.heights.get(&address).copied().unwrap_or(ESTIMATED_CARD_HEIGHT)
+    }
+
+    /// Records how far drawing the card advanced the layout, spacing included.
+    pub fn measured(&mut self, address: u64, height: f32) {
+        self.heights.insert(address, height);
+    }
+
+    /// Cards overlapping `top..bottom`, in list coordinates.
+    pub fn window(&self, addresses: &[u64], top: f32, bottom: f32) -> Window {
+        let mut y = 0.0;
+        let mut start = addresses.len();
+        let mut end = addresses.len();
+        let mut offset = 0.0;
+        for (i, address) in addresses.iter().enumerate() {
+            let height = self.height(*address);
+            if start == addresses.len() && y + height > top {
+                start = i;
+                offset = y;
+            }
+            if end == addresses.len() && y >= bottom {
+                end = i;
+            }
+            y += height;
+        }
+        if start == addresses.len() {
+            offset = y;
+        }
+        Window { range: start..end.max(start), offset, total: y }
+    }
+
+    pub fn clear(&mut self) {
+        self.heights.clear();
+    }
+}
@@ -0,0 +1,384 @@
+use crate::address_list;
+use crate::bluetooth;
+use crate::bond_backup::{self, Bond};
+use crate::config::{self, Config};
+use crate::config_schema;
+use crate::error::{AppError, Result};
+use crate::policy::Policy;
+use crate::purge;
+use crate::selftest;
+use crate::share::DevicePreset;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Running without a subcommand opens the GUI.
+#[derive(Parser)]
+#[command(version, about = "RedTooth Bluetooth device manager")]
+pub struct Cli {
+    /// Show device status only, e.g. on a wall display; connecting, pairing and settings are disabled
+    #[arg(long)]
+    pub kiosk: bool,
+    /// Start this audio profile once the GUI is up, connecting its devices first
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Start without Bluetooth, audio, plugins or your settings, e.g. when RedTooth keeps crashing
+    #[arg(long)]
+    pub safe_mode: bool,
+    /// Run in the terminal instead of opening a window, e.g. over SSH
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub tui: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Read or edit config.toml without opening the GUI
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Import or export the device blocklist and the watch area allowlist
+    Lists {
+        #[command(subcommand)]
+        action: ListAction,
+    },
+    /// Delete all your RedTooth data: device history, notes, reports, logs and config
+    Purge {
+        /// Keep config.toml and its backups
+        #[arg(long)]
+        keep_config: bool,
+        /// Don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Check the core library, adapter, radio, permissions, database and config
+    Selftest,
+    /// Back up pairings to an encrypted file and restore them, e.g. around a Windows reinstall
+    Bonds {
+        #[command(subcommand)]
+        action: BondAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BondAction {
+    /// Write the keys of every pairing to FILE, encrypted with a passphrase; needs administrator rights
+    Export { file: PathBuf },
+    /// Restore the pairings in FILE to this PC's adapter; needs administrator rights
+    Import {
+        file: PathBuf,
+        /// Keep the adapter recorded in the backup instead of using this PC's
+        #[arg(long)]
+        original_adapter: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ListAction {
+    /// Write a list to FILE: CSV if it ends in .csv, otherwise one address per line
+    Export { list: ListKind, file: PathBuf },
+    /// Add the addresses in FILE (CSV, or one per line) to a list
+    Import {
+        list: ListKind,
+        file: PathBuf,
+        /// Replace the list instead of adding to it
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListKind {
+    /// `blocked_devices` in the machine policy; importing needs write access to it
+    Blocklist,
+    /// Devices the watch area never alerts for (`watch.allowlist`)
+    Allowlist,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print a setting, e.g. `ui.status_style`, or a whole section
+    Get { key: String },
+    /// Change a setting, e.g. `audio.recording_max_minutes 30`
+    Set { key: String, value: String },
+    /// Save a device under a name, e.g. `"Headset" AA:BB:CC:DD:EE:FF`
+    AddDevice { name: String, address: String },
+    /// Print a share code for a saved device
+    ExportDevice { name: String },
+    /// Save a device from a share code printed by `export-device` or the GUI
+    ImportDevice { code: String },
+    /// Print an example config.toml with every setting, its default and what it does
+    Schema,
+}
+
+pub fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Config { action: ConfigAction::Schema } => {
+            print!("{}", config_schema::example()?);
+            Ok(())
+        }
+        Command::Config { action } => run_config(action),
+        Command::Lists { action } => run_lists(action),
+        Command::Purge { keep_config, yes } => run_purge(keep_config, yes),
+        Command::Selftest => run_selftest(),
+        Command::Bonds { action } => run_bonds(action),
+    }
+}
+
+/// Asks for `word` to be typed before doing something that can't be taken back.
+fn confirm(word: &str) -> Result<bool> {
+    print!("Type {} to confirm: ", word);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim() == word)
+}
+
+fn print_bonds(bonds: &[Bond]) {
+    let config = Config::load_strict().ok();
+    for bond in bonds {
+        let name = config
+            .as_ref()
+            .and_then(|c| c.devices.iter().find(|(_, a)| **a == bond.address).map(|(n, _)| n.clone()))
+            .unwrap_or_default();
+        println!("  {} {:<4} {}", config::address_key(bond.address), if bond.le { "LE" } else { "BR" }, name);
+    }
+}
+
+fn run_bonds(action: BondAction) -> Result<()> {
+    match action {
+        BondAction::Export { file } => {
+            let bonds = bond_backup::read_bonds()?;
+            if bonds.is_empty() {
+                println!("No pairings to back up");
+                return Ok(());
+            }
+            println!("Backing up the keys of {} pairings:", bonds.len());
+            print_bonds(&bonds);
+            println!("Anyone with this file and its passphrase can pose as this PC to these devices.");
+            println!("Keep it somewhere safe and delete it once the pairings are restored.");
+            if !confirm("EXPORT")? {
+                println!("Nothing exported");
+                return Ok(());
+            }
+            let passphrase = rpassword::prompt_password("Passphrase: ")?;
+            if passphrase.chars().count() < bond_backup::MIN_PASSPHRASE_LEN {
+                return Err(AppError::config(&format!(
+                    "The passphrase needs at least {} characters",
+                    bond_backup::MIN_PASSPHRASE_LEN
+                )));
+            }
+            if rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+                return Err(AppError::config("The passphrases don't match"));
+            }
+            bond_backup::save(&file, &bonds, &passphrase)?;
+            println!("Wrote {} pairings to {}", bonds.len(), file.display());
+        }
+        BondAction::Import { file, original_adapter } => {
+            let passphrase = rpassword::prompt_password("Passphrase: ")?;
+            let bonds = bond_backup::load(&file, &passphrase)?;
+            let adapter = if original_adapter { None } else { Some(bluetooth::adapter_info()?.address) };
+            println!("Restoring {} pairings:", bonds.len());
+            print_bonds(&bonds);
+            println!("Pairings to the same devices are overwritten, and Bluetooth restarts, dropping every connection.");
+            if !confirm("RESTORE")? {
+                println!("Nothing restored");
+                return Ok(());
+            }
+            let restored = bond_backup::restore(&bonds, adapter)?;
+            println!("Restored {} pairings", restored);
+        }
+    }
+    Ok(())
+}
+
+fn run_selftest() -> Result<()> {
+    let checks = selftest::run();
+    for check in &checks {
+        println!("{} {:<20} {}", if check.passed { "PASS" } else { "FAIL" }, check.name, check.detail);
+        if !check.passed {
+            println!("     {}", check.hint);
+        }
+    }
+    if selftest::all_passed(&checks) {
+        Ok(())
+    } else {
+        Err(AppError::Unknown(String::from("Some checks failed")))
+    }
+}
+
+fn run_purge(keep_config: bool, yes: bool) -> Result<()> {
+    let targets = purge::targets(keep_config);
+    println!("This permanently deletes:");
+    for path in &targets {
+        println!("  {}", path.display());
+    }
+    println!("  devices you published to this PC's shared list");
+    if !yes && !confirm("DELETE")? {
+        println!("Nothing deleted");
+        return Ok(());
+    }
+
+    let report = purge::purge(keep_config);
+    println!("Deleted {} items, {} shared devices", report.removed.len(), report.shared_removed);
+    for (path, error) in &report.failed {
+        eprintln!("Could not delete {}: {}", path.display(), error);
+    }
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Unknown(String::from("Some data could not be deleted; close RedTooth and try again")))
+    }
+}
+
+fn read_list(file: &Path) -> Result<Vec<u64>> {
+    let (addresses, skipped) = address_list::read(file)?;
+    if skipped > 0 {
+        eprintln!("Skipped {} lines without a Bluetooth address", skipped);
+    }
+    Ok(addresses)
+}
+
+fn run_lists(action: ListAction) -> Result<()> {
+    let policy = Policy::load();
+    match action {
+        ListAction::Export { list, file } => {
+            let addresses = match list {
+                ListKind::Blocklist => policy.blocked_addresses(),
+                ListKind::Allowlist => {
+                    let config = Config::load_strict()?;
+                    config.watch.allowlist.iter().filter_map(|k| config::parse_address(k)).collect()
+                }
+            };
+            address_list::write(&file, &addresses)?;
+            println!("Wrote {} addresses to {}", addresses.len(), file.display());
+        }
+        ListAction::Import { list: ListKind::Blocklist, file, replace } => {
+            let mut addresses = if replace { Vec::new() } else { policy.blocked_addresses() };
+            for address in read_list(&file)? {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+            let path = Policy::save_blocked_devices(&addresses)?;
+            println!("Blocklist now has {} addresses ({})", addresses.len(), path.display());
+        }
+        ListAction::Import { list: ListKind::Allowlist, file, replace } => {
+            if policy.is_locked("watch.allowlist") {
+                return Err(AppError::config("'watch.allowlist' is managed by your organization's policy"));
+            }
+            let mut config = Config::load_strict()?;
+            if replace {
+                config.watch.allowlist.clear();
+            }
+            for address in read_list(&file)? {
+                let key = config::address_key(address);
+                if !config.watch.allowlist.contains(&key) {
+                    config.watch.allowlist.push(key);
+                }
+            }
+            config.validate()?;
+            config.save()?;
+            println!("Allowlist now has {} addresses", config.watch.allowlist.len());
+        }
+    }
+    Ok(())
+}
+
+fn run_config(action: ConfigAction) -> Result<()> {
+    let mut config = Config::load_strict()?;
+    let policy = Policy::load();
+    match action {
+        ConfigAction::Get { key } => {
+            let root = to_value(&config)?;
+            let value = lookup(&root, &key)?;
+            match value {
+                toml::Value::String(s) => println!("{}", s),
+                toml::Value::Table(_) => print!("{}", toml::to_string(value).unwrap_or_default()),
+                other => println!("{}", other),
+            }
+            return Ok(());
+        }
+        ConfigAction::Set { key, value } => {
+            if policy.is_locked(&key) {
+                return Err(AppError::config(&format!("'{}' is managed by your organization's policy", key)));
+            }
+            let mut root = to_value(&config)?;
+            set_value(&mut root, &key, &value)?;
+            config = root
+                .try_into()
+                .map_err(|e| AppError::config(&format!("Invalid value for '{}': {}", key, e)))?;
+            println!("{} = {}", key, value);
+        }
+        ConfigAction::AddDevice { name, address } => {
+            let parsed = config::parse_address(&address)
+                .ok_or_else(|| AppError::config(&format!("'{}' is not a Bluetooth address", address)))?;
+            if policy.blocked_addresses().contains(&parsed) {
+                return Err(AppError::config("This device is blocked by your organization's policy"));
+            }
+            config.add_device(name.clone(), parsed);
+            println!("Added {} ({})", name, config::address_key(parsed));
+        }
+        ConfigAction::ExportDevice { name } => {
+            let preset = DevicePreset::from_config(&config, &name)
+                .ok_or_else(|| AppError::config(&format!("No saved device named '{}'", name)))?;
+            println!("{}", preset.to_code());
+            return Ok(());
+        }
+        ConfigAction::ImportDevice { code } => {
+            let preset = DevicePreset::from_code(&code)?;
+            if policy.blocked_addresses().contains(&preset.address()?) {
+                return Err(AppError::config("This device is blocked by your organization's policy"));
+            }
+            let address = preset.apply(&mut config)?;
+            println!("Added {} ({})", preset.name, config::address_key(address));
+        }
+        ConfigAction::Schema => unreachable!("handled without loading the config"),
+    }
+    // Locked settings were refused above; the policy's values stay out of the file
+    config.validate()?;
+    config.save()
+}
+
+fn to_value(config: &Config) -> Result<toml::Value> {
+    toml::Value::try_from(config).map_err(|e| AppError::config(&format!("Serialization failed: {}", e)))
+}
+
+fn lookup<'a>(root: &'a toml::Value, key: &str) -> Result<&'a toml::Value> {
+    key.split('.')
+        .try_fold(root, |value, part| value.get(part))
+        .ok_or_else(|| AppError::config(&format!("Unknown setting '{}'", key)))
+}
+
+/// Replaces an existing setting, parsing `raw` as the same TOML type it already has.
+fn set_value(root: &mut toml::Value, key: &str, raw: &str) -> Result<()> {
+    let mut value = root;
+    for part in key.split('.') {
+        value = value
+            .get_mut(part)
+            .ok_or_else(|| AppError::config(&format!("Unknown setting '{}'", key)))?;
+    }
+    let new_value = match value {
+        toml::Value::String(_) => toml::Value::String(raw.to_string()),
+        toml::Value::Table(_) => {
+            return Err(AppError::config(&format!("'{}' is a section; set one of its keys instead", key)));
+        }
+        _ => toml::from_str::<toml::Value>(&format!("v = {}", raw))
+            .ok()
+            .and_then(|mut parsed| parsed.as_table_mut().and_then(|t| t.remove("v")))
+            .ok_or_else(|| AppError::config(&format!("Could not parse '{}'", raw)))?,
+    };
+    if new_value.type_str() != value.type_str() {
+        return Err(AppError::config(&format!(
+            "'{}' expects a {}, got a {}",
+            key,
+            value.type_str(),
+            new_value.type_str()
+        )));
+    }
+    *value = new_value;
+    Ok(())
+}
@@ -1,19 +1,127 @@
+use crate::audio::{AppRoute, AudioProfile};
 use crate::error::{AppError, Result};
+use crate::handover::HandoverStep;
+use crate::paths;
+use crate::schedule::ProfileSchedule;
+use crate::settings::{
+    AudioSettings, BackupSettings, ConfirmSettings, NotificationSettings, PresenceLockSettings, RegistrySettings,
+    ScanSettings, ScriptSettings, UiSettings, WatchSettings, DEPRECATIONS,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use log::{info, warn, error};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Number of backups kept in the backup directory
+const MAX_BACKUPS: usize = 5;
+/// Saves within this long of the newest backup don't create another one,
+/// so dragging a slider doesn't rotate out every useful backup
+const BACKUP_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct Config {
-    pub devices: HashMap<String, u64>, // Name -> Address
-    pub auto_connect: Vec<String>, // List of names
+    /// List of names, in connect priority order
+    pub auto_connect: Vec<String>,
+    /// Display order of saved devices
+    #[serde(default)]
+    pub device_order: Vec<String>,
+    /// Hex addresses allowed to share contacts/call history
+    #[serde(default)]
+    pub pbap_consent: Vec<String>,
+    /// Hex addresses made the system default output when they connect
+    #[serde(default)]
+    pub default_output_on_connect: Vec<String>,
+    // TOML requires plain values before tables, so map fields must stay last
+    /// Device discovery
+    #[serde(default)]
+    pub scan: ScanSettings,
+    /// Main window and device list
+    #[serde(default)]
+    pub ui: UiSettings,
+    /// Broadcasting, recording and the audio engine
+    #[serde(default)]
+    pub audio: AudioSettings,
+    /// Device history kept in registry.db
+    #[serde(default)]
+    pub registry: RegistrySettings,
+    /// Scheduled backups of config and registry
+    #[serde(default)]
+    pub backup: BackupSettings,
+    /// Banners and quiet hours
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    #[serde(default)]
+    pub watch: WatchSettings,
+    #[serde(default)]
+    pub presence_lock: PresenceLockSettings,
+    #[serde(default)]
+    pub confirm: ConfirmSettings,
+    #[serde(default)]
+    pub scripts: ScriptSettings,
+    #[serde(default)]
+    pub audio_profiles: Vec<AudioProfile>,
+    #[serde(default)]
+    pub profile_schedules: Vec<ProfileSchedule>,
+    #[serde(default)]
+    pub app_routes: Vec<AppRoute>,
+    /// Hex address -> Tags
+    #[serde(default)]
+    pub device_tags: HashMap<String, Vec<String>>,
+    /// Hex address -> Room or place, e.g. "Office"
+    #[serde(default)]
+    pub device_locations: HashMap<String, String>,
+    /// Hex address -> Steps that pull a multipoint headset over
+    #[serde(default)]
+    pub handover_recipes: HashMap<String, Vec<HandoverStep>>,
+    /// Hex address -> Unix time its guest pairing is removed
+    #[serde(default)]
+    pub guest_expiry: HashMap<String, i64>,
+    /// Hex address -> Hex address of the local adapter to connect through, e.g. a
+    /// USB dongle for a game controller. Devices not listed use the system's choice.
+    #[serde(default)]
+    pub device_adapters: HashMap<String, String>,
+    /// Name -> Address
+    pub devices: HashMap<String, u64>,
+}
+
+/// Prefix marking a target list entry (e.g. in `auto_connect`) as a tag
+/// selector rather than a saved device name.
+pub const TAG_TARGET_PREFIX: &str = "tag:";
+
+pub fn config_path() -> PathBuf {
+    paths::user_file("config.toml")
+}
+
+fn backup_dir() -> PathBuf {
+    paths::user_file("config_backups")
+}
+
+pub fn address_key(address: u64) -> String {
+    format!("{:012X}", address)
+}
+
+/// Parses a Bluetooth address written as `AA:BB:CC:DD:EE:FF`, with dashes, or as plain hex.
+pub fn parse_address(text: &str) -> Option<u64> {
+    let hex: String = text.chars().filter(|c| *c != ':' && *c != '-').collect();
+    if hex.is_empty() || hex.len() > 12 {
+        return None;
+    }
+    u64::from_str_radix(&hex, 16).ok()
 }
 
 impl Config {
+    /// True while the Do Not Disturb schedule is in effect.
+    pub fn quiet_hours_active(&self) -> bool {
+        self.notifications.dnd.is_active_now()
+    }
+
     pub fn load() -> Result<Self> {
-        let config_path = Path::new("config.toml");
+        let config_path = config_path();
+        let config_path = config_path.as_path();
         
         if !config_path.exists() {
             info!("Config file not found, using defaults");
@@ -32,7 +140,7 @@ impl Config {
         };
         
         // Parse TOML with error handling
-        match toml::from_str::<Config>(&content) {
+        match Self::parse(&content) {
             Ok(config) => {
                 info!("Config loaded successfully with {} devices", config.devices.len());
                 Ok(config)
@@ -45,6 +153,63 @@ impl Config {
         }
     }
 
+    /// Like `load`, but a file that doesn't parse is an error instead of
+    /// silently becoming defaults. Used by scripted edits, which save afterwards.
+    pub fn load_strict() -> Result<Self> {
+        match fs::read_to_string(config_path()) {
+            Ok(content) => Self::parse(&content).map_err(|e| AppError::config(&e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(AppError::Io(e)),
+        }
+    }
+
+    fn parse(content: &str) -> std::result::Result<Self, String> {
+        let mut value = toml::from_str::<toml::Value>(content).map_err(|e| e.to_string())?;
+        migrate_deprecated(&mut value);
+        warn_unknown_keys(&value);
+        value.try_into::<Config>().map_err(|e| e.to_string())
+    }
+
+    /// Checks values the type system can't: times, ranges and supported rates.
+    pub fn validate(&self) -> Result<()> {
+        if !self.notifications.dnd.is_valid() {
+            return Err(AppError::config("Do Not Disturb times must be HH:MM (24h)"));
+        }
+        if !self.profile_schedules.iter().all(ProfileSchedule::is_valid) {
+            return Err(AppError::config("Profile schedule times must be HH:MM (24h) and days mon–sun"));
+        }
+        let hotkey = &self.ui.quick_connect_hotkey;
+        if !hotkey.is_empty() && crate::hotkey::Hotkey::parse(hotkey).is_none() {
+            return Err(AppError::config(
+                "ui.quick_connect_hotkey must be like Ctrl+Alt+B (Ctrl and/or Alt, optional Shift, and a letter, digit, F1-F12 or Space)",
+            ));
+        }
+        if self.backup.interval_hours == 0 || self.backup.keep == 0 {
+            return Err(AppError::config("backup.interval_hours and backup.keep must be at least 1"));
+        }
+        if !(-100..=0).contains(&self.watch.min_rssi) {
+            return Err(AppError::config("watch.min_rssi must be between -100 and 0 dBm"));
+        }
+        let lock = &self.presence_lock;
+        if lock.enabled && parse_address(&lock.device).is_none() {
+            return Err(AppError::config("presence_lock.device must be the phone's address"));
+        }
+        if !(-100..=0).contains(&lock.min_rssi) || lock.away_secs < 10 {
+            return Err(AppError::config("presence_lock needs min_rssi between -100 and 0 dBm and away_secs of at least 10"));
+        }
+        let engine = &self.audio.engine;
+        if !(10..=1000).contains(&engine.buffer_ms) {
+            return Err(AppError::config("audio.engine.buffer_ms must be between 10 and 1000"));
+        }
+        if !crate::audio::SAMPLE_RATES.contains(&engine.sample_rate) {
+            return Err(AppError::config(&format!(
+                "audio.engine.sample_rate must be one of {:?}",
+                crate::audio::SAMPLE_RATES
+            )));
+        }
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
         info!("Saving config...");
         
@@ -56,9 +221,15 @@ impl Config {
                 return Err(AppError::config(&format!("Serialization failed: {}", e)));
             }
         };
+
+        if let Err(e) = backup_current() {
+            // A failed backup shouldn't block saving the user's change
+            warn!("Failed to back up config: {}", e);
+        }
         
-        // Write to file
-        match fs::write("config.toml", content) {
+        // Write to a temp file and rename over the original, so a crash
+        // mid-write leaves either the old or the new config, never half of one
+        match write_atomic(&config_path(), content.as_bytes()) {
             Ok(_) => {
                 info!("Config saved successfully");
                 Ok(())
@@ -69,16 +240,76 @@ impl Config {
             }
         }
     }
+
+    /// Config backups, newest first.
+    pub fn list_backups() -> Vec<PathBuf> {
+        let mut backups: Vec<PathBuf> = match fs::read_dir(backup_dir()) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        // Names embed a sortable timestamp
+        backups.sort();
+        backups.reverse();
+        backups
+    }
+
+    /// Loads a backup and makes it the current config. The config being replaced
+    /// is itself backed up first, so a restore can be undone.
+    pub fn restore_backup(path: &Path) -> Result<Self> {
+        info!("Restoring config from {:?}", path);
+        let content = fs::read_to_string(path)?;
+        let config = toml::from_str::<Config>(&content)
+            .map_err(|e| AppError::config(&format!("Backup is not a valid config: {}", e)))?;
+        force_backup()?;
+        write_atomic(&config_path(), content.as_bytes())?;
+        Ok(config)
+    }
     
     pub fn add_device(&mut self, name: String, address: u64) {
         info!("Adding device: {} -> {}", name, address);
+        if !self.device_order.contains(&name) {
+            self.device_order.push(name.clone());
+        }
         self.devices.insert(name, address);
     }
     
     pub fn remove_device(&mut self, name: &str) -> bool {
         info!("Removing device: {}", name);
+        self.device_order.retain(|n| n != name);
         self.devices.remove(name).is_some()
     }
+
+    /// Saved devices in display order. Devices missing from `device_order`
+    /// (e.g. added by hand in config.toml) are appended alphabetically.
+    pub fn ordered_devices(&self) -> Vec<(String, u64)> {
+        let mut names: Vec<&String> = self
+            .device_order
+            .iter()
+            .filter(|n| self.devices.contains_key(*n))
+            .collect();
+        let mut rest: Vec<&String> = self
+            .devices
+            .keys()
+            .filter(|n| !self.device_order.contains(n))
+            .collect();
+        rest.sort();
+        names.extend(rest);
+
+        names
+            .into_iter()
+            .map(|n| (n.clone(), self.devices[n]))
+            .collect()
+    }
+
+    pub fn move_device(&mut self, from: usize, to: usize) {
+        let mut order: Vec<String> = self.ordered_devices().into_iter().map(|(n, _)| n).collect();
+        move_item(&mut order, from, to);
+        info!("Reordered saved devices: {:?}", order);
+        self.device_order = order;
+    }
     
     pub fn add_auto_connect(&mut self, name: String) {
         if !self.auto_connect.contains(&name) {
@@ -91,4 +322,404 @@ impl Config {
         info!("Removing {} from auto-connect list", name);
         self.auto_connect.retain(|n| n != name);
     }
+
+    pub fn tags_for(&self, address: u64) -> &[String] {
+        self.device_tags
+            .get(&address_key(address))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn add_tag(&mut self, address: u64, tag: &str) {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            return;
+        }
+        let tags = self.device_tags.entry(address_key(address)).or_default();
+        if !tags.contains(&tag) {
+            info!("Tagging {:X} with '{}'", address, tag);
+            tags.push(tag);
+        }
+    }
+
+    pub fn remove_tag(&mut self, address: u64, tag: &str) {
+        let key = address_key(address);
+        if let Some(tags) = self.device_tags.get_mut(&key) {
+            tags.retain(|t| t != tag);
+            if tags.is_empty() {
+                self.device_tags.remove(&key);
+            }
+        }
+    }
+
+    pub fn location_for(&self, address: u64) -> Option<&str> {
+        self.device_locations.get(&address_key(address)).map(String::as_str)
+    }
+
+    /// Sets where a device lives; an empty location clears it. Unlike tags,
+    /// locations keep their case since they are shown as headings.
+    pub fn set_location(&mut self, address: u64, location: &str) {
+        let location = location.trim();
+        if location.is_empty() {
+            self.device_locations.remove(&address_key(address));
+        } else {
+            info!("Locating {:X} in '{}'", address, location);
+            self.device_locations.insert(address_key(address), location.to_string());
+        }
+    }
+
+    /// Steps that move a multipoint headset to this PC; `None` for devices not marked multipoint.
+    pub fn handover_recipe(&self, address: u64) -> Option<&[HandoverStep]> {
+        self.handover_recipes.get(&address_key(address)).map(Vec::as_slice)
+    }
+
+    /// Replaces a device's recipe; `None` unmarks it as multipoint.
+    pub fn set_handover_recipe(&mut self, address: u64, steps: Option<Vec<HandoverStep>>) {
+        match steps {
+            Some(steps) => {
+                self.handover_recipes.insert(address_key(address), steps);
+            }
+            None => {
+                self.handover_recipes.remove(&address_key(address));
+            }
+        }
+    }
+
+    pub fn preferred_adapter(&self, address: u64) -> Option<u64> {
+        self.device_adapters.get(&address_key(address)).and_then(|a| parse_address(a))
+    }
+
+    /// `None` lets the system pick the adapter again.
+    pub fn set_preferred_adapter(&mut self, address: u64, adapter: Option<u64>) {
+        match adapter {
+            Some(adapter) => {
+                info!("Connecting {:X} through adapter {:X}", address, adapter);
+                self.device_adapters.insert(address_key(address), address_key(adapter));
+            }
+            None => {
+                self.device_adapters.remove(&address_key(address));
+            }
+        }
+    }
+
+    /// Device -> Adapter for every valid preference, for `bluetooth::set_preferred_adapters`.
+    pub fn preferred_adapters(&self) -> HashMap<u64, u64> {
+        self.device_adapters
+            .iter()
+            .filter_map(|(device, adapter)| Some((parse_address(device)?, parse_address(adapter)?)))
+            .collect()
+    }
+
+    /// Every location in use, sorted and deduplicated.
+    pub fn all_locations(&self) -> Vec<String> {
+        let mut locations: Vec<String> = self.device_locations.values().cloned().collect();
+        locations.sort();
+        locations.dedup();
+        locations
+    }
+
+    /// Every tag in use, sorted and deduplicated.
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.device_tags.values().flatten().cloned().collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Devices carrying `tag`, in My Devices order; tagged devices that aren't saved come last.
+    pub fn addresses_with_tag(&self, tag: &str) -> Vec<u64> {
+        let saved: Vec<u64> = self
+            .ordered_devices()
+            .into_iter()
+            .map(|(_, address)| address)
+            .collect();
+        let mut addresses: Vec<u64> = self
+            .device_tags
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|t| t == tag))
+            .filter_map(|(key, _)| u64::from_str_radix(key, 16).ok())
+            .collect();
+        addresses.sort_by_key(|a| (saved.iter().position(|s| s == a).unwrap_or(usize::MAX), *a));
+        addresses
+    }
+
+    /// Resolves a target list where each entry is either a saved device name or
+    /// `tag:<name>`, preserving list order and dropping duplicates.
+    pub fn resolve_targets(&self, entries: &[String]) -> Vec<u64> {
+        let mut addresses = Vec::new();
+        for entry in entries {
+            let resolved = match entry.strip_prefix(TAG_TARGET_PREFIX) {
+                Some(tag) => self.addresses_with_tag(tag),
+                None => self.devices.get(entry).copied().into_iter().collect(),
+            };
+            for address in resolved {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+        addresses
+    }
+
+    /// Addresses to auto-connect, highest priority first.
+    pub fn auto_connect_addresses(&self) -> Vec<u64> {
+        self.resolve_targets(&self.auto_connect)
+    }
+
+    pub fn has_pbap_consent(&self, address: u64) -> bool {
+        self.pbap_consent.contains(&address_key(address))
+    }
+
+    pub fn set_pbap_consent(&mut self, address: u64, allowed: bool) {
+        let key = address_key(address);
+        self.pbap_consent.retain(|k| *k != key);
+        if allowed {
+            self.pbap_consent.push(key);
+        }
+    }
+
+    pub fn takes_default_output(&self, address: u64) -> bool {
+        self.default_output_on_connect.contains(&address_key(address))
+    }
+
+    pub fn set_takes_default_output(&mut self, address: u64, enabled: bool) {
+        let key = address_key(address);
+        self.default_output_on_connect.retain(|k| *k != key);
+        if enabled {
+            self.default_output_on_connect.push(key);
+        }
+    }
+
+    /// When a guest pairing ends, as a Unix timestamp; `None` for regular devices.
+    pub fn guest_expiry(&self, address: u64) -> Option<i64> {
+        self.guest_expiry.get(&address_key(address)).copied()
+    }
+
+    pub fn set_guest_expiry(&mut self, address: u64, expires: Option<i64>) {
+        match expires {
+            Some(at) => {
+                info!("Guest pairing of {:X} ends at {}", address, at);
+                self.guest_expiry.insert(address_key(address), at);
+            }
+            None => {
+                self.guest_expiry.remove(&address_key(address));
+            }
+        }
+    }
+
+    /// Guests whose time ran out by `now` (Unix time).
+    pub fn expired_guests(&self, now: i64) -> Vec<u64> {
+        self.guest_expiry
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .filter_map(|(key, _)| parse_address(key))
+            .collect()
+    }
+
+    /// Drops everything saved about a device: its names, auto-connect entries and
+    /// per-device settings.
+    pub fn forget_device(&mut self, address: u64) {
+        info!("Forgetting {:X}", address);
+        let names: Vec<String> = self.devices.iter().filter(|(_, a)| **a == address).map(|(n, _)| n.clone()).collect();
+        for name in names {
+            self.remove_device(&name);
+            self.remove_auto_connect(&name);
+        }
+        let key = address_key(address);
+        self.pbap_consent.retain(|k| *k != key);
+        self.default_output_on_connect.retain(|k| *k != key);
+        self.device_tags.remove(&key);
+        self.device_locations.remove(&key);
+        self.handover_recipes.remove(&key);
+        self.guest_expiry.remove(&key);
+    }
+
+    pub fn move_auto_connect(&mut self, from: usize, to: usize) {
+        move_item(&mut self.auto_connect, from, to);
+        info!("Reordered auto-connect list: {:?}", self.auto_connect);
+    }
+}
+
+fn move_item(list: &mut Vec<String>, from: usize, to: usize) {
+    if from < list.len() && to < list.len() {
+        let item = list.remove(from);
+        list.insert(to, item);
+    }
+}
+
+/// Writes `contents` to `path` via a synced temp file and a rename.
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("toml.tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Copies the current config into the backup directory unless a recent backup exists.
+fn backup_current() -> std::io::Result<()> {
+    let newest = Config::list_backups().into_iter().next();
+    let recent = newest
+        .and_then(|p| fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_some_and(|age| age < BACKUP_MIN_INTERVAL);
+    if recent {
+        return Ok(());
+    }
+    force_backup()
+}
+
+fn force_backup() -> std::io::Result<()> {
+    let path = config_path();
+    if !path.exists() {
+        return Ok(());
+    }
+    let dir = backup_dir();
+    fs::create_dir_all(&dir)?;
+    let name = format!("config-{}.toml", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    fs::copy(&path, dir.join(name))?;
+
+    for old in Config::list_backups().into_iter().skip(MAX_BACKUPS) {
+        if let Err(e) = fs::remove_file(&old) {
+            warn!("Failed to remove old backup {:?}: {}", old, e);
+        }
+    }
+    Ok(())
+}
+
+/// Moves values from deprecated keys to their new location (see `DEPRECATIONS`).
+fn migrate_deprecated(value: &mut toml::Value) {
+    let Some(root) = value.as_table_mut() else {
+        return;
+    };
+    'renames: for (old, new) in DEPRECATIONS {
+        let Some(old_value) = take_path(root, old) else {
+            continue;
+        };
+        let mut keys: Vec<&str> = new.split('.').collect();
+        let leaf = keys.pop().unwrap_or(new);
+        let mut table = &mut *root;
+        for key in keys {
+            table = match table
+                .entry(key.to_string())
+                .or_insert_with(|| toml::Value::Table(Default::default()))
+            {
+                toml::Value::Table(t) => t,
+                _ => {
+                    warn!("Cannot migrate '{}': '{}' is not a table", old, key);
+                    continue 'renames;
+                }
+            };
+        }
+        if table.contains_key(leaf) {
+            warn!("Ignoring deprecated setting '{}': '{}' is already set", old, new);
+        } else {
+            warn!("Setting '{}' has moved to '{}'; migrating", old, new);
+            table.insert(leaf.to_string(), old_value);
+        }
+    }
+}
+
+/// Removes and returns the value at dotted `path`, if every table on the way exists.
+fn take_path(root: &mut toml::value::Table, path: &str) -> Option<toml::Value> {
+    let mut keys: Vec<&str> = path.split('.').collect();
+    let leaf = keys.pop()?;
+    let mut table = root;
+    for key in keys {
+        table = table.get_mut(key)?.as_table_mut()?;
+    }
+    table.remove(leaf)
+}
+
+/// Logs keys the current schema doesn't know about (typos, settings from newer versions).
+fn warn_unknown_keys(value: &toml::Value) {
+    let Ok(known) = toml::Value::try_from(Config::default()) else {
+        return;
+    };
+    fn walk(value: &toml::Value, known: &toml::Value, prefix: &str) {
+        let (Some(table), Some(known_table)) = (value.as_table(), known.as_table()) else {
+            return;
+        };
+        // Empty known tables are maps with user-defined keys (devices, tags)
+        if known_table.is_empty() {
+            return;
+        }
+        for (key, child) in table {
+            let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            match known_table.get(key) {
+                Some(known_child) => walk(child, known_child, &path),
+                None => warn!("Unknown config key '{}' will be ignored", path),
+            }
+        }
+    }
+    walk(value, &known, "");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated(text: &str) -> toml::Value {
+        let mut value: toml::Value = toml::from_str(text).unwrap();
+        migrate_deprecated(&mut value);
+        value
+    }
+
+    #[test]
+    fn moves_root_key_into_its_section() {
+        let value = migrated("status_style = \"icons\"\n[ui]\nfont_size = 14\n");
+        assert_eq!(value.get("status_style"), None);
+        assert_eq!(value["ui"]["status_style"].as_str(), Some("icons"));
+        assert_eq!(value["ui"]["font_size"].as_integer(), Some(14));
+    }
+
+    #[test]
+    fn creates_missing_section() {
+        let value = migrated("dnd = true\n");
+        assert_eq!(value["notifications"]["dnd"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn keeps_new_key_already_set() {
+        let value = migrated("audio_engine = \"legacy\"\n[audio]\nengine = \"wasapi\"\n");
+        assert_eq!(value.get("audio_engine"), None);
+        assert_eq!(value["audio"]["engine"].as_str(), Some("wasapi"));
+    }
+
+    #[test]
+    fn takes_dotted_paths() {
+        let mut root: toml::value::Table = toml::from_str("[a.b]\nc = 1\nd = 2\n").unwrap();
+        assert_eq!(take_path(&mut root, "a.b.c"), Some(toml::Value::Integer(1)));
+        assert_eq!(take_path(&mut root, "a.b.c"), None);
+        assert_eq!(take_path(&mut root, "a.x.d"), None);
+        assert_eq!(take_path(&mut root, "a.b.d.e"), None);
+        assert_eq!(root["a"]["b"]["d"].as_integer(), Some(2));
+    }
+
+    #[test]
+    fn leaves_non_table_in_the_way_alone() {
+        let value = migrated("full_power_on_battery = true\nscan = \"fast\"\n");
+        assert_eq!(value["scan"].as_str(), Some("fast"));
+    }
+
+    #[test]
+    fn tag_targets_follow_the_device_order() {
+        let mut config = Config::default();
+        for (name, address) in [("Desk", 0x30), ("Phone", 0x10), ("Speaker", 0x20)] {
+            config.devices.insert(name.to_string(), address);
+            config.add_tag(address, "home");
+        }
+        // Tagged, but not saved
+        config.add_tag(0x05, "home");
+        config.device_order = vec!["Desk".into(), "Speaker".into(), "Phone".into()];
+
+        config.auto_connect = vec![format!("{}home", TAG_TARGET_PREFIX)];
+        assert_eq!(config.auto_connect_addresses(), [0x30, 0x20, 0x10, 0x05]);
+
+        config.auto_connect.insert(0, "Phone".into());
+        assert_eq!(config.auto_connect_addresses(), [0x10, 0x30, 0x20, 0x05]);
+    }
 }
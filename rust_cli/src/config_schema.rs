@@ -0,0 +1,121 @@
+//! The example config.toml printed by `config schema`: every setting at its
+//! default, commented with the doc comments of the config structs.
+
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+
+pub fn example() -> Result<String> {
+    let root = schemars::schema_for!(Config);
+    // Through `Value`, which puts plain values before tables: serialized directly,
+    // the empty lists after the sections would not be valid TOML
+    let text = toml::Value::try_from(Config::default())
+        .and_then(|value| toml::to_string(&value))
+        .map_err(|e| AppError::config(&format!("Serialization failed: {}", e)))?;
+
+    let mut out = String::from(
+        "# RedTooth config.toml with every setting at its default.\n\
+         # Settings missing from your file fall back to these values.\n\n",
+    );
+    let mut section: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(header) = table_header(trimmed) {
+            section = header.split('.').map(|part| part.trim_matches('"').to_string()).collect();
+            comment(&mut out, &root, &section);
+        } else if let Some((key, _)) = trimmed.split_once(" = ") {
+            let mut path = section.clone();
+            path.push(key.trim_matches('"').to_string());
+            comment(&mut out, &root, &path);
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// `a.b` from `[a.b]` or `[[a.b]]`.
+fn table_header(line: &str) -> Option<&str> {
+    line.strip_prefix("[[")
+        .and_then(|h| h.strip_suffix("]]"))
+        .or_else(|| line.strip_prefix('[').and_then(|h| h.strip_suffix(']')))
+}
+
+/// Writes the description of the setting at `path`, then its allowed values if it is an enum.
+fn comment(out: &mut String, root: &RootSchema, path: &[String]) {
+    let Some(field) = lookup(root, path) else {
+        return;
+    };
+    let target = resolve(root, field);
+    // A field's own doc comment wins over the doc comment of its type
+    if let Some(description) = description(field).or_else(|| description(target)) {
+        for line in description.lines() {
+            if line.is_empty() {
+                out.push_str("#\n");
+            } else {
+                out.push_str(&format!("# {}\n", line));
+            }
+        }
+    }
+
+    if let Some(values) = &target.enum_values {
+        let names: Vec<String> = values.iter().filter_map(|v| v.as_str()).map(|v| format!("\"{}\"", v)).collect();
+        out.push_str(&format!("# One of {}\n", names.join(", ")));
+    } else if let Some(variants) = target.subschemas.as_ref().and_then(|s| s.one_of.as_ref()) {
+        for variant in variants {
+            let Schema::Object(variant) = variant else {
+                continue;
+            };
+            let Some(name) = variant.enum_values.as_ref().and_then(|v| v.first()).and_then(|v| v.as_str()) else {
+                continue;
+            };
+            match description(variant) {
+                Some(description) => out.push_str(&format!("#   \"{}\": {}\n", name, description)),
+                None => out.push_str(&format!("#   \"{}\"\n", name)),
+            }
+        }
+    }
+}
+
+fn description(schema: &SchemaObject) -> Option<&str> {
+    schema.metadata.as_ref()?.description.as_deref()
+}
+
+/// The property schema for a dotted setting path, before following references.
+fn lookup<'a>(root: &'a RootSchema, path: &[String]) -> Option<&'a SchemaObject> {
+    let mut schema = &root.schema;
+    for part in path {
+        match resolve(root, schema).object.as_ref()?.properties.get(part)? {
+            Schema::Object(property) => schema = property,
+            Schema::Bool(_) => return None,
+        }
+    }
+    Some(schema)
+}
+
+/// Follows `$ref`s, the `allOf` wrapper around documented fields, `Option`s and
+/// array items to the schema that describes the value itself.
+fn resolve<'a>(root: &'a RootSchema, schema: &'a SchemaObject) -> &'a SchemaObject {
+    if let Some(name) = schema.reference.as_deref().and_then(|r| r.strip_prefix("#/definitions/")) {
+        if let Some(Schema::Object(target)) = root.definitions.get(name) {
+            return resolve(root, target);
+        }
+    }
+    if let Some(subschemas) = &schema.subschemas {
+        let wrapped = subschemas.all_of.as_deref().or(subschemas.any_of.as_deref()).unwrap_or_default();
+        // `Option<T>` is `anyOf: [T, null]`
+        if let Some(Schema::Object(inner)) = wrapped.iter().find(|s| !is_null(s)) {
+            return resolve(root, inner);
+        }
+    }
+    if let Some(SingleOrVec::Single(items)) = schema.array.as_ref().and_then(|a| a.items.as_ref()) {
+        if let Schema::Object(item) = items.as_ref() {
+            return resolve(root, item);
+        }
+    }
+    schema
+}
+
+fn is_null(schema: &Schema) -> bool {
+    matches!(schema, Schema::Object(o) if o.instance_type == Some(SingleOrVec::Single(Box::new(InstanceType::Null))))
+}
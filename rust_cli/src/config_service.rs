@@ -0,0 +1,111 @@
+use crate::bluetooth;
+use crate::config::Config;
+use crate::error::Result;
+use crate::policy::Policy;
+use log::info;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The one shared copy of the configuration.
+///
+/// Cheap to clone; every clone refers to the same config. Each change bumps a
+/// revision number which is broadcast to subscribers, so holders of a snapshot
+/// (like the GUI's working copy) know to refresh.
+///
+/// Settings locked by the machine policy are re-applied after every change,
+/// so they can't be edited away. They are kept out of config.toml: only the
+/// user's own values are saved, so lifting a policy gives them back.
+#[derive(Clone)]
+pub struct ConfigService {
+    /// What everyone reads: the user's values with the policy applied
+    config: Arc<RwLock<Config>>,
+    /// The user's own values, as in config.toml
+    own: Arc<RwLock<Config>>,
+    policy: Arc<Policy>,
+    revision: Arc<AtomicU64>,
+    watchers: Arc<Mutex<Vec<Sender<u64>>>>,
+}
+
+impl ConfigService {
+    pub fn new(config: Config) -> Self {
+        let policy = Policy::load();
+        let own = config.clone();
+        let mut config = config;
+        policy.enforce(&mut config);
+        bluetooth::set_preferred_adapters(config.preferred_adapters());
+        ConfigService {
+            config: Arc::new(RwLock::new(config)),
+            own: Arc::new(RwLock::new(own)),
+            policy: Arc::new(policy),
+            revision: Arc::new(AtomicU64::new(0)),
+            watchers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn load() -> Result<Self> {
+        Ok(Self::new(Config::load()?))
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    pub fn snapshot(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Runs `f` against the current config without copying it.
+    pub fn read<R>(&self, f: impl FnOnce(&Config) -> R) -> R {
+        f(&self.config.read().unwrap())
+    }
+
+    /// Applies `f`, saves to disk and notifies subscribers. Returns the new revision.
+    pub fn update(&self, f: impl FnOnce(&mut Config)) -> Result<u64> {
+        {
+            // Saving under the write lock keeps concurrent saves from interleaving
+            let mut config = self.config.write().unwrap();
+            let mut own = self.own.write().unwrap();
+            let mut edited = config.clone();
+            f(&mut edited);
+            self.policy.withdraw(&mut edited, &own);
+            edited.save()?;
+            *own = edited.clone();
+            self.policy.enforce(&mut edited);
+            *config = edited;
+        }
+        Ok(self.notify())
+    }
+
+    /// Replaces the whole config (e.g. with the GUI's edited copy) and saves it.
+    pub fn replace(&self, config: Config) -> Result<u64> {
+        self.update(|current| *current = config)
+    }
+
+    /// Restores a backup from disk and makes it current.
+    pub fn restore_backup(&self, path: &Path) -> Result<u64> {
+        let mut restored = Config::restore_backup(path)?;
+        *self.own.write().unwrap() = restored.clone();
+        self.policy.enforce(&mut restored);
+        *self.config.write().unwrap() = restored;
+        Ok(self.notify())
+    }
+
+    /// Receives the new revision number after every change.
+    pub fn subscribe(&self) -> Receiver<u64> {
+        let (tx, rx) = mpsc::channel();
+        self.watchers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn notify(&self) -> u64 {
+        // Every connect path honors these, so they are kept next to the Bluetooth calls
+        bluetooth::set_preferred_adapters(self.read(Config::preferred_adapters));
+        let revision = self.revision.fetch_add(1, Ordering::SeqCst) + 1;
+        info!("Config changed (revision {})", revision);
+        // Dropped receivers are pruned here
+        self.watchers.lock().unwrap().retain(|tx| tx.send(revision).is_ok());
+        revision
+    }
+}
@@ -0,0 +1,164 @@
+//! Safe mode after repeated crashes. Each GUI session marks itself running in
+//! `session.toml` and clears the mark on a clean exit; a mark still set at the
+//! next start means the last session died. The config of the last session
+//! that exited cleanly is kept so the settings changed since can be undone.
+
+use crate::config::{self, Config};
+use crate::error::{AppError, Result};
+use crate::paths;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Consecutive abnormal exits before the next start is in safe mode
+pub const SAFE_MODE_AFTER: u32 = 3;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Session {
+    running: bool,
+    crashes: u32,
+}
+
+fn session_path() -> PathBuf {
+    paths::user_file("session.toml")
+}
+
+fn known_good_path() -> PathBuf {
+    paths::user_file("config_known_good.toml")
+}
+
+fn read_session() -> Session {
+    fs::read_to_string(session_path())
+        .ok()
+        .and_then(|text| toml::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_session(session: &Session) {
+    let result = toml::to_string(session)
+        .map_err(|e| AppError::config(&format!("Serialization failed: {}", e)))
+        .and_then(|text| fs::write(session_path(), text).map_err(AppError::from));
+    if let Err(e) = result {
+        warn!("Failed to record session state: {}", e);
+    }
+}
+
+/// Marks a session as started. Returns how many sessions in a row before it
+/// ended without a clean exit.
+pub fn begin() -> u32 {
+    let mut session = read_session();
+    if session.running {
+        session.crashes += 1;
+        warn!("The previous session did not exit cleanly ({} in a row)", session.crashes);
+    } else {
+        session.crashes = 0;
+    }
+    let crashes = session.crashes;
+    session.running = true;
+    write_session(&session);
+    crashes
+}
+
+/// Marks the session as ended cleanly and, if `config_ran` is set, keeps the
+/// config it ran with as the known-good one.
+pub fn end_clean(config_ran: bool) {
+    write_session(&Session::default());
+    let current = config::config_path();
+    if config_ran && current.exists() {
+        if let Err(e) = fs::copy(&current, known_good_path()) {
+            warn!("Failed to keep the known-good config: {}", e);
+        }
+    }
+}
+
+/// One setting that differs from the known-good config.
+#[derive(Debug, Clone)]
+pub struct SettingChange {
+    /// Table keys down to the setting; kept apart since device names may contain dots
+    pub keys: Vec<String>,
+    /// Value in the known-good config; `None` if it wasn't set there
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl SettingChange {
+    /// Dotted path, as used by `config get`
+    pub fn path(&self) -> String {
+        self.keys.join(".")
+    }
+}
+
+/// Settings changed since the last clean exit. Empty if there is no known-good
+/// config yet, or either file doesn't parse.
+pub fn changed_settings() -> Vec<SettingChange> {
+    let (Some(before), Some(after)) = (read_value(&known_good_path()), read_value(&config::config_path())) else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    diff(Vec::new(), Some(&before), Some(&after), &mut changes);
+    changes
+}
+
+fn read_value(path: &Path) -> Option<toml::Value> {
+    toml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn diff(keys: Vec<String>, before: Option<&toml::Value>, after: Option<&toml::Value>, out: &mut Vec<SettingChange>) {
+    if before == after {
+        return;
+    }
+    if let (Some(toml::Value::Table(before)), Some(toml::Value::Table(after))) = (before, after) {
+        let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+        names.sort();
+        names.dedup();
+        for key in names {
+            let mut child = keys.clone();
+            child.push(key.clone());
+            diff(child, before.get(key), after.get(key), out);
+        }
+        return;
+    }
+    out.push(SettingChange { keys, before: before.map(|v| v.to_string()), after: after.map(|v| v.to_string()) });
+}
+
+/// Puts the given settings back to their known-good values and saves config.toml.
+/// The rest of the file is left as it is.
+pub fn revert(changes: &[SettingChange]) -> Result<()> {
+    let known_good = read_value(&known_good_path()).ok_or_else(|| AppError::config("No known-good config to revert to"))?;
+    let mut current = read_value(&config::config_path()).ok_or_else(|| AppError::config("config.toml doesn't parse"))?;
+    for change in changes {
+        let value = change.keys.iter().try_fold(&known_good, |v, key| v.get(key.as_str()));
+        set(&mut current, &change.keys, value.cloned());
+    }
+    let config: Config = current.try_into().map_err(|e| AppError::config(&e.to_string()))?;
+    config.validate()?;
+    config.save()?;
+    info!("Reverted {} setting(s) to the known-good config", changes.len());
+    Ok(())
+}
+
+/// Sets or, for `None`, removes the value at `keys`, creating tables on the way.
+fn set(value: &mut toml::Value, keys: &[String], new: Option<toml::Value>) {
+    let Some((last, parents)) = keys.split_last() else {
+        return;
+    };
+    let mut table = value;
+    for key in parents {
+        let Some(t) = table.as_table_mut() else {
+            return;
+        };
+        table = t.entry(key.clone()).or_insert_with(|| toml::Value::Table(Default::default()));
+    }
+    let Some(table) = table.as_table_mut() else {
+        return;
+    };
+    match new {
+        Some(new) => {
+            table.insert(last.clone(), new);
+        }
+        None => {
+            table.remove(last);
+        }
+    }
+}
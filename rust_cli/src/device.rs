@@ -0,0 +1,186 @@
+use std::fmt;
+
+/// Where a device's link stands.
+///
+/// Connect and disconnect move through `Connecting`/`Disconnecting`; a failed
+/// connect lands in `Failed`, which otherwise behaves like `Disconnected`.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    Disconnecting,
+    Failed(String),
+}
+
+impl ConnectionState {
+    pub fn is_connected(&self) -> bool {
+        *self == ConnectionState::Connected
+    }
+
+    /// A connect or disconnect is in flight; no other link operation may start.
+    pub fn in_transition(&self) -> bool {
+        matches!(self, ConnectionState::Connecting | ConnectionState::Disconnecting)
+    }
+
+    pub fn can_connect(&self) -> bool {
+        matches!(self, ConnectionState::Disconnected | ConnectionState::Failed(_))
+    }
+
+    /// Folds a scanner report into the current state. Reports lag behind, so
+    /// transitions in flight are kept, and so is a failure until the device
+    /// actually shows up connected.
+    pub fn with_report(&self, connected: bool) -> ConnectionState {
+        match self {
+            ConnectionState::Connecting | ConnectionState::Disconnecting => self.clone(),
+            ConnectionState::Failed(_) if !connected => self.clone(),
+            _ if connected => ConnectionState::Connected,
+            _ => ConnectionState::Disconnected,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Disconnected => f.write_str("disconnected"),
+            ConnectionState::Connecting => f.write_str("connecting"),
+            ConnectionState::Connected => f.write_str("connected"),
+            ConnectionState::Disconnecting => f.write_str("disconnecting"),
+            ConnectionState::Failed(reason) => write!(f, "failed ({})", reason),
+        }
+    }
+}
+
+/// A device as last reported by the scanner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BluetoothDevice {
+    pub address: u64,
+    pub name: String,
+    pub state: ConnectionState,
+    pub authenticated: bool,
+    pub rssi: i32,
+    pub cod: u32,
+}
+
+impl BluetoothDevice {
+    pub fn major_class(&self) -> MajorClass {
+        MajorClass::from_cod(self.cod)
+    }
+
+    /// Audio service bit of the Class of Device, set by most headsets and speakers.
+    pub fn has_audio_service(&self) -> bool {
+        self.cod & COD_SERVICE_AUDIO != 0
+    }
+
+    /// Headsets and speakers. Phones set the audio bit for calls, so they don't count.
+    pub fn is_audio_output(&self) -> bool {
+        self.has_audio_service() && self.major_class() != MajorClass::Phone
+    }
+
+    /// Keyboards, mice and other HID peripherals; losing one can leave the PC without input.
+    pub fn is_input_device(&self) -> bool {
+        self.major_class() == MajorClass::Peripheral
+    }
+
+    /// Shortcuts worth offering on the device's card, from its class and service bits.
+    pub fn quick_actions(&self) -> Vec<QuickAction> {
+        let mut actions = Vec::new();
+        if self.is_audio_output() {
+            actions.extend([QuickAction::Volume, QuickAction::PlayPause]);
+        }
+        if self.is_input_device() {
+            actions.extend([QuickAction::Reconnect, QuickAction::Battery]);
+        }
+        let class = self.major_class();
+        if class == MajorClass::Phone || class == MajorClass::Computer || self.cod & COD_SERVICE_OBJECT_TRANSFER != 0 {
+            actions.push(QuickAction::SendFile);
+        }
+        if class == MajorClass::Phone || self.cod & COD_SERVICE_NETWORKING != 0 {
+            actions.push(QuickAction::Tether);
+        }
+        actions
+    }
+}
+
+/// A class-specific shortcut on a device card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuickAction {
+    /// Volume of the device's audio output
+    Volume,
+    /// Play/Pause media key for whatever is playing
+    PlayPause,
+    /// Drops and re-establishes the link, the usual cure for a laggy keyboard or mouse
+    Reconnect,
+    Battery,
+    SendFile,
+    /// Shares the phone's internet over PAN; offered once SDP shows it has a NAP
+    Tether,
+}
+
+/// Major service class bit 21 (Audio)
+pub const COD_SERVICE_AUDIO: u32 = 0x200000;
+/// Major service class bit 17 (Networking)
+pub const COD_SERVICE_NETWORKING: u32 = 0x020000;
+/// Major service class bit 20 (Object Transfer)
+pub const COD_SERVICE_OBJECT_TRANSFER: u32 = 0x100000;
+
+/// Major device class, bits 8-12 of the Class of Device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MajorClass {
+    Miscellaneous,
+    Computer,
+    Phone,
+    NetworkAccessPoint,
+    AudioVideo,
+    Peripheral,
+    Imaging,
+    Wearable,
+    Toy,
+    Health,
+    Uncategorized,
+    /// Values the spec doesn't assign yet
+    Reserved(u8),
+}
+
+impl MajorClass {
+    pub fn from_cod(cod: u32) -> Self {
+        match ((cod >> 8) & 0x1F) as u8 {
+            0x00 => MajorClass::Miscellaneous,
+            0x01 => MajorClass::Computer,
+            0x02 => MajorClass::Phone,
+            0x03 => MajorClass::NetworkAccessPoint,
+            0x04 => MajorClass::AudioVideo,
+            0x05 => MajorClass::Peripheral,
+            0x06 => MajorClass::Imaging,
+            0x07 => MajorClass::Wearable,
+            0x08 => MajorClass::Toy,
+            0x09 => MajorClass::Health,
+            0x1F => MajorClass::Uncategorized,
+            other => MajorClass::Reserved(other),
+        }
+    }
+
+    /// How devices of this class are usually put into pairing mode.
+    pub fn pairing_instructions(self) -> &'static str {
+        match self {
+            MajorClass::AudioVideo => {
+                "Turn the headset or speaker off, then hold the power (or Bluetooth) button for 5–7 seconds \
+                 until the light flashes, often blue and red. Some models announce \"pairing\"."
+            }
+            MajorClass::Peripheral => {
+                "Press the connect or pairing button, usually underneath or next to the power switch, \
+                 until the light blinks quickly. Multi-device keyboards hold a channel key for 3 seconds."
+            }
+            MajorClass::Phone | MajorClass::Computer => {
+                "Remove this PC from the device's Bluetooth list too, then keep its Bluetooth settings \
+                 screen open so it stays discoverable."
+            }
+            MajorClass::Wearable | MajorClass::Health => {
+                "Open the device's companion app or settings menu and choose to pair a new device."
+            }
+            _ => "Put the device into pairing mode; its manual describes how. A fast-blinking light usually means it's ready.",
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::device::BluetoothDevice;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Devices seen while scanning, in discovery order, indexed by address.
+///
+/// Scans report the same devices over and over; reports that change nothing
+/// leave the store clean, and `snapshot()` only rebuilds after a real change,
+/// so busy discovery doesn't cost a list copy every frame.
+#[derive(Default)]
+pub struct DeviceStore {
+    devices: Vec<BluetoothDevice>,
+    index: HashMap<u64, usize>,
+    snapshot: Option<Arc<[BluetoothDevice]>>,
+}
+
+impl DeviceStore {
+    /// Adds or replaces a device. Returns false if it was already stored unchanged.
+    pub fn upsert(&mut self, device: BluetoothDevice) -> bool {
+        match self.index.get(&device.address) {
+            Some(&i) if self.devices[i] == device => return false,
+            Some(&i) => self.devices[i] = device,
+            None => {
+                self.index.insert(device.address, self.devices.len());
+                self.devices.push(device);
+            }
+        }
+        self.snapshot = None;
+        true
+    }
+
+    /// Applies `f` to a stored device. Returns false if the device is unknown or unchanged.
+    pub fn update(&mut self, address: u64, f: impl FnOnce(&mut BluetoothDevice)) -> bool {
+        let Some(&i) = self.index.get(&address) else {
+            return false;
+        };
+        let before = self.devices[i].clone();
+        f(&mut self.devices[i]);
+        if self.devices[i] == before {
+            return false;
+        }
+        self.snapshot = None;
+        true
+    }
+
+    pub fn get(&self, address: u64) -> Option<&BluetoothDevice> {
+        self.index.get(&address).map(|&i| &self.devices[i])
+    }
+
+    pub fn clear(&mut self) {
+        self.devices.clear();
+        self.index.clear();
+        self.snapshot = None;
+    }
+
+    /// Shared copy of the list for drawing; cheap to call again until the next change.
+    pub fn snapshot(&mut self) -> Arc<[BluetoothDevice]> {
+        self.snapshot.get_or_insert_with(|| self.devices.as_slice().into()).clone()
+    }
+}
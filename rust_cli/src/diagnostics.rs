@@ -0,0 +1,71 @@
+use crate::audio;
+use crate::error::AppError;
+use crate::ffi::AudioSinkStats;
+use log::warn;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Samples kept per sink (two minutes at the poll interval)
+pub const HISTORY_LEN: usize = 120;
+
+/// Rolling per-sink audio statistics, polled from the engine while a session runs.
+#[derive(Debug, Default)]
+pub struct AudioDiagnostics {
+    pub history: HashMap<u64, VecDeque<AudioSinkStats>>,
+    /// Set when the engine can't report statistics; polling stops until `clear`
+    pub unsupported: Option<String>,
+    last_poll: Option<Instant>,
+}
+
+impl AudioDiagnostics {
+    /// Samples every sink if the poll interval has elapsed. Sinks no longer in
+    /// `sinks` are dropped so a new session starts with a clean graph.
+    pub fn poll(&mut self, sinks: &[u64]) {
+        if self.unsupported.is_some() || self.last_poll.is_some_and(|t| t.elapsed() < POLL_INTERVAL) {
+            return;
+        }
+        self.last_poll = Some(Instant::now());
+        self.history.retain(|address, _| sinks.contains(address));
+
+        for address in sinks {
+            match audio::sink_stats(*address) {
+                Ok(Some(stats)) => {
+                    let samples = self.history.entry(*address).or_default();
+                    if samples.len() == HISTORY_LEN {
+                        samples.pop_front();
+                    }
+                    samples.push_back(stats);
+                }
+                Ok(None) => {}
+                Err(AppError::NotSupported(reason)) => {
+                    warn!("Audio stats unavailable: {}", reason);
+                    self.unsupported = Some(reason);
+                    return;
+                }
+                Err(e) => warn!("Failed to read audio stats for {:X}: {}", address, e),
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.unsupported = None;
+        self.last_poll = None;
+    }
+
+    pub fn latest(&self, address: u64) -> Option<&AudioSinkStats> {
+        self.history.get(&address).and_then(|s| s.back())
+    }
+
+    /// Counter increase over the retained window, e.g. underruns in the last two minutes.
+    pub fn recent_delta(&self, address: u64, counter: fn(&AudioSinkStats) -> u32) -> u32 {
+        match self.history.get(&address) {
+            Some(samples) => match (samples.front(), samples.back()) {
+                (Some(first), Some(last)) => counter(last).saturating_sub(counter(first)),
+                _ => 0,
+            },
+            None => 0,
+        }
+    }
+}
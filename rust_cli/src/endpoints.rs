@@ -0,0 +1,154 @@
+use crate::audio;
+use crate::bluetooth;
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+use std::time::{Duration, Instant};
+
+/// The default can change outside the app (Sound settings, plugging in a USB headset)
+pub const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// Windows usually creates a headset's endpoint within a few seconds of connecting
+pub const READY_TIMEOUT: Duration = Duration::from_secs(15);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Bluetooth device behind the system default output, or `None` if the default
+/// is a built-in or wired output.
+pub fn default_output() -> Result<Option<u64>> {
+    let mut address = 0u64;
+    match ffi_call!(audio_get_default_output(&mut address)) {
+        ffi::FfiErrorCode::Success => Ok((address != 0).then_some(address)),
+        _ => Err(AppError::audio(&audio::last_error())),
+    }
+}
+
+/// Makes the device's audio endpoint the system default output for all roles.
+pub fn make_default_output(address: u64) -> Result<()> {
+    println!("CLI: Action -> Make Default Output {:X}", address);
+    match ffi_call!(audio_set_default_output(address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&audio::last_error())),
+    }
+}
+
+/// Opaque ID of the current default output endpoint, whatever kind it is, so it
+/// can be put back with `restore_default_output`.
+pub fn default_output_id() -> Option<String> {
+    let id = unsafe {
+        let ptr = ffi::audio_get_default_output_id();
+        if ptr.is_null() {
+            return None;
+        }
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    };
+    (!id.is_empty()).then_some(id)
+}
+
+pub fn restore_default_output(endpoint_id: &str) -> Result<()> {
+    println!("CLI: Action -> Restore Default Output");
+    let id = CString::new(endpoint_id).map_err(|_| AppError::audio("Endpoint ID contains a NUL byte"))?;
+    match ffi_call!(audio_set_default_output_id(id.as_ptr())) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&audio::last_error())),
+    }
+}
+
+/// True once Windows has created the device's audio output; `audio::add_sink`
+/// fails before that.
+pub fn endpoint_ready(address: u64) -> bool {
+    ffi_call!(audio_endpoint_ready(address))
+}
+
+/// Battery percentage Windows reports for a headset over hands-free, `None` if it reports none.
+pub fn battery_level(address: u64) -> Result<Option<u8>> {
+    let mut level: c_int = -1;
+    match ffi_call!(bt_get_battery_level(address, &mut level)) {
+        ffi::FfiErrorCode::Success => Ok(u8::try_from(level).ok()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+/// Volume of the device's audio output, 0.0 to 1.0.
+pub fn volume(address: u64) -> Result<f32> {
+    let mut level = 0.0f32;
+    match ffi_call!(audio_get_device_volume(address, &mut level)) {
+        ffi::FfiErrorCode::Success => Ok(level),
+        _ => Err(AppError::audio(&audio::last_error())),
+    }
+}
+
+pub fn set_volume(address: u64, level: f32) -> Result<()> {
+    match ffi_call!(audio_set_device_volume(address, level.clamp(0.0, 1.0))) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&audio::last_error())),
+    }
+}
+
+/// Presses Play/Pause for whichever app holds the system media session.
+pub fn media_play_pause() -> Result<()> {
+    println!("CLI: Action -> Media Play/Pause");
+    match ffi_call!(audio_media_play_pause()) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::audio(&audio::last_error())),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitState {
+    Waiting,
+    Ready,
+    /// Devices whose endpoint never appeared
+    TimedOut(Vec<u64>),
+}
+
+/// Waits for the endpoints of freshly connected devices without blocking the
+/// UI. Call `poll` every frame; the core is asked at most every half second.
+#[derive(Debug, Clone)]
+pub struct EndpointWait {
+    pending: Vec<u64>,
+    total: usize,
+    started: Instant,
+    last_poll: Option<Instant>,
+}
+
+impl EndpointWait {
+    pub fn new(addresses: &[u64]) -> Self {
+        EndpointWait {
+            pending: addresses.to_vec(),
+            total: addresses.len(),
+            started: Instant::now(),
+            last_poll: None,
+        }
+    }
+
+    pub fn poll(&mut self) -> WaitState {
+        if self.last_poll.is_some_and(|t| t.elapsed() < READY_POLL_INTERVAL) {
+            return WaitState::Waiting;
+        }
+        self.last_poll = Some(Instant::now());
+        self.pending.retain(|address| !endpoint_ready(*address));
+
+        if self.pending.is_empty() {
+            WaitState::Ready
+        } else if self.started.elapsed() >= READY_TIMEOUT {
+            WaitState::TimedOut(self.pending.clone())
+        } else {
+            WaitState::Waiting
+        }
+    }
+
+    /// Devices still without an endpoint
+    pub fn pending(&self) -> &[u64] {
+        &self.pending
+    }
+
+    /// Devices ready so far, out of the total waited for
+    pub fn ready_count(&self) -> (usize, usize) {
+        (self.total - self.pending.len(), self.total)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
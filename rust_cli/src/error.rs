@@ -1,3 +1,4 @@
+use crate::device::ConnectionState;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -14,6 +15,27 @@ pub enum AppError {
     #[error("Bluetooth operation failed: {0}")]
     Bluetooth(String),
     
+    /// The device is known but its bond is broken; re-pairing usually fixes it
+    #[error("Pairing problem: {0}")]
+    Pairing(String),
+    
+    /// The device didn't answer the page: switched off or out of range
+    #[error("Device not responding: {0}")]
+    Unreachable(String),
+    
+    /// The device is in range but refused the page, typically because it is
+    /// connected to another computer or phone
+    #[error("Likely connected elsewhere: {0}")]
+    ConnectedElsewhere(String),
+    
+    /// The link is mid-transition, e.g. disconnect requested while still connecting
+    #[error("Cannot {action} {address:X} while it is {state}")]
+    InvalidState { address: u64, action: &'static str, state: ConnectionState },
+    
+    /// The core has no implementation for this on this system
+    #[error("Not supported: {0}")]
+    NotSupported(String),
+    
     #[error("Audio operation failed: {0}")]
     Audio(String),
     
@@ -46,6 +68,15 @@ impl AppError {
     pub fn config(msg: &str) -> Self {
         AppError::Config(msg.to_string())
     }
+
+    /// SQLite gave up waiting for another connection to release the database.
+    pub fn is_database_locked(&self) -> bool {
+        matches!(
+            self,
+            AppError::Database(rusqlite::Error::SqliteFailure(e, _))
+                if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+        )
+    }
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
\ No newline at end of file
@@ -10,6 +10,94 @@ pub struct DiscoveredDevice {
     pub cod: u32,
 }
 
+// Local adapter identity; versions and subversions come from the controller's HCI info
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AdapterInfo {
+    pub address: u64,
+    /// UTF-8, NUL-terminated
+    pub name: [c_char; 248],
+    /// Bluetooth SIG company identifier
+    pub manufacturer: u16,
+    pub hci_version: u8,
+    pub hci_revision: u16,
+    pub lmp_version: u8,
+    pub lmp_subversion: u16,
+}
+
+impl Default for AdapterInfo {
+    fn default() -> Self {
+        AdapterInfo {
+            address: 0,
+            name: [0; 248],
+            manufacturer: 0,
+            hci_version: 0,
+            hci_revision: 0,
+            lmp_version: 0,
+            lmp_subversion: 0,
+        }
+    }
+}
+
+// Classic inquiry tuning, see bt_set_scan_tuning
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanTuning {
+    /// In 1.28 s units, 1-48
+    pub inquiry_length: u8,
+    /// Idle time between inquiries, 500-60000
+    pub cycle_gap_ms: u32,
+}
+
+// Per-sink renderer diagnostics (counters are cumulative since audio_add_device)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioSinkStats {
+    pub buffer_fill_percent: u32,
+    pub underruns: u32,
+    pub resyncs: u32,
+}
+
+// PAN link state; byte counters are since the link came up
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PanLinkStatus {
+    pub connected: bool,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+// An LE Audio (Auracast) broadcast source seen in extended advertising
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct BroadcastSource {
+    pub address: u64,
+    /// 0 public, 1 random
+    pub address_type: u8,
+    pub advertising_sid: u8,
+    /// 24-bit Broadcast_ID
+    pub broadcast_id: u32,
+    /// Broadcast Name, or null; valid during the callback only
+    pub name: *const c_char,
+    pub rssi: c_int,
+    pub encrypted: bool,
+    /// 0 unknown, 1 standard, 2 high quality
+    pub quality: u8,
+}
+
+// A bond from the stack's key store
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct BondRecord {
+    pub adapter: u64,
+    pub address: u64,
+    /// LE keys rather than a BR/EDR link key
+    pub le: bool,
+    /// Serialized registry values; valid during the callback only
+    pub values: *const u8,
+    pub values_len: u32,
+}
+
 // Error codes for FFI operations
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -21,12 +109,71 @@ pub enum FfiErrorCode {
     DeviceNotFound = 4,
     ConnectionFailed = 5,
     AudioInitFailed = 6,
+    AuthFailed = 7,
+    PageTimeout = 8,
+    PeerBusy = 9,
+    NotSupported = 10,
     UnknownError = 255,
 }
 
 // Callback types
 pub type OnDeviceFoundCallback = extern "C" fn(device: DiscoveredDevice);
 pub type OnErrorCallback = extern "C" fn(error_code: FfiErrorCode, message: *const c_char);
+pub type OnRemoteNameCallback = extern "C" fn(address: u64, name: *const c_char);
+pub type OnConnectionChangedCallback = extern "C" fn(address: u64, connected: bool);
+// call_state is an HfpCallState value; passed as int so unknown values can't produce an invalid enum
+pub type OnHfpStateCallback = extern "C" fn(address: u64, call_state: c_int, mic_muted: bool);
+
+#[cfg(feature = "gatt")]
+pub type OnGattValueCallback =
+    extern "C" fn(address: u64, characteristic_uuid: u16, value: *const u8, length: u32);
+#[cfg(feature = "gatt")]
+pub type OnGattCharacteristicCallback =
+    extern "C" fn(address: u64, service_uuid: u16, characteristic_uuid: u16, properties: u8);
+#[cfg(feature = "obex")]
+pub type OnObexProgressCallback =
+    extern "C" fn(transfer_id: u32, state: c_int, bytes_done: u64, bytes_total: u64);
+#[cfg(feature = "obex")]
+pub type OnObexIncomingCallback =
+    extern "C" fn(transfer_id: u32, address: u64, file_name: *const c_char, size: u64);
+pub type OnPbapResultCallback =
+    extern "C" fn(address: u64, folder: c_int, result: FfiErrorCode, vcards: *const c_char);
+pub type OnPairingResultCallback = extern "C" fn(address: u64, result: FfiErrorCode);
+pub type OnAudioTapCallback =
+    extern "C" fn(samples: *const i16, frames: u32, channels: u16, sample_rate: u32);
+pub type OnHciPacketCallback = extern "C" fn(packet_type: u8, received: bool, data: *const u8, length: u32);
+pub type OnBondCallback = extern "C" fn(bond: *const BondRecord);
+pub type OnBroadcastFoundCallback = extern "C" fn(source: *const BroadcastSource);
+pub type OnSignalCallback = extern "C" fn(address: u64, rssi: c_int);
+
+#[cfg(feature = "obex")]
+pub const OBEX_PENDING: c_int = 0;
+#[cfg(feature = "obex")]
+pub const OBEX_IN_PROGRESS: c_int = 1;
+#[cfg(feature = "obex")]
+pub const OBEX_COMPLETE: c_int = 2;
+#[cfg(feature = "obex")]
+pub const OBEX_FAILED: c_int = 3;
+#[cfg(feature = "obex")]
+pub const OBEX_CANCELLED: c_int = 4;
+
+pub const PBAP_PHONEBOOK: c_int = 0;
+pub const PBAP_CALL_HISTORY: c_int = 1;
+
+pub const RESAMPLER_LOW: c_int = 0;
+pub const RESAMPLER_MEDIUM: c_int = 1;
+pub const RESAMPLER_HIGH: c_int = 2;
+
+/// Must match `BT_API_VERSION` in BluetoothManager.h
+pub const BT_API_VERSION: u32 = 16;
+
+pub const RADIO_ABSENT: c_int = 0;
+pub const RADIO_OFF: c_int = 1;
+pub const RADIO_ON: c_int = 2;
+
+pub const HFP_CALL_IDLE: c_int = 0;
+pub const HFP_CALL_RINGING: c_int = 1;
+pub const HFP_CALL_ACTIVE: c_int = 2;
 
 // #[link(name = "bt_core", kind = "static")]
 extern "C" {
@@ -34,8 +181,56 @@ extern "C" {
     pub fn bt_init(error_callback: OnErrorCallback) -> FfiErrorCode;
     pub fn bt_start_scan(callback: OnDeviceFoundCallback, error_callback: OnErrorCallback) -> FfiErrorCode;
     pub fn bt_stop_scan() -> FfiErrorCode;
+    pub fn bt_set_low_power_scan(enabled: bool) -> FfiErrorCode;
+    pub fn bt_set_scan_tuning(tuning: *const ScanTuning) -> FfiErrorCode;
+    pub fn bt_signal_monitor_supported() -> bool;
+    pub fn bt_signal_monitor_start(callback: OnSignalCallback) -> FfiErrorCode;
+    pub fn bt_signal_monitor_stop() -> FfiErrorCode;
     pub fn bt_connect_device(address: u64) -> FfiErrorCode;
+    pub fn bt_connect_device_via(address: u64, adapter_address: u64) -> FfiErrorCode;
     pub fn bt_disconnect_device(address: u64) -> FfiErrorCode;
+    pub fn bt_unpair_device(address: u64) -> FfiErrorCode;
+    pub fn bt_pair_device(address: u64, callback: OnPairingResultCallback) -> FfiErrorCode;
+    pub fn bt_request_remote_name(address: u64, callback: OnRemoteNameCallback) -> FfiErrorCode;
+    pub fn bt_set_connection_callback(callback: OnConnectionChangedCallback) -> FfiErrorCode;
+    pub fn bt_hfp_supported() -> bool;
+    pub fn bt_set_hfp_callback(callback: OnHfpStateCallback) -> FfiErrorCode;
+    pub fn bt_set_mic_mute(address: u64, muted: bool) -> FfiErrorCode;
+    pub fn bt_send_at_command(address: u64, channel: u8, command: *const c_char, reply: *mut c_char, reply_len: usize) -> FfiErrorCode;
+    pub fn bt_rfcomm_exchange(
+        address: u64,
+        service_uuid: *const c_char,
+        request: *const u8,
+        request_len: usize,
+        reply: *mut u8,
+        reply_cap: usize,
+        reply_len: *mut usize,
+    ) -> FfiErrorCode;
+    #[cfg(feature = "gatt")]
+    pub fn bt_gatt_set_value_callback(callback: OnGattValueCallback) -> FfiErrorCode;
+    #[cfg(feature = "gatt")]
+    pub fn bt_gatt_discover(address: u64, callback: OnGattCharacteristicCallback) -> FfiErrorCode;
+    #[cfg(feature = "gatt")]
+    pub fn bt_gatt_subscribe(address: u64, service_uuid: u16, characteristic_uuid: u16) -> FfiErrorCode;
+    #[cfg(feature = "gatt")]
+    pub fn bt_gatt_unsubscribe(address: u64, characteristic_uuid: u16) -> FfiErrorCode;
+    #[cfg(feature = "gatt")]
+    pub fn bt_gatt_read(address: u64, service_uuid: u16, characteristic_uuid: u16) -> FfiErrorCode;
+    #[cfg(feature = "obex")]
+    pub fn bt_obex_set_callbacks(progress: OnObexProgressCallback, incoming: OnObexIncomingCallback) -> FfiErrorCode;
+    #[cfg(feature = "obex")]
+    pub fn bt_obex_send_file(address: u64, path: *const c_char, transfer_id: *mut u32) -> FfiErrorCode;
+    #[cfg(feature = "obex")]
+    pub fn bt_obex_accept(transfer_id: u32, save_path: *const c_char) -> FfiErrorCode;
+    #[cfg(feature = "obex")]
+    pub fn bt_obex_reject(transfer_id: u32) -> FfiErrorCode;
+    #[cfg(feature = "obex")]
+    pub fn bt_obex_cancel(transfer_id: u32) -> FfiErrorCode;
+    pub fn bt_pan_has_nap(address: u64) -> bool;
+    pub fn bt_pan_connect(address: u64) -> FfiErrorCode;
+    pub fn bt_pan_disconnect(address: u64) -> FfiErrorCode;
+    pub fn bt_pan_get_status(address: u64, out: *mut PanLinkStatus) -> FfiErrorCode;
+    pub fn bt_pbap_pull(address: u64, folder: c_int, callback: OnPbapResultCallback) -> FfiErrorCode;
     
     // Audio
     pub fn audio_init(error_callback: OnErrorCallback) -> FfiErrorCode;
@@ -43,6 +238,47 @@ extern "C" {
     pub fn audio_stop() -> FfiErrorCode;
     pub fn audio_add_device(address: u64) -> FfiErrorCode;
     pub fn audio_remove_device(address: u64) -> FfiErrorCode;
+    #[cfg(feature = "audio-routing")]
+    pub fn audio_app_routes_supported() -> bool;
+    #[cfg(feature = "audio-routing")]
+    pub fn audio_set_app_route(process_name: *const c_char, sink_address: u64) -> FfiErrorCode;
+    #[cfg(feature = "audio-routing")]
+    pub fn audio_clear_app_routes() -> FfiErrorCode;
+    pub fn audio_tap_supported() -> bool;
+    pub fn audio_set_tap(callback: Option<OnAudioTapCallback>) -> FfiErrorCode;
+    pub fn audio_play_test_tone(address: u64) -> FfiErrorCode;
+    pub fn audio_set_sink_muted(address: u64, muted: bool) -> FfiErrorCode;
+    pub fn audio_crossfade_supported() -> bool;
+    pub fn audio_crossfade(from: u64, to: u64, duration_ms: u32) -> FfiErrorCode;
+    pub fn audio_engine_settings_supported() -> bool;
+    pub fn audio_set_buffer_ms(buffer_ms: u32) -> FfiErrorCode;
+    pub fn audio_set_resampler_quality(quality: c_int) -> FfiErrorCode;
+    pub fn audio_set_sample_rate(sample_rate: u32) -> FfiErrorCode;
+    pub fn audio_get_sink_stats(address: u64, out: *mut AudioSinkStats) -> FfiErrorCode;
+    pub fn audio_get_default_output(address: *mut u64) -> FfiErrorCode;
+    pub fn audio_set_default_output(address: u64) -> FfiErrorCode;
+    pub fn audio_get_default_output_id() -> *const c_char;
+    pub fn audio_set_default_output_id(endpoint_id: *const c_char) -> FfiErrorCode;
+    pub fn audio_endpoint_ready(address: u64) -> bool;
+    pub fn bt_get_battery_level(address: u64, level: *mut c_int) -> FfiErrorCode;
+    pub fn audio_get_device_volume(address: u64, level: *mut f32) -> FfiErrorCode;
+    pub fn audio_set_device_volume(address: u64, level: f32) -> FfiErrorCode;
+    pub fn audio_media_play_pause() -> FfiErrorCode;
+
+    // LE Audio broadcasts
+    pub fn bt_le_audio_supported() -> bool;
+    pub fn bt_broadcast_scan_start(callback: OnBroadcastFoundCallback) -> FfiErrorCode;
+    pub fn bt_broadcast_scan_stop() -> FfiErrorCode;
+    pub fn bt_broadcast_join(sink_address: u64, source: *const BroadcastSource, broadcast_code: *const u8) -> FfiErrorCode;
+
+    // Developer HCI capture
+    pub fn bt_hci_capture_start(callback: OnHciPacketCallback) -> FfiErrorCode;
+    pub fn bt_hci_capture_stop() -> FfiErrorCode;
+
+    // Bond backup
+    pub fn bt_bonds_export(callback: OnBondCallback) -> FfiErrorCode;
+    pub fn bt_bond_import(bond: *const BondRecord) -> FfiErrorCode;
+    pub fn bt_bonds_reload() -> FfiErrorCode;
     
     // Error handling
     pub fn bt_get_last_error() -> *const c_char;
@@ -53,4 +289,10 @@ extern "C" {
     
     // Permission check
     pub fn bt_check_permission() -> bool;
+
+    // Self-test
+    pub fn bt_get_api_version() -> u32;
+    pub fn bt_get_radio_state() -> c_int;
+    pub fn bt_get_adapter_info(out: *mut AdapterInfo) -> FfiErrorCode;
+    pub fn bt_list_adapters(out: *mut AdapterInfo, capacity: u32, count: *mut u32) -> FfiErrorCode;
 }
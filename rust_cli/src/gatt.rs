@@ -0,0 +1,277 @@
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use log::warn;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// ---- Standard UUIDs (Bluetooth SIG assigned numbers) ----
+
+pub const SERVICE_HEART_RATE: u16 = 0x180D;
+pub const SERVICE_BATTERY: u16 = 0x180F;
+pub const SERVICE_DEVICE_INFORMATION: u16 = 0x180A;
+
+pub const CHAR_HEART_RATE_MEASUREMENT: u16 = 0x2A37;
+pub const CHAR_BATTERY_LEVEL: u16 = 0x2A19;
+pub const CHAR_MODEL_NUMBER: u16 = 0x2A24;
+pub const CHAR_FIRMWARE_REVISION: u16 = 0x2A26;
+pub const CHAR_MANUFACTURER_NAME: u16 = 0x2A29;
+
+const PROP_READ: u8 = 0x02;
+const PROP_NOTIFY: u8 = 0x10;
+const PROP_INDICATE: u8 = 0x20;
+
+/// Human-readable name for the UUIDs we know about.
+pub fn uuid_name(uuid: u16) -> Option<&'static str> {
+    Some(match uuid {
+        SERVICE_HEART_RATE => "Heart Rate",
+        SERVICE_BATTERY => "Battery",
+        SERVICE_DEVICE_INFORMATION => "Device Information",
+        CHAR_HEART_RATE_MEASUREMENT => "Heart Rate Measurement",
+        CHAR_BATTERY_LEVEL => "Battery Level",
+        CHAR_MODEL_NUMBER => "Model Number",
+        CHAR_FIRMWARE_REVISION => "Firmware Revision",
+        CHAR_MANUFACTURER_NAME => "Manufacturer Name",
+        _ => return None,
+    })
+}
+
+/// A characteristic reported by GATT discovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Characteristic {
+    pub service: u16,
+    pub uuid: u16,
+    pub properties: u8,
+}
+
+impl Characteristic {
+    pub fn can_read(&self) -> bool {
+        self.properties & PROP_READ != 0
+    }
+
+    pub fn can_subscribe(&self) -> bool {
+        self.properties & (PROP_NOTIFY | PROP_INDICATE) != 0
+    }
+
+    pub fn label(&self) -> String {
+        match uuid_name(self.uuid) {
+            Some(name) => format!("{} (0x{:04X})", name, self.uuid),
+            None => format!("0x{:04X}", self.uuid),
+        }
+    }
+}
+
+// ---- Parsers ----
+
+/// Decoded Heart Rate Measurement (0x2A37) notification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartRateMeasurement {
+    pub bpm: u16,
+    /// `None` when the sensor doesn't support contact detection
+    pub sensor_contact: Option<bool>,
+    /// Kilojoules since the last reset
+    pub energy_expended: Option<u16>,
+    /// RR intervals in 1/1024 s units
+    pub rr_intervals: Vec<u16>,
+}
+
+pub fn parse_heart_rate(value: &[u8]) -> Option<HeartRateMeasurement> {
+    fn take_u16(rest: &mut &[u8]) -> Option<u16> {
+        let (bytes, tail) = rest.split_first_chunk::<2>()?;
+        *rest = tail;
+        Some(u16::from_le_bytes(*bytes))
+    }
+
+    let (&flags, mut rest) = value.split_first()?;
+
+    let bpm = if flags & 0x01 != 0 {
+        take_u16(&mut rest)?
+    } else {
+        let (&bpm, tail) = rest.split_first()?;
+        rest = tail;
+        bpm as u16
+    };
+    let sensor_contact = (flags & 0x04 != 0).then_some(flags & 0x02 != 0);
+    let energy_expended = if flags & 0x08 != 0 { Some(take_u16(&mut rest)?) } else { None };
+    let mut rr_intervals = Vec::new();
+    if flags & 0x10 != 0 {
+        while let Some(rr) = take_u16(&mut rest) {
+            rr_intervals.push(rr);
+        }
+    }
+
+    Some(HeartRateMeasurement { bpm, sensor_contact, energy_expended, rr_intervals })
+}
+
+pub fn parse_battery_level(value: &[u8]) -> Option<u8> {
+    value.first().copied().filter(|level| *level <= 100)
+}
+
+/// Device Information strings are UTF-8, sometimes NUL-padded.
+pub fn parse_utf8(value: &[u8]) -> String {
+    let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+    String::from_utf8_lossy(&value[..end]).trim().to_string()
+}
+
+pub fn to_hex(value: &[u8]) -> String {
+    value.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+}
+
+/// Decoded text for characteristics we have a parser for.
+pub fn decode(characteristic: u16, value: &[u8]) -> Option<String> {
+    match characteristic {
+        CHAR_HEART_RATE_MEASUREMENT => parse_heart_rate(value).map(|hr| format!("{} bpm", hr.bpm)),
+        CHAR_BATTERY_LEVEL => parse_battery_level(value).map(|b| format!("{}%", b)),
+        CHAR_MANUFACTURER_NAME | CHAR_MODEL_NUMBER | CHAR_FIRMWARE_REVISION => Some(parse_utf8(value)),
+        _ => None,
+    }
+}
+
+// ---- Notification Logging ----
+
+/// Appends every value of one characteristic to a CSV file.
+pub struct CsvLogger {
+    file: BufWriter<File>,
+    pub path: PathBuf,
+    pub rows: usize,
+}
+
+impl CsvLogger {
+    /// Creates `<dir>/gatt-<address>-<uuid>-<timestamp>.csv` with a header row.
+    pub fn create(dir: &Path, address: u64, characteristic: u16) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "gatt-{:012X}-{:04X}-{}.csv",
+            address,
+            characteristic,
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let mut file = BufWriter::new(File::create(&path)?);
+        writeln!(file, "timestamp,address,characteristic,raw_hex,decoded")?;
+        Ok(CsvLogger { file, path, rows: 0 })
+    }
+
+    pub fn log(&mut self, address: u64, characteristic: u16, value: &[u8]) -> Result<()> {
+        let decoded = decode(characteristic, value).unwrap_or_default().replace('"', "\"\"");
+        writeln!(
+            self.file,
+            "{},{:012X},0x{:04X},{},\"{}\"",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+            address,
+            characteristic,
+            to_hex(value),
+            decoded
+        )?;
+        // Flush per row so a crash mid-session keeps everything captured so far
+        self.file.flush()?;
+        self.rows += 1;
+        Ok(())
+    }
+}
+
+// ---- Live Readout ----
+
+/// Latest standard-profile values received from one sensor.
+#[derive(Debug, Clone, Default)]
+pub struct SensorReadout {
+    pub heart_rate: Option<HeartRateMeasurement>,
+    pub battery: Option<u8>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub firmware: Option<String>,
+    pub last_update: Option<Instant>,
+}
+
+impl SensorReadout {
+    /// Folds a GATT value into the readout. Returns false for characteristics it doesn't know.
+    pub fn apply(&mut self, characteristic: u16, value: &[u8]) -> bool {
+        match characteristic {
+            CHAR_HEART_RATE_MEASUREMENT => self.heart_rate = parse_heart_rate(value),
+            CHAR_BATTERY_LEVEL => self.battery = parse_battery_level(value),
+            CHAR_MANUFACTURER_NAME => self.manufacturer = Some(parse_utf8(value)),
+            CHAR_MODEL_NUMBER => self.model = Some(parse_utf8(value)),
+            CHAR_FIRMWARE_REVISION => self.firmware = Some(parse_utf8(value)),
+            _ => return false,
+        }
+        self.last_update = Some(Instant::now());
+        true
+    }
+}
+
+/// Subscribes to heart rate and battery notifications and requests the one-off
+/// Device Information values. Characteristics the sensor lacks are skipped and
+/// simply never report; it only fails if neither notification could be set up.
+pub fn start_sensor_readout(address: u64) -> Result<()> {
+    println!("CLI: Action -> Sensor Readout {:X}", address);
+    let mut subscribed = 0;
+    for (service, characteristic) in [(SERVICE_HEART_RATE, CHAR_HEART_RATE_MEASUREMENT), (SERVICE_BATTERY, CHAR_BATTERY_LEVEL)] {
+        match subscribe(address, service, characteristic) {
+            Ok(()) => subscribed += 1,
+            Err(e) => warn!("Sensor {:X}: skipping 0x{:04X}: {}", address, characteristic, e),
+        }
+    }
+    if subscribed == 0 {
+        return Err(AppError::bluetooth("The device has no heart rate or battery notifications"));
+    }
+    let reads = [
+        (SERVICE_BATTERY, CHAR_BATTERY_LEVEL),
+        (SERVICE_DEVICE_INFORMATION, CHAR_MANUFACTURER_NAME),
+        (SERVICE_DEVICE_INFORMATION, CHAR_MODEL_NUMBER),
+        (SERVICE_DEVICE_INFORMATION, CHAR_FIRMWARE_REVISION),
+    ];
+    for (service, characteristic) in reads {
+        if let Err(e) = read(address, service, characteristic) {
+            warn!("Sensor {:X}: skipping 0x{:04X}: {}", address, characteristic, e);
+        }
+    }
+    Ok(())
+}
+
+pub fn stop_sensor_readout(address: u64) {
+    println!("CLI: Action -> Stop Sensor Readout {:X}", address);
+    // Not-subscribed is fine here; the sensor may never have exposed the service
+    let _ = unsubscribe(address, CHAR_HEART_RATE_MEASUREMENT);
+    let _ = unsubscribe(address, CHAR_BATTERY_LEVEL);
+}
+
+// ---- FFI Wrappers ----
+
+extern "C" fn on_characteristic(address: u64, service_uuid: u16, characteristic_uuid: u16, properties: u8) {
+    let characteristic = Characteristic { service: service_uuid, uuid: characteristic_uuid, properties };
+    bluetooth::send_event(BluetoothEvent::GattCharacteristicFound { address, characteristic });
+}
+
+/// Starts service discovery; results arrive as `GattCharacteristicFound` events
+/// and a failure as an `Error` event.
+pub fn discover(address: u64) -> Result<()> {
+    println!("CLI: Action -> GATT Discover {:X}", address);
+    match ffi_call!(bt_gatt_discover(address, on_characteristic)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+pub fn subscribe(address: u64, service: u16, characteristic: u16) -> Result<()> {
+    match ffi_call!(bt_gatt_subscribe(address, service, characteristic)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+pub fn unsubscribe(address: u64, characteristic: u16) -> Result<()> {
+    match ffi_call!(bt_gatt_unsubscribe(address, characteristic)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+/// Requests one value; it arrives as a `GattValue` event, a failure as an `Error` event.
+pub fn read(address: u64, service: u16, characteristic: u16) -> Result<()> {
+    match ffi_call!(bt_gatt_read(address, service, characteristic)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
@@ -1,53 +1,562 @@
-use crate::bluetooth::{self, BluetoothDevice, BluetoothEvent};
-use crate::config::Config;
+use crate::activity::ActivityFeed;
+use crate::adapter::Adapter;
+use crate::at_console::{self, AtConsole};
+use crate::audio::{self, AudioProfile, AudioSession, ResamplerQuality};
+use crate::backup;
+#[cfg(feature = "audio-routing")]
+use crate::audio::{AppRoute, ROUTE_ALL_OTHERS};
+use crate::bluetooth::{self, BluetoothDevice, BluetoothEvent, CallState, ConnectionState, MajorClass, QuickAction};
+use crate::capabilities::CAPABILITIES;
+use crate::card_layout::CardLayout;
+use crate::config::{self, Config, TAG_TARGET_PREFIX};
+use crate::config_service::ConfigService;
+use crate::crash_guard::{self, SettingChange};
+use crate::device_store::DeviceStore;
+use crate::diagnostics::{self, AudioDiagnostics};
+use crate::endpoints::{self, EndpointWait, WaitState};
 use crate::error::AppError;
+#[cfg(feature = "obex")]
+use crate::obex::{self, Transfer, TransferState};
+use crate::pan::{self, PanLink};
+use crate::power::{self, WakeLock};
+use crate::presence_lock::{LockAction, PresenceLock};
+use crate::problem_report::{self, ReportContext};
+use crate::purge;
+use crate::operations::{self, Operation, OperationTracker};
+use crate::pbap::{self, CallKind, PhoneBook};
+#[cfg(feature = "gatt")]
+use crate::gatt::{self, Characteristic, CsvLogger, SensorReadout};
 use crate::ffi;
-use crate::registry::Registry;
+use crate::handover::{self, HandoverStep};
+use crate::health::{self, HealthAlert};
+use crate::hotkey::{self, Hotkey};
+use crate::le_audio::{self, BroadcastQuality, BroadcastSource};
+use crate::names::{self, NameStyle};
+use crate::recorder::{self, Recording};
+use crate::search::{self, OfflineHit, Query, Sources};
+use crate::hci_snoop::{self, HciCapture};
+use crate::registry::{self, DeviceEvent, DeviceSummary, EventKind, Registry, RegistryStats};
+use crate::report::{self, UsageReport};
+use crate::perf::PerfStats;
+use crate::plugins::{NoiseMode, PluginRegistry};
+use crate::scan_progress::ScanProgress;
+use crate::scan_watchdog::{Recovery, ScanWatchdog};
+use crate::schedule::{self, ProfileSchedule};
+#[cfg(feature = "scripting")]
+use crate::scripting::{self, ScriptEvent, Scripts};
+use crate::selftest::{self, Check};
+use crate::settings::{CardAction, CardDensity, LowBatteryAction, ScanTuning, StatusStyle};
+use crate::share::DevicePreset;
+use crate::status::{Level, Status, Subsystem};
+use crate::shared_store::{SharedDevice, SharedStore};
+use crate::toast::{Severity, Toasts};
+use crate::trace::{self, TraceEntry};
+use crate::watch::{AreaWatch, WatchAlert};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use eframe::{egui, App, Frame};
 use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::mpsc::Receiver;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Where GATT notification CSV logs are written
+#[cfg(feature = "gatt")]
+const GATT_LOG_DIR: &str = "gatt_logs";
+
+/// Consecutive authentication failures before "Fix pairing" is offered
+const REPAIR_OFFER_AFTER: u32 = 3;
+/// Connect/disconnect results listed on a device card
+const OUTCOMES_SHOWN: usize = 3;
+/// How often a locked registry is reopened
+const REGISTRY_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// Guest pairing length offered by default, e.g. a working day for a visitor
+const GUEST_DEFAULT_HOURS: u32 = 8;
+/// How often the battery of each sink in the broadcast is read
+const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How often expired guest pairings are looked for
+const GUEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often it is checked whether a scheduled backup is due
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the volume and battery shown by card quick actions are re-read
+const READOUT_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Steps of the guided "Fix pairing" flow.
+#[derive(Debug, Clone, PartialEq)]
+enum RepairStep {
+    /// Explains what will happen; nothing has been changed yet
+    Confirm,
+    /// Bond removed; pairing starts as soon as the scanner sees the device unpaired
+    WaitingForDevice { since: Instant },
+    Pairing,
+    /// Paired; the connect runs until `ConnectFinished`
+    Connecting,
+    Done,
+    Failed(String),
+}
+
+struct RepairFlow {
+    address: u64,
+    step: RepairStep,
+}
+
+/// An action that can cut off audio or input, held until the user confirms it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Disruptive {
+    /// Keyboards and mice are skipped unless `include_inputs`
+    DisconnectAll { include_inputs: bool },
+    Disconnect(u64),
+    Unpair(u64),
+}
+
+impl Disruptive {
+    /// The `confirm.*` setting that controls whether this action asks first.
+    fn setting(self) -> &'static str {
+        match self {
+            Disruptive::DisconnectAll { .. } => "confirm.disconnect_all",
+            Disruptive::Disconnect(_) => "confirm.disconnect_input",
+            Disruptive::Unpair(_) => "confirm.unpair",
+        }
+    }
+}
+
+struct PendingConfirm {
+    action: Disruptive,
+    dont_ask: bool,
+}
+
+/// The quick connect overlay closes itself after this long without a choice
+const QUICK_CONNECT_TIMEOUT: Duration = Duration::from_secs(6);
+/// Keys that toggle the numbered favorites in the quick connect overlay
+const QUICK_CONNECT_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+/// How long a device has been connected today and this week, from the event log.
+struct ConnectedTime {
+    day: NaiveDate,
+    day_start: DateTime<Local>,
+    week_start: DateTime<Local>,
+    /// Closed sessions only, in seconds
+    today: u64,
+    week: u64,
+    /// Start of the session the log still has open
+    since: Option<DateTime<Local>>,
+}
+
+impl ConnectedTime {
+    /// Today's and this week's totals, with the open session counted up to now while the device is connected.
+    fn totals(&self, connected: bool) -> (u64, u64) {
+        let live = |start: DateTime<Local>| match self.since.filter(|_| connected) {
+            Some(since) => (Local::now() - since.max(start)).num_seconds().max(0) as u64,
+            None => 0,
+        };
+        (self.today + live(self.day_start), self.week + live(self.week_start))
+    }
+}
+
+/// Volume and battery shown by a card's quick actions, as last read.
+#[derive(Debug, Default)]
+struct CardReadout {
+    volume: Option<f32>,
+    battery: Option<u8>,
+    read: Option<Instant>,
+}
+
+/// State of the hotkey-summoned quick connect overlay.
+struct QuickConnect {
+    opened: Instant,
+    /// Whether the window was minimized when summoned, so dismissing can put it back.
+    /// `None` until the first frame has brought the window forward.
+    was_minimized: Option<bool>,
+}
+
+/// What to do once the audio endpoints being waited for have appeared.
+enum AfterEndpoints {
+    StartProfile(AudioProfile),
+    /// Attach the backup that took over from `from` to the running stream
+    Failover { from: u64, to: u64 },
+    /// Move the stream from `from` to `to`; `disconnect` drops `from` once the
+    /// fade is over (conference room mode)
+    HandOver { from: u64, to: u64, disconnect: bool },
+    TakeDefaultOutput(u64),
+}
+
+struct EndpointJob {
+    wait: EndpointWait,
+    then: AfterEndpoints,
+}
+
+/// State of the developer console window.
+struct DevConsole {
+    filter: String,
+    /// Trace frozen at the moment "Pause" was ticked
+    paused: Option<Vec<TraceEntry>>,
+    call: &'static str,
+    address: String,
+    last_result: Option<String>,
+}
+
+impl Default for DevConsole {
+    fn default() -> Self {
+        DevConsole {
+            filter: String::new(),
+            paused: None,
+            call: trace::MANUAL_CALLS[0],
+            address: String::new(),
+            last_result: None,
+        }
+    }
+}
+
+/// Offered at a safe-mode start: the settings changed since the last clean exit, to undo.
+struct SafeModeDialog {
+    crashes: u32,
+    /// Each change and whether it's ticked for reverting
+    changes: Vec<(SettingChange, bool)>,
+}
+
+/// A sink about to be asked to join an LE Audio broadcast.
+struct BroadcastJoin {
+    broadcast_id: u32,
+    sink: Option<u64>,
+    /// Broadcast_Code for encrypted broadcasts
+    code: String,
+}
+
+/// Issue text being reviewed before it's sent to GitHub.
+struct ProblemReport {
+    title: String,
+    body: String,
+}
+
+/// Opt-in dialog for the presence lock.
+#[derive(Default)]
+struct PresenceSetup {
+    device: Option<u64>,
+    understood: bool,
+}
+
+/// State of the script editor window.
+#[cfg(feature = "scripting")]
+#[derive(Default)]
+struct ScriptEditor {
+    /// Script being edited; empty until one is opened or created
+    name: String,
+    source: String,
+    dirty: bool,
+    new_name: String,
+    /// Result of the last syntax check
+    check: Option<Result<(), String>>,
+}
 
 pub struct BluetoothApp {
     // Devices are now owned by the GUI thread
-    devices: Vec<BluetoothDevice>,
+    devices: DeviceStore,
     // Channel to receive events from Bluetooth Manager
     event_receiver: Option<Receiver<BluetoothEvent>>,
     
     registry: Result<Registry, AppError>,
+    // Last attempt to reopen a registry another process had locked
+    registry_retry: Option<Instant>,
+    // Working copy of the shared config; pushed to the service on save and
+    // refreshed when another subsystem changes it
     config: Result<Config, AppError>,
-    error_message: Option<String>,
+    config_service: ConfigService,
+    config_changes: Receiver<u64>,
+    config_revision: u64,
+    toasts: Toasts,
+    show_toast_history: bool,
+    /// Cheat sheet of the shortcuts in effect, toggled with `?`
+    show_shortcuts: bool,
     scanning: bool,
     permission_granted: bool,
+    // Compact always-on-top strip showing only saved devices
+    mini_mode: bool,
+    show_settings: bool,
+    show_diagnostics: bool,
+    // Self-test results; shown at startup only if something failed
+    selftest: Option<Vec<Check>>,
+    adapter: Option<Adapter>,
+    // Every adapter this profile has used, with first and last use
+    adapter_history: Vec<(Adapter, String, String)>,
+    // Adapters plugged in now, offered as a device's preferred adapter
+    local_adapters: Vec<Adapter>,
+    console: Option<DevConsole>,
+    // Developer HCI capture; keeps running while the console is closed
+    hci_capture: Option<HciCapture>,
+    problem_report: Option<ProblemReport>,
+    at_console: Option<AtConsole>,
+    plugins: PluginRegistry,
+    // Last noise control mode each device confirmed; unknown until switched from here
+    noise_modes: HashMap<u64, NoiseMode>,
+    // Per-device notes cached from the registry
+    notes: HashMap<u64, String>,
+    // Latest connect/disconnect results per device, read from the event log on first draw
+    outcomes: HashMap<u64, Vec<DeviceEvent>>,
+    // Connected time per device, read like `outcomes` and reloaded when the day changes
+    connected_times: HashMap<u64, ConnectedTime>,
+    search: String,
+    // Registry history for search results, read when a search starts
+    known_devices: Option<Vec<DeviceSummary>>,
+    detail_device: Option<u64>,
+    note_draft: String,
+    tag_draft: String,
+    tag_filter: Option<String>,
+    location_draft: String,
+    location_filter: Option<String>,
+    // Auto-connect is attempted once per device per session
+    auto_connect_attempted: HashSet<u64>,
+    // Auto-connects started and not yet connected or failed, so the feed can tell them apart
+    auto_connecting: HashSet<u64>,
+    activity: ActivityFeed,
+    audio_session: Option<AudioSession>,
+    // Why the audio engine failed to start, if it did
+    audio_error: Option<String>,
+    // Held while streaming if the keep-awake setting is on
+    wake_lock: Option<WakeLock>,
+    // Reduced scan duty cycle while on battery; power source re-checked periodically
+    low_power: bool,
+    power_checked: Option<Instant>,
+    // Scan tuning last pushed to the core
+    scan_tuning: Option<ScanTuning>,
+    // Restarts a scan that reports success but never delivers discoveries
+    scan_watchdog: ScanWatchdog,
+    scan_progress: ScanProgress,
+    perf: PerfStats,
+    // Measured card heights; only cards in view are drawn
+    card_layout: CardLayout,
+    recording: Option<Recording>,
+    diagnostics: AudioDiagnostics,
+    profile_draft: Option<AudioProfile>,
+    // Name typed for "Save as Profile" while broadcasting
+    preset_name: Option<String>,
+    // Profile named with --profile, started on the first frame
+    startup_profile: Option<String>,
+    #[cfg(feature = "audio-routing")]
+    route_draft: AppRoute,
+    // HFP state per headset: (call state, mic muted)
+    call_states: HashMap<u64, (CallState, bool)>,
+    // Live BLE sensor readouts, keyed by devices with an active subscription
+    #[cfg(feature = "gatt")]
+    sensors: HashMap<u64, SensorReadout>,
+    // GATT browser: device being browsed, discovered characteristics and latest values
+    #[cfg(feature = "gatt")]
+    gatt_browser: Option<u64>,
+    #[cfg(feature = "gatt")]
+    gatt_characteristics: HashMap<u64, Vec<Characteristic>>,
+    #[cfg(feature = "gatt")]
+    gatt_values: HashMap<(u64, u16), Vec<u8>>,
+    #[cfg(feature = "gatt")]
+    gatt_subscriptions: HashSet<(u64, u16)>,
+    #[cfg(feature = "gatt")]
+    gatt_loggers: HashMap<(u64, u16), CsvLogger>,
+    // OBEX transfers shown in the transfer window, and the "Send file" dialog (address, path)
+    #[cfg(feature = "obex")]
+    transfers: Vec<Transfer>,
+    #[cfg(feature = "obex")]
+    send_file_draft: Option<(u64, String)>,
+    // PBAP data is read-only and never persisted
+    phonebooks: HashMap<u64, PhoneBook>,
+    phonebook_view: Option<(u64, pbap::Folder)>,
+    // NAP support is looked up once per device; active tethering links by address
+    nap_support: HashMap<u64, bool>,
+    pan_links: HashMap<u64, PanLink>,
+    // Quick action readouts of connected devices, and devices to connect again once their disconnect lands
+    card_readouts: HashMap<u64, CardReadout>,
+    reconnecting: HashSet<u64>,
+    // Weekly health analysis results and when it last ran; dismissed alerts are dropped
+    health_alerts: Vec<HealthAlert>,
+    health_checked: Option<Instant>,
+    rssi_rolled_up: Option<Instant>,
+    // Last RSSI sample written to the registry per saved device
+    rssi_sampled: HashMap<u64, Instant>,
+    // Consecutive connect attempts rejected for authentication, and the open "Fix pairing" flow
+    auth_failures: HashMap<u64, u32>,
+    repair: Option<RepairFlow>,
+    // "Pair as guest" dialog: device and hours
+    guest_dialog: Option<(u64, u32)>,
+    // Guest pairings started but not finished, with their length in hours
+    guest_pending: HashMap<u64, u32>,
+    guests_checked: Option<Instant>,
+    // Expired guests whose removal failed, warned about once
+    guest_failures: HashSet<u64>,
+    // Quick connect hotkey as last applied from config, and whether it is registered system-wide
+    // (otherwise it only works while the window has focus)
+    hotkey_spec: String,
+    hotkey: Option<Hotkey>,
+    hotkey_global: bool,
+    // Settings text for the hotkey while it's being edited; applied when it parses
+    hotkey_draft: Option<String>,
+    quick_connect: Option<QuickConnect>,
+    // Preset shown as a share code / QR, and the paste box for importing one
+    share_view: Option<DevicePreset>,
+    import_draft: Option<String>,
+    // Machine-wide known devices; None while the setting is off
+    shared_store: Option<Result<SharedStore, String>>,
+    show_known_devices: bool,
+    // Bluetooth device behind the system default output, polled since it can change outside the app
+    default_output: Option<u64>,
+    default_output_checked: Option<Instant>,
+    // Device that took over the default output on connect, and the endpoint to put back when it leaves
+    replaced_output: Option<(u64, String)>,
+    // Devices already checked against the registry this session, and those it had never seen
+    sighted: HashSet<u64>,
+    new_devices: HashSet<u64>,
+    // Presence monitor state and the alerts it raised, newest last
+    area_watch: AreaWatch,
+    presence_lock: PresenceLock,
+    presence_setup: Option<PresenceSetup>,
+    watch_alerts: Vec<WatchAlert>,
+    // Session-only: nothing is written to the registry (history, events, RSSI, sightings)
+    incognito: bool,
+    // "Delete all my data" confirmation; the flag is "keep settings"
+    purge_confirm: Option<bool>,
+    // Database size for the Data settings, read when first shown; and a running compaction with the size before it
+    registry_stats: Option<RegistryStats>,
+    compacting: Option<(Instant, u64)>,
+    // Backup zip being written on a background thread, and when a scheduled one was last looked for
+    backing_up: bool,
+    backup_checked: Option<Instant>,
+    // Disconnect All / input device disconnect / unpair waiting for confirmation
+    confirm: Option<PendingConfirm>,
+    // Devices picked with the select gesture, for bulk connect/disconnect
+    selected: HashSet<u64>,
+    // Profile schedule entries are due when their time falls after this
+    schedule_checked: NaiveDateTime,
+    // Actions held back until Windows has created the devices' audio endpoints
+    endpoint_jobs: Vec<EndpointJob>,
+    operations: OperationTracker,
+    // LE Audio broadcasts by Broadcast_ID; `None` until the adapter was asked whether it can see them
+    le_audio_supported: Option<bool>,
+    // Live RSSI from LE advertisements, run while scanning; `None` until the adapter was asked
+    signal_supported: Option<bool>,
+    signal_monitor: bool,
+    // Headset call state from the core; `None` until asked
+    hfp_supported: Option<bool>,
+    // Cleared once the core reports the channel test as not supported, hiding its buttons
+    test_tone_supported: bool,
+    // Likewise for muting single sinks, hiding the mute and solo toggles
+    sink_mute_supported: bool,
+    // Cross-fades between sinks in the core; `None` until asked
+    crossfade_supported: Option<bool>,
+    // Recording the broadcast mix in the core; `None` until asked
+    recording_supported: Option<bool>,
+    // Buffer, resampler and sample rate settings in the core's engine; `None` until asked
+    engine_settings_supported: Option<bool>,
+    // Per-application audio routing in the core; `None` until asked
+    #[cfg(feature = "audio-routing")]
+    app_routes_supported: Option<bool>,
+    broadcast_scanning: bool,
+    broadcasts: HashMap<u32, BroadcastSource>,
+    broadcast_join: Option<BroadcastJoin>,
+    // Sink waiting for BroadcastJoinFinished
+    broadcast_joining: Option<u64>,
+    // Left unfinished by the last session; offered for resuming until dismissed
+    interrupted: Vec<Operation>,
+    // Battery of the sinks in the broadcast, as last read
+    sink_batteries: HashMap<u64, u8>,
+    batteries_checked: Option<Instant>,
+    // Low sinks already suggested or moved, until they charge again; and the open suggestion (from, to)
+    low_battery_handled: HashSet<u64>,
+    battery_suggestion: Option<(u64, u64)>,
+    // Sinks handed over in conference room mode, disconnected when their fade-out ends
+    fade_disconnects: Vec<(u64, Instant)>,
+    #[cfg(feature = "scripting")]
+    scripts: Scripts,
+    #[cfg(feature = "scripting")]
+    script_editor: Option<ScriptEditor>,
+    // --kiosk: status display only; nothing that connects, pairs or edits config is offered
+    kiosk: bool,
+    // Started without Bluetooth, audio, plugins or config after repeated crashes (or --safe-mode)
+    safe_mode: bool,
+    safe_mode_dialog: Option<SafeModeDialog>,
 }
 
 impl BluetoothApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(
+        _cc: &eframe::CreationContext<'_>,
+        config_service: ConfigService,
+        config_error: Option<AppError>,
+        kiosk: bool,
+        startup_profile: Option<String>,
+        // Crashes in a row that led to safe mode, if started in it
+        safe_mode: Option<u32>,
+    ) -> Self {
         println!("CLI: GUI Initializing...");
         info!("Initializing BluetoothApp GUI...");
         
-        // Load configuration
-        let config = Config::load();
+        let config = match config_error {
+            Some(e) => Err(e),
+            None => Ok(config_service.snapshot()),
+        };
+        let config_changes = config_service.subscribe();
         
         // Initialize registry
-        let registry = Registry::new();
+        let registry = Registry::open(config.as_ref().map_or(registry::DEFAULT_BUSY_TIMEOUT, |c| c.registry.busy_timeout()));
+        let retention_days = config.as_ref().map_or(0, |c| c.registry.history_retention_days);
+        if let (Ok(reg), true) = (&registry, retention_days > 0) {
+            if let Err(e) = reg.cleanup_old_entries(retention_days as i32) {
+                warn!("Failed to prune device history: {}", e);
+            }
+        }
+        if let (Ok(reg), Ok(true)) = (&registry, config.as_ref().map(|c| c.registry.monthly_report)) {
+            if let Err(e) = report::write_previous_month(reg) {
+                warn!("Failed to write usage report: {}", e);
+            }
+        }
+        let notes = match &registry {
+            Ok(reg) => reg.get_all_notes().unwrap_or_else(|e| {
+                warn!("Failed to load device notes: {}", e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
         
+        let (adapter, adapter_history) = read_adapter(registry.as_ref().ok(), true);
+        let local_adapters = present_adapters();
+
         // Initialize Bluetooth Subsystem
         // This gives us the receiver for events
-        let event_receiver = match bluetooth::init() {
-            Ok(rx) => Some(rx),
-            Err(e) => {
-                error!("Failed to init bluetooth: {}", e);
-                None
-            }
+        let event_receiver = match safe_mode {
+            Some(_) => None,
+            None => match bluetooth::init() {
+                Ok(rx) => Some(rx),
+                Err(e) => {
+                    error!("Failed to init bluetooth: {}", e);
+                    None
+                }
+            },
+        };
+
+        let audio_error = match safe_mode {
+            Some(_) => Some(String::from("Not started in safe mode")),
+            None => audio::init().err().map(|e| {
+                error!("Failed to init audio: {}", e);
+                e.to_string()
+            }),
         };
 
         // Check permissions
         let permission_granted = bluetooth::check_permission();
         println!("CLI: Permission Grant Status: {}", permission_granted);
 
+        let checks = selftest::run();
+        for check in checks.iter().filter(|c| !c.passed) {
+            warn!("Self-test: {} failed: {}", check.name, check.detail);
+        }
+        let selftest = (!selftest::all_passed(&checks)).then_some(checks);
+
         // Auto-start scan
-        let scanning = if permission_granted {
+        let scanning = if permission_granted && safe_mode.is_none() {
             if let Ok(_) = bluetooth::start_scan() {
                 true
             } else {
@@ -57,141 +566,6508 @@ impl BluetoothApp {
             false
         };
         
+        let mut toasts = Toasts::default();
+        let (plugins, plugin_errors) = match safe_mode {
+            Some(_) => (PluginRegistry::default(), Vec::new()),
+            None => PluginRegistry::load(),
+        };
+        for e in plugin_errors {
+            toasts.push(Severity::Warning, "A vendor plugin failed to load", Some(e));
+        }
+
+        #[cfg(feature = "scripting")]
+        let mut scripts = Scripts::default();
+        #[cfg(feature = "scripting")]
+        if let (Ok(config), false) = (&config, kiosk) {
+            for name in &config.scripts.startup {
+                match scripting::load(name) {
+                    Ok(source) => {
+                        scripts.start(name, source);
+                    }
+                    Err(e) => {
+                        toasts.push(Severity::Warning, format!("Startup script '{}' not run", name), Some(e.to_string()))
+                    }
+                }
+            }
+        }
+
         Self {
-            devices: Vec::new(),
+            devices: DeviceStore::default(),
             event_receiver,
             registry,
+            registry_retry: None,
             config,
-            error_message: None,
+            config_service,
+            config_changes,
+            config_revision: 0,
+            toasts,
+            show_toast_history: false,
+            show_shortcuts: false,
             scanning,
             permission_granted,
+            mini_mode: false,
+            show_settings: false,
+            show_diagnostics: false,
+            selftest,
+            adapter,
+            adapter_history,
+            local_adapters,
+            console: None,
+            hci_capture: None,
+            problem_report: None,
+            at_console: None,
+            plugins,
+            noise_modes: HashMap::new(),
+            notes,
+            outcomes: HashMap::new(),
+            connected_times: HashMap::new(),
+            search: String::new(),
+            known_devices: None,
+            detail_device: None,
+            note_draft: String::new(),
+            tag_draft: String::new(),
+            tag_filter: None,
+            location_draft: String::new(),
+            location_filter: None,
+            auto_connect_attempted: HashSet::new(),
+            auto_connecting: HashSet::new(),
+            activity: ActivityFeed::default(),
+            audio_session: None,
+            audio_error,
+            wake_lock: None,
+            low_power: false,
+            power_checked: None,
+            scan_tuning: None,
+            scan_watchdog: ScanWatchdog::default(),
+            scan_progress: ScanProgress::default(),
+            perf: PerfStats::default(),
+            card_layout: CardLayout::default(),
+            recording: None,
+            diagnostics: AudioDiagnostics::default(),
+            profile_draft: None,
+            preset_name: None,
+            startup_profile: startup_profile.filter(|_| safe_mode.is_none()),
+            #[cfg(feature = "audio-routing")]
+            route_draft: AppRoute::default(),
+            call_states: HashMap::new(),
+            #[cfg(feature = "gatt")]
+            sensors: HashMap::new(),
+            #[cfg(feature = "gatt")]
+            gatt_browser: None,
+            #[cfg(feature = "gatt")]
+            gatt_characteristics: HashMap::new(),
+            #[cfg(feature = "gatt")]
+            gatt_values: HashMap::new(),
+            #[cfg(feature = "gatt")]
+            gatt_subscriptions: HashSet::new(),
+            #[cfg(feature = "gatt")]
+            gatt_loggers: HashMap::new(),
+            #[cfg(feature = "obex")]
+            transfers: Vec::new(),
+            #[cfg(feature = "obex")]
+            send_file_draft: None,
+            phonebooks: HashMap::new(),
+            phonebook_view: None,
+            nap_support: HashMap::new(),
+            pan_links: HashMap::new(),
+            card_readouts: HashMap::new(),
+            reconnecting: HashSet::new(),
+            health_alerts: Vec::new(),
+            health_checked: None,
+            rssi_rolled_up: None,
+            registry_stats: None,
+            compacting: None,
+            backing_up: false,
+            backup_checked: None,
+            rssi_sampled: HashMap::new(),
+            auth_failures: HashMap::new(),
+            repair: None,
+            guest_dialog: None,
+            guest_pending: HashMap::new(),
+            guests_checked: None,
+            guest_failures: HashSet::new(),
+            hotkey_spec: String::new(),
+            hotkey: None,
+            hotkey_global: false,
+            hotkey_draft: None,
+            quick_connect: None,
+            share_view: None,
+            import_draft: None,
+            shared_store: None,
+            show_known_devices: false,
+            default_output: None,
+            default_output_checked: None,
+            replaced_output: None,
+            sighted: HashSet::new(),
+            new_devices: HashSet::new(),
+            area_watch: AreaWatch::default(),
+            presence_lock: PresenceLock::default(),
+            presence_setup: None,
+            watch_alerts: Vec::new(),
+            incognito: false,
+            purge_confirm: None,
+            confirm: None,
+            selected: HashSet::new(),
+            // Entries due shortly before launch still run, like after waking from sleep
+            schedule_checked: Local::now().naive_local() - chrono::Duration::minutes(schedule::CATCH_UP_MINUTES),
+            endpoint_jobs: Vec::new(),
+            operations: OperationTracker::default(),
+            le_audio_supported: None,
+            signal_supported: None,
+            signal_monitor: false,
+            hfp_supported: None,
+            test_tone_supported: true,
+            sink_mute_supported: true,
+            crossfade_supported: None,
+            recording_supported: None,
+            engine_settings_supported: None,
+            #[cfg(feature = "audio-routing")]
+            app_routes_supported: None,
+            broadcast_scanning: false,
+            broadcasts: HashMap::new(),
+            broadcast_join: None,
+            broadcast_joining: None,
+            // Safe mode leaves them for the next normal start
+            interrupted: if kiosk || safe_mode.is_some() { Vec::new() } else { operations::take_interrupted() },
+            fade_disconnects: Vec::new(),
+            sink_batteries: HashMap::new(),
+            batteries_checked: None,
+            low_battery_handled: HashSet::new(),
+            battery_suggestion: None,
+            #[cfg(feature = "scripting")]
+            scripts,
+            #[cfg(feature = "scripting")]
+            script_editor: None,
+            kiosk,
+            safe_mode: safe_mode.is_some(),
+            safe_mode_dialog: safe_mode.map(|crashes| SafeModeDialog {
+                crashes,
+                changes: crash_guard::changed_settings().into_iter().map(|c| (c, false)).collect(),
+            }),
         }
     }
     
     fn process_events(&mut self) {
-        if let Some(rx) = &self.event_receiver {
-            // Non-blocking loop to drain all pending events
-            while let Ok(event) = rx.try_recv() {
-                match event {
-                    BluetoothEvent::DeviceFound(dev) => {
-                        // println!("CLI: GUI Received Device: {}", dev.name); // Optional: verbose
-                        
-                        // Update or Add
-                        if let Some(existing) = self.devices.iter_mut().find(|d| d.address == dev.address) {
-                            *existing = dev;
-                        } else {
-                            self.devices.push(dev);
+        // Drain pending events first so handlers are free to borrow `self` mutably
+        let events: Vec<BluetoothEvent> = match &self.event_receiver {
+            Some(rx) => rx.try_iter().collect(),
+            None => return,
+        };
+        self.perf.events(events.len());
+        // Connected after the batch, so a scan's results go in priority order
+        let mut auto_connect = Vec::new();
+
+        for event in events {
+            self.record_activity(&event);
+            match event {
+                BluetoothEvent::DeviceFound(dev) => {
+                    // println!("CLI: GUI Received Device: {}", dev.name); // Optional: verbose
+                    
+                    self.scan_watchdog.activity();
+                    self.scan_progress.record(dev.address);
+                    self.check_new_device(&dev);
+                    self.check_watch(&dev);
+                    self.check_presence_lock(&dev);
+                    if self.wants_auto_connect(&dev) {
+                        auto_connect.push(dev.address);
+                    }
+                    self.sample_rssi(&dev);
+                    self.advance_repair(&dev);
+                    self.nap_support.entry(dev.address).or_insert_with(|| pan::has_nap(dev.address));
+
+                    // Inquiry results carry no signal; keep the last reading for display
+                    let mut dev = dev;
+                    if dev.rssi == 0 {
+                        dev.rssi = self.devices.get(dev.address).map_or(0, |d| d.rssi);
+                    }
+                    self.devices.upsert(dev);
+                },
+                BluetoothEvent::Signal { address, rssi } => {
+                    self.devices.update(address, |d| d.rssi = rssi);
+                    let dev = self.devices.get(address).cloned().unwrap_or(BluetoothDevice {
+                        address,
+                        name: String::new(),
+                        state: ConnectionState::Disconnected,
+                        authenticated: false,
+                        rssi,
+                        cod: 0,
+                    });
+                    self.check_watch(&dev);
+                    self.check_presence_lock(&dev);
+                    self.sample_rssi(&dev);
+                },
+                BluetoothEvent::ScanStarted => {
+                    println!("CLI: GUI Event -> Scan Started");
+                    self.scanning = true;
+                    self.scan_progress.start();
+                },
+                BluetoothEvent::ScanStopped => {
+                    println!("CLI: GUI Event -> Scan Stopped");
+                    self.scanning = false;
+                    self.scan_progress.stop();
+                },
+                BluetoothEvent::Connected(addr) => {
+                    println!("CLI: GUI Event -> Connected to {:X}", addr);
+                    self.devices.update(addr, |d| d.state = ConnectionState::Connected);
+                    if let Some(registry) = self.history() {
+                        let name = self.raw_label(addr);
+                        let recorded = registry.transaction(|r| {
+                            r.log_device(addr, &name)?;
+                            r.record_event(addr, EventKind::Connected, None)?;
+                            r.record_sighting(addr)
+                        });
+                        if let Err(e) = recorded {
+                            warn!("Failed to record connection to {:X}: {}", addr, e);
+                        }
+                    }
+                    self.outcomes.remove(&addr);
+                    self.connected_times.remove(&addr);
+                    self.operations.device_done(addr);
+                    self.enforce_exclusive_output(addr);
+                    self.take_default_output(addr);
+                },
+                BluetoothEvent::LinkState { address, state } => {
+                    println!("CLI: GUI Event -> {:X} is {}", address, state);
+                    if matches!(state, ConnectionState::Failed(_)) {
+                        self.operations.device_done(address);
+                    }
+                    self.devices.update(address, |d| d.state = state);
+                },
+                BluetoothEvent::ConnectFinished { address, result, bond_rejected } => {
+                    self.connect_finished(address, result, bond_rejected);
+                },
+                BluetoothEvent::DisconnectFinished { address, result } => {
+                    if let Err(e) = result {
+                        println!("CLI: GUI Event -> Disconnect from {:X} failed: {}", address, e);
+                        self.reconnecting.remove(&address);
+                        self.disconnect_failed(address, &e);
+                        self.toasts.error(format!("Disconnecting {} failed: {}", self.device_label(address), e));
+                    }
+                },
+                BluetoothEvent::Disconnected(addr) => {
+                    println!("CLI: GUI Event -> Disconnected from {:X}", addr);
+                    // Ours went through Disconnecting; a link that dropped by itself didn't
+                    let requested = self.link_state(addr) == ConnectionState::Disconnecting;
+                    self.devices.update(addr, |d| d.state = ConnectionState::Disconnected);
+                    if let Some(registry) = self.history() {
+                        let _ = registry.record_event(addr, EventKind::Disconnected, None);
+                    }
+                    self.outcomes.remove(&addr);
+                    self.connected_times.remove(&addr);
+                    self.handle_audio_disconnect(addr, requested);
+                    self.restore_default_output(addr);
+                    self.endpoint_jobs
+                        .retain(|job| !matches!(job.then, AfterEndpoints::TakeDefaultOutput(a) if a == addr));
+                    self.pan_links.remove(&addr);
+                    self.card_readouts.remove(&addr);
+                    if self.reconnecting.remove(&addr) {
+                        if let Err(e) = self.connect_device(addr) {
+                            self.toasts.error(format!("Reconnecting {}: {}", self.device_label(addr), e));
+                        }
+                    }
+                },
+                BluetoothEvent::NameResolved(addr, name) => {
+                    println!("CLI: GUI Event -> Name for {:X}: {}", addr, name);
+                    self.devices.update(addr, |d| d.name = name);
+                },
+                BluetoothEvent::CallStateChanged { address, state, mic_muted } => {
+                    println!("CLI: GUI Event -> Call state {:X}: {:?}", address, state);
+                    self.call_states.insert(address, (state, mic_muted));
+                    self.sync_audio_with_calls();
+                },
+                #[cfg(feature = "gatt")]
+                BluetoothEvent::GattCharacteristicFound { address, characteristic } => {
+                    let list = self.gatt_characteristics.entry(address).or_default();
+                    if !list.contains(&characteristic) {
+                        list.push(characteristic);
+                    }
+                },
+                #[cfg(feature = "gatt")]
+                BluetoothEvent::GattValue { address, characteristic, value } => {
+                    if let Some(readout) = self.sensors.get_mut(&address) {
+                        readout.apply(characteristic, &value);
+                    }
+                    if let Some(logger) = self.gatt_loggers.get_mut(&(address, characteristic)) {
+                        if let Err(e) = logger.log(address, characteristic, &value) {
+                            error!("GATT log write failed, stopping log: {}", e);
+                            self.gatt_loggers.remove(&(address, characteristic));
+                        }
+                    }
+                    self.gatt_values.insert((address, characteristic), value);
+                },
+                #[cfg(feature = "obex")]
+                BluetoothEvent::ObexIncoming(transfer) => {
+                    println!("CLI: GUI Event -> Incoming file {}", transfer.file_name);
+                    self.notify(format!(
+                        "{} wants to send you '{}'",
+                        self.device_label(transfer.address),
+                        transfer.file_name
+                    ));
+                    self.transfers.push(transfer);
+                },
+                #[cfg(feature = "obex")]
+                BluetoothEvent::ObexProgress { id, state, bytes_done, bytes_total } => {
+                    if let Some(t) = self.transfers.iter_mut().find(|t| t.id == id) {
+                        t.state = state;
+                        t.bytes_done = bytes_done;
+                        t.bytes_total = bytes_total;
+                    }
+                },
+                BluetoothEvent::PhoneBookPulled { address, folder, data } => {
+                    // Consent may have been withdrawn while the download was running
+                    if !self.config.as_ref().is_ok_and(|c| c.has_pbap_consent(address)) {
+                        continue;
+                    }
+                    match data {
+                        Ok(text) => {
+                            let book = self.phonebooks.entry(address).or_default();
+                            match folder {
+                                pbap::Folder::Contacts => book.contacts = pbap::parse_contacts(&text),
+                                pbap::Folder::CallHistory => book.calls = pbap::parse_call_history(&text),
+                            }
+                            self.phonebook_view = Some((address, folder));
+                        }
+                        Err(msg) => self.toasts.error(msg),
+                    }
+                },
+                BluetoothEvent::RecordingFinished { path, result } => {
+                    println!("CLI: GUI Event -> Recording finished: {:?}", result);
+                    self.recording = None;
+                    match result {
+                        Ok(reason) => self.notify(format!("Recording saved to {} ({})", path, reason)),
+                        Err(e) => self.toasts.error(format!("Recording failed: {}", e)),
+                    }
+                },
+                BluetoothEvent::BroadcastFound(source) => {
+                    self.broadcasts.insert(source.broadcast_id, source);
+                },
+                BluetoothEvent::BroadcastJoinFinished { sink, broadcast_id, result } => {
+                    println!("CLI: GUI Event -> {:X} joining broadcast {:06X} finished", sink, broadcast_id);
+                    self.broadcast_joining = None;
+                    let broadcast = self
+                        .broadcasts
+                        .get(&broadcast_id)
+                        .map_or_else(|| format!("broadcast {:06X}", broadcast_id), |b| b.label());
+                    match result {
+                        Ok(()) => self.notify(format!("{} joined {}", self.device_label(sink), broadcast)),
+                        Err(e) => self.toasts.error(format!("{} couldn't join {}: {}", self.device_label(sink), broadcast, e)),
+                    }
+                },
+                BluetoothEvent::HciCaptureFinished { path, packets, reason } => {
+                    println!("CLI: GUI Event -> HCI capture finished: {}", reason);
+                    // Also stops the core's session if the writer gave up on its own
+                    if self.hci_capture.as_ref().is_some_and(|c| c.path.display().to_string() == path) {
+                        if let Some(capture) = self.hci_capture.take() {
+                            capture.stop();
+                        }
+                    }
+                    self.notify(format!("HCI capture saved to {} ({} packets, {})", path, packets, reason));
+                },
+                BluetoothEvent::PairingFinished { address, result } => {
+                    println!("CLI: GUI Event -> Pairing with {:X} finished", address);
+                    if let Some(hours) = self.guest_pending.remove(&address) {
+                        self.finish_guest_pairing(address, hours, &result);
+                    }
+                    let in_flow = self.repair.as_ref().is_some_and(|r| r.address == address);
+                    let step = match result {
+                        Ok(()) => {
+                            self.auth_failures.remove(&address);
+                            match self.connect_device(address) {
+                                Ok(()) => RepairStep::Connecting,
+                                Err(e) => RepairStep::Failed(format!("Paired, but connecting failed: {}", e)),
+                            }
+                        }
+                        Err(msg) => RepairStep::Failed(msg),
+                    };
+                    if in_flow {
+                        self.repair = Some(RepairFlow { address, step });
+                    }
+                },
+                BluetoothEvent::RegistryCompacted { result } => {
+                    println!("CLI: GUI Event -> Registry compacted");
+                    let before = self.compacting.take().map_or(0, |(_, bytes)| bytes);
+                    self.registry_stats = None;
+                    match result {
+                        Ok(()) => {
+                            let after = std::fs::metadata(Registry::path()).map(|m| m.len()).unwrap_or(0);
+                            self.notify(format!("Database compacted: {} → {}", format_size(before), format_size(after)));
                         }
-                    },
-                    BluetoothEvent::ScanStarted => {
-                        println!("CLI: GUI Event -> Scan Started");
-                        self.scanning = true;
-                    },
-                    BluetoothEvent::ScanStopped => {
-                        println!("CLI: GUI Event -> Scan Stopped");
-                        self.scanning = false;
-                    },
-                    BluetoothEvent::Connected(addr) => {
-                        println!("CLI: GUI Event -> Connected to {:X}", addr);
-                        if let Some(d) = self.devices.iter_mut().find(|d| d.address == addr) {
-                            d.connected = true;
+                        Err(msg) => self.toasts.push(
+                            Severity::Error,
+                            "Could not compact the database",
+                            Some(format!("{}\n\nClose other RedTooth windows and try again.", msg)),
+                        ),
+                    }
+                },
+                BluetoothEvent::BackupFinished { result } => {
+                    println!("CLI: GUI Event -> Backup finished");
+                    self.backing_up = false;
+                    match result {
+                        Ok(path) => info!("Backup written to {}", path.display()),
+                        Err(msg) => self.toasts.push(
+                            Severity::Error,
+                            "Backup failed",
+                            Some(format!("{}\n\nCheck that the backup folder in Settings → Data exists and is writable.", msg)),
+                        ),
+                    }
+                },
+                BluetoothEvent::HandoverFinished { address, result } => {
+                    println!("CLI: GUI Event -> Handover of {:X} finished", address);
+                    let name = self.device_label(address);
+                    match result {
+                        Ok(()) => self.notify(format!("{} is now connected to this PC", name)),
+                        Err(msg) => self.toasts.push(
+                            Severity::Error,
+                            format!("Could not take over {}", name),
+                            Some(format!(
+                                "{}\n\nIf the phone keeps winning, lengthen the wait or add the headset's vendor AT command.",
+                                msg
+                            )),
+                        ),
+                    }
+                },
+                BluetoothEvent::AtReply { address, command, reply } => {
+                    println!("CLI: GUI Event -> AT reply from {:X}", address);
+                    if let Some(console) = self.at_console.as_mut().filter(|c| c.address == address) {
+                        console.answered(command, reply);
+                    }
+                },
+                BluetoothEvent::PluginFinished { address, plugin, action, result } => {
+                    println!("CLI: GUI Event -> {} '{}' on {:X} finished", plugin, action, address);
+                    match result {
+                        Ok(reply) if reply.is_empty() => self.notify(format!("{}: {} done", plugin, action)),
+                        Ok(reply) => self.notify(format!("{}: {} → {}", plugin, action, reply)),
+                        Err(msg) => self.toasts.push(Severity::Error, format!("{}: {} failed", plugin, action), Some(msg)),
+                    }
+                },
+                BluetoothEvent::NoiseControlSet { address, mode, result } => {
+                    println!("CLI: GUI Event -> Noise control on {:X}: {:?}", address, mode);
+                    match result {
+                        Ok(()) => {
+                            self.noise_modes.insert(address, mode);
                         }
-                    },
-                    BluetoothEvent::Disconnected(addr) => {
-                        println!("CLI: GUI Event -> Disconnected from {:X}", addr);
-                         if let Some(d) = self.devices.iter_mut().find(|d| d.address == addr) {
-                            d.connected = false;
+                        Err(msg) => {
+                            let name = self.device_label(address);
+                            self.toasts.error(format!("Could not switch {} to {}: {}", name, mode.label(), msg));
                         }
-                    },
-                    BluetoothEvent::Error(msg) => {
-                        println!("CLI: GUI Event -> Error: {}", msg);
-                        self.error_message = Some(msg);
                     }
+                },
+                #[cfg(feature = "scripting")]
+                BluetoothEvent::Script(event) => {
+                    self.handle_script_event(event);
+                },
+                BluetoothEvent::QuickConnectRequested => {
+                    self.open_quick_connect();
+                },
+                BluetoothEvent::Error(msg) => {
+                    println!("CLI: GUI Event -> Error: {}", msg);
+                    self.toasts.error(msg);
                 }
             }
         }
+        self.auto_connect(auto_connect);
     }
 
-    fn show_error_dialog(&mut self, ctx: &egui::Context, message: &str) {
-        egui::Window::new("Error")
-            .collapsible(false)
-            .resizable(false)
-            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-            .show(ctx, |ui| {
-                ui.label(egui::RichText::new(message).color(egui::Color32::RED));
-                ui.separator();
-                if ui.button("OK").clicked() {
-                    self.error_message = None;
-                }
-            });
+    /// Shows an info toast unless banners are off or Do Not Disturb is active.
+    fn notify(&mut self, message: String) {
+        info!("{}", message);
+        if self
+            .config
+            .as_ref()
+            .is_ok_and(|c| !c.notifications.show_banners || c.quiet_hours_active())
+        {
+            return;
+        }
+        self.toasts.info(message);
     }
 
-    fn draw_device_card(&mut self, ui: &mut egui::Ui, device: &BluetoothDevice) {
-        ui.group(|ui| {
-            ui.horizontal(|ui| {
-                ui.label(match device.cod {
-                    // Simple heuristic for icons
-                    c if c & 0x200000 != 0 => "🎧",
-                    _ => "📱",
+    /// The registry for recording history; `None` while incognito.
+    fn history(&self) -> Option<&Registry> {
+        if self.incognito {
+            None
+        } else {
+            self.registry.as_ref().ok()
+        }
+    }
+
+    fn name_style(&self) -> NameStyle {
+        self.config.as_ref().map_or(NameStyle::default(), |c| NameStyle {
+            strip_emoji: c.ui.strip_name_emoji,
+            isolate_rtl: c.ui.isolate_rtl_names,
+        })
+    }
+
+    /// Best available display name for an address, normalized for display;
+    /// `raw_label` is what gets stored.
+    fn device_label(&self, address: u64) -> String {
+        names::display(&self.raw_label(address), self.name_style())
+    }
+
+    fn raw_label(&self, address: u64) -> String {
+        if let Some(d) = self.devices.get(address).filter(|d| !d.name.is_empty()) {
+            return d.name.clone();
+        }
+        if let Ok(config) = &self.config {
+            if let Some((name, _)) = config.devices.iter().find(|(_, a)| **a == address) {
+                return name.clone();
+            }
+        }
+        format!("{:X}", address)
+    }
+
+    fn handle_audio_disconnect(&mut self, address: u64, requested: bool) {
+        let Some(session) = &mut self.audio_session else {
+            return;
+        };
+        match session.handle_disconnect(address, requested) {
+            Ok(Some(backup)) => {
+                self.endpoint_jobs.push(EndpointJob {
+                    wait: EndpointWait::new(&[backup]),
+                    then: AfterEndpoints::Failover { from: address, to: backup },
                 });
-                
-                ui.vertical(|ui| {
-                    ui.label(egui::RichText::new(&device.name).strong());
-                    ui.small(format!("{:X}", device.address));
-                    
-                    if device.connected {
-                        ui.colored_label(egui::Color32::GREEN, "Connected");
-                    } else {
-                        ui.label("Disconnected");
+            }
+            Ok(None) => {}
+            Err(e) => self.toasts.error(format!("Audio failover failed: {}", e)),
+        }
+    }
+
+    /// Pauses the broadcast while any headset is in a call, if enabled in settings.
+    fn sync_audio_with_calls(&mut self) {
+        let enabled = self.config.as_ref().is_ok_and(|c| c.audio.pause_during_calls);
+        let in_call = self.call_states.values().any(|(state, _)| state.in_call());
+        let Some(session) = &mut self.audio_session else {
+            return;
+        };
+
+        let result = if enabled && in_call && !session.paused {
+            session.pause().map(|_| Some("Broadcast paused during call"))
+        } else if session.paused && !(enabled && in_call) {
+            session.resume().map(|_| Some("Broadcast resumed"))
+        } else {
+            Ok(None)
+        };
+
+        match result {
+            Ok(Some(message)) => self.notify(message.to_string()),
+            Ok(None) => {}
+            Err(e) => self.toasts.error(format!("Failed to update broadcast: {}", e)),
+        }
+    }
+
+    /// Switches the scanner to low-power mode on battery unless the user opted out.
+    fn sync_power_mode(&mut self) {
+        if self.power_checked.is_some_and(|t| t.elapsed() < Duration::from_secs(30)) {
+            return;
+        }
+        self.power_checked = Some(Instant::now());
+
+        let opted_out = self.config.as_ref().is_ok_and(|c| c.scan.full_power_on_battery);
+        let wanted = !opted_out && power::on_battery().unwrap_or(false);
+        if wanted != self.low_power {
+            match bluetooth::set_low_power_scan(wanted) {
+                Ok(()) => self.low_power = wanted,
+                Err(e) => warn!("{}", e),
+            }
+        }
+    }
+
+    /// Pushes the advanced scan tuning to the core when it changes.
+    fn sync_scan_tuning(&mut self) {
+        let wanted = self.config.as_ref().map_or_else(|_| ScanTuning::default(), |c| c.scan.tuning);
+        if self.scan_tuning == Some(wanted) {
+            return;
+        }
+        // Recorded even on failure so a bad value isn't retried every frame
+        self.scan_tuning = Some(wanted);
+        if let Err(e) = bluetooth::set_scan_tuning(&wanted) {
+            self.toasts.error(format!("Scan tuning not applied: {}", e));
+        }
+    }
+
+    /// Reopens the registry while another process holds it locked, so history
+    /// resumes on its own once the lock is released.
+    fn sync_registry(&mut self) {
+        if !self.registry.as_ref().is_err_and(|e| e.is_database_locked()) {
+            return;
+        }
+        if self.registry_retry.is_some_and(|t| t.elapsed() < REGISTRY_RETRY_INTERVAL) {
+            return;
+        }
+        self.registry_retry = Some(Instant::now());
+
+        let timeout = self.config.as_ref().map_or(registry::DEFAULT_BUSY_TIMEOUT, |c| c.registry.busy_timeout());
+        match Registry::open(timeout) {
+            Ok(registry) => {
+                info!("Registry lock released; device history resumed");
+                self.registry = Ok(registry);
+                self.notify("Device history is available again".to_string());
+            }
+            Err(e) => {
+                if !e.is_database_locked() {
+                    warn!("Registry reopen failed: {}", e);
+                }
+                self.registry = Err(e);
+            }
+        }
+    }
+
+    /// Recovers from a scan that went silent: restart it, then re-initialize the
+    /// core, then tell the user. Every attempt is recorded in the call trace.
+    fn sync_scan_watchdog(&mut self) {
+        let secs = self.config.as_ref().map_or(60, |c| c.scan.watchdog_secs);
+        if secs == 0 {
+            return;
+        }
+        // The low-power duty cycle, and long tuned cycles, legitimately leave longer gaps
+        let factor = if self.low_power { 2 } else { 1 };
+        let tuning = self.config.as_ref().map_or_else(|_| ScanTuning::default(), |c| c.scan.tuning.clamped());
+        let cycle = Duration::from_millis(u64::from(tuning.inquiry_length) * 1280 + u64::from(tuning.cycle_gap_ms));
+        let limit = Duration::from_secs(u64::from(secs) * factor).max(cycle * 2);
+        let Some(recovery) = self.scan_watchdog.check(self.scanning, limit) else {
+            return;
+        };
+
+        let silent = format!("{}s", limit.as_secs());
+        let started = Instant::now();
+        let (action, outcome) = match recovery {
+            Recovery::RestartScan => {
+                warn!("No discoveries for {}; restarting scan", silent);
+                let _ = bluetooth::stop_scan();
+                ("restart scan", bluetooth::start_scan())
+            }
+            Recovery::Reinitialize => {
+                warn!("Scan still silent after restarts; reinitializing Bluetooth");
+                let result = bluetooth::reinit().and_then(|()| bluetooth::start_scan());
+                ("reinitialize", result)
+            }
+            Recovery::GiveUp => {
+                error!("Scan watchdog gave up after {} attempts", self.scan_watchdog.attempts());
+                self.toasts.push(
+                    Severity::Error,
+                    "Scanning appears stuck: no devices were reported even after restarting Bluetooth",
+                    Some("Try toggling Bluetooth in Windows settings or re-plugging the adapter.".to_string()),
+                );
+                ("give up", Ok(()))
+            }
+        };
+        let result = match &outcome {
+            Ok(()) => action.to_string(),
+            Err(e) => {
+                warn!("Scan watchdog could not {}: {}", action, e);
+                format!("FAILED: {} ({})", action, e)
+            }
+        };
+        trace::record("scan_watchdog", vec![format!("silent={}", silent)], started.elapsed(), result);
+    }
+
+    /// Holds the sleep inhibitor exactly while a broadcast is actively streaming.
+    fn sync_wake_lock(&mut self) {
+        let wanted = self.config.as_ref().is_ok_and(|c| c.audio.keep_awake)
+            && self.audio_session.as_ref().is_some_and(|s| !s.paused);
+        if wanted && self.wake_lock.is_none() {
+            self.wake_lock = Some(WakeLock::acquire());
+        } else if !wanted {
+            self.wake_lock = None;
+        }
+    }
+
+    fn draw_audio_panel(&mut self, ui: &mut egui::Ui) {
+        let saved = match &self.config {
+            Ok(config) => config.ordered_devices(),
+            Err(_) => return,
+        };
+
+        let mut start_profile = None;
+        let mut delete_profile = None;
+        let mut stop = false;
+        let mut toggle_recording = false;
+        let mut test_sink = None;
+        let test_tone = self.test_tone_supported;
+        let mut mute_sink = None;
+        let mut solo_sink = None;
+        let sink_mute = self.sink_mute_supported;
+        let mut move_sink = None;
+        let mut save_preset = false;
+        let mut accept_suggestion = false;
+        let mut dismiss_suggestion = false;
+        let mut cancel_preset = false;
+        // Connected headsets and speakers the stream could move to
+        let spare_outputs: Vec<(u64, String)> = match &self.audio_session {
+            Some(session) => self
+                .devices
+                .snapshot()
+                .iter()
+                .filter(|d| d.state.is_connected() && d.is_audio_output() && !session.sinks.contains(&d.address))
+                .map(|d| (d.address, self.device_label(d.address)))
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut save_draft = false;
+        let mut cancel_draft = false;
+        let mut cancel_wait = false;
+
+        egui::CollapsingHeader::new("Audio").default_open(false).show(ui, |ui| {
+            let waiting = self.endpoint_jobs.iter().find(|job| {
+                matches!(
+                    job.then,
+                    AfterEndpoints::StartProfile(_) | AfterEndpoints::Failover { .. } | AfterEndpoints::HandOver { .. }
+                )
+            });
+            if let Some(job) = waiting {
+                let (ready, total) = job.wait.ready_count();
+                let names: Vec<String> = job.wait.pending().iter().map(|a| self.device_label(*a)).collect();
+                ui.label(format!("⏳ Waiting for audio output from {}", names.join(", ")));
+                let progress = job.wait.elapsed().as_secs_f32() / endpoints::READY_TIMEOUT.as_secs_f32();
+                ui.add(
+                    egui::ProgressBar::new(progress.min(1.0))
+                        .text(format!("{}/{} ready · {} s", ready, total, job.wait.elapsed().as_secs())),
+                );
+                if matches!(job.then, AfterEndpoints::StartProfile(_)) {
+                    cancel_wait = ui.button("Cancel").clicked();
+                }
+                return;
+            }
+
+            if let Some(session) = &self.audio_session {
+                if session.paused {
+                    ui.label(format!("⏸ '{}' paused", session.profile));
+                } else {
+                    ui.label(format!("▶ Broadcasting '{}'", session.profile));
+                }
+                if session.low_latency {
+                    ui.label("⚡ Low latency").on_hover_text("Short buffer, no cross-fades");
+                }
+                let several = session.sinks.len() > 1;
+                for address in &session.sinks {
+                    let mut text = self.device_label(*address);
+                    if session.primary == Some(*address) {
+                        text.push_str(" (primary)");
+                    }
+                    ui.horizontal(|ui| {
+                        let icon = if session.is_audible(*address) { "🔊" } else { "🔇" };
+                        ui.label(format!("  {} {}", icon, text));
+                        if let Some(level) = self.sink_batteries.get(address) {
+                            ui.small(format!("🔋 {}%", level));
+                        }
+                        if sink_mute
+                            && ui
+                                .selectable_label(session.muted.contains(address), "M")
+                                .on_hover_text("Mute this sink; it stays in the broadcast")
+                                .clicked()
+                        {
+                            mute_sink = Some(*address);
+                        }
+                        if sink_mute
+                            && several
+                            && ui
+                                .selectable_label(session.solo == Some(*address), "S")
+                                .on_hover_text("Play only on this sink")
+                                .clicked()
+                        {
+                            solo_sink = Some(*address);
+                        }
+                        if !spare_outputs.is_empty() {
+                            ui.menu_button("⇄", |ui| {
+                                for (to, label) in &spare_outputs {
+                                    if ui.button(label).clicked() {
+                                        move_sink = Some((*address, *to));
+                                        ui.close_menu();
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text("Move the stream from this sink to another device");
+                        }
+                    });
+                }
+                if let Some(backup) = session.backup {
+                    ui.small(format!("Backup: {}", self.device_label(backup)));
+                }
+                if let Some((from, to)) = self.battery_suggestion {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("🔋 {} is running low. Move its audio to {}?", self.device_label(from), self.device_label(to)),
+                        );
+                        accept_suggestion = ui.button("Move").clicked();
+                        dismiss_suggestion = ui.button("Dismiss").clicked();
+                    });
+                }
+                match &mut self.preset_name {
+                    Some(name) => {
+                        ui.horizontal(|ui| {
+                            ui.label("Save as");
+                            ui.text_edit_singleline(name);
+                            save_preset = ui.add_enabled(!name.trim().is_empty(), egui::Button::new("Save")).clicked();
+                            cancel_preset = ui.button("Cancel").clicked();
+                        });
+                        ui.small("Saves the sinks, mutes, solo and app routes; an existing profile of that name is replaced");
+                    }
+                    None => {
+                        if ui.button("💾 Save as Profile…").clicked() {
+                            self.preset_name = Some(session.profile.clone());
+                        }
+                    }
+                }
+                let can_record = *self.recording_supported.get_or_insert_with(recorder::supported);
+                ui.horizontal(|ui| {
+                    stop = ui.button("Stop").clicked();
+                    match &self.recording {
+                        Some(recording) => {
+                            let secs = recording.started.elapsed().as_secs();
+                            toggle_recording = ui.button("⏹ Stop Recording").clicked();
+                            ui.label(format!("⏺ {:02}:{:02}", secs / 60, secs % 60))
+                                .on_hover_text(recording.path.display().to_string());
+                        }
+                        None if can_record => {
+                            toggle_recording = ui.button("⏺ Record").clicked();
+                        }
+                        None => {}
                     }
                 });
+                return;
+            }
 
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    if device.connected {
-                        if ui.button("Disconnect").clicked() {
-                             let _ = bluetooth::disconnect(device.address);
+            // Low latency only changes the engine settings, so it is hidden while the engine ignores them
+            let low_latency_supported = *self.engine_settings_supported.get_or_insert_with(audio::engine_settings_supported);
+            if let Some(draft) = &mut self.profile_draft {
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut draft.name);
+                });
+                ui.label("Sinks");
+                for (name, address) in &saved {
+                    ui.horizontal(|ui| {
+                        let mut selected = draft.sinks.contains(name);
+                        if ui.checkbox(&mut selected, name).changed() {
+                            if selected {
+                                draft.sinks.push(name.clone());
+                            } else {
+                                draft.sinks.retain(|n| n != name);
+                            }
                         }
-                    } else {
-                        if ui.button("Connect").clicked() {
-                             let _ = bluetooth::connect(device.address);
+                        if test_tone && ui.small_button("🔈 Test").on_hover_text("Play left/right channel test").clicked() {
+                            test_sink = Some(*address);
+                        }
+                    });
+                }
+                if draft.primary.as_ref().is_some_and(|p| !draft.sinks.contains(p)) {
+                    draft.primary = None;
+                }
+
+                let sinks = draft.sinks.clone();
+                egui::ComboBox::from_label("Primary")
+                    .selected_text(draft.primary.clone().unwrap_or_else(|| "None".to_string()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut draft.primary, None, "None");
+                        for name in &sinks {
+                            ui.selectable_value(&mut draft.primary, Some(name.clone()), name);
                         }
+                    });
+                ui.horizontal(|ui| {
+                    ui.label("Hotkey");
+                    ui.add(egui::TextEdit::singleline(&mut draft.hotkey).hint_text("e.g. Ctrl+Alt+1").desired_width(100.0));
+                    if !draft.hotkey.trim().is_empty() && Hotkey::parse(&draft.hotkey).is_none() {
+                        ui.colored_label(egui::Color32::LIGHT_RED, "Not a valid combination");
                     }
-                     ui.label(format!("{} dB", device.rssi));
                 });
-                
-            });
+                if low_latency_supported {
+                    ui.checkbox(&mut draft.low_latency, "⚡ Low latency").on_hover_text(format!(
+                        "For gaming headsets: {} ms buffer and no cross-fades, at the cost of more dropouts on a weak link",
+                        audio::LOW_LATENCY_BUFFER_MS
+                    ));
+                }
+                egui::ComboBox::from_label("Backup")
+                    .selected_text(draft.backup.clone().unwrap_or_else(|| "None".to_string()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut draft.backup, None, "None");
+                        for (name, _) in saved.iter().filter(|(n, _)| !sinks.contains(n)) {
+                            ui.selectable_value(&mut draft.backup, Some(name.clone()), name);
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    let valid = !draft.name.trim().is_empty() && !draft.sinks.is_empty();
+                    save_draft = ui.add_enabled(valid, egui::Button::new("Save Profile")).clicked();
+                    cancel_draft = ui.button("Cancel").clicked();
+                });
+                return;
+            }
+
+            let profiles = self.config.as_ref().map(|c| c.audio_profiles.clone()).unwrap_or_default();
+            if profiles.is_empty() {
+                ui.small("No audio profiles yet");
+            }
+            for (i, profile) in profiles.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(&profile.name);
+                    ui.small(format!("{} sinks", profile.sinks.len()));
+                    if profile.backup.is_some() {
+                        ui.small("+ backup");
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("🗑").clicked() {
+                            delete_profile = Some(i);
+                        }
+                        if ui.small_button("▶").on_hover_text("Start broadcast").clicked() {
+                            start_profile = Some(profile.clone());
+                        }
+                    });
+                });
+            }
+            if ui.button("New Profile").clicked() {
+                self.profile_draft = Some(AudioProfile::default());
+            }
         });
-    }
-}
 
-impl App for BluetoothApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        // 1. Process Events
-        self.process_events();
-        
-        ctx.request_repaint_after(Duration::from_millis(50)); // Responsive repaint
+        if cancel_draft {
+            self.profile_draft = None;
+        }
 
-        // Show error dialog if there's an error message
-        if let Some(error_msg) = self.error_message.clone() {
-            self.show_error_dialog(ctx, &error_msg);
+        if let Some(address) = test_sink {
+            self.play_test_tone(address);
         }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Project RedTooth");
-            
-            // Permission Warning
-            if !self.permission_granted {
-                ui.colored_label(egui::Color32::RED, "⚠ PERMISSION DENIED - Check OS Settings");
-                if ui.button("Check Again").clicked() {
-                    self.permission_granted = bluetooth::check_permission();
+        if let Some(session) = &mut self.audio_session {
+            let result = match (mute_sink, solo_sink) {
+                (Some(address), _) => session.toggle_mute(address),
+                (_, Some(address)) => session.toggle_solo(address),
+                _ => Ok(()),
+            };
+            match result {
+                Ok(()) => {}
+                Err(AppError::NotSupported(reason)) => {
+                    self.sink_mute_supported = false;
+                    self.toasts.error(reason);
+                }
+                Err(e) => {
+                    self.toasts.error(format!("Failed to mute sink: {}", e));
                 }
             }
+        }
+        if save_preset {
+            self.save_preset();
+        }
+        if accept_suggestion {
+            if let Some((from, to)) = self.battery_suggestion.take() {
+                self.endpoint_jobs.push(EndpointJob {
+                    wait: EndpointWait::new(&[to]),
+                    then: AfterEndpoints::HandOver { from, to, disconnect: false },
+                });
+            }
+        }
+        if dismiss_suggestion {
+            self.battery_suggestion = None;
+        }
+        if cancel_preset {
+            self.preset_name = None;
+        }
+        if let Some((from, to)) = move_sink {
+            self.endpoint_jobs.push(EndpointJob {
+                wait: EndpointWait::new(&[to]),
+                then: AfterEndpoints::HandOver { from, to, disconnect: false },
+            });
+        }
 
-            ui.horizontal(|ui| {
-                 if ui.button(if self.scanning { "Stop Scan" } else { "Start Scan" }).clicked() {
-                     if self.scanning {
-                         let _ = bluetooth::stop_scan();
-                     } else {
+        if toggle_recording && !stop {
+            match &self.recording {
+                Some(recording) => recording.stop(),
+                None => {
+                    let max_minutes = self.config.as_ref().map(|c| c.audio.recording_max_minutes).unwrap_or(0);
+                    let limit = (max_minutes > 0).then(|| Duration::from_secs(max_minutes as u64 * 60));
+                    match Recording::start(Path::new(recorder::RECORDINGS_DIR), limit) {
+                        Ok(recording) => self.recording = Some(recording),
+                        Err(e) => self.toasts.error(format!("Failed to start recording: {}", e)),
+                    }
+                }
+            }
+        }
+
+        if stop {
+            self.stop_broadcast();
+        }
+
+        if let Some(profile) = start_profile {
+            if let Ok(config) = &self.config {
+                let sinks = config.resolve_targets(&profile.sinks);
+                self.operations.begin(Operation::StartProfile { name: profile.name.clone() });
+                self.endpoint_jobs.push(EndpointJob {
+                    wait: EndpointWait::new(&sinks),
+                    then: AfterEndpoints::StartProfile(profile),
+                });
+            }
+        }
+        if cancel_wait {
+            self.endpoint_jobs.retain(|job| !matches!(job.then, AfterEndpoints::StartProfile(_)));
+            self.operations.profile_done();
+        }
+
+        if save_draft {
+            if let (Some(draft), Ok(config)) = (self.profile_draft.take(), &mut self.config) {
+                config.audio_profiles.retain(|p| p.name != draft.name);
+                config.audio_profiles.push(draft);
+            }
+            self.save_config();
+        }
+
+        if let Some(i) = delete_profile {
+            if let Ok(config) = &mut self.config {
+                config.audio_profiles.remove(i);
+            }
+            self.save_config();
+        }
+    }
+
+    /// LE Audio broadcasts nearby, and joining one with a capable sink.
+    fn draw_broadcasts(&mut self, ui: &mut egui::Ui) {
+        let mut toggle_scan = false;
+        let mut join = false;
+        let stale = Duration::from_secs(le_audio::STALE_AFTER_SECS);
+        self.broadcasts.retain(|_, b| b.seen.elapsed() < stale);
+        // Connected headsets and speakers; whether they speak LE Audio shows when they're asked
+        let sinks: Vec<(u64, String)> = self
+            .devices
+            .snapshot()
+            .iter()
+            .filter(|d| d.state.is_connected() && d.is_audio_output())
+            .map(|d| (d.address, self.device_label(d.address)))
+            .collect();
+
+        egui::CollapsingHeader::new("LE Audio Broadcasts").default_open(false).show(ui, |ui| {
+            let supported = *self.le_audio_supported.get_or_insert_with(le_audio::supported);
+            if !supported {
+                ui.small("This adapter can't receive extended advertising, which LE Audio broadcasts use.");
+                return;
+            }
+            let label = if self.broadcast_scanning { "Stop Looking" } else { "Look for Broadcasts" };
+            toggle_scan = ui.button(label).clicked();
+            if self.broadcasts.is_empty() {
+                ui.small(if self.broadcast_scanning { "Looking for Auracast broadcasts…" } else { "No broadcasts found" });
+            }
+
+            let mut broadcasts: Vec<&BroadcastSource> = self.broadcasts.values().collect();
+            broadcasts.sort_by_key(|b| std::cmp::Reverse(b.rssi));
+            for broadcast in broadcasts {
+                ui.horizontal(|ui| {
+                    ui.label(broadcast.label());
+                    if broadcast.encrypted {
+                        ui.small("🔒").on_hover_text("Needs the broadcast code");
+                    }
+                    match broadcast.quality {
+                        BroadcastQuality::High => {
+                            ui.small("HQ").on_hover_text("High quality audio");
+                        }
+                        BroadcastQuality::Standard => {
+                            ui.small("SQ").on_hover_text("Standard quality audio");
+                        }
+                        BroadcastQuality::Unknown => {}
+                    }
+                    ui.small(format!("{} dBm", broadcast.rssi));
+                    let selected = self.broadcast_join.as_ref().is_some_and(|j| j.broadcast_id == broadcast.broadcast_id);
+                    if !selected && ui.small_button("Join…").clicked() {
+                        self.broadcast_join = Some(BroadcastJoin {
+                            broadcast_id: broadcast.broadcast_id,
+                            sink: sinks.first().map(|(a, _)| *a),
+                            code: String::new(),
+                        });
+                    }
+                });
+            }
+
+            let Some(draft) = &mut self.broadcast_join else {
+                return;
+            };
+            let Some(broadcast) = self.broadcasts.get(&draft.broadcast_id) else {
+                return;
+            };
+            ui.group(|ui| {
+                ui.label(format!("Join {}", broadcast.label()));
+                if sinks.is_empty() {
+                    ui.small("Connect an LE Audio headset or speaker first.");
+                    return;
+                }
+                let current = sinks.iter().find(|(a, _)| Some(*a) == draft.sink).map_or("Choose a sink", |(_, n)| n.as_str());
+                egui::ComboBox::from_id_source("broadcast_sink").selected_text(current).show_ui(ui, |ui| {
+                    for (address, name) in &sinks {
+                        ui.selectable_value(&mut draft.sink, Some(*address), name);
+                    }
+                });
+                let code_ok = !broadcast.encrypted || le_audio::broadcast_code(&draft.code).is_some();
+                if broadcast.encrypted {
+                    ui.add(egui::TextEdit::singleline(&mut draft.code).hint_text("Broadcast code").password(true));
+                    if !draft.code.is_empty() && !code_ok {
+                        ui.small("The code is at most 16 bytes");
+                    }
+                }
+                let busy = self.broadcast_joining.is_some();
+                join = ui
+                    .add_enabled(draft.sink.is_some() && code_ok && !busy, egui::Button::new(if busy { "Joining…" } else { "Join" }))
+                    .clicked();
+            });
+        });
+
+        if toggle_scan {
+            if self.broadcast_scanning {
+                le_audio::stop_scan();
+                self.broadcast_scanning = false;
+            } else {
+                match le_audio::start_scan() {
+                    Ok(()) => self.broadcast_scanning = true,
+                    Err(e) => self.toasts.error(format!("Can't look for broadcasts: {}", e)),
+                }
+            }
+        }
+        if join {
+            if let Some(draft) = self.broadcast_join.take() {
+                if let (Some(sink), Some(broadcast)) = (draft.sink, self.broadcasts.get(&draft.broadcast_id)) {
+                    let code = if broadcast.encrypted { le_audio::broadcast_code(&draft.code) } else { None };
+                    le_audio::join(sink, broadcast, code);
+                    self.broadcast_joining = Some(sink);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "audio-routing")]
+    fn draw_app_routes(&mut self, ui: &mut egui::Ui) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let supported = *self.app_routes_supported.get_or_insert_with(audio::app_routes_supported);
+        // Only offered where the core can route; rules saved earlier can still be removed
+        if !supported && config.app_routes.is_empty() {
+            return;
+        }
+        let saved = config.ordered_devices();
+        let routes = config.app_routes.clone();
+
+        let mut remove = None;
+        let mut add = false;
+        egui::CollapsingHeader::new("Audio Routing").default_open(false).show(ui, |ui| {
+            if supported {
+                ui.small(format!("Use {} as the process to route every other application", ROUTE_ALL_OTHERS));
+            } else {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    "Per-application routing isn't supported by the audio engine yet; these rules aren't applied.",
+                );
+            }
+            for (i, route) in routes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let process = if route.process == ROUTE_ALL_OTHERS { "Everything else" } else { &route.process };
+                    ui.label(format!("{} → {}", process, route.sink));
+                    if ui.small_button("🗑").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.route_draft.process)
+                        .hint_text("Spotify.exe")
+                        .desired_width(110.0),
+                );
+                let selected = if self.route_draft.sink.is_empty() { "Sink…" } else { self.route_draft.sink.as_str() };
+                egui::ComboBox::from_id_source("route_sink")
+                    .selected_text(selected.to_string())
+                    .show_ui(ui, |ui| {
+                        for (name, _) in &saved {
+                            ui.selectable_value(&mut self.route_draft.sink, name.clone(), name);
+                        }
+                    });
+                let valid = !self.route_draft.process.trim().is_empty() && !self.route_draft.sink.is_empty();
+                add = ui.add_enabled(supported && valid, egui::Button::new("Add")).clicked();
+            });
+        });
+
+        if remove.is_none() && !add {
+            return;
+        }
+        if let Ok(config) = &mut self.config {
+            if let Some(i) = remove {
+                config.app_routes.remove(i);
+            }
+            if add {
+                let route = AppRoute {
+                    process: self.route_draft.process.trim().to_string(),
+                    sink: self.route_draft.sink.clone(),
+                };
+                // One rule per process; a new rule replaces the old one
+                config.app_routes.retain(|r| !r.process.eq_ignore_ascii_case(&route.process));
+                config.app_routes.push(route);
+                self.route_draft = AppRoute::default();
+            }
+            if let Some(session) = &mut self.audio_session {
+                session.app_routes = config.app_routes.clone();
+                if let Err(e) = session.apply_routes(&session.app_routes, config) {
+                    self.toasts.error(format!("Failed to apply routing: {}", e));
+                }
+            }
+        }
+        self.save_config();
+    }
+
+    fn wants_auto_connect(&self, dev: &BluetoothDevice) -> bool {
+        if self.kiosk || !dev.state.can_connect() || self.auto_connect_attempted.contains(&dev.address) {
+            return false;
+        }
+        let Ok(config) = &self.config else {
+            return false;
+        };
+        // During quiet hours it isn't marked as attempted, so it connects once they end
+        config.auto_connect_addresses().contains(&dev.address) && !config.quiet_hours_active()
+    }
+
+    /// Connects `candidates`, in the order of the auto-connect list.
+    fn auto_connect(&mut self, mut candidates: Vec<u64>) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let priority = config.auto_connect_addresses();
+        candidates.sort_by_key(|a| priority.iter().position(|p| p == a));
+        candidates.dedup();
+
+        for address in candidates {
+            println!("CLI: Auto-connecting to {:X}", address);
+            self.auto_connect_attempted.insert(address);
+            // Before connecting, so the link state events are labeled as auto-connect;
+            // `record_activity` adds the feed entry whichever way it goes
+            self.auto_connecting.insert(address);
+            if let Err(e) = self.connect_device(address) {
+                // Refused before it started, so no link state event follows
+                warn!("Auto-connect to {:X} failed: {}", address, e);
+                self.auto_connecting.remove(&address);
+            }
+        }
+    }
+
+    /// Adds the feed entry for an event, if it is one people care about.
+    fn record_activity(&mut self, event: &BluetoothEvent) {
+        match event {
+            BluetoothEvent::Connected(address) => {
+                let verb = if self.auto_connecting.remove(address) { "Auto-connected" } else { "Connected" };
+                self.activity.push(Some(*address), format!("{} {}", verb, self.device_label(*address)));
+            }
+            BluetoothEvent::Disconnected(address) => {
+                self.auto_connecting.remove(address);
+                self.activity.push(Some(*address), format!("Disconnected {}", self.device_label(*address)));
+            }
+            BluetoothEvent::LinkState { address, state: ConnectionState::Failed(reason) } => {
+                let label = self.device_label(*address);
+                let text = if self.auto_connecting.remove(address) {
+                    format!("Auto-connect failed: {} ({})", label, reason)
+                } else {
+                    format!("Connecting {} failed: {}", label, reason)
+                };
+                self.activity.push_failure(Some(*address), text);
+            }
+            BluetoothEvent::PairingFinished { address, result } => {
+                let label = self.device_label(*address);
+                match result {
+                    Ok(()) => self.activity.push(Some(*address), format!("Paired {}", label)),
+                    Err(e) => self.activity.push_failure(Some(*address), format!("Pairing {} failed: {}", label, e)),
+                }
+            }
+            BluetoothEvent::HandoverFinished { address, result } => {
+                let label = self.device_label(*address);
+                match result {
+                    Ok(()) => self.activity.push(Some(*address), format!("Took over {} from another host", label)),
+                    Err(e) => self.activity.push_failure(Some(*address), format!("Taking over {} failed: {}", label, e)),
+                }
+            }
+            BluetoothEvent::BroadcastJoinFinished { sink, broadcast_id, result } => {
+                let label = self.device_label(*sink);
+                match result {
+                    Ok(()) => self.activity.push(Some(*sink), format!("{} joined broadcast {:06X}", label, broadcast_id)),
+                    Err(e) => self.activity.push_failure(Some(*sink), format!("{} couldn't join broadcast {:06X}: {}", label, broadcast_id, e)),
+                }
+            }
+            BluetoothEvent::RecordingFinished { result, .. } => match result {
+                Ok(reason) => self.activity.push(None, format!("Recording stopped ({})", reason)),
+                Err(e) => self.activity.push_failure(None, format!("Recording failed: {}", e)),
+            },
+            BluetoothEvent::BackupFinished { result } => match result {
+                Ok(_) => self.activity.push(None, "Backup written"),
+                Err(e) => self.activity.push_failure(None, format!("Backup failed: {}", e)),
+            },
+            BluetoothEvent::Error(msg) => self.activity.push_failure(None, msg.clone()),
+            _ => {}
+        }
+    }
+
+    /// Marks devices this PC has never seen before, once per session.
+    fn check_new_device(&mut self, dev: &BluetoothDevice) {
+        if self.sighted.contains(&dev.address) {
+            return;
+        }
+        let Some(registry) = self.history() else {
+            return;
+        };
+        let sighting = registry.record_sighting(dev.address);
+        self.sighted.insert(dev.address);
+        match sighting {
+            Ok(true) => {
+                info!("First sighting of {:X}", dev.address);
+                self.new_devices.insert(dev.address);
+                let name = if dev.name.is_empty() {
+                    format!("{:X}", dev.address)
+                } else {
+                    names::display(&dev.name, self.name_style())
+                };
+                self.activity.push(Some(dev.address), format!("New device detected: {}", name));
+                if self.config.as_ref().is_ok_and(|c| c.notifications.new_devices) {
+                    self.notify(format!("New device nearby: {}", name));
+                }
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to record sighting of {:X}: {}", dev.address, e),
+        }
+    }
+
+    /// Raises a watch alert when a device that isn't saved or allowlisted lingers nearby.
+    fn check_watch(&mut self, dev: &BluetoothDevice) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        if !config.watch.enabled {
+            self.area_watch.clear();
+            return;
+        }
+        let allowed = config.devices.values().any(|a| *a == dev.address)
+            || config.watch.allowlist.contains(&config::address_key(dev.address));
+        let Some(alert) = self.area_watch.observe(dev, &config.watch, allowed) else {
+            return;
+        };
+
+        warn!(
+            "Watch alert: {:X} '{}' at {} dBm for {} s",
+            alert.address,
+            alert.name,
+            alert.rssi,
+            alert.dwell.as_secs()
+        );
+        if let Some(registry) = self.history() {
+            let _ = registry.record_event(alert.address, EventKind::WatchAlert, Some(alert.dwell));
+        }
+        self.notify(format!("Unknown device nearby: {}", self.device_label(alert.address)));
+        self.watch_alerts.push(alert);
+    }
+
+    /// Wakes the display when the presence lock phone comes back after a lock.
+    fn check_presence_lock(&mut self, dev: &BluetoothDevice) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let settings = &config.presence_lock;
+        if self.kiosk || !settings.enabled || config::parse_address(&settings.device) != Some(dev.address) {
+            return;
+        }
+        if self.presence_lock.sighting(dev.rssi, settings) == Some(LockAction::Wake) {
+            info!("Presence lock: {:X} is back at {} dBm, waking the display", dev.address, dev.rssi);
+            power::wake_display();
+        }
+    }
+
+    /// Runs the signal monitor alongside the scan. Presence lock, the area watch and
+    /// signal health all need its readings, since inquiry results have none.
+    fn sync_signal_monitor(&mut self) {
+        let wanted = self.scanning && !self.kiosk;
+        if wanted == self.signal_monitor {
+            return;
+        }
+        if !wanted {
+            bluetooth::stop_signal_monitor();
+            self.signal_monitor = false;
+            return;
+        }
+        if !*self.signal_supported.get_or_insert_with(bluetooth::signal_supported) {
+            return;
+        }
+        match bluetooth::start_signal_monitor() {
+            Ok(()) => self.signal_monitor = true,
+            Err(e) => {
+                warn!("Signal monitor unavailable: {}", e);
+                // Not retried every frame; the next start of the app tries again
+                self.signal_supported = Some(false);
+            }
+        }
+    }
+
+    /// Locks the PC once the presence lock phone has been away long enough.
+    /// Absence can only be judged while scanning, so the timer starts over when it stops.
+    fn sync_presence_lock(&mut self) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let settings = &config.presence_lock;
+        if self.kiosk || !settings.enabled || !self.scanning {
+            self.presence_lock.reset();
+            return;
+        }
+        let away_secs = settings.away_secs;
+        if self.presence_lock.poll(settings) != Some(LockAction::Lock) {
+            return;
+        }
+        warn!("Presence lock: phone away for {} s, locking the workstation", away_secs);
+        if !power::lock_workstation() {
+            self.toasts.error("Presence lock could not lock this PC");
+        }
+    }
+
+    fn draw_presence_setup(&mut self, ctx: &egui::Context) {
+        let style = self.name_style();
+        let signal_supported = *self.signal_supported.get_or_insert_with(bluetooth::signal_supported);
+        let Some(setup) = &mut self.presence_setup else {
+            return;
+        };
+        let phones: Vec<(u64, String)> = self
+            .devices
+            .snapshot()
+            .iter()
+            .filter(|d| d.major_class() == MajorClass::Phone && d.authenticated)
+            .map(|d| (d.address, if d.name.is_empty() { format!("{:X}", d.address) } else { names::display(&d.name, style) }))
+            .collect();
+        let mut open = true;
+        let mut enable = None;
+
+        egui::Window::new("Set up presence lock")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label("Locks this PC when your phone's signal stays weak or disappears for a while.");
+                ui.add_space(4.0);
+                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⚠ Before you turn this on");
+                for warning in [
+                    "It is a convenience, not security. It never unlocks the PC; you still sign in as usual.",
+                    "Bluetooth signal is noisy. Walls, your body or a phone in a bag can lock the PC while you sit at it.",
+                    "Someone else holding your phone nearby keeps the PC from locking.",
+                    "It only works while RedTooth is open and scanning. Lock the PC yourself when you leave.",
+                ] {
+                    ui.label(format!("• {}", warning));
+                }
+                ui.separator();
+
+                if phones.is_empty() {
+                    ui.small("No paired phone in range. Pair your phone and keep scanning, then try again.");
+                } else {
+                    let selected = setup
+                        .device
+                        .and_then(|a| phones.iter().find(|(p, _)| *p == a))
+                        .map_or("Choose your phone", |(_, n)| n.as_str());
+                    egui::ComboBox::from_label("Phone").selected_text(selected).show_ui(ui, |ui| {
+                        for (address, name) in &phones {
+                            ui.selectable_value(&mut setup.device, Some(*address), name);
+                        }
+                    });
+                }
+                ui.small(
+                    "Signal is read from the Bluetooth LE advertisements of your phone. Phones that only \
+                     advertise under a private address while locked are never heard, and nothing is locked \
+                     until RedTooth has heard your phone at least once; the 🔐 in the status bar shows when.",
+                );
+                if !signal_supported {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "This adapter doesn't report signal strength (it needs Bluetooth LE), so presence lock can't work here.",
+                    );
+                }
+                ui.checkbox(&mut setup.understood, "I understand this is not a security feature");
+                let ready = signal_supported && setup.device.is_some() && setup.understood;
+                if ui.add_enabled(ready, egui::Button::new("Turn On Presence Lock")).clicked() {
+                    enable = setup.device;
+                }
+            });
+
+        if let Some(address) = enable {
+            println!("CLI: Action -> Presence lock on for {:X}", address);
+            if let Ok(config) = &mut self.config {
+                config.presence_lock.enabled = true;
+                config.presence_lock.device = config::address_key(address);
+            }
+            self.presence_lock.reset();
+            self.save_config();
+            self.presence_setup = None;
+        } else if !open {
+            self.presence_setup = None;
+        }
+    }
+
+    fn draw_watch_alerts(&mut self, ui: &mut egui::Ui) {
+        let mut dismiss = None;
+        let mut allow = None;
+        for (i, alert) in self.watch_alerts.iter().enumerate() {
+            ui.horizontal_wrapped(|ui| {
+                let name = if alert.name.is_empty() { String::from("Unknown device") } else { alert.name.clone() };
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 120, 80),
+                    format!(
+                        "🚨 {} ({:X}) at {} dBm for {} s, {}",
+                        name,
+                        alert.address,
+                        alert.rssi,
+                        alert.dwell.as_secs(),
+                        alert.at.format("%H:%M")
+                    ),
+                );
+                if !self.kiosk && ui.small_button("Allow").on_hover_text("Never alert for this device again").clicked() {
+                    allow = Some(alert.address);
+                }
+                if ui.small_button("Dismiss").clicked() {
+                    dismiss = Some(i);
+                }
+            });
+        }
+        if let Some(i) = dismiss {
+            self.watch_alerts.remove(i);
+        }
+        if let Some(address) = allow {
+            if let Ok(config) = &mut self.config {
+                let key = config::address_key(address);
+                if !config.watch.allowlist.contains(&key) {
+                    config.watch.allowlist.push(key);
+                }
+            }
+            self.watch_alerts.retain(|a| a.address != address);
+            self.save_config();
+        }
+    }
+
+    /// Feeds the health analysis with a signal reading for saved devices, throttled per device.
+    fn sample_rssi(&mut self, dev: &BluetoothDevice) {
+        if self.incognito {
+            return;
+        }
+        let (Ok(config), Ok(registry)) = (&self.config, &self.registry) else {
+            return;
+        };
+        // Inquiry results carry no reading; only LE advertisements do
+        if dev.rssi == 0 || !config.devices.values().any(|a| *a == dev.address) {
+            return;
+        }
+        if self.rssi_sampled.get(&dev.address).is_some_and(|t| t.elapsed() < health::RSSI_SAMPLE_INTERVAL) {
+            return;
+        }
+        self.rssi_sampled.insert(dev.address, Instant::now());
+        if let Err(e) = registry.record_rssi(dev.address, dev.rssi) {
+            warn!("Failed to record RSSI for {:X}: {}", dev.address, e);
+        }
+    }
+
+    /// Runs the device health analysis at startup and then weekly.
+    fn sync_health(&mut self) {
+        let enabled = self.config.as_ref().is_ok_and(|c| c.registry.health_alerts);
+        if !enabled {
+            self.health_alerts.clear();
+            return;
+        }
+        if self.health_checked.is_some_and(|t| t.elapsed() < health::ANALYSIS_INTERVAL) {
+            return;
+        }
+        self.health_checked = Some(Instant::now());
+        if let Ok(registry) = &self.registry {
+            // The registry stores UTC timestamps
+            match health::analyze(registry, Utc::now().date_naive()) {
+                Ok(alerts) => self.health_alerts = alerts,
+                Err(e) => warn!("Device health analysis failed: {}", e),
+            }
+        }
+    }
+
+    /// Runs VACUUM on a second connection; `RegistryCompacted` reports back.
+    fn compact_registry(&mut self) {
+        if self.compacting.is_some() {
+            return;
+        }
+        let before = self.registry_stats.as_ref().map_or(0, |s| s.bytes);
+        self.compacting = Some((Instant::now(), before));
+        let timeout = self.config.as_ref().map_or(registry::DEFAULT_BUSY_TIMEOUT, |c| c.registry.busy_timeout());
+        std::thread::spawn(move || {
+            let result = Registry::open(timeout).and_then(|r| r.compact()).map_err(|e| e.to_string());
+            bluetooth::send_event(BluetoothEvent::RegistryCompacted { result });
+        });
+    }
+
+    /// Writes a backup zip on a background thread; `BackupFinished` reports back.
+    fn start_backup(&mut self) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        if self.backing_up {
+            return;
+        }
+        self.backing_up = true;
+        let dir = backup::folder(&config.backup);
+        let keep = config.backup.keep as usize;
+        let timeout = config.registry.busy_timeout();
+        std::thread::spawn(move || {
+            let result = backup::create(&dir, keep, timeout).map_err(|e| e.to_string());
+            bluetooth::send_event(BluetoothEvent::BackupFinished { result });
+        });
+    }
+
+    /// Starts a scheduled backup once the newest one in the folder is older than the interval.
+    fn sync_backups(&mut self) {
+        if self.kiosk || self.safe_mode || self.backing_up {
+            return;
+        }
+        if self.backup_checked.is_some_and(|t| t.elapsed() < BACKUP_CHECK_INTERVAL) {
+            return;
+        }
+        self.backup_checked = Some(Instant::now());
+        let due = self
+            .config
+            .as_ref()
+            .is_ok_and(|c| c.backup.enabled && backup::due(&backup::folder(&c.backup), c.backup.interval()));
+        if due {
+            self.start_backup();
+        }
+    }
+
+    /// Restores config and device history from a backup zip. The registry
+    /// connection is closed while registry.db is replaced.
+    fn restore_from_backup(&mut self, path: &Path) {
+        let extracted = match backup::extract(path) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                self.toasts.error(format!("Restore failed: {}", e));
+                return;
+            }
+        };
+        if let Some(snapshot) = &extracted.registry {
+            self.registry = Err(AppError::Unknown(String::from("Restoring from backup")));
+            if let Err(e) = backup::restore_registry(snapshot) {
+                self.toasts.error(format!("Could not restore device history: {}", e));
+            }
+            let timeout = self.config.as_ref().map_or(registry::DEFAULT_BUSY_TIMEOUT, |c| c.registry.busy_timeout());
+            self.registry = Registry::open(timeout);
+            self.registry_stats = None;
+        }
+        if let Some(config) = &extracted.config {
+            match self.config_service.restore_backup(config) {
+                Ok(revision) => {
+                    self.config = Ok(self.config_service.snapshot());
+                    self.config_revision = revision;
+                }
+                Err(e) => {
+                    self.toasts.error(format!("Could not restore settings: {}", e));
+                    return;
+                }
+            }
+        }
+        self.notify(format!("Restored from {}", path.display()));
+    }
+
+    /// Keeps the RSSI history small: at startup and then daily, raw samples
+    /// older than a day are rolled up into hourly rows.
+    fn sync_rssi_rollup(&mut self) {
+        if self.rssi_rolled_up.is_some_and(|t| t.elapsed() < registry::ROLLUP_INTERVAL) {
+            return;
+        }
+        let Ok(registry) = &mut self.registry else {
+            return;
+        };
+        self.rssi_rolled_up = Some(Instant::now());
+        if let Err(e) = registry.rollup_rssi() {
+            warn!("Failed to roll up RSSI history: {}", e);
+        }
+    }
+
+    fn draw_health_alerts(&mut self, ui: &mut egui::Ui) {
+        let mut dismiss = None;
+        for (i, alert) in self.health_alerts.iter().enumerate() {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(format!(
+                    "💡 {} is {}. {}",
+                    self.device_label(alert.address),
+                    alert.summary,
+                    alert.suggestion.text()
+                ));
+                if ui.small_button("Dismiss").clicked() {
+                    dismiss = Some(i);
+                }
+            });
+        }
+        if let Some(i) = dismiss {
+            self.health_alerts.remove(i);
+        }
+    }
+
+    fn play_test_tone(&mut self, address: u64) {
+        match audio::play_test_tone(address) {
+            Ok(()) => {}
+            Err(AppError::NotSupported(reason)) => {
+                self.test_tone_supported = false;
+                self.toasts.error(reason);
+            }
+            Err(e) => {
+                self.toasts.error(format!("Test tone failed: {}", e));
+            }
+        }
+    }
+
+    /// Starts `bluetooth::start_connect`. `Ok` means the page is under way; how it
+    /// ends arrives as `ConnectFinished` and goes through `connect_finished`.
+    fn connect_device(&mut self, address: u64) -> crate::error::Result<()> {
+        if self.kiosk {
+            return Err(AppError::bluetooth("Connecting is disabled in kiosk mode"));
+        }
+        let result = bluetooth::start_connect(address);
+        match &result {
+            // Refused before anything was attempted
+            Err(AppError::InvalidState { .. }) | Ok(()) => {}
+            Err(e) => self.connect_finished(address, Err(e.to_string()), false),
+        }
+        result
+    }
+
+    /// Failures are logged for usage reports and authentication failures counted
+    /// towards offering "Fix pairing".
+    fn connect_finished(&mut self, address: u64, result: std::result::Result<(), String>, bond_rejected: bool) {
+        if let Some(repair) = self.repair.as_mut().filter(|r| r.address == address && r.step == RepairStep::Connecting) {
+            repair.step = match &result {
+                Ok(()) => RepairStep::Done,
+                Err(e) => RepairStep::Failed(format!("Paired, but connecting failed: {}", e)),
+            };
+        }
+        match result {
+            Ok(()) => {
+                self.auth_failures.remove(&address);
+            }
+            Err(e) => {
+                if let Some(registry) = self.history() {
+                    let _ = registry.record_failure(address, EventKind::ConnectFailed, &e);
+                }
+                self.outcomes.remove(&address);
+                self.operations.device_done(address);
+                if bond_rejected {
+                    *self.auth_failures.entry(address).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    /// Starts `bluetooth::start_disconnect`; failures are logged so the card can show them.
+    fn disconnect_device(&mut self, address: u64) -> crate::error::Result<()> {
+        let result = bluetooth::start_disconnect(address);
+        if let Err(e) = &result {
+            if !matches!(e, AppError::InvalidState { .. }) {
+                self.disconnect_failed(address, &e.to_string());
+            }
+        }
+        result
+    }
+
+    fn disconnect_failed(&mut self, address: u64, error: &str) {
+        if let Some(registry) = self.history() {
+            let _ = registry.record_failure(address, EventKind::DisconnectFailed, error);
+        }
+        self.outcomes.remove(&address);
+    }
+
+    /// Connected time of a device, loaded from the event log on first use each day.
+    fn connected_time(&mut self, address: u64) -> Option<&ConnectedTime> {
+        let today = Local::now().date_naive();
+        if self.connected_times.get(&address).is_none_or(|t| t.day != today) {
+            let registry = self.registry.as_ref().ok()?;
+            let day_start = local_midnight(today);
+            let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+            let week_start = local_midnight(monday);
+            let loaded = registry.connected_secs(address, week_start.naive_utc()).and_then(|(week, since)| {
+                let (today, _) = registry.connected_secs(address, day_start.naive_utc())?;
+                Ok((today, week, since))
+            });
+            // Cached even on failure so a broken log isn't queried every frame
+            let (today_secs, week, since) = loaded.unwrap_or_else(|e| {
+                warn!("Failed to load connected time for {:X}: {}", address, e);
+                (0, 0, None)
+            });
+            let since = since.map(|at| Utc.from_utc_datetime(&at).with_timezone(&Local));
+            self.connected_times
+                .insert(address, ConnectedTime { day: today, day_start, week_start, today: today_secs, week, since });
+        }
+        self.connected_times.get(&address)
+    }
+
+    /// The last few connect/disconnect results under a device card, e.g.
+    /// "Connect failed: … — 2 min ago", so a red status explains itself.
+    fn draw_outcomes(&mut self, ui: &mut egui::Ui, address: u64) {
+        let cached = self.outcomes.contains_key(&address);
+        self.perf.cache_lookup(cached);
+        if !cached {
+            let Ok(registry) = &self.registry else {
+                return;
+            };
+            let events = registry.recent_events(address, 20).unwrap_or_else(|e| {
+                warn!("Failed to load recent events for {:X}: {}", address, e);
+                Vec::new()
+            });
+            let outcomes = events
+                .into_iter()
+                .filter(|e| !matches!(e.kind, EventKind::AudioStreamed | EventKind::WatchAlert))
+                .take(OUTCOMES_SHOWN)
+                .collect();
+            self.outcomes.insert(address, outcomes);
+        }
+
+        let now = Utc::now().naive_utc();
+        for event in &self.outcomes[&address] {
+            let (text, failed) = match (event.kind, event.detail.as_deref()) {
+                (EventKind::Connected, _) => ("Connected".to_string(), false),
+                (EventKind::Disconnected, _) => ("Disconnected".to_string(), false),
+                (EventKind::ConnectFailed, Some(detail)) => (format!("Connect failed: {}", detail), true),
+                (EventKind::ConnectFailed, None) => ("Connect failed".to_string(), true),
+                (EventKind::DisconnectFailed, Some(detail)) => (format!("Disconnect failed: {}", detail), true),
+                (EventKind::DisconnectFailed, None) => ("Disconnect failed".to_string(), true),
+                (EventKind::AudioStreamed | EventKind::WatchAlert, _) => continue,
+            };
+            let line = egui::RichText::new(format!("{} — {}", text, time_ago(now - event.at))).small();
+            if failed {
+                ui.colored_label(egui::Color32::LIGHT_RED, line);
+            } else {
+                ui.label(line.weak());
+            }
+        }
+    }
+
+    fn sync_default_output(&mut self) {
+        if self.default_output_checked.is_some_and(|t| t.elapsed() < endpoints::POLL_INTERVAL) {
+            return;
+        }
+        self.default_output_checked = Some(Instant::now());
+        match endpoints::default_output() {
+            Ok(address) => self.default_output = address,
+            Err(e) => warn!("Failed to read the default output: {}", e),
+        }
+    }
+
+    /// Conference room mode: when a headset or speaker connects, disconnects every
+    /// other one and moves the broadcast and the default output over to it.
+    fn enforce_exclusive_output(&mut self, address: u64) {
+        if self.kiosk || !self.config.as_ref().is_ok_and(|c| c.audio.exclusive_output) {
+            return;
+        }
+        if !self.devices.get(address).is_some_and(|d| d.is_audio_output()) {
+            return;
+        }
+        let others: Vec<u64> = self
+            .devices
+            .snapshot()
+            .iter()
+            .filter(|d| d.address != address && d.state.is_connected() && d.is_audio_output())
+            .map(|d| d.address)
+            .collect();
+        if others.is_empty() {
+            return;
+        }
+        println!("CLI: Action -> Conference room mode, {:X} replaces {:X?}", address, others);
+
+        // The sink playing now stays connected until the stream has faded over to the new one
+        let from = self
+            .audio_session
+            .as_ref()
+            .and_then(|session| others.iter().copied().find(|a| session.sinks.contains(a)));
+        if let Some(from) = from {
+            self.endpoint_jobs.push(EndpointJob {
+                wait: EndpointWait::new(&[address]),
+                then: AfterEndpoints::HandOver { from, to: address, disconnect: true },
+            });
+        }
+        if self.default_output.is_some_and(|a| others.contains(&a)) {
+            self.endpoint_jobs.push(EndpointJob {
+                wait: EndpointWait::new(&[address]),
+                then: AfterEndpoints::TakeDefaultOutput(address),
+            });
+        }
+
+        let mut names = Vec::new();
+        for other in others {
+            names.push(self.device_label(other));
+            if Some(other) == from {
+                continue;
+            }
+            if let Err(e) = self.disconnect_device(other) {
+                self.toasts.error(format!("Conference room mode couldn't disconnect {}: {}", self.device_label(other), e));
+            }
+        }
+        self.notify(format!("{} connected; disconnected {}", self.device_label(address), names.join(", ")));
+    }
+
+    /// Makes a device that opted in the default output, remembering the one it replaces.
+    fn take_default_output(&mut self, address: u64) {
+        if self.kiosk || !self.config.as_ref().is_ok_and(|c| c.takes_default_output(address)) {
+            return;
+        }
+        if self.default_output == Some(address) {
+            return;
+        }
+        self.endpoint_jobs.push(EndpointJob {
+            wait: EndpointWait::new(&[address]),
+            then: AfterEndpoints::TakeDefaultOutput(address),
+        });
+    }
+
+    fn apply_default_output(&mut self, address: u64) {
+        let previous = endpoints::default_output_id();
+        match endpoints::make_default_output(address) {
+            Ok(()) => {
+                self.default_output = Some(address);
+                // Keep the original default if another device already took over
+                match &mut self.replaced_output {
+                    Some((owner, _)) => *owner = address,
+                    None => self.replaced_output = previous.map(|id| (address, id)),
+                }
+            }
+            Err(e) => warn!("Could not make {:X} the default output: {}", address, e),
+        }
+    }
+
+    fn restore_default_output(&mut self, address: u64) {
+        match self.replaced_output.take() {
+            Some((owner, endpoint)) if owner == address => {
+                if let Err(e) = endpoints::restore_default_output(&endpoint) {
+                    warn!("Failed to restore the default output: {}", e);
+                }
+                // Re-read on the next frame
+                self.default_output_checked = None;
+            }
+            other => self.replaced_output = other,
+        }
+    }
+
+    /// Polls pending endpoint waits and runs whatever was waiting on them.
+    /// Stops the broadcast and any recording of it, logging how long each sink streamed.
+    fn stop_broadcast(&mut self) {
+        // The writer thread reports back via RecordingFinished once the file is closed
+        if let Some(recording) = &self.recording {
+            recording.stop();
+        }
+        if let Some(mut session) = self.audio_session.take() {
+            if let Some(registry) = self.history() {
+                let streamed = session.started.elapsed();
+                for address in &session.sinks {
+                    let _ = registry.record_event(*address, EventKind::AudioStreamed, Some(streamed));
+                }
+            }
+            if let Err(e) = session.stop() {
+                self.toasts.error(format!("Failed to stop audio: {}", e));
+            }
+        }
+    }
+
+    /// Starts audio profiles whose schedule entry came due since the last frame.
+    fn sync_profile_schedules(&mut self) {
+        let now = Local::now().naive_local();
+        let since = std::mem::replace(&mut self.schedule_checked, now);
+        if self.kiosk {
+            return;
+        }
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let mut due = Vec::new();
+        for entry in config.profile_schedules.iter().filter(|e| e.is_due(since, now)) {
+            match config.audio_profiles.iter().find(|p| p.name == entry.profile) {
+                Some(profile) => due.push(profile.clone()),
+                None => warn!("Scheduled audio profile '{}' no longer exists", entry.profile),
+            }
+        }
+        for profile in due {
+            self.activate_profile(profile, "on schedule");
+        }
+    }
+
+    /// Reads the battery of each sink in the broadcast and, per settings, suggests
+    /// or performs moving a low sink's audio to a spare headset or speaker.
+    fn sync_sink_batteries(&mut self) {
+        if self.batteries_checked.is_some_and(|t| t.elapsed() < BATTERY_POLL_INTERVAL) {
+            return;
+        }
+        self.batteries_checked = Some(Instant::now());
+        let Some(session) = &self.audio_session else {
+            self.sink_batteries.clear();
+            self.battery_suggestion = None;
+            return;
+        };
+        let sinks = session.sinks.clone();
+        let (threshold, action) =
+            self.config.as_ref().map_or((0, LowBatteryAction::Nothing), |c| (c.audio.low_battery_percent, c.audio.low_battery));
+
+        self.sink_batteries.retain(|a, _| sinks.contains(a));
+        for address in &sinks {
+            match endpoints::battery_level(*address) {
+                Ok(Some(level)) => {
+                    self.sink_batteries.insert(*address, level);
+                }
+                Ok(None) => {
+                    self.sink_batteries.remove(address);
+                }
+                Err(e) => warn!("Failed to read the battery of {:X}: {}", address, e),
+            }
+        }
+        // Charged again, or left the broadcast
+        self.low_battery_handled.retain(|a| self.sink_batteries.get(a).is_some_and(|level| *level < threshold));
+        if self.battery_suggestion.is_some_and(|(from, _)| !self.low_battery_handled.contains(&from)) {
+            self.battery_suggestion = None;
+        }
+
+        if self.kiosk || action == LowBatteryAction::Nothing || self.battery_suggestion.is_some() {
+            return;
+        }
+        let Some((&from, &level)) = self
+            .sink_batteries
+            .iter()
+            .find(|(a, level)| **level < threshold && !self.low_battery_handled.contains(*a))
+        else {
+            return;
+        };
+        self.low_battery_handled.insert(from);
+
+        // A spare that isn't low itself; one that doesn't report a battery is likely mains powered
+        let spare = self
+            .devices
+            .snapshot()
+            .iter()
+            .filter(|d| d.state.is_connected() && d.is_audio_output() && !sinks.contains(&d.address))
+            .map(|d| d.address)
+            .find(|a| endpoints::battery_level(*a).ok().flatten().is_none_or(|l| l >= threshold));
+        let label = self.device_label(from);
+        let Some(to) = spare else {
+            self.toasts.push(
+                Severity::Warning,
+                format!("{} is at {}% battery", label, level),
+                Some("No other connected headset or speaker could take over its audio.".to_string()),
+            );
+            return;
+        };
+        match action {
+            LowBatteryAction::Move => {
+                self.notify(format!("{} is at {}% battery; moving its audio to {}", label, level, self.device_label(to)));
+                self.endpoint_jobs.push(EndpointJob {
+                    wait: EndpointWait::new(&[to]),
+                    then: AfterEndpoints::HandOver { from, to, disconnect: false },
+                });
+            }
+            _ => {
+                self.notify(format!("{} is at {}% battery; see the Audio panel to move its audio", label, level));
+                self.battery_suggestion = Some((from, to));
+            }
+        }
+    }
+
+    /// Saves the running session as a profile under the name typed in the Audio panel.
+    fn save_preset(&mut self) {
+        let Some(name) = self.preset_name.take() else {
+            return;
+        };
+        let name = name.trim().to_string();
+        let (Some(session), Ok(config)) = (&mut self.audio_session, &mut self.config) else {
+            return;
+        };
+        let profile = session.to_profile(&name, config);
+        match config.audio_profiles.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = profile,
+            None => config.audio_profiles.push(profile),
+        }
+        // The session now plays the saved profile
+        session.profile = name.clone();
+        self.save_config();
+        self.notify(format!("Saved audio profile '{}'", name));
+    }
+
+    /// Starts profiles whose hotkey was pressed this frame, and the one named with --profile.
+    fn sync_profile_shortcuts(&mut self, ctx: &egui::Context) {
+        if self.kiosk {
+            return;
+        }
+        let Ok(config) = &self.config else {
+            return;
+        };
+        if let Some(name) = self.startup_profile.take() {
+            match config.audio_profiles.iter().find(|p| p.name == name).cloned() {
+                Some(profile) => self.activate_profile(profile, "from the command line"),
+                None => self.toasts.error(format!("No audio profile '{}'", name)),
+            }
+            return;
+        }
+        let pressed = config
+            .audio_profiles
+            .iter()
+            .find(|p| Hotkey::parse(&p.hotkey).is_some_and(|k| k.consume(ctx)))
+            .cloned();
+        if let Some(profile) = pressed {
+            self.activate_profile(profile, "by hotkey");
+        }
+    }
+
+    /// Connects a profile's sinks and starts it once their endpoints exist,
+    /// replacing whatever is broadcasting. Used by the profile schedule, hotkeys and scripts;
+    /// `why` finishes the notification, e.g. "on schedule".
+    fn activate_profile(&mut self, profile: AudioProfile, why: &str) {
+        if self.audio_session.as_ref().is_some_and(|s| s.profile == profile.name) {
+            return;
+        }
+        println!("CLI: Action -> Activate profile '{}' {}", profile.name, why);
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let sinks = config.resolve_targets(&profile.sinks);
+        self.stop_broadcast();
+        for address in &sinks {
+            if self.link_state(*address).can_connect() && !bluetooth::is_blocked(*address) {
+                let _ = self.connect_device(*address);
+            }
+        }
+        self.endpoint_jobs.retain(|job| !matches!(job.then, AfterEndpoints::StartProfile(_)));
+        self.notify(format!("Switching to audio profile '{}' {}", profile.name, why));
+        self.operations.begin(Operation::StartProfile { name: profile.name.clone() });
+        self.endpoint_jobs.push(EndpointJob {
+            wait: EndpointWait::new(&sinks),
+            then: AfterEndpoints::StartProfile(profile),
+        });
+    }
+
+    fn sync_endpoint_jobs(&mut self) {
+        let now = Instant::now();
+        let (due, waiting) = std::mem::take(&mut self.fade_disconnects).into_iter().partition(|(_, at)| *at <= now);
+        self.fade_disconnects = waiting;
+        for (address, _) in due {
+            if let Some(session) = &mut self.audio_session {
+                if session.sinks.contains(&address) {
+                    if let Err(e) = session.drop_sink(address) {
+                        warn!("Failed to remove sink {:X}: {}", address, e);
+                    }
+                }
+            }
+            if let Err(e) = self.disconnect_device(address) {
+                self.toasts.error(format!("Conference room mode couldn't disconnect {}: {}", self.device_label(address), e));
+            }
+        }
+
+        for mut job in std::mem::take(&mut self.endpoint_jobs) {
+            let missing = match job.wait.poll() {
+                WaitState::Waiting => {
+                    self.endpoint_jobs.push(job);
+                    continue;
+                }
+                WaitState::Ready => Vec::new(),
+                WaitState::TimedOut(missing) => missing,
+            };
+            let then = job.then;
+            if matches!(then, AfterEndpoints::StartProfile(_)) {
+                self.operations.profile_done();
+            }
+            if !missing.is_empty() {
+                let names: Vec<String> = missing.iter().map(|a| self.device_label(*a)).collect();
+                warn!("Audio endpoint did not appear for {}", names.join(", "));
+                match then {
+                    AfterEndpoints::StartProfile(profile) => {
+                        self.toasts.push(
+                            Severity::Warning,
+                            format!("Failed to start '{}': no audio output from {} yet", profile.name, names.join(", ")),
+                            Some("Windows hasn't created an audio endpoint for the device. Reconnecting it usually helps.".to_string()),
+                        );
+                    }
+                    AfterEndpoints::Failover { to, .. } => {
+                        let message = format!("Audio failover failed: no audio output from {} yet", self.device_label(to));
+                        self.toasts.push(Severity::Warning, message, None);
+                    }
+                    AfterEndpoints::HandOver { from, to, disconnect } => {
+                        let message = format!("Moving audio failed: no audio output from {} yet", self.device_label(to));
+                        self.toasts.push(Severity::Warning, message, None);
+                        if disconnect {
+                            self.fade_disconnects.push((from, Instant::now()));
+                        }
+                    }
+                    AfterEndpoints::TakeDefaultOutput(_) => {}
+                }
+                continue;
+            }
+            match then {
+                AfterEndpoints::StartProfile(profile) => {
+                    if let Ok(config) = &self.config {
+                        match AudioSession::start(&profile, config) {
+                            Ok(session) => self.audio_session = Some(session),
+                            Err(e) => self.toasts.error(format!("Failed to start audio: {}", e)),
+                        }
+                    }
+                }
+                AfterEndpoints::Failover { from, to } => {
+                    let Some(session) = &mut self.audio_session else {
+                        continue;
+                    };
+                    match session.attach_sink(to) {
+                        Ok(()) => {
+                            let message = format!(
+                                "Audio moved from {} to backup {}",
+                                self.device_label(from),
+                                self.device_label(to)
+                            );
+                            self.notify(message);
+                        }
+                        Err(e) => self.toasts.error(format!("Audio failover failed: {}", e)),
+                    }
+                }
+                AfterEndpoints::HandOver { from, to, disconnect } => {
+                    let fades = *self.crossfade_supported.get_or_insert_with(audio::crossfade_supported);
+                    let fade_ms = self.config.as_ref().map_or(0, |c| if fades { c.audio.crossfade_ms } else { 0 });
+                    if disconnect {
+                        self.fade_disconnects.push((from, Instant::now() + Duration::from_millis(fade_ms as u64)));
+                    }
+                    let Some(session) = &mut self.audio_session else {
+                        continue;
+                    };
+                    match session.move_sink(from, to, fade_ms) {
+                        Ok(()) => {
+                            let message =
+                                format!("Audio moved from {} to {}", self.device_label(from), self.device_label(to));
+                            self.notify(message);
+                        }
+                        Err(e) => self.toasts.error(format!("Moving audio to {} failed: {}", self.device_label(to), e)),
+                    }
+                }
+                AfterEndpoints::TakeDefaultOutput(address) => self.apply_default_output(address),
+            }
+        }
+    }
+
+    /// Connected keyboards, mice and other HID devices, which Disconnect All leaves alone by default.
+    fn connected_inputs(&mut self) -> Vec<BluetoothDevice> {
+        self.devices.snapshot().iter().filter(|d| d.state.is_connected() && d.is_input_device()).cloned().collect()
+    }
+
+    /// Connected devices an action would cut off.
+    fn affected_by(&mut self, action: Disruptive) -> Vec<BluetoothDevice> {
+        match action {
+            Disruptive::DisconnectAll { include_inputs } => self
+                .devices
+                .snapshot()
+                .iter()
+                .filter(|d| d.state.is_connected() && (include_inputs || !d.is_input_device()))
+                .cloned()
+                .collect(),
+            Disruptive::Disconnect(address) | Disruptive::Unpair(address) => {
+                self.devices.get(address).cloned().into_iter().collect()
+            }
+        }
+    }
+
+    /// Runs `action` right away unless its confirmation is turned on, or it
+    /// would drop an input device and that confirmation is on.
+    fn request(&mut self, action: Disruptive) {
+        let input_affected = self.affected_by(action).iter().any(|d| d.is_input_device());
+        let ask = match &self.config {
+            Ok(config) => match action {
+                Disruptive::DisconnectAll { .. } => config.confirm.disconnect_all,
+                Disruptive::Disconnect(_) => config.confirm.disconnect_input && input_affected,
+                Disruptive::Unpair(_) => config.confirm.unpair,
+            },
+            Err(_) => true,
+        };
+        if ask {
+            self.confirm = Some(PendingConfirm { action, dont_ask: false });
+        } else {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: Disruptive) {
+        match action {
+            Disruptive::DisconnectAll { include_inputs } => {
+                println!("CLI: Action -> Disconnect All (inputs: {})", include_inputs);
+                for device in self.affected_by(action) {
+                    if let Err(e) = self.disconnect_device(device.address) {
+                        self.toasts.error(format!("{}: {}", self.device_label(device.address), e));
+                    }
+                }
+                let kept: Vec<String> = if include_inputs {
+                    Vec::new()
+                } else {
+                    self.connected_inputs().iter().map(|d| self.device_label(d.address)).collect()
+                };
+                if !kept.is_empty() {
+                    self.notify(format!("Kept {} connected", kept.join(", ")));
+                }
+            }
+            Disruptive::Disconnect(address) => {
+                let _ = self.disconnect_device(address);
+            }
+            Disruptive::Unpair(address) => match bluetooth::unpair(address) {
+                Ok(()) => self.notify(format!("{} unpaired", self.device_label(address))),
+                Err(e) => self.toasts.error(format!("Unpair failed: {}", e)),
+            },
+        }
+    }
+
+    /// Asks before a disruptive action, spelling out when a keyboard or mouse would be lost.
+    fn draw_confirm(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.confirm.as_ref().map(|c| c.action) else {
+            return;
+        };
+        let affected = self.affected_by(action);
+        let inputs: Vec<String> =
+            affected.iter().filter(|d| d.is_input_device()).map(|d| self.device_label(d.address)).collect();
+        // Only Disconnect All has inputs that can be held back
+        let held: Vec<String> = match action {
+            Disruptive::DisconnectAll { .. } => self.connected_inputs().iter().map(|d| self.device_label(d.address)).collect(),
+            _ => Vec::new(),
+        };
+        let (title, question, button) = match action {
+            Disruptive::DisconnectAll { .. } => (
+                "Disconnect all devices",
+                format!("Disconnect {} connected devices?", affected.len()),
+                "Disconnect all",
+            ),
+            Disruptive::Disconnect(address) => {
+                ("Disconnect device", format!("Disconnect {}?", self.device_label(address)), "Disconnect")
+            }
+            Disruptive::Unpair(address) => (
+                "Unpair device",
+                format!("Unpair {}? It has to be paired again before it can connect.", self.device_label(address)),
+                "Unpair",
+            ),
+        };
+        let can_skip = !self.config_service.policy().is_locked(action.setting());
+        let Some(pending) = &mut self.confirm else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancel = false;
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.set_max_width(340.0);
+                ui.label(question);
+                if let Disruptive::DisconnectAll { include_inputs } = &mut pending.action {
+                    if !held.is_empty() {
+                        ui.checkbox(include_inputs, "Also disconnect keyboards and mice");
+                        if !*include_inputs {
+                            ui.small(format!("Staying connected: {}", held.join(", ")));
+                        }
+                    }
+                }
+                if !inputs.is_empty() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 165, 0),
+                        format!(
+                            "⚠ This cuts off {}, an input device. Keep another keyboard or mouse at hand, \
+                             or you may not be able to get it back.",
+                            inputs.join(", ")
+                        ),
+                    );
+                }
+                ui.add_enabled(can_skip, egui::Checkbox::new(&mut pending.dont_ask, "Don't ask again"))
+                    .on_disabled_hover_text("Managed by your organization");
+                ui.horizontal(|ui| {
+                    confirmed = ui.button(egui::RichText::new(button).color(egui::Color32::RED)).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if !open || cancel {
+            self.confirm = None;
+            return;
+        }
+        if !confirmed {
+            return;
+        }
+        let action = pending.action;
+        if pending.dont_ask {
+            if let Ok(config) = &mut self.config {
+                match action {
+                    Disruptive::DisconnectAll { .. } => config.confirm.disconnect_all = false,
+                    Disruptive::Disconnect(_) => config.confirm.disconnect_input = false,
+                    Disruptive::Unpair(_) => config.confirm.unpair = false,
+                }
+            }
+            self.save_config();
+        }
+        self.confirm = None;
+        self.perform(action);
+    }
+
+    /// Confirmation for "Delete all my data". The app closes afterwards since
+    /// the registry and config it holds are gone.
+    fn draw_purge(&mut self, ctx: &egui::Context) {
+        let Some(keep_config) = &mut self.purge_confirm else {
+            return;
+        };
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancel = false;
+        egui::Window::new("Delete all my data")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("This permanently deletes your device history, notes, usage reports and logs, and withdraws devices you shared with other users of this PC.");
+                ui.checkbox(keep_config, "Keep my settings and saved devices (config.toml)");
+                ui.small("Recordings and received files are not touched.");
+                let backups = purge::custom_backups();
+                if let Some(dir) = backups.first().and_then(|p| p.parent()) {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("⚠ Also deletes {} backup zip(s) in {}; they contain your device history.", backups.len(), dir.display()),
+                    );
+                }
+                egui::CollapsingHeader::new("Files").show(ui, |ui| {
+                    for path in purge::targets(*keep_config) {
+                        ui.small(path.display().to_string());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    confirmed = ui
+                        .button(egui::RichText::new("Delete and close RedTooth").color(egui::Color32::RED))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if !open || cancel {
+            self.purge_confirm = None;
+            return;
+        }
+        if !confirmed {
+            return;
+        }
+
+        let keep_config = *keep_config;
+        println!("CLI: Action -> Delete all data (keep config: {})", keep_config);
+        // Release open files so Windows lets them be deleted
+        self.registry = Err(AppError::Unknown(String::from("Data deleted")));
+        self.shared_store = None;
+        #[cfg(feature = "gatt")]
+        self.gatt_loggers.clear();
+        let report = purge::purge(keep_config);
+        println!("CLI: Deleted {} items, {} shared devices", report.removed.len(), report.shared_removed);
+        self.purge_confirm = None;
+        if report.failed.is_empty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
+        }
+        for (path, error) in &report.failed {
+            error!("Could not delete {:?}: {}", path, error);
+        }
+        let paths: Vec<String> = report.failed.iter().map(|(path, error)| format!("{}: {}", path.display(), error)).collect();
+        self.toasts.push(
+            Severity::Error,
+            format!("{} items could not be deleted. Close RedTooth and run `btmanager purge` to finish.", report.failed.len()),
+            Some(paths.join("\n")),
+        );
+    }
+
+    /// Re-registers the quick connect hotkey when the configured combination changes.
+    fn sync_hotkey(&mut self) {
+        if self.kiosk {
+            return;
+        }
+        let spec = self.config.as_ref().map(|c| c.ui.quick_connect_hotkey.clone()).unwrap_or_default();
+        if spec == self.hotkey_spec {
+            return;
+        }
+        self.hotkey_spec = spec;
+        self.hotkey = Hotkey::parse(&self.hotkey_spec);
+        self.hotkey_global = false;
+        hotkey::unregister();
+        if let Some(key) = self.hotkey {
+            match hotkey::register(key) {
+                Ok(()) => {
+                    info!("Quick connect hotkey {} registered", self.hotkey_spec);
+                    self.hotkey_global = true;
+                }
+                Err(e) => warn!("Quick connect hotkey {} only works in-app: {}", self.hotkey_spec, e),
+            }
+        }
+    }
+
+    fn open_quick_connect(&mut self) {
+        println!("CLI: Action -> Quick connect");
+        self.quick_connect = Some(QuickConnect { opened: Instant::now(), was_minimized: None });
+    }
+
+    fn close_quick_connect(&mut self, ctx: &egui::Context) {
+        if let Some(overlay) = self.quick_connect.take() {
+            if !self.mini_mode {
+                ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
+            }
+            if overlay.was_minimized == Some(true) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            }
+        }
+    }
+
+    /// Numbered favorites; 1–9 toggles the connection and closes the overlay, Esc closes it.
+    fn draw_quick_connect(&mut self, ctx: &egui::Context) {
+        let Some(overlay) = &mut self.quick_connect else {
+            return;
+        };
+        if overlay.was_minimized.is_none() {
+            overlay.was_minimized = Some(ctx.input(|i| i.viewport().minimized.unwrap_or(false)));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+        let remaining = QUICK_CONNECT_TIMEOUT.saturating_sub(overlay.opened.elapsed());
+
+        let favorites: Vec<(String, u64)> = match &self.config {
+            Ok(config) => config.ordered_devices().into_iter().take(QUICK_CONNECT_KEYS.len()).collect(),
+            Err(_) => Vec::new(),
+        };
+        let style = self.status_style();
+        let mut chosen = ctx.input_mut(|i| {
+            QUICK_CONNECT_KEYS.iter().take(favorites.len()).position(|k| i.consume_key(egui::Modifiers::NONE, *k))
+        });
+        let mut close = remaining.is_zero() || ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape));
+
+        egui::Window::new("Quick connect")
+            .collapsible(false)
+            .resizable(false)
+            .title_bar(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Quick connect");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.small(format!("closes in {}s · Esc", remaining.as_secs() + 1));
+                    });
+                });
+                ui.separator();
+                if favorites.is_empty() {
+                    ui.label("Save devices to My Devices to use quick connect");
+                }
+                for (i, (name, address)) in favorites.iter().enumerate() {
+                    let state = self.link_state(*address);
+                    let row = ui.horizontal(|ui| {
+                        ui.monospace(format!("{}", i + 1));
+                        draw_status_label(ui, &state, style);
+                        ui.label(name);
+                    });
+                    if row.response.interact(egui::Sense::click()).clicked() {
+                        chosen = Some(i);
+                    }
+                }
+                if ui.small_button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+
+        if let Some((name, address)) = chosen.and_then(|i| favorites.get(i).cloned()) {
+            let was_connected = self.is_connected(address);
+            let result = if was_connected {
+                self.disconnect_device(address)
+            } else {
+                self.connect_device(address)
+            };
+            match result {
+                Ok(()) => self.notify(format!("{} {}", name, if was_connected { "disconnecting…" } else { "connecting…" })),
+                Err(e) => self.toasts.error(format!("{}: {}", name, e)),
+            }
+            close = true;
+        }
+        if close {
+            self.close_quick_connect(ctx);
+        }
+    }
+
+    fn draw_guest_dialog(&mut self, ctx: &egui::Context) {
+        let Some((address, mut hours)) = self.guest_dialog else {
+            return;
+        };
+        let label = self.device_label(address);
+        let mut open = true;
+        let mut pair = false;
+
+        egui::Window::new(format!("Pair {} as guest", label))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.set_max_width(360.0);
+                ui.label(
+                    "The device is paired as usual, then unpaired and forgotten automatically once its time is up, \
+                     even if RedTooth was closed in between.",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Remove after");
+                    ui.add(egui::DragValue::new(&mut hours).clamp_range(1..=168).suffix(" h"));
+                });
+                ui.small(format!("Until {}", (Local::now() + chrono::Duration::hours(hours as i64)).format("%a %H:%M")));
+                pair = ui.button("Pair").clicked();
+            });
+
+        if pair {
+            match bluetooth::pair(address) {
+                Ok(()) => {
+                    self.guest_pending.insert(address, hours);
+                    self.notify(format!("Pairing {} as guest… accept the prompt if Windows shows one", label));
+                }
+                Err(e) => self.toasts.error(format!("Pairing {} failed: {}", label, e)),
+            }
+            self.guest_dialog = None;
+        } else {
+            self.guest_dialog = open.then_some((address, hours));
+        }
+    }
+
+    /// Saves a freshly paired guest and when it expires.
+    fn finish_guest_pairing(&mut self, address: u64, hours: u32, result: &Result<(), String>) {
+        let label = self.device_label(address);
+        if let Err(msg) = result {
+            self.toasts.error(format!("Pairing {} as guest failed: {}", label, msg));
+            return;
+        }
+        let expires = Local::now() + chrono::Duration::hours(hours as i64);
+        let raw = self.raw_label(address);
+        if let Ok(config) = &mut self.config {
+            if !config.devices.values().any(|a| *a == address) {
+                config.add_device(raw, address);
+            }
+            config.set_guest_expiry(address, Some(expires.timestamp()));
+        }
+        self.save_config();
+        self.notify(format!("{} paired as guest until {}", label, expires.format("%a %H:%M")));
+    }
+
+    /// Janitor for guest pairings: unpairs and forgets guests whose time is up.
+    /// A failed unpair (radio off, say) is retried on the next check.
+    fn sync_guests(&mut self) {
+        if self.kiosk || self.guests_checked.is_some_and(|t| t.elapsed() < GUEST_CHECK_INTERVAL) {
+            return;
+        }
+        self.guests_checked = Some(Instant::now());
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let expired = config.expired_guests(Local::now().timestamp());
+        for address in expired {
+            self.end_guest(address);
+        }
+    }
+
+    /// Disconnects, unpairs and forgets a guest device.
+    fn end_guest(&mut self, address: u64) {
+        let label = self.device_label(address);
+        println!("CLI: Action -> End guest pairing of {:X}", address);
+        if self.is_connected(address) {
+            let _ = self.disconnect_device(address);
+        }
+        if let Err(e) = bluetooth::unpair(address) {
+            warn!("Guest {:X} could not be unpaired: {}", address, e);
+            if self.guest_failures.insert(address) {
+                self.toasts.push(
+                    Severity::Warning,
+                    format!("Guest pairing of {} has expired but could not be removed", label),
+                    Some(format!("{}\n\nRedTooth keeps trying every minute.", e)),
+                );
+            }
+            return;
+        }
+        self.guest_failures.remove(&address);
+        if let Ok(config) = &mut self.config {
+            config.forget_device(address);
+        }
+        self.save_config();
+        self.notify(format!("Guest pairing of {} ended", label));
+    }
+
+    fn open_repair(&mut self, address: u64) {
+        println!("CLI: Action -> Fix pairing for {:X}", address);
+        self.repair = Some(RepairFlow { address, step: RepairStep::Confirm });
+    }
+
+    /// Starts pairing once the device being repaired shows up unpaired, i.e. in pairing mode.
+    fn advance_repair(&mut self, dev: &BluetoothDevice) {
+        let Some(flow) = &mut self.repair else {
+            return;
+        };
+        if flow.address != dev.address || dev.authenticated || !matches!(flow.step, RepairStep::WaitingForDevice { .. }) {
+            return;
+        }
+        flow.step = match bluetooth::pair(dev.address) {
+            Ok(()) => RepairStep::Pairing,
+            Err(e) => RepairStep::Failed(e.to_string()),
+        };
+    }
+
+    fn draw_repair(&mut self, ctx: &egui::Context) {
+        let Some(flow) = &self.repair else {
+            return;
+        };
+        let address = flow.address;
+        let step = flow.step.clone();
+        let label = self.device_label(address);
+        let class = self.devices.get(address).map_or(MajorClass::Uncategorized, |d| d.major_class());
+        let mut open = true;
+        let mut next = None;
+
+        egui::Window::new(format!("Fix pairing — {}", label))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.set_max_width(360.0);
+                match &step {
+                    RepairStep::Confirm => {
+                        ui.label(format!(
+                            "Connecting to {} keeps failing with authentication errors. The pairing stored on this PC \
+                             and the one on the device probably no longer match.",
+                            label
+                        ));
+                        ui.label("RedTooth will remove the pairing, wait for the device to enter pairing mode, and pair it again.");
+                        if ui.button("Start").clicked() {
+                            next = Some(match bluetooth::unpair(address) {
+                                Ok(()) => RepairStep::WaitingForDevice { since: Instant::now() },
+                                Err(e) => RepairStep::Failed(e.to_string()),
+                            });
+                        }
+                    }
+                    RepairStep::WaitingForDevice { since } => {
+                        ui.strong("Put the device into pairing mode");
+                        ui.label(class.pairing_instructions());
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("Waiting for {}… {}s", label, since.elapsed().as_secs()));
+                        });
+                        if since.elapsed() > Duration::from_secs(120) {
+                            ui.small("Still nothing? Move the device closer and check that its light is blinking.");
+                        }
+                    }
+                    RepairStep::Pairing => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Pairing… accept the prompt if Windows shows one.");
+                        });
+                    }
+                    RepairStep::Connecting => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(format!("Paired. Connecting {}…", label));
+                        });
+                    }
+                    RepairStep::Done => {
+                        ui.label(format!("✔ {} is paired and connected again.", label));
+                    }
+                    RepairStep::Failed(msg) => {
+                        ui.colored_label(egui::Color32::RED, msg);
+                        if ui.button("Try again").clicked() {
+                            next = Some(RepairStep::Confirm);
+                        }
+                    }
+                }
+            });
+
+        if matches!(next, Some(RepairStep::WaitingForDevice { .. })) && !self.scanning {
+            let _ = bluetooth::start_scan();
+        }
+        match (open, next) {
+            (false, _) => self.repair = None,
+            (true, Some(step)) => self.repair = Some(RepairFlow { address, step }),
+            (true, None) => {}
+        }
+    }
+
+    /// Stacked toasts in the bottom-right corner, newest at the bottom.
+    fn draw_toasts(&mut self, ctx: &egui::Context) {
+        self.toasts.expire();
+        let mut dismissed = None;
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -36.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(320.0);
+                for toast in self.toasts.active_mut() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(severity_color(toast.severity), toast.severity.icon());
+                            ui.label(&toast.message);
+                            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                                dismissed = Some(toast.id);
+                            }
+                        });
+                        if let Some(details) = &toast.details {
+                            let label = if toast.expanded { "Hide details" } else { "Details" };
+                            if ui.small_button(label).clicked() {
+                                toast.expanded = !toast.expanded;
+                            }
+                            if toast.expanded {
+                                ui.small(details);
+                            }
+                        }
+                    });
+                }
+            });
+        if let Some(id) = dismissed {
+            self.toasts.dismiss(id);
+        }
+    }
+
+    /// The shortcuts in effect, by where they work, read from the same bindings
+    /// the hotkey, gesture and profile handlers use.
+    fn shortcuts(&self) -> Vec<(&'static str, Vec<(String, String)>)> {
+        let mut global = Vec::new();
+        let mut in_app = vec![(String::from("?"), String::from("Show or hide this list"))];
+        let mut overlay = Vec::new();
+        let mut cards = Vec::new();
+        if !self.kiosk {
+            if let Some(key) = self.hotkey {
+                let list = if self.hotkey_global { &mut global } else { &mut in_app };
+                list.push((key.to_string(), String::from("Quick connect")));
+                let last = QUICK_CONNECT_KEYS.len().min(self.config.as_ref().map_or(0, |c| c.ordered_devices().len()));
+                if last > 0 {
+                    overlay.push((format!("1–{}", last), String::from("Connect or disconnect that favorite")));
+                }
+                overlay.push((String::from("Esc"), String::from("Close")));
+            }
+            if let Ok(config) = &self.config {
+                for profile in &config.audio_profiles {
+                    if let Some(key) = Hotkey::parse(&profile.hotkey) {
+                        in_app.push((key.to_string(), format!("Start audio profile '{}'", profile.name)));
+                    }
+                }
+                for (gesture, action) in config.ui.gestures.bindings() {
+                    if action != CardAction::Nothing {
+                        cards.push((gesture.to_string(), action.label().to_string()));
+                    }
+                }
+            }
+        }
+        [("Anywhere", global), ("In RedTooth", in_app), ("Quick connect overlay", overlay), ("Device cards", cards)]
+            .into_iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect()
+    }
+
+    fn draw_shortcuts(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                for (scope, keys) in self.shortcuts() {
+                    ui.strong(scope);
+                    egui::Grid::new(("shortcuts", scope)).num_columns(2).striped(true).show(ui, |ui| {
+                        for (key, action) in keys {
+                            ui.monospace(key);
+                            ui.label(action);
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(6.0);
+                }
+                ui.small("Change them in Settings → Quick connect and Gestures, or in an audio profile's editor.");
+            });
+        if !open || ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            self.show_shortcuts = false;
+        }
+    }
+
+    /// Everything shown as a toast this session, for errors that expired unread.
+    fn draw_toast_history(&mut self, ctx: &egui::Context) {
+        let mut clear = false;
+        egui::SidePanel::right("toast_history").resizable(true).default_width(280.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Notifications");
+                if ui.small_button("Clear").clicked() {
+                    clear = true;
+                }
+                if ui.small_button("✖").on_hover_text("Close").clicked() {
+                    self.show_toast_history = false;
+                }
+            });
+            ui.separator();
+            if self.toasts.history_len() == 0 {
+                ui.label("Nothing yet");
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for toast in self.toasts.history() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.colored_label(severity_color(toast.severity), toast.severity.icon());
+                        ui.small(toast.at.format("%H:%M:%S").to_string());
+                        ui.label(&toast.message);
+                    });
+                    if let Some(details) = &toast.details {
+                        egui::CollapsingHeader::new("Details").id_source(("toast_details", toast.id)).show(ui, |ui| {
+                            ui.small(details);
+                        });
+                    }
+                    ui.separator();
+                }
+            });
+        });
+        if clear {
+            self.toasts.clear_history();
+        }
+    }
+
+    /// Collapsible feed of this session's events; device entries open Details.
+    fn draw_activity(&mut self, ui: &mut egui::Ui) {
+        let mut open_device = None;
+        let mut clear = false;
+        egui::CollapsingHeader::new(format!("Activity ({})", self.activity.len()))
+            .id_source("activity_feed")
+            .default_open(false)
+            .show(ui, |ui| {
+                if self.activity.is_empty() {
+                    ui.small("Connections, failures and new devices show up here");
+                    return;
+                }
+                if ui.small_button("Clear").clicked() {
+                    clear = true;
+                }
+                egui::ScrollArea::vertical().id_source("activity_scroll").max_height(160.0).show(ui, |ui| {
+                    for entry in self.activity.entries() {
+                        ui.horizontal(|ui| {
+                            ui.small(entry.at.format("%H:%M:%S").to_string());
+                            let text = if entry.failed {
+                                egui::RichText::new(&entry.text).color(egui::Color32::LIGHT_RED)
+                            } else {
+                                egui::RichText::new(&entry.text)
+                            };
+                            match entry.address {
+                                Some(address) => {
+                                    if ui.link(text).on_hover_text("Show device details").clicked() {
+                                        open_device = Some(address);
+                                    }
+                                }
+                                None => {
+                                    ui.label(text);
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+        if clear {
+            self.activity.clear();
+        }
+        if let Some(address) = open_device {
+            self.open_details(address);
+        }
+    }
+
+    fn is_connected(&self, address: u64) -> bool {
+        self.devices.get(address).is_some_and(|d| d.state.is_connected())
+    }
+
+    fn link_state(&self, address: u64) -> ConnectionState {
+        self.devices.get(address).map(|d| d.state.clone()).unwrap_or_default()
+    }
+
+    fn set_mini_mode(&mut self, ctx: &egui::Context, enabled: bool) {
+        println!("CLI: Action -> Mini Mode {}", if enabled { "On" } else { "Off" });
+        self.mini_mode = enabled;
+
+        if enabled {
+            let rows = self.config.as_ref().map(|c| c.devices.len()).unwrap_or(0).max(1);
+            let height = 36.0 + rows as f32 * 26.0;
+            ctx.send_viewport_cmd(egui::ViewportCommand::MinInnerSize(egui::vec2(200.0, 60.0)));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(260.0, height)));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(true));
+            ctx.send_viewport_cmd(egui::ViewportCommand::MinInnerSize(egui::vec2(300.0, 400.0)));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(400.0, 600.0)));
+        }
+    }
+
+    fn draw_mini_mode(&mut self, ctx: &egui::Context) {
+        // Saved devices from config act as the favorites shown in the strip
+        let favorites = match &self.config {
+            Ok(config) => config.ordered_devices(),
+            Err(_) => Vec::new(),
+        };
+        let style = self.status_style();
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Window has no decorations in mini mode, so the header doubles as a drag handle
+                let handle = ui.add(egui::Label::new(egui::RichText::new("RedTooth").strong()).sense(egui::Sense::drag()));
+                if handle.drag_started() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("⛶").on_hover_text("Exit mini mode").clicked() {
+                        self.set_mini_mode(ctx, false);
+                    }
+                });
+            });
+
+            if favorites.is_empty() {
+                ui.small("No saved devices");
+            }
+
+            for (name, address) in favorites {
+                let state = self.link_state(address);
+                let connected = state.is_connected();
+                ui.horizontal(|ui| {
+                    match (style, connected) {
+                        _ if state.in_transition() => ui.label("⏳"),
+                        (StatusStyle::Color, true) => ui.colored_label(egui::Color32::GREEN, "●"),
+                        (StatusStyle::Color, false) => ui.colored_label(egui::Color32::GRAY, "○"),
+                        (StatusStyle::Symbols, true) => ui.strong("✔"),
+                        (StatusStyle::Symbols, false) => ui.label("✖"),
+                    };
+                    ui.label(&name);
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let button = egui::Button::new(if connected { "Disconnect" } else { "Connect" }).small();
+                        if ui.add_enabled(!state.in_transition(), button).clicked() {
+                            let _ = if connected {
+                                self.disconnect_device(address)
+                            } else {
+                                self.connect_device(address)
+                            };
+                        }
+                    });
+                });
+            }
+        });
+    }
+
+    fn save_config(&mut self) {
+        if self.kiosk {
+            warn!("Kiosk mode: config change not saved");
+            return;
+        }
+        if let Ok(config) = &self.config {
+            match self.config_service.replace(config.clone()) {
+                Ok(revision) => self.config_revision = revision,
+                Err(e) => self.toasts.error(format!("Failed to save config: {}", e)),
+            }
+        }
+    }
+
+    /// Picks up config changes made outside the GUI (restores, other subsystems).
+    fn sync_config(&mut self) {
+        let latest = self.config_changes.try_iter().max();
+        if latest.is_some_and(|revision| revision > self.config_revision) && self.config.is_ok() {
+            self.config = Ok(self.config_service.snapshot());
+            self.config_revision = latest.unwrap_or_default();
+        }
+    }
+
+    fn draw_my_devices(&mut self, ui: &mut egui::Ui) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let saved: Vec<String> = config.ordered_devices().into_iter().map(|(n, _)| n).collect();
+        let auto_connect = config.auto_connect.clone();
+
+        let mut changed = false;
+        let mut shared = None;
+        let mut import = false;
+        let mut known = false;
+        let shared_enabled = self.shared_store.is_some();
+        let kiosk = self.kiosk;
+        egui::CollapsingHeader::new(format!("My Devices ({})", saved.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                if kiosk {
+                    for name in &saved {
+                        ui.label(name);
+                    }
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    if ui.small_button("📥 Import preset…").on_hover_text("Add a device from a share code").clicked() {
+                        import = true;
+                    }
+                    if shared_enabled
+                        && ui.small_button("🖧 Known devices").on_hover_text("Your devices and this PC's shared list").clicked()
+                    {
+                        known = true;
+                    }
+                });
+                if saved.is_empty() {
+                    ui.small("Save a device with ☆ to pin it here");
+                    return;
+                }
+
+                let mut toggled = None;
+                let moved = reorderable_list(ui, ui.id().with("saved_devices"), &saved, |ui, i| {
+                    let mut auto = auto_connect.contains(&saved[i]);
+                    if ui.checkbox(&mut auto, "auto").changed() {
+                        toggled = Some((i, auto));
+                    }
+                    if ui.small_button("🔗").on_hover_text("Share as code or QR").clicked() {
+                        shared = Some(i);
+                    }
+                });
+
+                ui.add_space(4.0);
+                ui.label("Auto-connect priority");
+                let moved_auto = reorderable_list(ui, ui.id().with("auto_connect"), &auto_connect, |_, _| {});
+
+                if let Ok(config) = &mut self.config {
+                    if let Some((from, to)) = moved {
+                        config.move_device(from, to);
+                        changed = true;
+                    }
+                    if let Some((from, to)) = moved_auto {
+                        config.move_auto_connect(from, to);
+                        changed = true;
+                    }
+                    if let Some((i, auto)) = toggled {
+                        if auto {
+                            config.add_auto_connect(saved[i].clone());
+                        } else {
+                            config.remove_auto_connect(&saved[i]);
+                        }
+                        changed = true;
+                    }
+                }
+            });
+
+        if changed {
+            self.save_config();
+        }
+        if let (Some(i), Ok(config)) = (shared, &self.config) {
+            self.share_view = DevicePreset::from_config(config, &saved[i]);
+        }
+        if import {
+            self.import_draft = Some(String::new());
+        }
+        if known {
+            self.show_known_devices = !self.show_known_devices;
+        }
+    }
+
+    /// Opens or drops the machine-wide store as the setting changes.
+    fn sync_shared_store(&mut self) {
+        let enabled = self.config.as_ref().is_ok_and(|c| c.registry.shared_store);
+        match (enabled, self.shared_store.is_some()) {
+            (true, false) => {
+                self.shared_store = Some(SharedStore::open().map_err(|e| {
+                    warn!("Shared device store unavailable: {}", e);
+                    e.to_string()
+                }));
+            }
+            (false, true) => {
+                self.shared_store = None;
+                self.show_known_devices = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Merge view of personal saved devices and the machine-wide store.
+    fn draw_known_devices(&mut self, ctx: &egui::Context) {
+        let Some(store) = &self.shared_store else {
+            return;
+        };
+        let (shared, store_error): (Vec<SharedDevice>, Option<String>) = match store {
+            Ok(store) => match store.devices() {
+                Ok(devices) => (devices, None),
+                Err(e) => (Vec::new(), Some(e.to_string())),
+            },
+            Err(e) => (Vec::new(), Some(e.clone())),
+        };
+        let mine = self.config.as_ref().map(|c| c.ordered_devices()).unwrap_or_default();
+
+        // (name, address, personal name, shared entry), personal devices first
+        let mut rows: Vec<(String, u64, Option<String>, Option<&SharedDevice>)> = mine
+            .iter()
+            .map(|(name, address)| {
+                (name.clone(), *address, Some(name.clone()), shared.iter().find(|s| s.address == *address))
+            })
+            .collect();
+        for device in &shared {
+            if !rows.iter().any(|(_, address, _, _)| *address == device.address) {
+                rows.push((device.name.clone(), device.address, None, Some(device)));
+            }
+        }
+
+        let mut open = self.show_known_devices;
+        let mut save = None;
+        let mut publish = None;
+        let mut unpublish = None;
+        egui::Window::new("Known devices").open(&mut open).show(ctx, |ui| {
+            if let Some(e) = &store_error {
+                ui.colored_label(egui::Color32::RED, format!("Shared store unavailable: {}", e));
+            }
+            egui::Grid::new("known_devices").striped(true).show(ui, |ui| {
+                ui.strong("Name");
+                ui.strong("Address");
+                ui.strong("Source");
+                ui.end_row();
+                for (name, address, personal, shared_entry) in &rows {
+                    ui.label(name);
+                    ui.monospace(config::address_key(*address));
+                    match (personal, shared_entry) {
+                        (Some(_), Some(s)) => ui.label("Mine · shared").on_hover_text(format!("Shared by {}", s.added_by)),
+                        (Some(_), None) => ui.label("Mine"),
+                        (None, Some(s)) => ui.label("Shared").on_hover_text(format!("Shared by {}", s.added_by)),
+                        (None, None) => ui.label(""),
+                    };
+                    ui.horizontal(|ui| {
+                        if personal.is_none() && ui.small_button("☆ Save").on_hover_text("Add to My Devices").clicked() {
+                            save = Some((name.clone(), *address));
+                        }
+                        if store_error.is_none() {
+                            if shared_entry.is_none() {
+                                if ui.small_button("Share").on_hover_text("Make it known to every user of this PC").clicked() {
+                                    publish = Some((name.clone(), *address));
+                                }
+                            } else if ui.small_button("Unshare").clicked() {
+                                unpublish = Some(*address);
+                            }
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+            if rows.is_empty() {
+                ui.label("No saved or shared devices yet");
+            }
+        });
+        self.show_known_devices = open;
+
+        if let Some((name, address)) = save {
+            if let Ok(config) = &mut self.config {
+                config.add_device(name, address);
+            }
+            self.save_config();
+        }
+        if let Some(Ok(store)) = &self.shared_store {
+            let result = match (publish, unpublish) {
+                (Some((name, address)), _) => store.publish(address, &name),
+                (None, Some(address)) => store.remove(address),
+                (None, None) => Ok(()),
+            };
+            if let Err(e) = result {
+                self.toasts.push(Severity::Warning, format!("Shared store: {}", e), None);
+            }
+        }
+    }
+
+    fn draw_share(&mut self, ctx: &egui::Context) {
+        let Some(preset) = &self.share_view else {
+            return;
+        };
+        let code = preset.to_code();
+        let modules = preset.qr_modules();
+        let mut open = true;
+
+        egui::Window::new(format!("Share {}", preset.name))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                match &modules {
+                    Ok(modules) => draw_qr(ui, modules),
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                }
+                ui.add(egui::TextEdit::multiline(&mut code.as_str()).font(egui::TextStyle::Monospace).desired_rows(2));
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Copy code").clicked() {
+                        ui.output_mut(|o| o.copied_text = code.clone());
+                    }
+                    ui.small("Import it from My Devices → Import preset");
+                });
+                ui.small("Includes the name, address, tags and auto-connect setting. Notes and history stay here.");
+            });
+
+        if !open {
+            self.share_view = None;
+        }
+    }
+
+    fn draw_import(&mut self, ctx: &egui::Context) {
+        let Some(draft) = &mut self.import_draft else {
+            return;
+        };
+        let mut open = true;
+        let mut import = None;
+
+        egui::Window::new("Import device preset")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(draft)
+                        .hint_text("Paste a share code (RT1.…)")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_rows(2),
+                );
+                if draft.trim().is_empty() {
+                    return;
+                }
+                match DevicePreset::from_code(draft) {
+                    Ok(preset) => {
+                        let address = preset.address().unwrap_or_default();
+                        ui.label(format!("{} ({})", preset.name, config::address_key(address)));
+                        if !preset.tags.is_empty() {
+                            ui.small(preset.tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "));
+                        }
+                        if preset.auto_connect {
+                            ui.small("Auto-connect on");
+                        }
+                        let exists = self.config.as_ref().is_ok_and(|c| c.devices.contains_key(&preset.name));
+                        if exists {
+                            ui.small("Replaces the saved device with the same name");
+                        }
+                        let blocked = bluetooth::is_blocked(address);
+                        if ui
+                            .add_enabled(!blocked, egui::Button::new("Add to My Devices"))
+                            .on_disabled_hover_text("Blocked by your organization's policy")
+                            .clicked()
+                        {
+                            import = Some(preset);
+                        }
+                    }
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                }
+            });
+
+        if let Some(preset) = import {
+            if let Ok(config) = &mut self.config {
+                match preset.apply(config) {
+                    Ok(_) => {
+                        self.save_config();
+                        self.notify(format!("Added {} to My Devices", preset.name));
+                        open = false;
+                    }
+                    Err(e) => self.toasts.error(e.to_string()),
+                }
+            }
+        }
+        if !open {
+            self.import_draft = None;
+        }
+    }
+
+    fn status_style(&self) -> StatusStyle {
+        self.config.as_ref().map(|c| c.ui.status_style).unwrap_or_default()
+    }
+
+    fn card_density(&self) -> CardDensity {
+        self.config.as_ref().map(|c| c.ui.card_density).unwrap_or_default()
+    }
+
+    fn draw_console(&mut self, ctx: &egui::Context) {
+        let Some(console) = &mut self.console else {
+            return;
+        };
+        let mut open = true;
+        let mut toggle_capture = false;
+
+        egui::Window::new("Developer Console")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("console_call")
+                        .selected_text(console.call)
+                        .show_ui(ui, |ui| {
+                            for name in trace::MANUAL_CALLS {
+                                ui.selectable_value(&mut console.call, *name, *name);
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut console.address)
+                            .hint_text("AA:BB:CC:DD:EE:FF")
+                            .desired_width(140.0),
+                    );
+                    if ui.button("Call").clicked() {
+                        console.last_result = match config::parse_address(&console.address).filter(|a| *a != 0) {
+                            Some(address) => trace::invoke(console.call, address),
+                            None if !trace::takes_address(console.call) => trace::invoke(console.call, 0),
+                            None => Some(format!("{} needs a device address, e.g. AA:BB:CC:DD:EE:FF", console.call)),
+                        };
+                    }
+                });
+                if let Some(result) = &console.last_result {
+                    ui.monospace(format!("→ {}", result));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| match &self.hci_capture {
+                    Some(capture) => {
+                        toggle_capture = ui.button("⏹ Stop HCI Capture").clicked();
+                        ui.colored_label(egui::Color32::RED, "●");
+                        ui.label(format!(
+                            "{} packets in {}",
+                            capture.packets(),
+                            format_span(capture.started.elapsed().as_secs() as i64)
+                        ))
+                        .on_hover_text(capture.path.display().to_string());
+                    }
+                    None => {
+                        toggle_capture = ui
+                            .button("⏺ Start HCI Capture")
+                            .on_hover_text(format!(
+                                "Writes raw HCI traffic to a btsnoop file in {} that Wireshark can open. \
+                                 Needs RedTooth to run as administrator.",
+                                hci_snoop::captures_dir().display()
+                            ))
+                            .clicked();
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut console.filter).hint_text("Filter").desired_width(160.0));
+                    let mut paused = console.paused.is_some();
+                    if ui.checkbox(&mut paused, "Pause").changed() {
+                        console.paused = paused.then(trace::entries);
+                    }
+                    if ui.button("Clear").clicked() {
+                        trace::clear();
+                        console.paused = None;
+                    }
+                });
+
+                let entries = console.paused.clone().unwrap_or_else(trace::entries);
+                let filter = console.filter.to_lowercase();
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    egui::Grid::new("ffi_trace").num_columns(4).striped(true).show(ui, |ui| {
+                        for entry in entries.iter().rev() {
+                            let call = format!("{}({})", entry.function, entry.args.join(", "));
+                            if !filter.is_empty() && !call.to_lowercase().contains(&filter) {
+                                continue;
+                            }
+                            ui.monospace(entry.at.format("%H:%M:%S%.3f").to_string());
+                            ui.monospace(call);
+                            ui.monospace(format!("{} µs", entry.duration.as_micros()));
+                            if entry.failed() {
+                                ui.colored_label(egui::Color32::RED, &entry.result);
+                            } else {
+                                ui.monospace(&entry.result);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        if toggle_capture {
+            match self.hci_capture.take() {
+                // The writer reports back via HciCaptureFinished once the file is closed
+                Some(capture) => capture.stop(),
+                None => match HciCapture::start() {
+                    Ok(capture) => self.hci_capture = Some(capture),
+                    Err(e) => self.toasts.error(format!("HCI capture failed to start: {}", e)),
+                },
+            }
+        }
+        if !open {
+            self.console = None;
+        }
+    }
+
+    fn draw_safe_mode(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.safe_mode_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut revert = false;
+
+        egui::Window::new("Safe Mode")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                if dialog.crashes > 0 {
+                    ui.label(format!("RedTooth closed unexpectedly {} times in a row, so it started in safe mode.", dialog.crashes));
+                }
+                ui.label(
+                    "Bluetooth and audio weren't started, plugins weren't loaded and settings are at their defaults. \
+                     config.toml is left as it is.",
+                );
+                ui.separator();
+                if dialog.changes.is_empty() {
+                    ui.weak("No settings changed since RedTooth last closed normally.");
+                } else {
+                    ui.label("Settings changed since RedTooth last closed normally. Tick the ones to undo:");
+                    egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                        for (change, selected) in &mut dialog.changes {
+                            let describe = |v: &Option<String>| v.clone().unwrap_or_else(|| String::from("(not set)"));
+                            ui.checkbox(selected, change.path()).on_hover_text(format!(
+                                "Was {}\nNow {}",
+                                describe(&change.before),
+                                describe(&change.after)
+                            ));
+                        }
+                    });
+                    let any = dialog.changes.iter().any(|(_, selected)| *selected);
+                    revert = ui.add_enabled(any, egui::Button::new("Undo Selected")).clicked();
+                }
+                ui.weak("Close and reopen RedTooth to start normally.");
+            });
+
+        if revert {
+            let selected: Vec<SettingChange> =
+                dialog.changes.iter().filter(|(_, selected)| *selected).map(|(c, _)| c.clone()).collect();
+            println!("CLI: Action -> Undo {} Settings From Safe Mode", selected.len());
+            match crash_guard::revert(&selected) {
+                Ok(()) => {
+                    dialog.changes.retain(|(_, selected)| !*selected);
+                    self.notify(String::from("Settings undone; reopen RedTooth to use them"));
+                }
+                Err(e) => self.toasts.error(format!("Failed to undo settings: {}", e)),
+            }
+        }
+        if !open {
+            self.safe_mode_dialog = None;
+        }
+    }
+
+    /// Gathers diagnostics into an issue draft the user reviews before sending.
+    fn open_problem_report(&mut self) {
+        let checks = selftest::run();
+        let context = ReportContext {
+            adapter: self.adapter.as_ref(),
+            adapters: self.local_adapters.len(),
+            checks: Some(&checks),
+            errors: self
+                .toasts
+                .history()
+                .filter(|t| t.severity == Severity::Error)
+                .map(|t| (t.at, t.message.clone()))
+                .collect(),
+        };
+        let body = problem_report::build(&context);
+        // The most recent error makes a better starting title than nothing
+        let title = context.errors.last().map(|(_, m)| problem_report::sanitize(m)).unwrap_or_default();
+        println!("CLI: Action -> Open Problem Report");
+        self.problem_report = Some(ProblemReport { title, body });
+    }
+
+    fn draw_problem_report(&mut self, ctx: &egui::Context) {
+        let Some(report) = &mut self.problem_report else {
+            return;
+        };
+        let mut open = true;
+        let mut submit = false;
+
+        egui::Window::new("Report Problem")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    "This opens a new GitHub issue with the text below. Addresses and your user and computer \
+                     names have been masked; check it for device names or anything else you'd rather not share.",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Title");
+                    ui.add(egui::TextEdit::singleline(&mut report.title).desired_width(f32::INFINITY));
+                });
+                egui::ScrollArea::vertical().max_height(380.0).show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut report.body)
+                            .code_editor()
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    submit = ui.add_enabled(!report.title.trim().is_empty(), egui::Button::new("Open GitHub")).clicked();
+                    if ui.button("Copy").clicked() {
+                        ui.output_mut(|o| o.copied_text = report.body.clone());
+                    }
+                });
+            });
+
+        if submit {
+            let (url, shortened) = problem_report::issue_url(report.title.trim(), &report.body);
+            if shortened {
+                let body = report.body.clone();
+                ctx.output_mut(|o| o.copied_text = body);
+                self.notify(String::from("The report was too long for the link; paste the full text from the clipboard"));
+            }
+            println!("CLI: Action -> Report Problem on GitHub");
+            ctx.open_url(egui::OpenUrl::new_tab(url));
+            self.problem_report = None;
+        } else if !open {
+            self.problem_report = None;
+        }
+    }
+
+    fn draw_at_console(&mut self, ctx: &egui::Context) {
+        let Some(address) = self.at_console.as_ref().map(|c| c.address) else {
+            return;
+        };
+        let title = format!("AT Console – {}", self.device_label(address));
+        let Some(console) = &mut self.at_console else {
+            return;
+        };
+        let mut open = true;
+
+        egui::Window::new(title)
+            .id(egui::Id::new("at_console"))
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(300.0).stick_to_bottom(true).show(ui, |ui| {
+                    for exchange in &console.transcript {
+                        ui.monospace(format!("{} > {}", exchange.at.format("%H:%M:%S"), exchange.command));
+                        match &exchange.reply {
+                            Ok(reply) if reply.is_empty() => {
+                                ui.weak("(no reply)");
+                            }
+                            Ok(reply) => {
+                                ui.monospace(reply);
+                            }
+                            Err(e) => {
+                                ui.colored_label(egui::Color32::RED, e);
+                            }
+                        }
+                    }
+                    if let Some(command) = &console.pending {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.monospace(format!("> {}", command));
+                        });
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut console.input)
+                            .font(egui::TextStyle::Monospace)
+                            .hint_text("AT+…")
+                            .desired_width(200.0),
+                    );
+                    if input.has_focus() {
+                        if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
+                            console.recall_previous();
+                        } else if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
+                            console.recall_next();
+                        }
+                    }
+                    let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let idle = console.pending.is_none();
+                    if (ui.add_enabled(idle, egui::Button::new("Send")).clicked() || submitted) && idle {
+                        let command = console.input.clone();
+                        console.send(&command);
+                        input.request_focus();
+                    }
+                    ui.menu_button("Canned", |ui| {
+                        for (command, what) in at_console::CANNED {
+                            if ui.add_enabled(idle, egui::Button::new(format!("{}  {}", command, what))).clicked() {
+                                console.send(command);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.add(egui::DragValue::new(&mut console.channel).clamp_range(0..=30).prefix("ch "))
+                        .on_hover_text("RFCOMM channel; 0 looks up the Serial Port service");
+                });
+            });
+
+        if !open {
+            self.at_console = None;
+        }
+    }
+
+    /// Carries out what a script asked for and files its output.
+    #[cfg(feature = "scripting")]
+    fn handle_script_event(&mut self, event: ScriptEvent) {
+        self.scripts.record(&event);
+        let name = |app: &Self, run| app.scripts.name_of(run).to_string();
+        match event {
+            ScriptEvent::StartProfile { run, name: profile } => {
+                let found = self
+                    .config
+                    .as_ref()
+                    .ok()
+                    .and_then(|c| c.audio_profiles.iter().find(|p| p.name == profile).cloned());
+                match found {
+                    Some(found) => {
+                        let why = format!("for script '{}'", name(self, run));
+                        self.activate_profile(found, &why);
+                    }
+                    None => self.toasts.error(format!("Script '{}': no audio profile '{}'", name(self, run), profile)),
+                }
+            }
+            ScriptEvent::StopAudio { run } => {
+                info!("Script '{}' stopped the broadcast", name(self, run));
+                self.stop_broadcast();
+            }
+            ScriptEvent::Notify { run, message } => self.notify(format!("{}: {}", name(self, run), message)),
+            ScriptEvent::Finished { run, result: Err(msg) } if msg != "Stopped" => {
+                self.toasts.push(Severity::Error, format!("Script '{}' failed", name(self, run)), Some(msg));
+            }
+            ScriptEvent::Output { .. } | ScriptEvent::Finished { .. } => {}
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    fn draw_script_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.script_editor else {
+            return;
+        };
+        let startup_locked = self.config_service.policy().is_locked("scripts.startup");
+        let mut open = true;
+        let mut startup_changed = false;
+        let mut error = None;
+
+        egui::Window::new("Scripts").open(&mut open).default_width(640.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let names = scripting::list();
+                egui::ComboBox::from_id_source("script_name")
+                    .selected_text(if editor.name.is_empty() { "Open…" } else { editor.name.as_str() })
+                    .show_ui(ui, |ui| {
+                        for name in names {
+                            if ui.selectable_label(editor.name == name, &name).clicked() && editor.name != name {
+                                match scripting::load(&name) {
+                                    Ok(source) => {
+                                        *editor = ScriptEditor { name, source, ..Default::default() };
+                                    }
+                                    Err(e) => error = Some(e.to_string()),
+                                }
+                            }
+                        }
+                    });
+                ui.add(egui::TextEdit::singleline(&mut editor.new_name).hint_text("new script").desired_width(120.0));
+                if ui.add_enabled(!editor.new_name.trim().is_empty(), egui::Button::new("New")).clicked() {
+                    let name = editor.new_name.trim().to_string();
+                    *editor = ScriptEditor {
+                        name,
+                        source: String::from("// print(devices());\n"),
+                        dirty: true,
+                        ..Default::default()
+                    };
+                }
+            });
+            if editor.name.is_empty() {
+                ui.small(format!("Scripts are .rhai files in {}", scripting::scripts_dir().display()));
+            } else {
+                let input = ui.add(
+                    egui::TextEdit::multiline(&mut editor.source)
+                        .code_editor()
+                        .desired_rows(16)
+                        .desired_width(f32::INFINITY),
+                );
+                if input.changed() {
+                    editor.dirty = true;
+                    editor.check = None;
+                }
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(editor.dirty, egui::Button::new("Save")).clicked() {
+                        match scripting::save(&editor.name, &editor.source) {
+                            Ok(()) => editor.dirty = false,
+                            Err(e) => error = Some(format!("Could not save '{}': {}", editor.name, e)),
+                        }
+                    }
+                    if ui.button("Check").clicked() {
+                        editor.check = Some(scripting::check(&editor.source));
+                    }
+                    if ui.button("▶ Run").clicked() {
+                        self.scripts.start(&editor.name, editor.source.clone());
+                    }
+                    if let Ok(config) = &mut self.config {
+                        let mut startup = config.scripts.startup.contains(&editor.name);
+                        if ui
+                            .add_enabled(!startup_locked, egui::Checkbox::new(&mut startup, "Run at startup"))
+                            .on_hover_text("Runs the saved file each time RedTooth starts")
+                            .changed()
+                        {
+                            config.scripts.startup.retain(|n| *n != editor.name);
+                            if startup {
+                                config.scripts.startup.push(editor.name.clone());
+                            }
+                            startup_changed = true;
+                        }
+                    }
+                });
+                match &editor.check {
+                    Some(Ok(())) => {
+                        ui.colored_label(egui::Color32::GREEN, "No syntax errors");
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    None => {}
+                }
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.strong("Runs");
+                if ui.small_button("Clear finished").clicked() {
+                    self.scripts.clear_finished();
+                }
+            });
+            let mut stop = None;
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for run in self.scripts.runs().iter().rev() {
+                    ui.horizontal(|ui| {
+                        match &run.result {
+                            None => {
+                                ui.spinner();
+                                if ui.small_button("⏹ Stop").clicked() {
+                                    stop = Some(run.id);
+                                }
+                            }
+                            Some(Ok(())) => {
+                                ui.label("✔");
+                            }
+                            Some(Err(e)) => {
+                                ui.colored_label(egui::Color32::RED, "✖").on_hover_text(e);
+                            }
+                        }
+                        ui.label(&run.name);
+                    });
+                    for line in &run.output {
+                        ui.monospace(line);
+                    }
+                }
+            });
+            if let Some(id) = stop {
+                self.scripts.stop(id);
+            }
+        });
+
+        if startup_changed {
+            self.save_config();
+        }
+        if let Some(msg) = error {
+            self.toasts.error(msg);
+        }
+        if !open {
+            self.script_editor = None;
+        }
+    }
+
+    fn draw_diagnostics(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_diagnostics;
+
+        egui::Window::new("Audio Diagnostics")
+            .open(&mut open)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                let Some(session) = &self.audio_session else {
+                    ui.label("Start an audio profile to collect statistics");
+                    return;
+                };
+                if let Some(reason) = &self.diagnostics.unsupported {
+                    ui.label(reason.as_str());
+                    return;
+                }
+                ui.small(format!(
+                    "Buffer fill over the last {} s; counts are for the same window",
+                    diagnostics::HISTORY_LEN
+                ));
+
+                for address in &session.sinks {
+                    ui.separator();
+                    ui.strong(self.device_label(*address));
+                    let Some(latest) = self.diagnostics.latest(*address) else {
+                        ui.small("No data yet");
+                        continue;
+                    };
+                    let underruns = self.diagnostics.recent_delta(*address, |s| s.underruns);
+                    let resyncs = self.diagnostics.recent_delta(*address, |s| s.resyncs);
+
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Fill {}%", latest.buffer_fill_percent));
+                        let color = if underruns > 0 { egui::Color32::RED } else { ui.visuals().text_color() };
+                        ui.colored_label(color, format!("Underruns {} (total {})", underruns, latest.underruns));
+                        ui.label(format!("Resyncs {} (total {})", resyncs, latest.resyncs));
+                    });
+                    if let Some(samples) = self.diagnostics.history.get(address) {
+                        let fill: Vec<f32> = samples.iter().map(|s| s.buffer_fill_percent as f32).collect();
+                        draw_sparkline(ui, &fill, 100.0);
+                    }
+                }
+            });
+
+        self.show_diagnostics = open;
+    }
+
+    /// Elapsed time, devices found and discovery rate for the running scan.
+    fn draw_scan_progress(&self, ui: &mut egui::Ui, elapsed: Duration) {
+        let secs = elapsed.as_secs();
+        let rate = self.scan_progress.rate();
+        ui.horizontal(|ui| {
+            ui.add(egui::Spinner::new().size(12.0));
+            ui.small(format!("Scanning {}:{:02}", secs / 60, secs % 60));
+            ui.small(format!("· {} found", self.scan_progress.unique_count()));
+            // Nothing for a whole rate window is worth a warning color well before the watchdog acts
+            let (color, hint) = if rate > 0.0 {
+                (egui::Color32::GREEN, "Discovery reports are arriving")
+            } else if secs < 10 {
+                (egui::Color32::GRAY, "Waiting for the first reports")
+            } else {
+                (egui::Color32::from_rgb(255, 165, 0), "No reports for 10 s; the scan may have stalled")
+            };
+            ui.colored_label(color, egui::RichText::new(format!("· {:.1}/s", rate)).small())
+                .on_hover_text(hint);
+        });
+    }
+
+    /// Debug counters in the top-right corner, toggled with `ui.perf_overlay`.
+    fn draw_perf_overlay(&mut self, ctx: &egui::Context) {
+        let devices = self.devices.snapshot().len();
+        let hit_rate = match self.perf.cache_hit_rate() {
+            Some(rate) => format!("{:.0}%", rate * 100.0),
+            None => String::from("–"),
+        };
+        let lines = [
+            format!(
+                "frame  {:.1} ms avg, {:.1} ms worst",
+                self.perf.average_frame().as_secs_f32() * 1000.0,
+                self.perf.worst_frame().as_secs_f32() * 1000.0
+            ),
+            format!("devices  {}", devices),
+            format!("events  {}/frame (peak {})", self.perf.events_last_frame, self.perf.events_peak),
+            format!("registry cache  {} hit", hit_rate),
+        ];
+        egui::Area::new("perf_overlay")
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 40.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for line in lines {
+                        ui.label(egui::RichText::new(line).monospace().small());
+                    }
+                });
+            });
+    }
+
+    /// Health of each subsystem for the status bar.
+    fn status(&self) -> Status {
+        let mut status = Status::default();
+
+        status.push(if self.event_receiver.is_none() {
+            Subsystem::new("Bluetooth", Level::Down, "Failed to initialize")
+        } else if !self.permission_granted {
+            Subsystem::new("Bluetooth", Level::Down, "Permission denied")
+        } else if self.scan_watchdog.stuck() {
+            Subsystem::new("Bluetooth", Level::Degraded, "Scanning appears stuck")
+        } else if self.scanning {
+            Subsystem::new("Bluetooth", Level::Ok, "Scanning")
+        } else {
+            Subsystem::new("Bluetooth", Level::Ok, "Idle")
+        });
+
+        status.push(match (&self.audio_error, &self.audio_session) {
+            (Some(e), _) => Subsystem::new("Audio", Level::Down, e.clone()),
+            (None, Some(session)) => {
+                let mode = if session.low_latency { ", low latency" } else { "" };
+                Subsystem::new("Audio", Level::Ok, format!("Streaming to {} device(s){}", session.sinks.len(), mode))
+            }
+            (None, None) => Subsystem::new("Audio", Level::Ok, "Ready"),
+        });
+
+        status.push(match &self.registry {
+            Ok(_) if self.incognito => Subsystem::new("Registry", Level::Ok, "Paused (incognito)"),
+            Ok(_) => Subsystem::new("Registry", Level::Ok, "Recording history"),
+            Err(e) if e.is_database_locked() => {
+                Subsystem::new("Registry", Level::Degraded, "Locked by another program; retrying")
+            }
+            Err(e) => Subsystem::new("Registry", Level::Degraded, format!("History not saved: {}", e)),
+        });
+
+        status.push(match &self.config {
+            Ok(_) if self.kiosk => Subsystem::new("Config", Level::Ok, "Read-only (kiosk)"),
+            Ok(_) => Subsystem::new("Config", Level::Ok, "Loaded"),
+            Err(e) => Subsystem::new("Config", Level::Degraded, format!("Using defaults: {}", e)),
+        });
+
+        status
+    }
+
+    /// Traffic light with a per-subsystem breakdown on hover; clicking opens the self-test.
+    fn draw_status_bar(&mut self, ctx: &egui::Context) {
+        let status = self.status();
+        let color = level_color(status.overall());
+        let mut open_selftest = false;
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            let response = ui
+                .horizontal(|ui| {
+                    ui.colored_label(color, "●");
+                    ui.small(status.summary());
+                })
+                .response
+                .interact(egui::Sense::click())
+                .on_hover_ui(|ui| {
+                    egui::Grid::new("status_breakdown").num_columns(3).show(ui, |ui| {
+                        for subsystem in &status.subsystems {
+                            ui.colored_label(level_color(subsystem.level), "●");
+                            ui.strong(subsystem.name);
+                            ui.label(&subsystem.detail);
+                            ui.end_row();
+                        }
+                    });
+                    ui.small("Click for diagnostics");
+                });
+            if response.clicked() {
+                open_selftest = true;
+            }
+        });
+
+        if open_selftest {
+            self.run_selftest();
+        }
+    }
+
+    /// Runs the checks and re-reads the adapter, which may have been swapped since startup.
+    fn run_selftest(&mut self) {
+        self.selftest = Some(selftest::run());
+        let record = !self.incognito;
+        (self.adapter, self.adapter_history) = read_adapter(self.registry.as_ref().ok(), record);
+        self.local_adapters = present_adapters();
+    }
+
+    fn draw_selftest(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut rerun = false;
+
+        egui::Window::new("Self-test")
+            .open(&mut open)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                let Some(checks) = &self.selftest else {
+                    return;
+                };
+                for check in checks {
+                    ui.horizontal(|ui| {
+                        if check.passed {
+                            ui.colored_label(egui::Color32::GREEN, "✔");
+                        } else {
+                            ui.colored_label(egui::Color32::RED, "✖");
+                        }
+                        ui.strong(check.name);
+                        ui.small(&check.detail);
+                    });
+                    if !check.passed {
+                        ui.small(format!("→ {}", check.hint));
+                    }
+                }
+
+                ui.separator();
+                ui.strong("Adapter");
+                match &self.adapter {
+                    Some(adapter) => {
+                        egui::Grid::new("adapter_info").num_columns(2).show(ui, |ui| {
+                            ui.label("Name");
+                            ui.label(&adapter.name);
+                            ui.end_row();
+                            ui.label("Address");
+                            ui.monospace(format!("{:X}", adapter.address));
+                            ui.end_row();
+                            ui.label("Manufacturer");
+                            ui.label(adapter.manufacturer_label());
+                            ui.end_row();
+                            ui.label("Bluetooth");
+                            ui.label(format!("{} (HCI {}, LMP {})", adapter.core_version(), adapter.hci_version, adapter.lmp_version));
+                            ui.end_row();
+                            ui.label("Firmware");
+                            ui.label(adapter.firmware());
+                            ui.end_row();
+                        });
+                    }
+                    None => {
+                        ui.small("No adapter information available");
+                    }
+                }
+                let previous: Vec<_> = self
+                    .adapter_history
+                    .iter()
+                    .filter(|(a, _, _)| self.adapter.as_ref().map(|current| current.address) != Some(a.address))
+                    .collect();
+                if !previous.is_empty() {
+                    ui.small("Previously used on this PC:");
+                    for (adapter, first, last) in previous {
+                        ui.small(format!(
+                            "{} · {:X} · {} to {}",
+                            adapter.name,
+                            adapter.address,
+                            first.get(..10).unwrap_or(first),
+                            last.get(..10).unwrap_or(last)
+                        ));
+                    }
+                }
+
+                ui.separator();
+                if ui.button("Run again").clicked() {
+                    rerun = true;
+                }
+            });
+
+        if rerun {
+            self.run_selftest();
+        } else if !open {
+            self.selftest = None;
+        }
+    }
+
+    fn draw_settings(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let mut changed = false;
+        let mut restore = None;
+        let mut restore_zip = None;
+        let mut back_up_now = false;
+        let mut generate_report = false;
+        let mut compact_registry = false;
+        let policy = self.config_service.policy().clone();
+        let unlocked = |key: &str| !policy.is_locked(key);
+        let lock_phone = self
+            .config
+            .as_ref()
+            .ok()
+            .and_then(|c| config::parse_address(&c.presence_lock.device))
+            .map(|a| self.device_label(a));
+        let signal_supported = *self.signal_supported.get_or_insert_with(bluetooth::signal_supported);
+        let hfp_supported = *self.hfp_supported.get_or_insert_with(bluetooth::hfp_supported);
+        let crossfade_supported = *self.crossfade_supported.get_or_insert_with(audio::crossfade_supported);
+        let recording_supported = *self.recording_supported.get_or_insert_with(recorder::supported);
+        let engine_supported = *self.engine_settings_supported.get_or_insert_with(audio::engine_settings_supported);
+
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Ok(config) = &mut self.config else {
+                    ui.colored_label(egui::Color32::RED, "Configuration failed to load");
+                    return;
+                };
+                if policy.is_active() {
+                    ui.label("🔒 Some settings are managed by your organization");
+                    ui.separator();
+                }
+
+                ui.heading("Accessibility");
+                ui.label("Status indicators");
+                ui.add_enabled_ui(unlocked("ui.status_style"), |ui| {
+                    changed |= ui
+                        .radio_value(&mut config.ui.status_style, StatusStyle::Color, "Colour")
+                        .changed();
+                    changed |= ui
+                        .radio_value(&mut config.ui.status_style, StatusStyle::Symbols, "High contrast symbols (✔/✖)")
+                        .changed();
+                });
+                ui.label("Device list");
+                ui.add_enabled_ui(unlocked("ui.card_density"), |ui| {
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .radio_value(&mut config.ui.card_density, CardDensity::Compact, "Compact")
+                            .on_hover_text("One line per device, for small windows")
+                            .changed();
+                        changed |= ui
+                            .radio_value(&mut config.ui.card_density, CardDensity::Comfortable, "Comfortable")
+                            .changed();
+                        changed |= ui
+                            .radio_value(&mut config.ui.card_density, CardDensity::Detailed, "Detailed")
+                            .on_hover_text("Adds device class, pairing and live audio statistics")
+                            .changed();
+                    });
+                });
+                changed |= ui
+                    .add_enabled(
+                        unlocked("ui.strip_name_emoji"),
+                        egui::Checkbox::new(&mut config.ui.strip_name_emoji, "Hide emoji in device names"),
+                    )
+                    .on_hover_text("Device Details still shows the name as the device advertises it")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("ui.isolate_rtl_names"),
+                        egui::Checkbox::new(&mut config.ui.isolate_rtl_names, "Isolate right-to-left names"),
+                    )
+                    .on_hover_text("Keeps Arabic or Hebrew names from reordering the status next to them")
+                    .changed();
+
+                ui.separator();
+                ui.heading("Quick connect");
+                ui.horizontal(|ui| {
+                    ui.label("Hotkey");
+                    let draft = self.hotkey_draft.get_or_insert_with(|| config.ui.quick_connect_hotkey.clone());
+                    let response = ui.add_enabled(
+                        unlocked("ui.quick_connect_hotkey"),
+                        egui::TextEdit::singleline(draft).hint_text("Ctrl+Alt+B").desired_width(120.0),
+                    );
+                    let valid = draft.is_empty() || Hotkey::parse(draft).is_some();
+                    if response.lost_focus() && valid && *draft != config.ui.quick_connect_hotkey {
+                        config.ui.quick_connect_hotkey = draft.trim().to_string();
+                        changed = true;
+                    }
+                    if !response.has_focus() && valid {
+                        self.hotkey_draft = None;
+                    }
+                    if !valid {
+                        ui.colored_label(egui::Color32::RED, "Use Ctrl and/or Alt plus a key");
+                    }
+                });
+                ui.small(if self.hotkey_global {
+                    "Works from any application. Press 1–9 to toggle a saved device."
+                } else {
+                    "Only works while RedTooth has focus. Press 1–9 to toggle a saved device."
+                });
+
+                ui.separator();
+                ui.heading("Gestures");
+                ui.small("On the empty parts of a device card");
+                ui.add_enabled_ui(unlocked("ui.gestures"), |ui| {
+                    egui::Grid::new("gesture_bindings").num_columns(2).show(ui, |ui| {
+                        for (name, binding) in config.ui.gestures.bindings_mut() {
+                            ui.label(name);
+                            egui::ComboBox::from_id_source(name).selected_text(binding.label()).show_ui(ui, |ui| {
+                                for action in CardAction::ALL {
+                                    changed |= ui.selectable_value(binding, action, action.label()).changed();
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.heading("Power");
+                if ui
+                    .add_enabled(
+                        unlocked("scan.full_power_on_battery"),
+                        egui::Checkbox::new(&mut config.scan.full_power_on_battery, "Scan at full power even on battery"),
+                    )
+                    .changed()
+                {
+                    changed = true;
+                    // Re-evaluate on the next frame
+                    self.power_checked = None;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Restart a scan that finds nothing for");
+                    changed |= ui
+                        .add_enabled(
+                            unlocked("scan.watchdog_secs"),
+                            egui::DragValue::new(&mut config.scan.watchdog_secs).clamp_range(0..=3600).suffix(" s"),
+                        )
+                        .on_hover_text("0 turns the watchdog off")
+                        .changed();
+                });
+                egui::CollapsingHeader::new("Advanced radio tuning").default_open(false).show(ui, |ui| {
+                    ui.small("Changes apply from the next scan cycle. Low-power mode still shortens inquiries on battery.");
+                    let tuning_before = config.scan.tuning;
+                    let tuning = &mut config.scan.tuning;
+                    ui.add_enabled_ui(unlocked("scan.tuning"), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Inquiry length");
+                            ui.add(
+                                egui::DragValue::new(&mut tuning.inquiry_length)
+                                    .clamp_range(ScanTuning::INQUIRY_LENGTH)
+                                    .custom_formatter(|n, _| format!("{:.1} s", n * 1.28))
+                                    .custom_parser(|text| {
+                                        text.trim_end_matches('s').trim().parse::<f64>().ok().map(|secs| (secs / 1.28).round())
+                                    }),
+                            )
+                            .on_hover_text("Longer inquiries find devices that answer slowly but keep the radio busy");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Pause between scans");
+                            ui.add(
+                                egui::DragValue::new(&mut tuning.cycle_gap_ms)
+                                    .clamp_range(ScanTuning::CYCLE_GAP_MS)
+                                    .speed(50.0)
+                                    .suffix(" ms"),
+                            )
+                            .on_hover_text("Longer pauses leave more airtime to headsets and controllers");
+                        });
+                        if ui.add_enabled(*tuning != ScanTuning::default(), egui::Button::new("Reset to defaults")).clicked() {
+                            *tuning = ScanTuning::default();
+                        }
+                    });
+                    changed |= config.scan.tuning != tuning_before;
+                });
+
+                ui.separator();
+                ui.heading("Audio");
+                // Only offered where the core reports call state; an old setting can still be turned off
+                if hfp_supported || config.audio.pause_during_calls {
+                    changed |= ui
+                        .add_enabled(
+                            unlocked("audio.pause_during_calls"),
+                            egui::Checkbox::new(&mut config.audio.pause_during_calls, "Pause broadcast while a headset is in a call"),
+                        )
+                        .changed();
+                }
+                changed |= ui
+                    .add_enabled(
+                        unlocked("audio.keep_awake"),
+                        egui::Checkbox::new(&mut config.audio.keep_awake, "Keep the PC awake while broadcasting"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("audio.exclusive_output"),
+                        egui::Checkbox::new(&mut config.audio.exclusive_output, "Conference room mode: one audio device at a time"),
+                    )
+                    .on_hover_text(
+                        "Connecting a headset or speaker disconnects the one before it and moves the audio over. \
+                         Profiles that broadcast to several speakers play on the newest one only.",
+                    )
+                    .changed();
+                // Only offered where the core can fade; elsewhere moves always cut over
+                if crossfade_supported {
+                    ui.horizontal(|ui| {
+                        ui.label("Cross-fade when moving the stream");
+                        changed |= ui
+                            .add_enabled(
+                                unlocked("audio.crossfade_ms"),
+                                egui::DragValue::new(&mut config.audio.crossfade_ms).clamp_range(0..=5000).suffix(" ms"),
+                            )
+                            .on_hover_text("0 switches over at once")
+                            .changed();
+                    });
+                }
+                ui.add_enabled_ui(unlocked("audio.low_battery"), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("When a sink drops below");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut config.audio.low_battery_percent).clamp_range(1..=50).suffix(" %"))
+                            .changed();
+                        egui::ComboBox::from_id_source("low_battery")
+                            .selected_text(config.audio.low_battery.label())
+                            .show_ui(ui, |ui| {
+                                for action in LowBatteryAction::ALL {
+                                    changed |= ui.selectable_value(&mut config.audio.low_battery, action, action.label()).changed();
+                                }
+                            });
+                    });
+                    ui.small("Only headsets that report their battery to Windows are checked, once a minute");
+                });
+                if recording_supported {
+                    ui.horizontal(|ui| {
+                        ui.label("Stop recordings after");
+                        changed |= ui
+                            .add_enabled(
+                                unlocked("audio.recording_max_minutes"),
+                                egui::DragValue::new(&mut config.audio.recording_max_minutes).clamp_range(0..=600).suffix(" min"),
+                            )
+                            .changed();
+                    });
+                    ui.small("0 records until stopped; recording always stops when disk space runs low");
+                }
+
+                ui.label("Engine");
+                let engine_before = config.audio.engine.clone();
+                let engine = &mut config.audio.engine;
+                ui.add_enabled_ui(engine_supported && unlocked("audio.engine"), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Buffer");
+                        ui.add(egui::DragValue::new(&mut engine.buffer_ms).clamp_range(10..=1000).suffix(" ms"));
+                    });
+                    egui::ComboBox::from_label("Resampler quality")
+                        .selected_text(format!("{:?}", engine.resampler_quality))
+                        .show_ui(ui, |ui| {
+                            for quality in [ResamplerQuality::Low, ResamplerQuality::Medium, ResamplerQuality::High] {
+                                ui.selectable_value(&mut engine.resampler_quality, quality, format!("{:?}", quality));
+                            }
+                        });
+                    egui::ComboBox::from_label("Sample rate")
+                        .selected_text(format!("{} Hz", engine.sample_rate))
+                        .show_ui(ui, |ui| {
+                            for rate in audio::SAMPLE_RATES {
+                                ui.selectable_value(&mut engine.sample_rate, rate, format!("{} Hz", rate));
+                            }
+                        });
+                });
+                changed |= config.audio.engine != engine_before;
+                if !engine_supported {
+                    ui.small("The audio engine doesn't use these settings yet");
+                } else if self.audio_session.is_some() {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        "⚠ Streaming: engine changes apply when the broadcast is restarted",
+                    );
+                }
+
+                ui.separator();
+                ui.heading("Profile schedule");
+                ui.small("Starts an audio profile at a set time, connecting its devices first");
+                let profiles: Vec<String> = config.audio_profiles.iter().map(|p| p.name.clone()).collect();
+                let mut remove_entry = None;
+                ui.add_enabled_ui(unlocked("profile_schedules"), |ui| {
+                    for (i, entry) in config.profile_schedules.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            changed |= ui.checkbox(&mut entry.enabled, "").changed();
+                            let selected = if entry.profile.is_empty() { "Choose profile" } else { entry.profile.as_str() };
+                            egui::ComboBox::from_id_source(("schedule_profile", i))
+                                .selected_text(selected.to_string())
+                                .show_ui(ui, |ui| {
+                                    for name in &profiles {
+                                        changed |= ui.selectable_value(&mut entry.profile, name.clone(), name).changed();
+                                    }
+                                });
+                            ui.label("at");
+                            changed |= ui.add(egui::TextEdit::singleline(&mut entry.at).desired_width(50.0)).lost_focus();
+                            for (day, label) in schedule::WEEKDAYS.iter().zip(["M", "T", "W", "T", "F", "S", "S"]) {
+                                let mut on = entry.days.iter().any(|d| d.eq_ignore_ascii_case(day));
+                                if ui.toggle_value(&mut on, label).changed() {
+                                    entry.days.retain(|d| !d.eq_ignore_ascii_case(day));
+                                    if on {
+                                        entry.days.push(day.to_string());
+                                    }
+                                    entry.days.sort_by_key(|d| schedule::WEEKDAYS.iter().position(|w| w.eq_ignore_ascii_case(d)));
+                                    changed = true;
+                                }
+                            }
+                            if entry.days.is_empty() {
+                                ui.small("every day");
+                            }
+                            if ui.small_button("🗑").clicked() {
+                                remove_entry = Some(i);
+                            }
+                        });
+                        if !entry.is_valid() {
+                            ui.colored_label(egui::Color32::RED, "Time must be HH:MM (24h)");
+                        }
+                    }
+                    if ui.add_enabled(!profiles.is_empty(), egui::Button::new("Add schedule")).clicked() {
+                        let profile = profiles[0].clone();
+                        config.profile_schedules.push(ProfileSchedule { profile, ..Default::default() });
+                        changed = true;
+                    }
+                });
+                if let Some(i) = remove_entry {
+                    config.profile_schedules.remove(i);
+                    changed = true;
+                }
+
+                ui.separator();
+                ui.heading("Notifications");
+                changed |= ui
+                    .add_enabled(
+                        unlocked("notifications.show_banners"),
+                        egui::Checkbox::new(&mut config.notifications.show_banners, "Show notification banners"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("notifications.new_devices"),
+                        egui::Checkbox::new(&mut config.notifications.new_devices, "Notify when an unknown device appears"),
+                    )
+                    .changed();
+                ui.label("Do Not Disturb");
+                ui.small("Suppresses auto-connect and notifications during these hours");
+                let dnd_unlocked = unlocked("notifications.dnd");
+                changed |= ui
+                    .add_enabled(dnd_unlocked, egui::Checkbox::new(&mut config.notifications.dnd.enabled, "Enable schedule"))
+                    .changed();
+                ui.add_enabled_ui(dnd_unlocked && config.notifications.dnd.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("From");
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut config.notifications.dnd.start).desired_width(50.0))
+                            .lost_focus();
+                        ui.label("to");
+                        changed |= ui
+                            .add(egui::TextEdit::singleline(&mut config.notifications.dnd.end).desired_width(50.0))
+                            .lost_focus();
+                    });
+                    changed |= ui.checkbox(&mut config.notifications.dnd.weekends, "All day on weekends").changed();
+                    if !config.notifications.dnd.is_valid() {
+                        ui.colored_label(egui::Color32::RED, "Times must be HH:MM (24h)");
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Watch area");
+                ui.small("Alerts when a device that isn't saved or allowed stays nearby");
+                let watch_unlocked = unlocked("watch");
+                if !signal_supported {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "Unavailable: this adapter doesn't report signal strength (it needs Bluetooth LE)",
+                    );
+                }
+                changed |= ui
+                    .add_enabled(
+                        watch_unlocked && (signal_supported || config.watch.enabled),
+                        egui::Checkbox::new(&mut config.watch.enabled, "Watch for unknown devices"),
+                    )
+                    .on_hover_text("Judged by the signal of LE advertisements; devices that only answer classic inquiries aren't seen")
+                    .changed();
+                ui.add_enabled_ui(watch_unlocked && config.watch.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Closer than");
+                        changed |= ui
+                            .add(egui::Slider::new(&mut config.watch.min_rssi, -100..=-30).suffix(" dBm"))
+                            .changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("For at least");
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut config.watch.dwell_secs).clamp_range(1..=3600).suffix(" s"))
+                            .changed();
+                    });
+                    let mut remove = None;
+                    for (i, key) in config.watch.allowlist.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("✔ {}", key));
+                            if ui.small_button("✖").on_hover_text("Remove from allowlist").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = remove {
+                        config.watch.allowlist.remove(i);
+                        changed = true;
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Presence lock");
+                ui.small("Locks this PC when your phone leaves");
+                let lock_unlocked = unlocked("presence_lock");
+                if config.presence_lock.enabled {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("On for {}", lock_phone.as_deref().unwrap_or(&config.presence_lock.device)));
+                        if ui.add_enabled(lock_unlocked, egui::Button::new("Turn Off")).clicked() {
+                            config.presence_lock.enabled = false;
+                            changed = true;
+                        }
+                    });
+                    ui.add_enabled_ui(lock_unlocked, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Away when weaker than");
+                            changed |= ui
+                                .add(egui::Slider::new(&mut config.presence_lock.min_rssi, -100..=-40).suffix(" dBm"))
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Lock after");
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut config.presence_lock.away_secs)
+                                        .clamp_range(10..=600)
+                                        .suffix(" s"),
+                                )
+                                .changed();
+                        });
+                        changed |= ui
+                            .checkbox(&mut config.presence_lock.wake_on_return, "Wake the display when the phone returns")
+                            .on_hover_text("Shows the sign-in screen; you still have to sign in")
+                            .changed();
+                    });
+                } else if ui.add_enabled(lock_unlocked && !self.kiosk, egui::Button::new("Set Up…")).clicked() {
+                    self.presence_setup = Some(PresenceSetup::default());
+                }
+
+                ui.separator();
+                ui.heading("Confirmations");
+                changed |= ui
+                    .add_enabled(
+                        unlocked("confirm.disconnect_all"),
+                        egui::Checkbox::new(&mut config.confirm.disconnect_all, "Ask before Disconnect All"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("confirm.disconnect_input"),
+                        egui::Checkbox::new(&mut config.confirm.disconnect_input, "Ask before disconnecting a keyboard or mouse"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("confirm.unpair"),
+                        egui::Checkbox::new(&mut config.confirm.unpair, "Ask before unpairing"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("confirm.keep_input_devices"),
+                        egui::Checkbox::new(&mut config.confirm.keep_input_devices, "Keep keyboards and mice connected on Disconnect All"),
+                    )
+                    .on_hover_text("Can still be overridden in the Disconnect All dialog")
+                    .changed();
+
+                ui.separator();
+                ui.heading("Data");
+                ui.horizontal(|ui| {
+                    ui.label("Keep device history for");
+                    changed |= ui
+                        .add_enabled(
+                            unlocked("registry.history_retention_days"),
+                            egui::DragValue::new(&mut config.registry.history_retention_days)
+                                .clamp_range(0..=3650)
+                                .suffix(" days"),
+                        )
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Wait for a locked database up to");
+                    changed |= ui
+                        .add_enabled(
+                            unlocked("registry.busy_timeout_ms"),
+                            egui::DragValue::new(&mut config.registry.busy_timeout_ms)
+                                .clamp_range(0..=30_000)
+                                .speed(100)
+                                .suffix(" ms"),
+                        )
+                        .on_hover_text("Applies the next time RedTooth opens the registry")
+                        .changed();
+                });
+                ui.small("0 keeps history forever; older entries are pruned at startup");
+                changed |= ui
+                    .add_enabled(
+                        unlocked("registry.monthly_report"),
+                        egui::Checkbox::new(&mut config.registry.monthly_report, "Write a monthly usage report"),
+                    )
+                    .on_hover_text("Connections, audio hours and failures from the local registry. Nothing is uploaded.")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("registry.health_alerts"),
+                        egui::Checkbox::new(&mut config.registry.health_alerts, "Suggest fixes for failing devices"),
+                    )
+                    .on_hover_text("Checked weekly against the last two weeks of connection and signal history")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("registry.shared_store"),
+                        egui::Checkbox::new(&mut config.registry.shared_store, "Use this PC's shared device list"),
+                    )
+                    .on_hover_text("Devices published for every user of this computer. Your history and notes stay private.")
+                    .changed();
+                if ui.button("Generate usage report").clicked() {
+                    generate_report = true;
+                }
+                ui.menu_button("Restore config…", |ui| {
+                    let backups = Config::list_backups();
+                    if backups.is_empty() {
+                        ui.label("No backups yet");
+                    }
+                    for path in backups {
+                        let label = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                        if ui.button(label).clicked() {
+                            restore = Some(path);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                if ui.button("Export device history (CSV)").clicked() {
+                    match &self.registry {
+                        Ok(registry) => match registry.export_csv(Path::new("device_export.csv")) {
+                            Ok(count) => info!("Exported {} devices to device_export.csv", count),
+                            Err(e) => self.toasts.error(format!("Export failed: {}", e)),
+                        },
+                        Err(e) => self.toasts.error(registry_unavailable(e)),
+                    }
+                }
+                compact_registry = draw_registry_stats(ui, &self.registry, &mut self.registry_stats, self.compacting);
+                changed |= ui
+                    .add_enabled(
+                        unlocked("backup.enabled"),
+                        egui::Checkbox::new(&mut config.backup.enabled, "Back up settings and device history automatically"),
+                    )
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label("Every");
+                    changed |= ui
+                        .add_enabled(
+                            unlocked("backup.interval_hours"),
+                            egui::DragValue::new(&mut config.backup.interval_hours).clamp_range(1..=24 * 30).suffix(" h"),
+                        )
+                        .changed();
+                    ui.label("keeping the newest");
+                    changed |= ui
+                        .add_enabled(unlocked("backup.keep"), egui::DragValue::new(&mut config.backup.keep).clamp_range(1..=100))
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Folder");
+                    let hint = backup::folder(&Default::default()).display().to_string();
+                    changed |= ui
+                        .add_enabled(
+                            unlocked("backup.folder"),
+                            egui::TextEdit::singleline(&mut config.backup.folder).hint_text(hint),
+                        )
+                        .on_hover_text("A folder your cloud drive syncs keeps the backups off this PC")
+                        .lost_focus();
+                });
+                ui.horizontal(|ui| {
+                    let label = if self.backing_up { "Backing up…" } else { "Back up now" };
+                    back_up_now = ui.add_enabled(!self.backing_up, egui::Button::new(label)).clicked();
+                    ui.menu_button("Restore backup…", |ui| {
+                        let backups = backup::list(&backup::folder(&config.backup));
+                        if backups.is_empty() {
+                            ui.label("No backups in the folder");
+                        }
+                        for path in backups {
+                            let label = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                            if ui.button(label).on_hover_text("Replaces your settings and device history").clicked() {
+                                restore_zip = Some(path);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                if ui.button("🗑 Delete all my data…").clicked() {
+                    self.purge_confirm = Some(false);
+                }
+                changed |= ui
+                    .add_enabled(
+                        unlocked("ui.perf_overlay"),
+                        egui::Checkbox::new(&mut config.ui.perf_overlay, "Show performance overlay"),
+                    )
+                    .on_hover_text("Frame time, device count, event queue and cache hit rate. Include it when reporting slowness.")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("ui.developer_console"),
+                        egui::Checkbox::new(&mut config.ui.developer_console, "Show developer console button"),
+                    )
+                    .on_hover_text("Call trace, HCI capture and raw core calls such as connect and disconnect")
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        unlocked("ui.at_console"),
+                        egui::Checkbox::new(&mut config.ui.at_console, "Enable AT console in device details"),
+                    )
+                    .on_hover_text("Sends raw commands to headsets and modems. Vendor commands can change device settings.")
+                    .changed();
+
+                ui.separator();
+                ui.collapsing("About this build", |ui| {
+                    ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
+                    for capability in CAPABILITIES {
+                        let mark = if capability.enabled { "✔" } else { "✖" };
+                        ui.label(format!("{} {} ({})", mark, capability.description, capability.feature));
+                    }
+                    let plugins: Vec<&str> = self.plugins.names().collect();
+                    if plugins.is_empty() {
+                        ui.label("No vendor plugins (add manifests to the plugins folder)");
+                    } else {
+                        ui.label(format!("Vendor plugins: {}", plugins.join(", ")));
+                    }
+                });
+            });
+
+        self.show_settings = open;
+        if changed {
+            self.save_config();
+        }
+
+        if let Some(path) = restore {
+            match self.config_service.restore_backup(&path) {
+                Ok(revision) => {
+                    self.config = Ok(self.config_service.snapshot());
+                    self.config_revision = revision;
+                    self.notify(format!("Restored config from {}", path.display()));
+                }
+                Err(e) => self.toasts.error(format!("Restore failed: {}", e)),
+            }
+        }
+
+        if let Some(path) = restore_zip {
+            self.restore_from_backup(&path);
+        }
+        if back_up_now {
+            self.start_backup();
+        }
+        if compact_registry {
+            self.compact_registry();
+        }
+        if generate_report {
+            // Month to date; the scheduled report covers complete months
+            let today = Local::now().date_naive();
+            match &self.registry {
+                Ok(registry) => match UsageReport::build(registry, today.year(), today.month())
+                    .and_then(|r| r.write(&report::report_dir()))
+                {
+                    Ok(path) => self.notify(format!("Usage report written to {}", path.display())),
+                    Err(e) => self.toasts.error(format!("Report failed: {}", e)),
+                },
+                Err(e) => self.toasts.error(registry_unavailable(e)),
+            }
+        }
+    }
+
+    /// ANC / Off / Ambient switch for headphones a vendor plugin can control.
+    fn draw_noise_control(&mut self, ui: &mut egui::Ui, device: &BluetoothDevice) {
+        let Some(plugin) = self.plugins.noise_control(device) else {
+            return;
+        };
+        let current = self.noise_modes.get(&device.address).copied();
+        let mut picked = None;
+        ui.horizontal(|ui| {
+            ui.small("🎧");
+            for mode in NoiseMode::ALL {
+                if ui.selectable_label(current == Some(mode), mode.label()).clicked() {
+                    picked = Some(mode);
+                }
+            }
+        });
+        if let Some(mode) = picked {
+            if let Err(e) = plugin.set_noise_control(device, mode) {
+                self.toasts.error(e.to_string());
+            }
+        }
+    }
+
+    /// Runs a multipoint handover recipe; the result arrives as `HandoverFinished`.
+    fn start_handover(&mut self, address: u64, steps: Vec<HandoverStep>) {
+        if bluetooth::is_blocked(address) {
+            self.toasts.error("This device is blocked by your organization's policy");
+            return;
+        }
+        handover::run(address, steps);
+    }
+
+    fn open_details(&mut self, address: u64) {
+        println!("CLI: Action -> Details for {:X}", address);
+        self.detail_device = Some(address);
+        self.note_draft = self.notes.get(&address).cloned().unwrap_or_default();
+        self.location_draft =
+            self.config.as_ref().ok().and_then(|c| c.location_for(address)).unwrap_or_default().to_string();
+    }
+
+    fn draw_details(&mut self, ctx: &egui::Context, address: u64) {
+        let device = self.devices.get(address).cloned();
+        let history = self
+            .registry
+            .as_ref()
+            .ok()
+            .and_then(|r| r.get_device_history(address).ok().flatten());
+        let by_adapter = self
+            .registry
+            .as_ref()
+            .ok()
+            .and_then(|r| r.adapter_reliability(address).ok())
+            .unwrap_or_default();
+        let connected = device.as_ref().is_some_and(|d| d.state.is_connected());
+        let connected_totals = self.connected_time(address).map(|t| t.totals(connected));
+
+        let mut open = true;
+        let mut save = false;
+        let mut tags_changed = false;
+        let mut location_changed = false;
+        let mut adapter_changed = false;
+        #[cfg(feature = "gatt")]
+        let mut toggle_sensor = false;
+        #[cfg(feature = "gatt")]
+        let mut browse_gatt = false;
+        let mut consent = None;
+        let mut pull = None;
+        let mut take_output = None;
+        let mut handover_changed = false;
+        let mut take_over = None;
+        let mut plugin_error = None;
+        let mut extend_guest = None;
+        let mut end_guest = false;
+        let mut unpair = false;
+        let name_style = self.name_style();
+        egui::Window::new("Device Details")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("device_details").num_columns(2).show(ui, |ui| {
+                    let raw = device.as_ref().map(|d| d.name.as_str()).unwrap_or("");
+                    let shown = names::display(raw, name_style);
+                    ui.label("Name");
+                    ui.label(&shown);
+                    ui.end_row();
+                    if shown != raw {
+                        // Escaped, so invisible and reordering characters show up
+                        ui.label("Raw name");
+                        ui.label(format!("{:?}", raw));
+                        ui.end_row();
+                    }
+                    ui.label("Address");
+                    ui.label(format!("{:X}", address));
+                    ui.end_row();
+                    if let Some(d) = &device {
+                        ui.label("Class");
+                        ui.label(format!("0x{:06X} ({:?})", d.cod, d.major_class()));
+                        ui.end_row();
+                        ui.label("RSSI");
+                        ui.label(format!("{} dB", d.rssi));
+                        ui.end_row();
+                    }
+                    if let Some(history) = &history {
+                        ui.label("Last seen");
+                        ui.label(&history.last_seen);
+                        ui.end_row();
+                        ui.label("Times seen");
+                        ui.label(history.connection_count.to_string());
+                        ui.end_row();
+                    }
+                    if let Some((today, week)) = connected_totals {
+                        ui.label("Connected today");
+                        ui.label(format_span(today as i64));
+                        ui.end_row();
+                        ui.label("This week");
+                        ui.label(format_span(week as i64));
+                        ui.end_row();
+                    }
+                    for stats in &by_adapter {
+                        let adapter = stats.adapter_name.clone().unwrap_or_else(|| format!("{:X}", stats.adapter));
+                        ui.label(format!("Via {}", adapter));
+                        ui.label(format!(
+                            "{} connects, {} failed ({:.0}%)",
+                            stats.connects,
+                            stats.failures,
+                            stats.success_rate() * 100.0
+                        ));
+                        ui.end_row();
+                    }
+                });
+
+                if self.test_tone_supported
+                    && device.as_ref().is_some_and(|d| d.state.is_connected())
+                    && ui.button("🔈 Test Speakers").on_hover_text("Play left/right channel test").clicked()
+                {
+                    self.play_test_tone(address);
+                }
+                if device.as_ref().is_some_and(|d| d.authenticated) {
+                    unpair = ui.button("Unpair").on_hover_text("Remove the pairing stored on this PC").clicked();
+                } else if device.is_some()
+                    && !self.kiosk
+                    && ui.button("Pair as Guest…").on_hover_text("Pair now, unpair and forget automatically later").clicked()
+                {
+                    self.guest_dialog = Some((address, GUEST_DEFAULT_HOURS));
+                }
+                if let Some(expires) = self.config.as_ref().ok().and_then(|c| c.guest_expiry(address)) {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("⏳ Guest until {}", format_guest_expiry(expires)));
+                        if ui.small_button("+1 h").clicked() {
+                            extend_guest = Some(expires + 3600);
+                        }
+                        if ui.small_button("End Now").clicked() {
+                            end_guest = true;
+                        }
+                    });
+                }
+                if self.config.as_ref().is_ok_and(|c| c.ui.at_console)
+                    && ui.button("AT Console…").on_hover_text("Send raw AT commands over RFCOMM").clicked()
+                    && self.at_console.as_ref().map(|c| c.address) != Some(address)
+                {
+                    self.at_console = Some(AtConsole::new(address));
+                }
+
+                ui.separator();
+                ui.label("Tags");
+                if let Ok(config) = &mut self.config {
+                    ui.horizontal_wrapped(|ui| {
+                        for tag in config.tags_for(address).to_vec() {
+                            if ui.small_button(format!("{} ✖", tag)).clicked() {
+                                config.remove_tag(address, &tag);
+                                tags_changed = true;
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let input = ui.add(
+                            egui::TextEdit::singleline(&mut self.tag_draft)
+                                .hint_text("work, travel, audio…")
+                                .desired_width(140.0),
+                        );
+                        let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if (ui.button("Add Tag").clicked() || submitted) && !self.tag_draft.trim().is_empty() {
+                            config.add_tag(address, &self.tag_draft);
+                            self.tag_draft.clear();
+                            tags_changed = true;
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Location");
+                        let input = ui.add(
+                            egui::TextEdit::singleline(&mut self.location_draft)
+                                .hint_text("Office, Living room…")
+                                .desired_width(140.0),
+                        );
+                        if input.lost_focus() && config.location_for(address) != Some(self.location_draft.trim()) {
+                            config.set_location(address, &self.location_draft);
+                            location_changed = true;
+                        }
+                        let known = config.all_locations();
+                        if !known.is_empty() {
+                            ui.menu_button("▾", |ui| {
+                                for location in known {
+                                    if ui.button(&location).clicked() {
+                                        config.set_location(address, &location);
+                                        self.location_draft = location;
+                                        location_changed = true;
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                        }
+                    });
+
+                    // Only worth offering with a second adapter, or to clear a stale choice
+                    let preferred = config.preferred_adapter(address);
+                    if self.local_adapters.len() > 1 || preferred.is_some() {
+                        ui.horizontal(|ui| {
+                            ui.label("Adapter");
+                            let label = |adapter: Option<u64>| match adapter {
+                                None => String::from("Automatic"),
+                                Some(wanted) => self
+                                    .local_adapters
+                                    .iter()
+                                    .find(|a| a.address == wanted)
+                                    .map(|a| format!("{} ({:X})", a.name, a.address))
+                                    .unwrap_or_else(|| format!("{:X} (not present)", wanted)),
+                            };
+                            let mut choice = preferred;
+                            egui::ComboBox::from_id_source(("preferred_adapter", address))
+                                .selected_text(label(preferred))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut choice, None, label(None));
+                                    for adapter in &self.local_adapters {
+                                        ui.selectable_value(&mut choice, Some(adapter.address), label(Some(adapter.address)));
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Local adapter to connect through, e.g. a low-latency dongle for a controller");
+                            if choice != preferred {
+                                config.set_preferred_adapter(address, choice);
+                                adapter_changed = true;
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                #[cfg(feature = "gatt")]
+                {
+                    if ui.button("Browse GATT…").clicked() {
+                        browse_gatt = true;
+                    }
+                    egui::CollapsingHeader::new("Sensor (BLE)").show(ui, |ui| {
+                        match self.sensors.get(&address) {
+                            Some(readout) => {
+                                draw_sensor_readout(ui, readout);
+                                toggle_sensor = ui.button("Stop Live Readout").clicked();
+                            }
+                            None => {
+                                ui.small("Heart Rate, Battery and Device Information profiles");
+                                toggle_sensor = ui.button("Start Live Readout").clicked();
+                            }
+                        }
+                    });
+                }
+
+                if device.as_ref().is_some_and(|d| d.has_audio_service()) {
+                    if let Ok(config) = &self.config {
+                        let mut enabled = config.takes_default_output(address);
+                        if ui
+                            .checkbox(&mut enabled, "Set as default output when connected")
+                            .on_hover_text("The previous default comes back when it disconnects")
+                            .changed()
+                        {
+                            take_output = Some(enabled);
+                        }
+                    }
+                    egui::CollapsingHeader::new("Multipoint Handover").show(ui, |ui| {
+                        let Ok(config) = &mut self.config else {
+                            return;
+                        };
+                        let mut multipoint = config.handover_recipe(address).is_some();
+                        if ui
+                            .checkbox(&mut multipoint, "Multipoint headset")
+                            .on_hover_text("Stays connected to a phone and this PC at the same time")
+                            .changed()
+                        {
+                            config.set_handover_recipe(address, multipoint.then(handover::default_recipe));
+                            handover_changed = true;
+                        }
+                        let Some(steps) = config.handover_recipes.get_mut(&config::address_key(address)) else {
+                            ui.small("Mark it as multipoint to pull it over from your phone in one click.");
+                            return;
+                        };
+                        let mut remove = None;
+                        for (i, step) in steps.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}.", i + 1));
+                                match step {
+                                    HandoverStep::Disconnect => {
+                                        ui.label("Disconnect");
+                                    }
+                                    HandoverStep::Connect => {
+                                        ui.label("Connect");
+                                    }
+                                    HandoverStep::Wait { ms } => {
+                                        ui.label("Wait");
+                                        let input = ui.add(
+                                            egui::DragValue::new(ms).clamp_range(0..=30_000).speed(50.0).suffix(" ms"),
+                                        );
+                                        handover_changed |= input.drag_released() || input.lost_focus();
+                                    }
+                                    HandoverStep::AtCommand { command, channel } => {
+                                        ui.label("Send");
+                                        let input = ui.add(
+                                            egui::TextEdit::singleline(command).hint_text("AT+…").desired_width(120.0),
+                                        );
+                                        handover_changed |= input.lost_focus();
+                                        let input = ui
+                                            .add(egui::DragValue::new(channel).clamp_range(0..=30).prefix("ch "))
+                                            .on_hover_text("RFCOMM channel; 0 looks up the Serial Port service");
+                                        handover_changed |= input.drag_released() || input.lost_focus();
+                                    }
+                                }
+                                if ui.small_button("🗑").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                        }
+                        if let Some(i) = remove {
+                            steps.remove(i);
+                            handover_changed = true;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.menu_button("Add Step", |ui| {
+                                let kinds = [
+                                    ("Disconnect", HandoverStep::Disconnect),
+                                    ("Wait", HandoverStep::Wait { ms: 1000 }),
+                                    ("Connect", HandoverStep::Connect),
+                                    ("AT command", HandoverStep::AtCommand { command: String::new(), channel: 0 }),
+                                ];
+                                for (label, step) in kinds {
+                                    if ui.button(label).clicked() {
+                                        steps.push(step);
+                                        handover_changed = true;
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            if ui.add_enabled(!steps.is_empty(), egui::Button::new("Take Over Now")).clicked() {
+                                take_over = Some(steps.clone());
+                            }
+                        });
+                    });
+                }
+
+                if let Some(d) = &device {
+                    for plugin in self.plugins.matching(d) {
+                        egui::CollapsingHeader::new(plugin.name()).id_source(("plugin", plugin.name().to_string())).show(
+                            ui,
+                            |ui| {
+                                ui.horizontal_wrapped(|ui| {
+                                    for action in plugin.actions(d) {
+                                        let mut button = ui.button(&action.label);
+                                        if let Some(hint) = &action.hint {
+                                            button = button.on_hover_text(hint);
+                                        }
+                                        if button.clicked() {
+                                            if let Err(e) = plugin.run(d, &action.id) {
+                                                plugin_error = Some(format!("{}: {}", plugin.name(), e));
+                                            }
+                                        }
+                                    }
+                                });
+                                plugin.panel(ui, d);
+                            },
+                        );
+                    }
+                }
+
+                egui::CollapsingHeader::new("Phone Book (PBAP)").show(ui, |ui| {
+                    let Ok(config) = &mut self.config else {
+                        return;
+                    };
+                    let mut allowed = config.has_pbap_consent(address);
+                    if ui
+                        .checkbox(&mut allowed, "Allow reading contacts and call history from this phone")
+                        .changed()
+                    {
+                        consent = Some(allowed);
+                    }
+                    if !allowed {
+                        ui.small("Nothing is downloaded until you allow it. Data stays in memory only.");
+                        return;
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Pull Contacts").clicked() {
+                            pull = Some(pbap::Folder::Contacts);
+                        }
+                        if ui.button("Pull Recent Calls").clicked() {
+                            pull = Some(pbap::Folder::CallHistory);
+                        }
+                        if self.phonebooks.contains_key(&address) && ui.button("View").clicked() {
+                            self.phonebook_view = Some((address, pbap::Folder::Contacts));
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.label("Notes");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.note_draft)
+                        .desired_rows(4)
+                        .hint_text("e.g. loaner headset, return to Sam"),
+                );
+                save = ui.button("Save Note").clicked();
+            });
+
+        if tags_changed || location_changed || adapter_changed {
+            self.save_config();
+        }
+
+        if let Some(allowed) = consent {
+            if let Ok(config) = &mut self.config {
+                config.set_pbap_consent(address, allowed);
+            }
+            if !allowed {
+                self.phonebooks.remove(&address);
+                if self.phonebook_view.is_some_and(|(a, _)| a == address) {
+                    self.phonebook_view = None;
+                }
+            }
+            self.save_config();
+        }
+
+        if let Some(enabled) = take_output {
+            if let Ok(config) = &mut self.config {
+                config.set_takes_default_output(address, enabled);
+            }
+            self.save_config();
+        }
+
+        if handover_changed {
+            self.save_config();
+        }
+
+        if let Some(msg) = plugin_error {
+            self.toasts.error(msg);
+        }
+
+        if let Some(expires) = extend_guest {
+            if let Ok(config) = &mut self.config {
+                config.set_guest_expiry(address, Some(expires));
+            }
+            self.save_config();
+        }
+        if end_guest {
+            self.end_guest(address);
+        }
+
+        if let Some(steps) = take_over {
+            self.start_handover(address, steps);
+        }
+
+        if unpair {
+            self.request(Disruptive::Unpair(address));
+        }
+
+        if let Some(folder) = pull {
+            if let Err(e) = pbap::pull(address, folder) {
+                self.toasts.error(format!("Phone book download failed: {}", e));
+            }
+        }
+
+        #[cfg(feature = "gatt")]
+        if browse_gatt {
+            self.gatt_characteristics.remove(&address);
+            match gatt::discover(address) {
+                Ok(()) => self.gatt_browser = Some(address),
+                Err(e) => self.toasts.error(format!("GATT discovery failed: {}", e)),
+            }
+        }
+
+        #[cfg(feature = "gatt")]
+        if toggle_sensor {
+            if self.sensors.remove(&address).is_some() {
+                gatt::stop_sensor_readout(address);
+            } else {
+                match gatt::start_sensor_readout(address) {
+                    Ok(()) => {
+                        self.sensors.insert(address, SensorReadout::default());
+                    }
+                    Err(e) => self.toasts.error(format!("Sensor readout failed: {}", e)),
+                }
+            }
+        }
+
+        if save {
+            match &self.registry {
+                Ok(registry) => match registry.set_note(address, &self.note_draft) {
+                    Ok(_) => {
+                        if self.note_draft.trim().is_empty() {
+                            self.notes.remove(&address);
+                        } else {
+                            self.notes.insert(address, self.note_draft.clone());
+                        }
+                    }
+                    Err(e) => self.toasts.error(format!("Failed to save note: {}", e)),
+                },
+                Err(e) => self.toasts.error(registry_unavailable(e)),
+            }
+        }
+
+        if !open {
+            self.detail_device = None;
+        }
+    }
+
+    #[cfg(feature = "gatt")]
+    fn draw_gatt_browser(&mut self, ctx: &egui::Context, address: u64) {
+        let characteristics = self.gatt_characteristics.get(&address).cloned().unwrap_or_default();
+        let mut open = true;
+        let mut read = None;
+        let mut toggle_subscribe = None;
+        let mut toggle_log = None;
+
+        egui::Window::new(format!("GATT – {}", self.device_label(address)))
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                if characteristics.is_empty() {
+                    ui.small("Discovering services…");
+                    return;
+                }
+                let mut services: Vec<u16> = characteristics.iter().map(|c| c.service).collect();
+                services.sort_unstable();
+                services.dedup();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for service in services {
+                        let title = match gatt::uuid_name(service) {
+                            Some(name) => format!("{} (0x{:04X})", name, service),
+                            None => format!("Service 0x{:04X}", service),
+                        };
+                        egui::CollapsingHeader::new(title).default_open(true).show(ui, |ui| {
+                            for c in characteristics.iter().filter(|c| c.service == service) {
+                                let key = (address, c.uuid);
+                                ui.horizontal(|ui| {
+                                    ui.label(c.label());
+                                    if c.can_read() && ui.small_button("Read").clicked() {
+                                        read = Some(*c);
+                                    }
+                                    if c.can_subscribe() {
+                                        let subscribed = self.gatt_subscriptions.contains(&key);
+                                        if ui.small_button(if subscribed { "Unsubscribe" } else { "Subscribe" }).clicked() {
+                                            toggle_subscribe = Some(*c);
+                                        }
+                                        let logging = self.gatt_loggers.get(&key);
+                                        let text = match logging {
+                                            Some(logger) => format!("⏹ CSV ({})", logger.rows),
+                                            None => "⏺ Log CSV".to_string(),
+                                        };
+                                        let button = ui.small_button(text);
+                                        let button = match logging {
+                                            Some(logger) => button.on_hover_text(logger.path.display().to_string()),
+                                            None => button.on_hover_text("Subscribe and write every value to a CSV file"),
+                                        };
+                                        if button.clicked() {
+                                            toggle_log = Some(*c);
+                                        }
+                                    }
+                                });
+                                if let Some(value) = self.gatt_values.get(&key) {
+                                    let text = gatt::decode(c.uuid, value).unwrap_or_else(|| gatt::to_hex(value));
+                                    ui.small(format!("    {}", text));
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(c) = read {
+            if let Err(e) = gatt::read(address, c.service, c.uuid) {
+                self.toasts.error(format!("GATT read failed: {}", e));
+            }
+        }
+
+        if let Some(c) = toggle_subscribe {
+            let key = (address, c.uuid);
+            if self.gatt_subscriptions.remove(&key) {
+                self.gatt_loggers.remove(&key);
+                let _ = gatt::unsubscribe(address, c.uuid);
+            } else {
+                match gatt::subscribe(address, c.service, c.uuid) {
+                    Ok(()) => {
+                        self.gatt_subscriptions.insert(key);
+                    }
+                    Err(e) => self.toasts.error(format!("Subscribe failed: {}", e)),
+                }
+            }
+        }
+
+        if let Some(c) = toggle_log {
+            let key = (address, c.uuid);
+            if let Some(logger) = self.gatt_loggers.remove(&key) {
+                self.notify(format!("Logged {} values to {}", logger.rows, logger.path.display()));
+            } else {
+                let subscribed = self.gatt_subscriptions.contains(&key) || gatt::subscribe(address, c.service, c.uuid).is_ok();
+                match (subscribed, CsvLogger::create(Path::new(GATT_LOG_DIR), address, c.uuid)) {
+                    (true, Ok(logger)) => {
+                        self.gatt_subscriptions.insert(key);
+                        self.gatt_loggers.insert(key, logger);
+                    }
+                    (false, _) => self.toasts.error(format!("Subscribe failed: {}", bluetooth::last_error())),
+                    (true, Err(e)) => self.toasts.error(format!("Failed to create log file: {}", e)),
+                }
+            }
+        }
+
+        if !open {
+            self.gatt_browser = None;
+        }
+    }
+
+    fn draw_phonebook(&mut self, ctx: &egui::Context, address: u64, mut folder: pbap::Folder) {
+        let mut open = true;
+        let book = self.phonebooks.get(&address).cloned().unwrap_or_default();
+
+        egui::Window::new(format!("Phone Book – {}", self.device_label(address)))
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut folder, pbap::Folder::Contacts, format!("Contacts ({})", book.contacts.len()));
+                    ui.selectable_value(&mut folder, pbap::Folder::CallHistory, format!("Recent Calls ({})", book.calls.len()));
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| match folder {
+                    pbap::Folder::Contacts => {
+                        if book.contacts.is_empty() {
+                            ui.small("No contacts pulled yet");
+                        }
+                        for contact in &book.contacts {
+                            ui.strong(&contact.name);
+                            for number in &contact.numbers {
+                                ui.small(format!("  {}", number));
+                            }
+                        }
+                    }
+                    pbap::Folder::CallHistory => {
+                        if book.calls.is_empty() {
+                            ui.small("No call history pulled yet");
+                        }
+                        for call in &book.calls {
+                            ui.horizontal(|ui| {
+                                ui.label(match call.kind {
+                                    Some(CallKind::Received) => "↙",
+                                    Some(CallKind::Dialed) => "↗",
+                                    Some(CallKind::Missed) => "✖",
+                                    None => "•",
+                                });
+                                ui.label(if call.name.is_empty() { &call.number } else { &call.name });
+                                ui.small(&call.time);
+                            });
+                        }
+                    }
+                });
+            });
+
+        self.phonebook_view = open.then_some((address, folder));
+    }
+
+    #[cfg(feature = "obex")]
+    fn draw_send_file(&mut self, ctx: &egui::Context) {
+        let Some(address) = self.send_file_draft.as_ref().map(|(a, _)| *a) else {
+            return;
+        };
+        let title = format!("Send file to {}", self.device_label(address));
+        let Some((_, path)) = &mut self.send_file_draft else {
+            return;
+        };
+        let mut send = false;
+        let mut cancel = false;
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File");
+                    ui.add(egui::TextEdit::singleline(path).hint_text("C:\\Users\\me\\photo.jpg").desired_width(260.0));
+                });
+                let exists = Path::new(path.trim()).is_file();
+                if !path.trim().is_empty() && !exists {
+                    ui.colored_label(egui::Color32::RED, "File not found");
+                }
+                ui.horizontal(|ui| {
+                    send = ui.add_enabled(exists, egui::Button::new("Send")).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if send {
+            if let Some((_, path)) = self.send_file_draft.take() {
+                match obex::send_file(address, Path::new(path.trim())) {
+                    Ok(transfer) => self.transfers.push(transfer),
+                    Err(e) => self.toasts.error(format!("Failed to send file: {}", e)),
+                }
+            }
+        } else if cancel {
+            self.send_file_draft = None;
+        }
+    }
+
+    #[cfg(feature = "obex")]
+    fn draw_transfers(&mut self, ctx: &egui::Context) {
+        let mut accept = None;
+        let mut reject = None;
+        let mut cancel = None;
+        let mut dismiss = None;
+
+        egui::Window::new("File Transfers").default_width(340.0).show(ctx, |ui| {
+            for transfer in &self.transfers {
+                ui.horizontal(|ui| {
+                    ui.label(if transfer.incoming { "📥" } else { "📤" });
+                    ui.vertical(|ui| {
+                        ui.strong(&transfer.file_name);
+                        ui.small(format!(
+                            "{} {}",
+                            if transfer.incoming { "from" } else { "to" },
+                            self.device_label(transfer.address)
+                        ));
+                    });
+                });
+                match transfer.state {
+                    TransferState::Pending if transfer.incoming => {
+                        ui.small(format!("{} KB — accept this file?", transfer.bytes_total / 1024));
+                        ui.horizontal(|ui| {
+                            if ui.button("Accept").clicked() {
+                                accept = Some(transfer.clone());
+                            }
+                            if ui.button("Reject").clicked() {
+                                reject = Some(transfer.id);
+                            }
+                        });
+                    }
+                    TransferState::Pending | TransferState::InProgress => {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::ProgressBar::new(transfer.progress()).show_percentage().desired_width(220.0));
+                            if ui.button("Cancel").clicked() {
+                                cancel = Some(transfer.id);
+                            }
+                        });
+                    }
+                    state => {
+                        ui.horizontal(|ui| {
+                            match state {
+                                TransferState::Complete => ui.colored_label(egui::Color32::GREEN, "✔ Complete"),
+                                TransferState::Cancelled => ui.label("Cancelled"),
+                                _ => ui.colored_label(egui::Color32::RED, "✖ Failed"),
+                            };
+                            if ui.small_button("Dismiss").clicked() {
+                                dismiss = Some(transfer.id);
+                            }
+                        });
+                    }
+                }
+                ui.separator();
+            }
+        });
+
+        if let Some(transfer) = accept {
+            match obex::accept(&transfer) {
+                Ok(()) => {
+                    if let Some(t) = self.transfers.iter_mut().find(|t| t.id == transfer.id) {
+                        t.state = TransferState::InProgress;
+                    }
+                }
+                Err(e) => self.toasts.error(format!("Failed to accept file: {}", e)),
+            }
+        }
+        if let Some(id) = reject {
+            if let Err(e) = obex::reject(id) {
+                warn!("Reject failed: {}", e);
+            }
+            self.transfers.retain(|t| t.id != id);
+        }
+        if let Some(id) = cancel {
+            if let Err(e) = obex::cancel(id) {
+                warn!("Cancel failed: {}", e);
+            }
+        }
+        if let Some(id) = dismiss {
+            self.transfers.retain(|t| t.id != id);
+        }
+    }
+
+    fn matches_search(&self, device: &BluetoothDevice) -> bool {
+        if let (Some(tag), Ok(config)) = (&self.tag_filter, &self.config) {
+            if !config.tags_for(device.address).contains(tag) {
+                return false;
+            }
+        }
+        if let (Some(location), Ok(config)) = (&self.location_filter, &self.config) {
+            if config.location_for(device.address) != Some(location.as_str()) {
+                return false;
+            }
+        }
+
+        let query = Query::new(&self.search);
+        query.is_empty() || self.search_sources().match_device(&query, device.address, &device.name).is_some()
+    }
+
+    fn search_sources(&self) -> Sources<'_> {
+        Sources { config: self.config.as_ref().ok(), notes: &self.notes }
+    }
+
+    /// Search results other than the devices in range: matching tags, and
+    /// known devices that are out of range or switched off.
+    fn draw_search_results(&mut self, ui: &mut egui::Ui, query: &Query, live: &[BluetoothDevice]) {
+        if self.known_devices.is_none() {
+            let read = self.registry.as_ref().map_err(|e| e.to_string()).and_then(|r| r.get_all_devices().map_err(|e| e.to_string()));
+            self.known_devices = Some(read.unwrap_or_else(|e| {
+                warn!("Failed to read device history for search: {}", e);
+                Vec::new()
+            }));
+        }
+        let sources = self.search_sources();
+        let tags = sources.tags(query);
+        let offline = sources.offline(query, live, self.known_devices.as_deref().unwrap_or(&[]));
+
+        let mut filter_tag = None;
+        let mut connect_tag = None;
+        let mut connect = None;
+        let mut details = None;
+        let kiosk = self.kiosk;
+        if !tags.is_empty() {
+            ui.strong(format!("Tags ({})", tags.len()));
+            for tag in &tags {
+                ui.horizontal(|ui| {
+                    if ui.link(format!("#{}", tag)).on_hover_text("Show only devices with this tag").clicked() {
+                        filter_tag = Some(tag.clone());
+                    }
+                    if !kiosk && ui.small_button("Connect all").clicked() {
+                        connect_tag = Some(tag.clone());
+                    }
+                });
+            }
+        }
+        if !offline.is_empty() {
+            let style = self.name_style();
+            ui.strong(format!("Not in range ({})", offline.len()));
+            for hit in offline.iter().take(search::MAX_OFFLINE_RESULTS) {
+                ui.horizontal(|ui| {
+                    draw_offline_hit(ui, hit, style);
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("Details").clicked() {
+                            details = Some(hit.address);
+                        }
+                        if !kiosk && ui.small_button("Connect").on_hover_text("Try it in case it is switched on").clicked() {
+                            connect = Some(hit.address);
+                        }
+                    });
+                });
+            }
+            if offline.len() > search::MAX_OFFLINE_RESULTS {
+                ui.small(format!("and {} more; refine the search to see them", offline.len() - search::MAX_OFFLINE_RESULTS));
+            }
+        }
+        if !tags.is_empty() || !offline.is_empty() {
+            ui.strong("In range");
+        }
+
+        if let Some(tag) = filter_tag {
+            self.tag_filter = Some(tag);
+            self.search.clear();
+        }
+        if let Some(tag) = connect_tag {
+            let addresses = self.config.as_ref().map(|c| c.addresses_with_tag(&tag)).unwrap_or_default();
+            self.connect_devices(&addresses);
+        }
+        if let Some(address) = connect {
+            if let Err(e) = self.connect_device(address) {
+                self.toasts.error(format!("{}: {}", self.device_label(address), e));
+            }
+        }
+        if let Some(address) = details {
+            self.open_details(address);
+        }
+    }
+
+    fn draw_tag_filter(&mut self, ui: &mut egui::Ui) {
+        let Ok(config) = &self.config else {
+            return;
+        };
+        let tags = config.all_tags();
+        if tags.is_empty() {
+            return;
+        }
+        let auto_connect = config.auto_connect.clone();
+
+        let mut auto_connect_toggle = None;
+        ui.horizontal_wrapped(|ui| {
+            if ui.selectable_label(self.tag_filter.is_none(), "All").clicked() {
+                self.tag_filter = None;
+            }
+            for tag in tags {
+                let selected = self.tag_filter.as_ref() == Some(&tag);
+                let chip = ui.selectable_label(selected, format!("#{}", tag));
+                if chip.clicked() {
+                    self.tag_filter = if selected { None } else { Some(tag.clone()) };
+                }
+
+                if self.kiosk {
+                    continue;
+                }
+                let target = format!("{}{}", TAG_TARGET_PREFIX, tag);
+                let enabled = auto_connect.contains(&target);
+                chip.context_menu(|ui| {
+                    let label = if enabled { "Stop auto-connecting this tag" } else { "Auto-connect devices with this tag" };
+                    if ui.button(label).clicked() {
+                        auto_connect_toggle = Some((target.clone(), !enabled));
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+
+        if let Some((target, enable)) = auto_connect_toggle {
+            if let Ok(config) = &mut self.config {
+                if enable {
+                    config.add_auto_connect(target);
+                } else {
+                    config.remove_auto_connect(&target);
+                }
+            }
+            self.save_config();
+        }
+    }
+
+    /// Location chips filtering the list, and the switch for grouping it by location.
+    fn draw_location_filter(&mut self, ui: &mut egui::Ui) {
+        let Ok(config) = &mut self.config else {
+            return;
+        };
+        let locations = config.all_locations();
+        if locations.is_empty() {
+            return;
+        }
+        let mut grouped = config.ui.group_by_location;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("📍");
+            if ui.selectable_label(self.location_filter.is_none(), "Everywhere").clicked() {
+                self.location_filter = None;
+            }
+            for location in locations {
+                let selected = self.location_filter.as_ref() == Some(&location);
+                if ui.selectable_label(selected, &location).clicked() {
+                    self.location_filter = if selected { None } else { Some(location) };
+                }
+            }
+            ui.separator();
+            ui.checkbox(&mut grouped, "Group");
+        });
+        if grouped != config.ui.group_by_location {
+            config.ui.group_by_location = grouped;
+            self.save_config();
+        }
+    }
+
+    /// One line: icon, name, status, signal and the connect button.
+    fn draw_compact_row(&mut self, ui: &mut egui::Ui, device: &BluetoothDevice) {
+        let style = self.status_style();
+        let row = ui.horizontal(|ui| {
+            ui.label(device_icon(device));
+            if device.name.is_empty() {
+                ui.label(egui::RichText::new(format!("{:X}", device.address)).italics());
+            } else {
+                ui.label(egui::RichText::new(names::display(&device.name, self.name_style())).strong());
+            }
+            if self.new_devices.contains(&device.address) {
+                ui.colored_label(egui::Color32::YELLOW, "🆕");
+            }
+            draw_status_label(ui, &device.state, style);
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if !self.kiosk {
+                    if ui.small_button("ℹ").on_hover_text("Details").clicked() {
+                        self.open_details(device.address);
+                    }
+                    self.draw_link_button(ui, device);
+                }
+                ui.label(format!("{} dB", device.rssi));
+                draw_rssi_bars(ui, device.rssi, style);
+            });
+        });
+        self.card_gestures(ui, &row.response, device);
+    }
+
+    /// Runs the configured gesture bindings on a drawn card or row and outlines it when selected.
+    fn card_gestures(&mut self, ui: &egui::Ui, response: &egui::Response, device: &BluetoothDevice) {
+        if self.kiosk {
+            return;
+        }
+        let response = response.interact(egui::Sense::click());
+        let gestures = self.config.as_ref().map(|c| c.ui.gestures.clone()).unwrap_or_default();
+        let action = if response.double_clicked() {
+            gestures.double_click
+        } else if response.middle_clicked() {
+            gestures.middle_click
+        } else if response.clicked() && ui.input(|i| i.modifiers.shift) {
+            gestures.shift_click
+        } else {
+            CardAction::Nothing
+        };
+
+        match action {
+            CardAction::Nothing => {}
+            CardAction::ToggleConnection => {
+                if device.state.is_connected() {
+                    self.request(Disruptive::Disconnect(device.address));
+                } else if device.state.can_connect() && !bluetooth::is_blocked(device.address) {
+                    let _ = self.connect_device(device.address);
+                }
+            }
+            CardAction::OpenDetails => self.open_details(device.address),
+            CardAction::Select => {
+                if !self.selected.remove(&device.address) {
+                    self.selected.insert(device.address);
+                }
+            }
+        }
+
+        if self.selected.contains(&device.address) {
+            ui.painter().rect_stroke(response.rect.expand(2.0), 4.0, ui.visuals().selection.stroke);
+        }
+    }
+
+    /// Connects each device that can be, tracked so a close midway can be resumed.
+    /// The whole list is recorded first, so closing during the loop loses none of it.
+    fn connect_devices(&mut self, addresses: &[u64]) {
+        if addresses.is_empty() {
+            return;
+        }
+        self.operations.begin(Operation::ConnectDevices { addresses: addresses.to_vec() });
+        for address in addresses {
+            // Devices not seen yet (e.g. resuming at startup) are tried too
+            let can_connect = self.link_state(*address).can_connect() && !bluetooth::is_blocked(*address);
+            if !can_connect || self.connect_device(*address).is_err() {
+                self.operations.device_done(*address);
+            }
+        }
+    }
+
+    /// Offers to resume what the last session left unfinished.
+    fn draw_interrupted(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut resume = None;
+        let mut dismiss = None;
+
+        egui::Window::new("Resume Unfinished Tasks")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("RedTooth was closed while these were still running:");
+                for (i, operation) in self.interrupted.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(operation.describe());
+                        if ui.button("Resume").clicked() {
+                            resume = Some(i);
+                        }
+                        if ui.button("Dismiss").clicked() {
+                            dismiss = Some(i);
+                        }
+                    });
+                }
+            });
+
+        if let Some(i) = resume {
+            let operation = self.interrupted.remove(i);
+            println!("CLI: Action -> Resume: {}", operation.describe());
+            match operation {
+                // Devices that connected on their own meanwhile are skipped
+                Operation::ConnectDevices { addresses } => self.connect_devices(&addresses),
+                Operation::StartProfile { name } => {
+                    let profile = self.config.as_ref().ok().and_then(|c| c.audio_profiles.iter().find(|p| p.name == name).cloned());
+                    match profile {
+                        Some(profile) => self.activate_profile(profile, "to resume"),
+                        None => self.toasts.error(format!("No audio profile '{}'", name)),
+                    }
+                }
+            }
+        }
+        if let Some(i) = dismiss {
+            self.interrupted.remove(i);
+        }
+        if !open {
+            self.interrupted.clear();
+        }
+    }
+
+    /// Bulk actions for the devices picked with the select gesture.
+    fn draw_selection_bar(&mut self, ui: &mut egui::Ui) {
+        self.selected.retain(|a| self.devices.get(*a).is_some());
+        if self.selected.is_empty() {
+            return;
+        }
+        let mut connect = false;
+        let mut disconnect = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} selected", self.selected.len()));
+            connect = ui.button("Connect").clicked();
+            disconnect = ui.button("Disconnect").clicked();
+            if ui.button("Clear selection").clicked() {
+                self.selected.clear();
+            }
+        });
+
+        let mut addresses: Vec<u64> = self.selected.iter().copied().collect();
+        addresses.sort_unstable();
+        if connect {
+            println!("CLI: Action -> Connect {} selected", addresses.len());
+            self.connect_devices(&addresses);
+        }
+        if disconnect {
+            println!("CLI: Action -> Disconnect {} selected", addresses.len());
+            // Bulk disconnects keep input devices, like Disconnect All
+            let keep_inputs = self.config.as_ref().map_or(true, |c| c.confirm.keep_input_devices);
+            let mut kept = Vec::new();
+            for address in addresses {
+                let Some(device) = self.devices.get(address).filter(|d| d.state.is_connected()).cloned() else {
+                    continue;
+                };
+                if keep_inputs && device.is_input_device() {
+                    kept.push(self.device_label(address));
+                } else if let Err(e) = self.disconnect_device(address) {
+                    self.toasts.error(format!("{}: {}", self.device_label(address), e));
+                }
+            }
+            if !kept.is_empty() {
+                self.notify(format!("Kept {} connected", kept.join(", ")));
+            }
+        }
+    }
+
+    /// Connect/Disconnect, disabled while a transition is in flight.
+    fn draw_link_button(&mut self, ui: &mut egui::Ui, device: &BluetoothDevice) {
+        if device.state.in_transition() {
+            let label = if device.state == ConnectionState::Connecting { "Connecting…" } else { "Disconnecting…" };
+            ui.add_enabled(false, egui::Button::new(label));
+        } else if device.state.is_connected() {
+            if ui.button("Disconnect").clicked() {
+                 self.request(Disruptive::Disconnect(device.address));
+            }
+        } else {
+            let blocked = bluetooth::is_blocked(device.address);
+            if ui
+                .add_enabled(!blocked, egui::Button::new("Connect"))
+                .on_disabled_hover_text("Blocked by your organization's policy")
+                .clicked()
+            {
+                 let _ = self.connect_device(device.address);
+            }
+        }
+    }
+
+    /// Shortcuts for what the device is: volume and Play/Pause for audio outputs,
+    /// reconnect and battery for keyboards and mice, file transfer and tethering for phones.
+    fn draw_quick_actions(&mut self, ui: &mut egui::Ui, device: &BluetoothDevice) {
+        let actions = device.quick_actions();
+        let connected = device.state.is_connected();
+        if actions.is_empty() {
+            return;
+        }
+        if connected {
+            let readout = self.card_readouts.entry(device.address).or_default();
+            if readout.read.is_none_or(|t| t.elapsed() >= READOUT_REFRESH_INTERVAL) {
+                if actions.contains(&QuickAction::Volume) {
+                    readout.volume = endpoints::volume(device.address).ok();
+                }
+                if actions.contains(&QuickAction::Battery) {
+                    readout.battery = endpoints::battery_level(device.address).ok().flatten();
+                }
+                readout.read = Some(Instant::now());
+            }
+        }
+
+        ui.horizontal(|ui| {
+            for action in actions {
+                match action {
+                    QuickAction::Volume if connected => {
+                        let Some(readout) = self.card_readouts.get_mut(&device.address) else {
+                            continue;
+                        };
+                        let Some(level) = readout.volume.as_mut() else {
+                            continue;
+                        };
+                        ui.label("🔉");
+                        let slider = egui::Slider::new(level, 0.0..=1.0).show_value(false);
+                        if ui.add(slider).on_hover_text(format!("Volume {:.0}%", *level * 100.0)).changed() {
+                            if let Err(e) = endpoints::set_volume(device.address, *level) {
+                                readout.volume = None;
+                                self.toasts.error(e.to_string());
+                            }
+                        }
+                    }
+                    QuickAction::PlayPause if connected => {
+                        let clicked = ui.small_button("⏯").on_hover_text("Play/Pause").clicked();
+                        if clicked {
+                            if let Err(e) = endpoints::media_play_pause() {
+                                self.toasts.error(e.to_string());
+                            }
+                        }
+                    }
+                    QuickAction::Reconnect if connected => {
+                        let busy = self.reconnecting.contains(&device.address);
+                        let button = ui
+                            .add_enabled(!busy, egui::Button::new("↻ Reconnect").small())
+                            .on_hover_text("Drop the link and connect again; often cures lag");
+                        if button.clicked() {
+                            println!("CLI: Action -> Reconnect {:X}", device.address);
+                            match self.disconnect_device(device.address) {
+                                Ok(()) => {
+                                    self.reconnecting.insert(device.address);
+                                }
+                                Err(e) => self.toasts.error(e.to_string()),
+                            }
+                        }
+                    }
+                    QuickAction::Battery if connected => {
+                        if let Some(level) = self.card_readouts.get(&device.address).and_then(|r| r.battery) {
+                            ui.small(format!("🔋 {}%", level));
+                        }
+                    }
+                    #[cfg(feature = "obex")]
+                    QuickAction::SendFile if connected && obex::transfers_allowed() => {
+                        let clicked = ui.small_button("📤 Send file").clicked();
+                        if clicked {
+                            self.send_file_draft = Some((device.address, String::new()));
+                        }
+                    }
+                    QuickAction::Tether if self.nap_support.get(&device.address).copied().unwrap_or(false) => {
+                        let linked = self.pan_links.contains_key(&device.address);
+                        if linked && ui.small_button("🌐 Stop tethering").clicked() {
+                            if let Err(e) = pan::disconnect(device.address) {
+                                self.toasts.error(e.to_string());
+                            }
+                            self.pan_links.remove(&device.address);
+                        } else if !linked && ui.small_button("🌐 Tether").on_hover_text("Use this phone's internet (PAN)").clicked() {
+                            match pan::connect(device.address) {
+                                Ok(link) => {
+                                    self.pan_links.insert(device.address, link);
+                                }
+                                Err(e) => self.toasts.error(e.to_string()),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    /// Extra telemetry for the detailed density.
+    fn draw_card_details(&self, ui: &mut egui::Ui, device: &BluetoothDevice) {
+        let mut traits = vec![format!("{:?} ({:#08x})", device.major_class(), device.cod)];
+        if device.authenticated {
+            traits.push(String::from("Paired"));
+        }
+        if device.has_audio_service() {
+            traits.push(String::from("Audio"));
+        }
+        ui.small(traits.join(" · "));
+        if let Some(stats) = self.diagnostics.latest(device.address) {
+            ui.small(format!(
+                "🎧 Buffer {}% · {} underruns · {} resyncs",
+                stats.buffer_fill_percent, stats.underruns, stats.resyncs
+            ));
+        }
+    }
+
+    fn draw_device_card(&mut self, ui: &mut egui::Ui, device: &BluetoothDevice) {
+        let density = self.card_density();
+        if density == CardDensity::Compact {
+            self.draw_compact_row(ui, device);
+            return;
+        }
+        let style = self.status_style();
+        let card = ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(device_icon(device));
+                
+                ui.vertical(|ui| {
+                    if device.name.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("Unknown device").italics());
+                            if ui.small_button("⟳").on_hover_text("Refresh name").clicked() {
+                                if let Err(e) = bluetooth::request_remote_name(device.address) {
+                                    self.toasts.error(e.to_string());
+                                }
+                            }
+                        });
+                    } else {
+                        ui.label(egui::RichText::new(names::display(&device.name, self.name_style())).strong());
+                    }
+                    if self.new_devices.contains(&device.address) {
+                        ui.colored_label(egui::Color32::YELLOW, "🆕 NEW").on_hover_text("Never seen on this PC before");
+                    }
+                    ui.small(format!("{:X}", device.address));
+                    if let Ok(config) = &self.config {
+                        let tags = config.tags_for(device.address);
+                        if !tags.is_empty() {
+                            ui.small(tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "));
+                        }
+                        if let Some(location) = config.location_for(device.address) {
+                            ui.small(format!("📍 {}", location));
+                        }
+                        if let Some(expires) = config.guest_expiry(device.address) {
+                            ui.small(format!("⏳ Guest until {}", format_guest_expiry(expires)));
+                        }
+                    }
+                    if let Some(note) = self.notes.get(&device.address) {
+                        let first_line = note.lines().next().unwrap_or("");
+                        ui.small(egui::RichText::new(format!("📝 {}", first_line)).italics());
+                    }
+                    
+                    draw_status_label(ui, &device.state, style);
+                    if device.state.is_connected() {
+                        if let Some(since) = self.connected_time(device.address).and_then(|t| t.since) {
+                            ui.small(format!("⏱ Connected for {}", format_span((Local::now() - since).num_seconds())));
+                        }
+                    }
+                    if self.default_output == Some(device.address) {
+                        ui.small("🔊 Default output");
+                    }
+                    if device.state.is_connected() && !self.kiosk {
+                        self.draw_noise_control(ui, device);
+                    }
+                    if !self.kiosk {
+                        self.draw_quick_actions(ui, device);
+                    }
+                    self.draw_outcomes(ui, device.address);
+                    if density == CardDensity::Detailed {
+                        self.draw_card_details(ui, device);
+                    }
+
+                    if let Some(link) = self.pan_links.get(&device.address) {
+                        ui.small(format!(
+                            "🌐 Network {} · ↓ {} ↑ {}",
+                            if link.connected { "up" } else { "down" },
+                            pan::format_rate(link.rx_rate),
+                            pan::format_rate(link.tx_rate)
+                        ));
+                    }
+
+                    if let Some((state, muted)) = self.call_states.get(&device.address).copied() {
+                        ui.horizontal(|ui| {
+                            match state {
+                                CallState::Active => ui.strong("📞 In call"),
+                                CallState::Ringing => ui.strong("📞 Ringing"),
+                                CallState::Idle => ui.small("📞 Idle"),
+                            };
+                            let label = if muted { "🔇 Unmute" } else { "🎤 Mute" };
+                            if !self.kiosk && ui.small_button(label).clicked() {
+                                if let Err(e) = bluetooth::set_mic_mute(device.address, !muted) {
+                                    self.toasts.error(e.to_string());
+                                }
+                            }
+                        });
+                    }
+                });
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if self.kiosk {
+                        ui.label(format!("{} dB", device.rssi));
+                        draw_rssi_bars(ui, device.rssi, style);
+                        return;
+                    }
+                    let saved_name = self.config.as_ref().ok().and_then(|c| {
+                        c.devices.iter().find(|(_, addr)| **addr == device.address).map(|(n, _)| n.clone())
+                    });
+                    if ui.button("ℹ").on_hover_text("Details").clicked() {
+                        self.open_details(device.address);
+                    }
+                    let recipe = self
+                        .config
+                        .as_ref()
+                        .ok()
+                        .and_then(|c| c.handover_recipe(device.address))
+                        .map(<[HandoverStep]>::to_vec);
+                    if let Some(steps) = recipe {
+                        if ui.button("⇄").on_hover_text("Take over from the other host").clicked() {
+                            self.start_handover(device.address, steps);
+                        }
+                    }
+
+                    let star = if saved_name.is_some() { "★" } else { "☆" };
+                    if ui.button(star).on_hover_text("Save to My Devices").clicked() {
+                        if let Ok(config) = &mut self.config {
+                            match &saved_name {
+                                Some(name) => {
+                                    config.remove_device(name);
+                                    config.remove_auto_connect(name);
+                                }
+                                None => {
+                                    let name = if device.name.is_empty() {
+                                        format!("{:X}", device.address)
+                                    } else {
+                                        device.name.clone()
+                                    };
+                                    config.add_device(name, device.address);
+                                }
+                            }
+                        }
+                        self.save_config();
+                    }
+
+                    if device.state.is_connected()
+                        && device.has_audio_service()
+                        && self.default_output != Some(device.address)
+                        && ui.button("🔊").on_hover_text("Make default output").clicked()
+                    {
+                        match endpoints::make_default_output(device.address) {
+                            Ok(()) => {
+                                self.default_output = Some(device.address);
+                                self.notify(format!("{} is now the default output", self.device_label(device.address)));
+                            }
+                            Err(e) => self.toasts.error(e.to_string()),
+                        }
+                    }
+
+                    self.draw_link_button(ui, device);
+                    if device.state.can_connect()
+                        && self.auth_failures.get(&device.address).is_some_and(|n| *n >= REPAIR_OFFER_AFTER)
+                        && ui.button("🔧 Fix pairing").on_hover_text("Connects keep failing authentication").clicked()
+                    {
+                        self.open_repair(device.address);
+                    }
+                     ui.label(format!("{} dB", device.rssi));
+                     draw_rssi_bars(ui, device.rssi, style);
+                });
+                
+            });
+        });
+        self.card_gestures(ui, &card.response, device);
+    }
+}
+
+impl App for BluetoothApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        let started = Instant::now();
+        self.draw_frame(ctx);
+        self.perf.frame(started.elapsed());
+        if self.config.as_ref().is_ok_and(|c| c.ui.perf_overlay) {
+            self.draw_perf_overlay(ctx);
+        }
+    }
+}
+
+impl BluetoothApp {
+    fn draw_frame(&mut self, ctx: &egui::Context) {
+        // 1. Process Events
+        self.process_events();
+        self.sync_scan_watchdog();
+        self.sync_registry();
+        self.sync_config();
+        self.sync_hotkey();
+        self.sync_shared_store();
+        if !self.hotkey_global && self.hotkey.is_some_and(|k| k.consume(ctx)) {
+            self.open_quick_connect();
+        }
+        self.sync_profile_shortcuts(ctx);
+        if self.quick_connect.is_some() {
+            self.draw_quick_connect(ctx);
+        }
+        
+        // Responsive repaint; back off on battery since nothing changes faster than the scan cycle
+        let repaint = if self.low_power { 500 } else { 50 };
+        ctx.request_repaint_after(Duration::from_millis(repaint));
+
+        if self.mini_mode {
+            self.draw_mini_mode(ctx);
+            return;
+        }
+
+        self.draw_toasts(ctx);
+        if self.show_toast_history {
+            self.draw_toast_history(ctx);
+        }
+        let typed_question = ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Text(t) if t == "?")));
+        if typed_question && !ctx.wants_keyboard_input() {
+            self.show_shortcuts = !self.show_shortcuts;
+        }
+        if self.show_shortcuts {
+            self.draw_shortcuts(ctx);
+        }
+
+        if self.show_settings {
+            self.draw_settings(ctx);
+        }
+
+        self.sync_wake_lock();
+        self.sync_power_mode();
+        self.sync_scan_tuning();
+        self.sync_health();
+        self.sync_rssi_rollup();
+        self.sync_backups();
+        self.sync_default_output();
+        self.sync_profile_schedules();
+        self.sync_signal_monitor();
+        self.sync_presence_lock();
+        self.sync_guests();
+        self.sync_endpoint_jobs();
+        self.sync_sink_batteries();
+
+        for (address, link) in self.pan_links.iter_mut() {
+            link.poll(*address);
+        }
+
+        match &self.audio_session {
+            Some(session) => self.diagnostics.poll(&session.sinks),
+            None => self.diagnostics.clear(),
+        }
+        if self.console.is_some() {
+            self.draw_console(ctx);
+        }
+        if self.problem_report.is_some() {
+            self.draw_problem_report(ctx);
+        }
+        if self.safe_mode_dialog.is_some() {
+            self.draw_safe_mode(ctx);
+        }
+        if !self.interrupted.is_empty() {
+            self.draw_interrupted(ctx);
+        }
+        if self.at_console.is_some() {
+            self.draw_at_console(ctx);
+        }
+        if self.show_diagnostics {
+            self.draw_diagnostics(ctx);
+        }
+        if self.presence_setup.is_some() {
+            self.draw_presence_setup(ctx);
+        }
+        #[cfg(feature = "scripting")]
+        {
+            self.scripts.publish(self.devices.snapshot());
+            if self.script_editor.is_some() {
+                self.draw_script_editor(ctx);
+            }
+        }
+        if self.selftest.is_some() {
+            self.draw_selftest(ctx);
+        }
+
+        if let Some(address) = self.detail_device {
+            self.draw_details(ctx, address);
+        }
+
+        if self.repair.is_some() {
+            self.draw_repair(ctx);
+        }
+        if self.guest_dialog.is_some() {
+            self.draw_guest_dialog(ctx);
+        }
+
+        if self.share_view.is_some() {
+            self.draw_share(ctx);
+        }
+        if self.show_known_devices {
+            self.draw_known_devices(ctx);
+        }
+        if self.import_draft.is_some() {
+            self.draw_import(ctx);
+        }
+        if self.purge_confirm.is_some() {
+            self.draw_purge(ctx);
+        }
+        if self.confirm.is_some() {
+            self.draw_confirm(ctx);
+        }
+
+        #[cfg(feature = "gatt")]
+        if let Some(address) = self.gatt_browser {
+            self.draw_gatt_browser(ctx, address);
+        }
+
+        if let Some((address, folder)) = self.phonebook_view {
+            self.draw_phonebook(ctx, address, folder);
+        }
+
+        #[cfg(feature = "obex")]
+        {
+            if self.send_file_draft.is_some() {
+                self.draw_send_file(ctx);
+            }
+            if !self.transfers.is_empty() {
+                self.draw_transfers(ctx);
+            }
+        }
+
+        self.draw_status_bar(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Project RedTooth");
+                if self.config.as_ref().is_ok_and(|c| c.quiet_hours_active()) {
+                    ui.label("🌙").on_hover_text("Do Not Disturb is active");
+                }
+                if self.low_power {
+                    ui.label("🔋").on_hover_text("On battery: low-power scanning");
+                }
+                if self.config.as_ref().is_ok_and(|c| c.watch.enabled) {
+                    if self.signal_supported == Some(false) {
+                        ui.colored_label(egui::Color32::RED, "👁✖")
+                            .on_hover_text("The area watch can't work: this adapter doesn't report signal strength (it needs Bluetooth LE)");
+                    } else {
+                        ui.label("👁").on_hover_text("Watching the area for unknown devices");
+                    }
+                }
+                if let Some(settings) = self.config.as_ref().ok().map(|c| &c.presence_lock).filter(|s| s.enabled) {
+                    if self.signal_supported == Some(false) {
+                        ui.colored_label(egui::Color32::RED, "🔐✖").on_hover_text(
+                            "Presence lock can't work: this adapter doesn't report signal strength (it needs Bluetooth LE)",
+                        );
+                    } else if !self.presence_lock.armed() {
+                        ui.colored_label(egui::Color32::GRAY, "🔐…").on_hover_text(
+                            "Presence lock hasn't heard your phone's signal yet and won't lock until it has. \
+                             Keep scanning, and check that the phone's Bluetooth is on.",
+                        );
+                    } else {
+                        match self.presence_lock.countdown(settings) {
+                            Some(secs) => {
+                                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), format!("🔐 Locking in {} s", secs))
+                                    .on_hover_text("Your phone is out of range");
+                            }
+                            None => {
+                                ui.label("🔐").on_hover_text("Presence lock: this PC locks when your phone leaves");
+                            }
+                        }
+                    }
+                }
+                if self.incognito {
+                    ui.colored_label(egui::Color32::LIGHT_GRAY, "🕶 Incognito")
+                        .on_hover_text("Nothing from this session is saved to device history");
+                }
+                if self.registry.as_ref().is_err_and(|e| e.is_database_locked()) {
+                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⏸ History locked").on_hover_text(
+                        "registry.db is in use by another program, such as a second RedTooth window or a backup tool. \
+                         Device history isn't saved until it is released; RedTooth keeps retrying.",
+                    );
+                }
+                if self.kiosk {
+                    ui.label("🔒").on_hover_text("Kiosk mode: status only");
+                }
+                if self.safe_mode {
+                    ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "🛟 Safe mode").on_hover_text(
+                        "Bluetooth, audio, plugins and your settings weren't loaded. Close and reopen RedTooth to start normally.",
+                    );
+                }
+            });
+            
+            // Permission Warning
+            if !self.permission_granted {
+                ui.colored_label(egui::Color32::RED, "⚠ PERMISSION DENIED - Check OS Settings");
+                if ui.button("Check Again").clicked() {
+                    self.permission_granted = bluetooth::check_permission();
+                }
+            }
+
+            ui.horizontal(|ui| {
+                 if ui.button(if self.scanning { "Stop Scan" } else { "Start Scan" }).clicked() {
+                     if self.scanning {
+                         let _ = bluetooth::stop_scan();
+                     } else {
                          let _ = bluetooth::start_scan();
                      }
                  }
@@ -199,21 +7075,535 @@ impl App for BluetoothApp {
                  if ui.button("Clear List").clicked() {
                      println!("CLI: Action -> Clear List");
                      self.devices.clear();
+                     self.card_layout.clear();
+                 }
+
+                 if self.kiosk {
+                     return;
+                 }
+
+                 let any_connected = self.devices.snapshot().iter().any(|d| d.state.is_connected());
+                 if ui.add_enabled(any_connected, egui::Button::new("Disconnect All")).clicked() {
+                     let include_inputs = self.config.as_ref().is_ok_and(|c| !c.confirm.keep_input_devices);
+                     self.request(Disruptive::DisconnectAll { include_inputs });
+                 }
+
+                 if ui.button("Mini Mode").clicked() {
+                     self.set_mini_mode(ctx, true);
+                 }
+
+                 if ui
+                     .selectable_label(self.incognito, "🕶")
+                     .on_hover_text("Incognito: stop recording history for this session")
+                     .clicked()
+                 {
+                     self.incognito = !self.incognito;
+                     println!("CLI: Action -> Incognito {}", if self.incognito { "on" } else { "off" });
+                 }
+
+                 if ui.button("🩺").on_hover_text("Self-test and adapter info").clicked() {
+                     self.run_selftest();
+                 }
+
+                 if ui.button("📈").on_hover_text("Audio diagnostics").clicked() {
+                     self.show_diagnostics = !self.show_diagnostics;
+                 }
+
+                 #[cfg(feature = "scripting")]
+                 if ui.button("📜").on_hover_text("Scripts").clicked() {
+                     self.script_editor = match self.script_editor {
+                         Some(_) => None,
+                         None => Some(ScriptEditor::default()),
+                     };
+                 }
+
+                 if ui.button("🐞").on_hover_text("Report a problem on GitHub").clicked() {
+                    self.open_problem_report();
+                }
+
+                if self.config.as_ref().is_ok_and(|c| c.ui.developer_console)
+                    && ui.button("🛠").on_hover_text("Developer console").clicked()
+                {
+                     self.console = match self.console {
+                         Some(_) => None,
+                         None => Some(DevConsole::default()),
+                     };
+                 }
+
+                 if ui
+                     .selectable_label(self.show_toast_history, format!("🔔 {}", self.toasts.history_len()))
+                     .on_hover_text("Notification history")
+                     .clicked()
+                 {
+                     self.show_toast_history = !self.show_toast_history;
+                 }
+
+                 if ui.button("⚙").on_hover_text("Settings").clicked() {
+                     self.show_settings = !self.show_settings;
                  }
             });
+
+            if let Some(elapsed) = self.scan_progress.elapsed() {
+                self.draw_scan_progress(ui, elapsed);
+            }
             
             ui.separator();
 
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                 // We have to clone to iterate bc logging/drawing might mutate?
-                 // Actually draw_device_card takes &mut self which is annoying if iterating self.devices.
-                 // We will separate data from drawing method slightly or clone list.
-                 // For now, let's just inline the draw logic or clone the device data to avoid borrow checker hell.
-                 let items = self.devices.clone(); 
-                 for device in items {
-                     self.draw_device_card(ui, &device);
-                 }
+            self.draw_watch_alerts(ui);
+            self.draw_health_alerts(ui);
+            self.draw_my_devices(ui);
+            self.draw_activity(ui);
+            if !self.kiosk {
+                self.draw_audio_panel(ui);
+                self.draw_broadcasts(ui);
+                #[cfg(feature = "audio-routing")]
+                self.draw_app_routes(ui);
+            }
+
+            ui.separator();
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("🔍 Search names, addresses, notes and tags")
+                    .desired_width(f32::INFINITY),
+            );
+
+            self.draw_tag_filter(ui);
+            self.draw_location_filter(ui);
+            self.draw_selection_bar(ui);
+
+            // draw_device_card takes &mut self, so draw from a shared snapshot
+            // rather than borrowing self.devices
+            let devices = self.devices.snapshot();
+            let query = Query::new(&self.search);
+            if query.is_empty() {
+                // Re-read at the next search, for devices logged meanwhile
+                self.known_devices = None;
+            } else {
+                self.draw_search_results(ui, &query, &devices);
+            }
+            let mut listed: Vec<&BluetoothDevice> = devices.iter().filter(|d| self.matches_search(d)).collect();
+            // Section per location, devices without one last; `None` while not grouping
+            let locations: Option<Vec<Option<String>>> = match &self.config {
+                Ok(config) if config.ui.group_by_location => {
+                    listed.sort_by_key(|d| {
+                        let location = config.location_for(d.address);
+                        (location.is_none(), location.map(str::to_string))
+                    });
+                    Some(listed.iter().map(|d| config.location_for(d.address).map(str::to_string)).collect())
+                }
+                _ => None,
+            };
+            let addresses: Vec<u64> = listed.iter().map(|d| d.address).collect();
+
+            egui::ScrollArea::vertical().show_viewport(ui, |ui, viewport| {
+                 let window = self.card_layout.window(&addresses, viewport.min.y, viewport.max.y);
+                 ui.set_height(window.total);
+                 let visible = egui::Rect::from_x_y_ranges(
+                     ui.max_rect().x_range(),
+                     ui.max_rect().top() + window.offset..=ui.max_rect().top() + window.total,
+                 );
+                 ui.allocate_ui_at_rect(visible, |ui| {
+                     for i in window.range {
+                         let top = ui.cursor().top();
+                         // The heading is measured as part of the first card in its section
+                         if let Some(locations) = &locations {
+                             if i == 0 || locations[i] != locations[i - 1] {
+                                 ui.strong(locations[i].as_deref().unwrap_or("No location"));
+                             }
+                         }
+                         self.draw_device_card(ui, listed[i]);
+                         self.card_layout.measured(addresses[i], ui.cursor().top() - top);
+                     }
+                 });
+            });
+        });
+    }
+}
+
+/// Database size and row counts for the Data settings. Returns true when
+/// "Compact database" was clicked.
+fn draw_registry_stats(
+    ui: &mut egui::Ui,
+    registry: &crate::error::Result<Registry>,
+    stats: &mut Option<RegistryStats>,
+    compacting: Option<(Instant, u64)>,
+) -> bool {
+    if stats.is_none() {
+        if let Ok(registry) = registry {
+            match registry.stats() {
+                Ok(read) => *stats = Some(read),
+                Err(e) => warn!("Failed to read registry stats: {}", e),
+            }
+        }
+    }
+    let mut compact = false;
+    ui.collapsing("Database", |ui| {
+        let Some(current) = stats else {
+            ui.label("Registry unavailable");
+            return;
+        };
+        ui.label(format!("registry.db is {}", format_size(current.bytes)));
+        egui::Grid::new("registry_rows").num_columns(2).show(ui, |ui| {
+            for (table, rows) in &current.rows {
+                ui.small(*table);
+                ui.small(rows.to_string());
+                ui.end_row();
+            }
+        });
+        match compacting {
+            Some((started, _)) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Compacting… {} s", started.elapsed().as_secs()));
+                });
+            }
+            None => {
+                ui.horizontal(|ui| {
+                    compact = ui
+                        .button("Compact database")
+                        .on_hover_text("Gives space freed by pruning back to the disk. Device history is read-only meanwhile.")
+                        .clicked();
+                    if ui.small_button("↻").on_hover_text("Refresh").clicked() {
+                        *stats = None;
+                    }
+                });
+            }
+        }
+    });
+    compact
+}
+
+/// Name, what matched and when the device was last seen.
+fn draw_offline_hit(ui: &mut egui::Ui, hit: &OfflineHit, style: NameStyle) {
+    if hit.name.is_empty() {
+        ui.label(egui::RichText::new(format!("{:X}", hit.address)).italics());
+    } else {
+        ui.label(names::display(&hit.name, style));
+    }
+    if let Some(matched) = hit.field.describe() {
+        ui.small(matched);
+    }
+    match &hit.last_seen {
+        Some(seen) => ui.small(format!("last seen {}", seen)),
+        None => ui.small("saved, no history"),
+    };
+}
+
+/// "2h 13m", or "13m" under an hour.
+fn format_span(secs: i64) -> String {
+    let minutes = secs.max(0) / 60;
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Start of `day` in local time.
+fn local_midnight(day: NaiveDate) -> DateTime<Local> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    // A DST change can skip local midnight; UTC midnight is within hours of it
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{} KB", bytes / 1024)
+    }
+}
+
+/// Local time a guest pairing ends, from Unix seconds.
+fn format_guest_expiry(expires: i64) -> String {
+    use chrono::TimeZone;
+    Local.timestamp_opt(expires, 0).single().map_or_else(|| String::from("?"), |t| t.format("%a %H:%M").to_string())
+}
+
+fn registry_unavailable(error: &AppError) -> String {
+    if error.is_database_locked() {
+        String::from("Device history is locked by another program (a second RedTooth window or a backup tool). Try again in a moment.")
+    } else {
+        format!("Registry unavailable: {}", error)
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::Ok => egui::Color32::GREEN,
+        Level::Degraded => egui::Color32::YELLOW,
+        Level::Down => egui::Color32::RED,
+    }
+}
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Info => egui::Color32::LIGHT_BLUE,
+        Severity::Warning => egui::Color32::from_rgb(255, 165, 0),
+        Severity::Error => egui::Color32::LIGHT_RED,
+    }
+}
+
+/// Current adapter, recorded in the registry if `record`, plus every adapter recorded so far.
+fn read_adapter(registry: Option<&Registry>, record: bool) -> (Option<Adapter>, Vec<(Adapter, String, String)>) {
+    let adapter = bluetooth::adapter_info()
+        .map_err(|e| warn!("Failed to read adapter info: {}", e))
+        .ok();
+    let Some(registry) = registry else {
+        return (adapter, Vec::new());
+    };
+    if let (Some(adapter), true) = (&adapter, record) {
+        if let Err(e) = registry.record_adapter(adapter) {
+            warn!("Failed to record adapter: {}", e);
+        }
+    }
+    let history = registry.adapters().unwrap_or_else(|e| {
+        warn!("Failed to load adapter history: {}", e);
+        Vec::new()
+    });
+    (adapter, history)
+}
+
+fn present_adapters() -> Vec<Adapter> {
+    bluetooth::adapters().unwrap_or_else(|e| {
+        warn!("Failed to list adapters: {}", e);
+        Vec::new()
+    })
+}
+
+/// "just now", "5 min ago", "3 h ago", "2 d ago"
+fn time_ago(elapsed: chrono::Duration) -> String {
+    match elapsed.num_minutes() {
+        m if m < 1 => "just now".to_string(),
+        m if m < 60 => format!("{} min ago", m),
+        m if m < 24 * 60 => format!("{} h ago", m / 60),
+        m => format!("{} d ago", m / (24 * 60)),
+    }
+}
+
+fn draw_status_label(ui: &mut egui::Ui, state: &ConnectionState, style: StatusStyle) {
+    match (style, state) {
+        (StatusStyle::Color, ConnectionState::Connected) => ui.colored_label(egui::Color32::GREEN, "Connected"),
+        (StatusStyle::Color, ConnectionState::Disconnected) => ui.label("Disconnected"),
+        (StatusStyle::Color, ConnectionState::Connecting) => ui.colored_label(egui::Color32::YELLOW, "Connecting…"),
+        (StatusStyle::Color, ConnectionState::Disconnecting) => ui.colored_label(egui::Color32::YELLOW, "Disconnecting…"),
+        (StatusStyle::Color, ConnectionState::Failed(reason)) => {
+            ui.colored_label(egui::Color32::RED, "Connection failed").on_hover_text(reason)
+        }
+        (StatusStyle::Symbols, ConnectionState::Connected) => ui.strong("✔ Connected"),
+        (StatusStyle::Symbols, ConnectionState::Disconnected) => ui.label("✖ Disconnected"),
+        (StatusStyle::Symbols, ConnectionState::Connecting) => ui.label("⏳ Connecting…"),
+        (StatusStyle::Symbols, ConnectionState::Disconnecting) => ui.label("⏳ Disconnecting…"),
+        (StatusStyle::Symbols, ConnectionState::Failed(reason)) => ui.strong("⚠ Connection failed").on_hover_text(reason),
+    };
+}
+
+#[cfg(feature = "gatt")]
+fn draw_sensor_readout(ui: &mut egui::Ui, readout: &SensorReadout) {
+    egui::Grid::new("sensor_readout").num_columns(2).show(ui, |ui| {
+        ui.label("Heart rate");
+        match &readout.heart_rate {
+            Some(hr) => ui.heading(format!("❤ {} bpm", hr.bpm)),
+            None => ui.label("—"),
+        };
+        ui.end_row();
+        if let Some(hr) = &readout.heart_rate {
+            ui.label("Sensor contact");
+            ui.label(match hr.sensor_contact {
+                Some(true) => "Yes",
+                Some(false) => "No",
+                None => "Not supported",
             });
+            ui.end_row();
+            if let Some(rr) = hr.rr_intervals.last() {
+                ui.label("RR interval");
+                ui.label(format!("{} ms", *rr as u32 * 1000 / 1024));
+                ui.end_row();
+            }
+            if let Some(kj) = hr.energy_expended {
+                ui.label("Energy");
+                ui.label(format!("{} kJ", kj));
+                ui.end_row();
+            }
+        }
+        ui.label("Battery");
+        ui.label(readout.battery.map(|b| format!("{}%", b)).unwrap_or_else(|| "—".to_string()));
+        ui.end_row();
+        for (label, value) in [
+            ("Manufacturer", &readout.manufacturer),
+            ("Model", &readout.model),
+            ("Firmware", &readout.firmware),
+        ] {
+            if let Some(value) = value {
+                ui.label(label);
+                ui.label(value);
+                ui.end_row();
+            }
+        }
+    });
+    match readout.last_update {
+        Some(t) => ui.small(format!("Updated {} s ago", t.elapsed().as_secs())),
+        None => ui.small("Waiting for data…"),
+    };
+}
+
+/// Line graph of `values` in `0..=max`, newest on the right.
+/// Paints QR modules as black squares on white, with the four-module quiet zone scanners need.
+fn draw_qr(ui: &mut egui::Ui, modules: &[Vec<bool>]) {
+    const MODULE: f32 = 4.0;
+    const QUIET: usize = 4;
+    let side = (modules.len() + 2 * QUIET) as f32 * MODULE;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    for (y, row) in modules.iter().enumerate() {
+        for (x, dark) in row.iter().enumerate() {
+            if *dark {
+                let min = rect.min + egui::vec2((x + QUIET) as f32 * MODULE, (y + QUIET) as f32 * MODULE);
+                painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(MODULE, MODULE)), 0.0, egui::Color32::BLACK);
+            }
+        }
+    }
+}
+
+fn draw_sparkline(ui: &mut egui::Ui, values: &[f32], max: f32) {
+    let size = egui::vec2(ui.available_width(), 48.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 2.0, ui.visuals().widgets.noninteractive.bg_stroke);
+
+    if values.len() < 2 {
+        return;
+    }
+    let step = rect.width() / (diagnostics::HISTORY_LEN - 1) as f32;
+    let offset = (diagnostics::HISTORY_LEN - values.len()) as f32 * step;
+    let points: Vec<egui::Pos2> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let x = rect.left() + offset + i as f32 * step;
+            let y = rect.bottom() - (v / max).clamp(0.0, 1.0) * rect.height();
+            egui::pos2(x, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE)));
+}
+
+fn device_icon(device: &BluetoothDevice) -> &'static str {
+    if device.has_audio_service() {
+        return "🎧";
+    }
+    match device.major_class() {
+        MajorClass::AudioVideo => "🎧",
+        MajorClass::Computer => "💻",
+        MajorClass::Peripheral => "⌨",
+        MajorClass::Wearable => "⌚",
+        _ => "📱",
+    }
+}
+
+/// Four-step signal meter. In `Symbols` mode unfilled bars are drawn as
+/// outlines so strength can be read without distinguishing colours.
+fn draw_rssi_bars(ui: &mut egui::Ui, rssi: i32, style: StatusStyle) {
+    // The core reports 0 when RSSI is unavailable
+    let level = match rssi {
+        0 => 0,
+        r if r >= -60 => 4,
+        r if r >= -70 => 3,
+        r if r >= -80 => 2,
+        r if r >= -90 => 1,
+        _ => 0,
+    };
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(22.0, 14.0), egui::Sense::hover());
+    let painter = ui.painter();
+    let text_color = ui.visuals().text_color();
+    let fill = match level {
+        4 | 3 => egui::Color32::GREEN,
+        2 => egui::Color32::YELLOW,
+        _ => egui::Color32::RED,
+    };
+
+    for i in 0..4 {
+        let height = rect.height() * (i + 1) as f32 / 4.0;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(rect.left() + i as f32 * 5.5, rect.bottom() - height),
+            egui::pos2(rect.left() + i as f32 * 5.5 + 4.0, rect.bottom()),
+        );
+        let filled = i < level;
+        match (style, filled) {
+            (StatusStyle::Color, true) => painter.rect_filled(bar, 0.0, fill),
+            (StatusStyle::Color, false) => painter.rect_filled(bar, 0.0, egui::Color32::DARK_GRAY),
+            (StatusStyle::Symbols, true) => painter.rect_filled(bar, 0.0, text_color),
+            (StatusStyle::Symbols, false) => {
+                painter.rect_stroke(bar, 0.0, egui::Stroke::new(1.0, text_color))
+            }
+        }
+    }
+}
+
+/// Draws `items` as rows with a drag handle, calling `add_row` to append extra
+/// widgets to each row. Returns `(from, to)` once a dragged row is dropped at a new index.
+fn reorderable_list(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    items: &[String],
+    mut add_row: impl FnMut(&mut egui::Ui, usize),
+) -> Option<(usize, usize)> {
+    let dragging: Option<usize> = ui.memory(|m| m.data.get_temp(id));
+    let mut row_rects = Vec::with_capacity(items.len());
+
+    for (i, item) in items.iter().enumerate() {
+        let row = ui.horizontal(|ui| {
+            let handle = ui
+                .add(egui::Label::new("☰").sense(egui::Sense::drag()))
+                .on_hover_cursor(egui::CursorIcon::Grab);
+            if handle.drag_started() {
+                ui.memory_mut(|m| m.data.insert_temp(id, i));
+            }
+
+            if dragging == Some(i) {
+                ui.label(egui::RichText::new(item).strong());
+            } else {
+                ui.label(item);
+            }
+            add_row(ui, i);
         });
+        row_rects.push(row.response.rect);
+    }
+
+    let from = dragging?;
+    let pointer = ui.input(|i| i.pointer.interact_pos());
+    // Insertion index: before the first row whose centre is below the pointer
+    let insert_at = pointer.map(|pos| {
+        row_rects
+            .iter()
+            .position(|r| pos.y < r.center().y)
+            .unwrap_or(items.len())
+    });
+
+    if let Some(index) = insert_at {
+        let y = row_rects
+            .get(index)
+            .map(|r| r.top())
+            .or_else(|| row_rects.last().map(|r| r.bottom()));
+        if let Some(y) = y {
+            ui.painter().hline(ui.max_rect().x_range(), y, ui.visuals().selection.stroke);
+        }
+    }
+
+    if !ui.input(|i| i.pointer.any_released()) {
+        return None;
     }
+    ui.memory_mut(|m| m.data.remove::<usize>(id));
+
+    let to = match insert_at? {
+        i if i > from => i - 1,
+        i => i,
+    };
+    (to != from).then_some((from, to))
 }
@@ -0,0 +1,68 @@
+use crate::bluetooth::{self, BluetoothEvent};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+
+/// One step of a handover recipe.
+///
+/// Multipoint headsets keep their link to the phone until something forces
+/// them to pick a host. What works differs per model: most give in to a plain
+/// disconnect/reconnect, some need a pause first, some have a vendor AT command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum HandoverStep {
+    Disconnect,
+    Connect,
+    Wait { ms: u32 },
+    /// Sent over RFCOMM; channel 0 finds the Serial Port service itself
+    AtCommand {
+        command: String,
+        #[serde(default)]
+        channel: u8,
+    },
+}
+
+impl HandoverStep {
+    pub fn label(&self) -> String {
+        match self {
+            HandoverStep::Disconnect => String::from("Disconnect"),
+            HandoverStep::Connect => String::from("Connect"),
+            HandoverStep::Wait { ms } => format!("Wait {} ms", ms),
+            HandoverStep::AtCommand { command, .. } => format!("Send {}", command),
+        }
+    }
+}
+
+/// The recipe a device starts with: drop the link, give the headset time to
+/// settle on the phone, then page it again so it takes this PC as the active host.
+pub fn default_recipe() -> Vec<HandoverStep> {
+    vec![HandoverStep::Disconnect, HandoverStep::Wait { ms: 1500 }, HandoverStep::Connect]
+}
+
+/// Runs `steps` on a worker thread and reports `BluetoothEvent::HandoverFinished`.
+/// Stops at the first step that fails; a failed disconnect is ignored since the
+/// headset may not be connected to this PC at all.
+pub fn run(address: u64, steps: Vec<HandoverStep>) {
+    println!("CLI: Action -> Handover of {:X} ({} steps)", address, steps.len());
+    thread::spawn(move || {
+        let result = steps.iter().try_for_each(|step| {
+            match step {
+                HandoverStep::Disconnect => {
+                    let _ = bluetooth::disconnect(address);
+                    Ok(())
+                }
+                HandoverStep::Connect => bluetooth::connect(address),
+                HandoverStep::Wait { ms } => {
+                    thread::sleep(Duration::from_millis(*ms as u64));
+                    Ok(())
+                }
+                HandoverStep::AtCommand { command, channel } => {
+                    bluetooth::send_at_command(address, *channel, command).map(|_| ())
+                }
+            }
+            .map_err(|e| format!("{}: {}", step.label(), e))
+        });
+        bluetooth::send_event(BluetoothEvent::HandoverFinished { address, result });
+    });
+}
@@ -0,0 +1,182 @@
+//! Developer HCI capture: packets from the core's ETW session written to a
+//! btsnoop file, which Wireshark opens as it is. Meant for attaching to bug
+//! reports against the core.
+
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::paths;
+use crate::trace::ffi_call;
+use log::{error, info};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// btsnoop datalink type for packets prefixed with their H4 indicator
+const DATALINK_H4: u32 = 1002;
+/// Microseconds from midnight, January 1st of year 0 to the Unix epoch; btsnoop's time base
+const EPOCH_OFFSET_US: u64 = 0x00dc_ddb3_0f2f_8000;
+const H4_COMMAND: u8 = 1;
+const H4_EVENT: u8 = 4;
+
+/// Captures live in the `captures` folder of the user data directory.
+pub fn captures_dir() -> PathBuf {
+    paths::user_file("captures")
+}
+
+// ---- btsnoop Writer ----
+
+pub struct HciPacket {
+    /// H4 indicator: 1 command, 2 ACL, 3 SCO, 4 event, 5 ISO
+    pub kind: u8,
+    pub received: bool,
+    pub data: Vec<u8>,
+    pub at: SystemTime,
+}
+
+/// Streams packets into a btsnoop version 1 file.
+pub struct BtsnoopWriter {
+    file: BufWriter<File>,
+}
+
+impl BtsnoopWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(b"btsnoop\0")?;
+        file.write_all(&1u32.to_be_bytes())?;
+        file.write_all(&DATALINK_H4.to_be_bytes())?;
+        Ok(BtsnoopWriter { file })
+    }
+
+    pub fn write_packet(&mut self, packet: &HciPacket) -> Result<()> {
+        // The H4 indicator is part of the record
+        let length = packet.data.len() as u32 + 1;
+        let mut flags = u32::from(packet.received);
+        if packet.kind == H4_COMMAND || packet.kind == H4_EVENT {
+            flags |= 2;
+        }
+        let since_unix = packet.at.duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+
+        let f = &mut self.file;
+        f.write_all(&length.to_be_bytes())?; // original length
+        f.write_all(&length.to_be_bytes())?; // included length
+        f.write_all(&flags.to_be_bytes())?;
+        f.write_all(&0u32.to_be_bytes())?; // cumulative drops
+        f.write_all(&(since_unix + EPOCH_OFFSET_US).to_be_bytes())?;
+        f.write_all(&[packet.kind])?;
+        f.write_all(&packet.data)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+// ---- Callback Plumbing ----
+
+enum CaptureMessage {
+    Packet(HciPacket),
+    Stop,
+}
+
+// Same pattern as the recorder's audio tap: the C callback only needs the Sender.
+lazy_static::lazy_static! {
+    static ref PACKET_SENDER: Mutex<Option<Sender<CaptureMessage>>> = Mutex::new(None);
+}
+
+/// Packets written by the current capture
+static PACKETS: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn on_hci_packet(packet_type: u8, received: bool, data: *const u8, length: u32) {
+    let data = if data.is_null() || length == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(data, length as usize) }.to_vec()
+    };
+    let packet = HciPacket { kind: packet_type, received, data, at: SystemTime::now() };
+    if let Ok(guard) = PACKET_SENDER.lock() {
+        if let Some(sender) = &*guard {
+            let _ = sender.send(CaptureMessage::Packet(packet));
+        }
+    }
+}
+
+// ---- Public API ----
+
+/// Handle to a running capture.
+pub struct HciCapture {
+    pub path: PathBuf,
+    pub started: Instant,
+    stop_tx: Sender<CaptureMessage>,
+}
+
+impl HciCapture {
+    /// Starts the core's capture and writes to `captures/hci-<timestamp>.btsnoop`.
+    pub fn start() -> Result<Self> {
+        let dir = captures_dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("hci-{}.btsnoop", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        let writer = BtsnoopWriter::create(&path)?;
+
+        let (tx, rx) = mpsc::channel();
+        *PACKET_SENDER.lock().unwrap() = Some(tx.clone());
+        PACKETS.store(0, Ordering::Relaxed);
+
+        println!("CLI: Action -> Start HCI Capture to {}", path.display());
+        if ffi_call!(bt_hci_capture_start(on_hci_packet as ffi::OnHciPacketCallback)) != ffi::FfiErrorCode::Success {
+            *PACKET_SENDER.lock().unwrap() = None;
+            let _ = fs::remove_file(&path);
+            return Err(AppError::bluetooth(&bluetooth::last_error()));
+        }
+
+        let writer_path = path.clone();
+        thread::spawn(move || {
+            let reason = run_writer(rx, writer);
+            bluetooth::send_event(BluetoothEvent::HciCaptureFinished {
+                path: writer_path.display().to_string(),
+                packets: PACKETS.load(Ordering::Relaxed),
+                reason,
+            });
+        });
+        Ok(HciCapture { path, started: Instant::now(), stop_tx: tx })
+    }
+
+    /// Stops the core's capture; the writer reports back with `HciCaptureFinished`.
+    pub fn stop(&self) {
+        println!("CLI: Action -> Stop HCI Capture");
+        ffi_call!(bt_hci_capture_stop());
+        *PACKET_SENDER.lock().unwrap() = None;
+        let _ = self.stop_tx.send(CaptureMessage::Stop);
+    }
+
+    pub fn packets(&self) -> u64 {
+        PACKETS.load(Ordering::Relaxed)
+    }
+}
+
+/// Writer thread body. Returns a human-readable reason the capture ended.
+fn run_writer(rx: Receiver<CaptureMessage>, mut writer: BtsnoopWriter) -> String {
+    let reason = loop {
+        match rx.recv() {
+            Ok(CaptureMessage::Packet(packet)) => {
+                if let Err(e) = writer.write_packet(&packet) {
+                    break format!("write failed: {}", e);
+                }
+                PACKETS.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(CaptureMessage::Stop) | Err(_) => break String::from("stopped"),
+        }
+    };
+    match writer.finish() {
+        Ok(()) => info!("HCI capture ended ({}, {} packets)", reason, PACKETS.load(Ordering::Relaxed)),
+        Err(e) => error!("Failed to finalize HCI capture: {}", e),
+    }
+    reason
+}
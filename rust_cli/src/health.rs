@@ -0,0 +1,213 @@
+use crate::error::Result;
+use crate::registry::{EventKind, Registry};
+use chrono::{Duration as Days, NaiveDate};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How often the GUI re-runs the analysis while it stays open
+pub const ANALYSIS_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Saved devices get at most one RSSI sample per interval
+pub const RSSI_SAMPLE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Fewer attempts than this in a week is too little to judge
+const MIN_ATTEMPTS: u32 = 5;
+const FAILURE_RATE_ALERT: f32 = 0.3;
+const MIN_RSSI_SAMPLES: u32 = 3;
+/// Drop in weekly mean RSSI (dB) worth mentioning
+const RSSI_DROP_ALERT: f32 = 10.0;
+/// Weekly mean below this counts as a poor link regardless of trend
+const WEAK_RSSI: f32 = -80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suggestion {
+    Repair,
+    ReplaceBattery,
+    MoveDongle,
+}
+
+impl Suggestion {
+    pub fn text(self) -> &'static str {
+        match self {
+            Suggestion::Repair => "Re-pairing it often helps.",
+            Suggestion::ReplaceBattery => "If it runs on batteries, they may be running low.",
+            Suggestion::MoveDongle => "Try moving the Bluetooth dongle closer, e.g. on a USB extension.",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthAlert {
+    pub address: u64,
+    /// What changed, e.g. "failing 40% of connects this week"
+    pub summary: String,
+    pub suggestion: Suggestion,
+}
+
+#[derive(Default)]
+struct Attempts {
+    connects: u32,
+    failures: u32,
+}
+
+impl Attempts {
+    fn total(&self) -> u32 {
+        self.connects + self.failures
+    }
+
+    fn failure_rate(&self) -> f32 {
+        if self.total() == 0 {
+            0.0
+        } else {
+            self.failures as f32 / self.total() as f32
+        }
+    }
+}
+
+/// Compares the seven days up to `today` with the seven before and returns at
+/// most one alert per device, connect failures taking precedence over signal.
+pub fn analyze(registry: &Registry, today: NaiveDate) -> Result<Vec<HealthAlert>> {
+    let end = today + Days::days(1);
+    let week_start = end - Days::days(7);
+    let previous_start = week_start - Days::days(7);
+
+    let this_week = attempts(registry, week_start, end)?;
+    let last_week = attempts(registry, previous_start, week_start)?;
+    let signal = registry.rssi_averages(week_start, end)?;
+    let previous_signal = registry.rssi_averages(previous_start, week_start)?;
+    let weak = |address: &u64| {
+        signal
+            .get(address)
+            .is_some_and(|(mean, count)| *count >= MIN_RSSI_SAMPLES && *mean < WEAK_RSSI)
+    };
+
+    let mut alerts = Vec::new();
+    for (address, now) in &this_week {
+        let rate = now.failure_rate();
+        let before = last_week.get(address).map_or(0.0, Attempts::failure_rate);
+        if now.total() < MIN_ATTEMPTS || rate < FAILURE_RATE_ALERT || rate <= before {
+            continue;
+        }
+        alerts.push(HealthAlert {
+            address: *address,
+            summary: format!("failing {:.0}% of connects this week (was {:.0}%)", rate * 100.0, before * 100.0),
+            suggestion: if weak(address) { Suggestion::MoveDongle } else { Suggestion::Repair },
+        });
+    }
+
+    for (address, (mean, count)) in &signal {
+        if *count < MIN_RSSI_SAMPLES || alerts.iter().any(|a| a.address == *address) {
+            continue;
+        }
+        let Some((before, _)) = previous_signal.get(address).filter(|(_, c)| *c >= MIN_RSSI_SAMPLES) else {
+            continue;
+        };
+        let drop = before - mean;
+        if drop < RSSI_DROP_ALERT {
+            continue;
+        }
+        alerts.push(HealthAlert {
+            address: *address,
+            summary: format!("signal {:.0} dB weaker than last week", drop),
+            suggestion: if *mean < WEAK_RSSI { Suggestion::MoveDongle } else { Suggestion::ReplaceBattery },
+        });
+    }
+
+    alerts.sort_by_key(|a| a.address);
+    Ok(alerts)
+}
+
+fn attempts(registry: &Registry, from: NaiveDate, to: NaiveDate) -> Result<HashMap<u64, Attempts>> {
+    let mut by_device: HashMap<u64, Attempts> = HashMap::new();
+    for event in registry.events_between(from, to)? {
+        let entry = by_device.entry(event.address).or_default();
+        match event.kind {
+            EventKind::Connected => entry.connects += 1,
+            EventKind::ConnectFailed => entry.failures += 1,
+            _ => {}
+        }
+    }
+    Ok(by_device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    const FAILING: u64 = 0xA1;
+    const FADING: u64 = 0xB2;
+    const WEAK_AND_FAILING: u64 = 0xC3;
+    const RARELY_USED: u64 = 0xD4;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 14).unwrap()
+    }
+
+    fn events(registry: &Registry, address: u64, kind: EventKind, at: &str, count: usize) {
+        for _ in 0..count {
+            registry.record_failure(address, kind, "").unwrap();
+            backdate(registry, "device_events", at);
+        }
+    }
+
+    fn samples(registry: &Registry, address: u64, rssi: i32, at: &str, count: usize) {
+        for _ in 0..count {
+            registry.record_rssi(address, rssi).unwrap();
+            backdate(registry, "rssi_samples", at);
+        }
+    }
+
+    /// Moves the row just recorded in `table` to `at`.
+    fn backdate(registry: &Registry, table: &str, at: &str) {
+        registry
+            .conn()
+            .execute(&format!("UPDATE {} SET at = ?1 WHERE rowid = last_insert_rowid()", table), params![at])
+            .unwrap();
+    }
+
+    #[test]
+    fn empty_registry_has_no_alerts() {
+        let registry = Registry::in_memory().unwrap();
+        assert!(analyze(&registry, today()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn alerts_on_failures_and_signal_drops() {
+        let registry = Registry::in_memory().unwrap();
+        let this_week = "2024-03-12 09:00:00";
+        let last_week = "2024-03-05 09:00:00";
+
+        events(&registry, FAILING, EventKind::Connected, last_week, 5);
+        events(&registry, FAILING, EventKind::Connected, this_week, 2);
+        events(&registry, FAILING, EventKind::ConnectFailed, this_week, 4);
+
+        samples(&registry, FADING, -50, last_week, 3);
+        samples(&registry, FADING, -65, this_week, 3);
+
+        events(&registry, WEAK_AND_FAILING, EventKind::ConnectFailed, this_week, 5);
+        samples(&registry, WEAK_AND_FAILING, -88, this_week, 3);
+
+        // Too few attempts to judge, however badly they went
+        events(&registry, RARELY_USED, EventKind::ConnectFailed, this_week, 3);
+        // Outside both weeks
+        events(&registry, RARELY_USED, EventKind::ConnectFailed, "2024-03-15 00:00:00", 10);
+
+        let alerts = analyze(&registry, today()).unwrap();
+        let found: Vec<(u64, Suggestion)> = alerts.iter().map(|a| (a.address, a.suggestion)).collect();
+        assert_eq!(
+            found,
+            [(FAILING, Suggestion::Repair), (FADING, Suggestion::ReplaceBattery), (WEAK_AND_FAILING, Suggestion::MoveDongle)]
+        );
+        assert_eq!(alerts[0].summary, "failing 67% of connects this week (was 0%)");
+        assert_eq!(alerts[1].summary, "signal 15 dB weaker than last week");
+    }
+
+    #[test]
+    fn no_alert_when_last_week_was_worse() {
+        let registry = Registry::in_memory().unwrap();
+        events(&registry, FAILING, EventKind::ConnectFailed, "2024-03-05 09:00:00", 6);
+        events(&registry, FAILING, EventKind::Connected, "2024-03-12 09:00:00", 3);
+        events(&registry, FAILING, EventKind::ConnectFailed, "2024-03-12 09:00:00", 3);
+        assert!(analyze(&registry, today()).unwrap().is_empty());
+    }
+}
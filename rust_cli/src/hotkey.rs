@@ -0,0 +1,166 @@
+#[cfg(windows)]
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use eframe::egui;
+use std::fmt;
+
+/// A key combination such as `Ctrl+Alt+B`. Keys are letters, digits, F1–F12 or Space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: egui::Key,
+}
+
+impl Hotkey {
+    /// Parses `Ctrl+Alt+B` style specs, case-insensitively. At least one of
+    /// Ctrl/Alt is required so the hotkey doesn't swallow normal typing.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut hotkey = Hotkey { ctrl: false, alt: false, shift: false, key: egui::Key::Space };
+        let mut key = None;
+        for part in spec.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => hotkey.ctrl = true,
+                "alt" => hotkey.alt = true,
+                "shift" => hotkey.shift = true,
+                other if key.is_none() => key = Some(parse_key(other)?),
+                _ => return None,
+            }
+        }
+        hotkey.key = key?;
+        (hotkey.ctrl || hotkey.alt).then_some(hotkey)
+    }
+
+    fn modifiers(&self) -> egui::Modifiers {
+        egui::Modifiers { alt: self.alt, ctrl: self.ctrl, shift: self.shift, ..Default::default() }
+    }
+
+    /// Consumes the combination if it was pressed this frame (used while the window has focus).
+    pub fn consume(&self, ctx: &egui::Context) -> bool {
+        ctx.input_mut(|i| i.consume_key(self.modifiers(), self.key))
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+")] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        f.write_str(self.key.name())
+    }
+}
+
+fn parse_key(name: &str) -> Option<egui::Key> {
+    use egui::Key::*;
+    const LETTERS: [egui::Key; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [egui::Key; 10] = [Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9];
+    const FUNCTION: [egui::Key; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    if name == "space" {
+        return Some(Space);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.as_str()) {
+        (Some(c @ 'a'..='z'), "") => Some(LETTERS[(c as u8 - b'a') as usize]),
+        (Some(c @ '0'..='9'), "") => Some(DIGITS[(c as u8 - b'0') as usize]),
+        (Some('f'), n) => n.parse::<usize>().ok().filter(|n| (1..=12).contains(n)).map(|n| FUNCTION[n - 1]),
+        _ => None,
+    }
+}
+
+/// Registers `hotkey` system-wide, replacing any previous registration. Presses
+/// arrive as `BluetoothEvent::QuickConnectRequested`. Fails if another
+/// application already owns the combination.
+#[cfg(windows)]
+pub fn register(hotkey: Hotkey) -> Result<()> {
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc;
+    use winapi::um::processthreadsapi::GetCurrentThreadId;
+    use winapi::um::winuser::{
+        GetMessageW, RegisterHotKey, UnregisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MSG, WM_HOTKEY,
+    };
+
+    unregister();
+
+    let mut modifiers = MOD_NOREPEAT as u32;
+    if hotkey.ctrl {
+        modifiers |= MOD_CONTROL as u32;
+    }
+    if hotkey.alt {
+        modifiers |= MOD_ALT as u32;
+    }
+    if hotkey.shift {
+        modifiers |= MOD_SHIFT as u32;
+    }
+    let vk = virtual_key(hotkey.key);
+
+    // WM_HOTKEY is posted to the registering thread, so it needs its own message loop
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let registered = unsafe { RegisterHotKey(std::ptr::null_mut(), 1, modifiers, vk) } != 0;
+        if registered {
+            HOTKEY_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::SeqCst);
+        }
+        let _ = tx.send(registered);
+        if !registered {
+            return;
+        }
+
+        let mut msg: MSG = Default::default();
+        // Returns 0 on WM_QUIT (posted by `unregister`) and -1 on error
+        while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+            if msg.message == WM_HOTKEY {
+                println!("CLI: Quick connect hotkey pressed");
+                bluetooth::send_event(BluetoothEvent::QuickConnectRequested);
+            }
+        }
+        unsafe { UnregisterHotKey(std::ptr::null_mut(), 1) };
+    });
+
+    match rx.recv() {
+        Ok(true) => Ok(()),
+        _ => Err(AppError::Unknown(String::from("The hotkey is already used by another application"))),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn register(_hotkey: Hotkey) -> Result<()> {
+    Err(AppError::Unknown(String::from("System-wide hotkeys are only supported on Windows")))
+}
+
+#[cfg(windows)]
+static HOTKEY_THREAD: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+/// Stops the hotkey thread, if any.
+#[cfg(windows)]
+pub fn unregister() {
+    use std::sync::atomic::Ordering;
+    use winapi::um::winuser::{PostThreadMessageW, WM_QUIT};
+
+    let thread = HOTKEY_THREAD.swap(0, Ordering::SeqCst);
+    if thread != 0 {
+        unsafe { PostThreadMessageW(thread, WM_QUIT, 0, 0) };
+    }
+}
+
+#[cfg(not(windows))]
+pub fn unregister() {}
+
+#[cfg(windows)]
+fn virtual_key(key: egui::Key) -> u32 {
+    use egui::Key::*;
+    let name = key.name();
+    match key {
+        Space => 0x20,
+        F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 => {
+            0x6F + name[1..].parse::<u32>().unwrap_or(1)
+        }
+        // Letters and digits share their ASCII codes
+        _ => name.chars().last().map_or(0, |c| c.to_ascii_uppercase() as u32),
+    }
+}
@@ -0,0 +1,129 @@
+//! LE Audio broadcasts (Auracast): sources found in extended advertising, and
+//! asking a capable sink to join one as its Broadcast Assistant.
+
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use std::ffi::CStr;
+use std::thread;
+use std::time::Instant;
+
+/// Broadcasts not announced for this long are dropped from the list
+pub const STALE_AFTER_SECS: u64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastQuality {
+    Unknown,
+    Standard,
+    High,
+}
+
+/// A broadcast source as last announced.
+#[derive(Debug, Clone)]
+pub struct BroadcastSource {
+    pub address: u64,
+    pub address_type: u8,
+    pub advertising_sid: u8,
+    pub broadcast_id: u32,
+    /// Broadcast Name; empty if the source doesn't advertise one
+    pub name: String,
+    pub rssi: i32,
+    pub encrypted: bool,
+    pub quality: BroadcastQuality,
+    pub seen: Instant,
+}
+
+impl BroadcastSource {
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("Broadcast {:06X}", self.broadcast_id)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    fn to_ffi(&self) -> ffi::BroadcastSource {
+        ffi::BroadcastSource {
+            address: self.address,
+            address_type: self.address_type,
+            advertising_sid: self.advertising_sid,
+            broadcast_id: self.broadcast_id,
+            name: std::ptr::null(),
+            rssi: self.rssi,
+            encrypted: self.encrypted,
+            quality: 0,
+        }
+    }
+}
+
+extern "C" fn on_broadcast_found(source: *const ffi::BroadcastSource) {
+    let Some(source) = (unsafe { source.as_ref() }) else {
+        return;
+    };
+    let name = if source.name.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(source.name) }.to_string_lossy().into_owned()
+    };
+    bluetooth::send_event(BluetoothEvent::BroadcastFound(BroadcastSource {
+        address: source.address,
+        address_type: source.address_type,
+        advertising_sid: source.advertising_sid,
+        broadcast_id: source.broadcast_id,
+        name,
+        rssi: source.rssi,
+        encrypted: source.encrypted,
+        quality: match source.quality {
+            1 => BroadcastQuality::Standard,
+            2 => BroadcastQuality::High,
+            _ => BroadcastQuality::Unknown,
+        },
+        seen: Instant::now(),
+    }));
+}
+
+/// The adapter can scan extended advertising, which broadcasts are announced in.
+pub fn supported() -> bool {
+    ffi_call!(bt_le_audio_supported())
+}
+
+/// Announcements arrive as `BluetoothEvent::BroadcastFound`, repeats included.
+pub fn start_scan() -> Result<()> {
+    println!("CLI: Action -> Start Broadcast Scan");
+    match ffi_call!(bt_broadcast_scan_start(on_broadcast_found as ffi::OnBroadcastFoundCallback)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+pub fn stop_scan() {
+    println!("CLI: Action -> Stop Broadcast Scan");
+    ffi_call!(bt_broadcast_scan_stop());
+}
+
+/// The Broadcast_Code for `text`: up to 16 bytes of UTF-8, zero padded.
+pub fn broadcast_code(text: &str) -> Option<[u8; 16]> {
+    if text.is_empty() || text.len() > 16 {
+        return None;
+    }
+    let mut code = [0u8; 16];
+    code[..text.len()].copy_from_slice(text.as_bytes());
+    Some(code)
+}
+
+/// Asks `sink` to join the broadcast in the background; the outcome arrives as
+/// `BluetoothEvent::BroadcastJoinFinished`.
+pub fn join(sink: u64, source: &BroadcastSource, code: Option<[u8; 16]>) {
+    println!("CLI: Action -> {:X} Join Broadcast {:06X}", sink, source.broadcast_id);
+    let source = source.clone();
+    thread::spawn(move || {
+        let (ffi_source, broadcast_id) = (source.to_ffi(), source.broadcast_id);
+        let code_ptr = code.as_ref().map_or(std::ptr::null(), |c| c.as_ptr());
+        let result = match ffi_call!(bt_broadcast_join(sink, &ffi_source, code_ptr)) {
+            ffi::FfiErrorCode::Success => Ok(()),
+            _ => Err(bluetooth::last_error()),
+        };
+        bluetooth::send_event(BluetoothEvent::BroadcastJoinFinished { sink, broadcast_id, result });
+    });
+}
@@ -1,16 +1,74 @@
 //#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Hide console in release
 
+mod activity;
+mod adapter;
+mod address_list;
+mod at_console;
+mod bond_backup;
+mod capabilities;
+mod card_layout;
+mod cli;
 mod error;
 mod ffi;
 mod bluetooth;
+mod device;
+mod device_store;
+#[cfg(feature = "gatt")]
+mod gatt;
+#[cfg(feature = "obex")]
+mod obex;
+mod names;
+mod operations;
+mod pan;
+mod paths;
+mod pbap;
+mod perf;
+mod plugins;
+mod policy;
+mod power;
+mod presence_lock;
+mod problem_report;
+mod purge;
+mod audio;
+mod backup;
+mod endpoints;
+mod recorder;
+mod diagnostics;
 mod config;
+mod config_service;
+mod config_schema;
+mod crash_guard;
 mod registry;
+mod report;
 mod gui;
+mod handover;
+mod le_audio;
+mod hci_snoop;
+mod health;
+mod hotkey;
+mod scan_progress;
+mod scan_watchdog;
+mod schedule;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod search;
+mod selftest;
+mod settings;
+mod share;
+mod status;
+mod shared_store;
+mod toast;
+mod trace;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
 
 use crate::error::{AppError, Result};
+use clap::Parser;
 use eframe::egui;
+use config_service::ConfigService;
 use gui::BluetoothApp;
-use log::{error, info, LevelFilter};
+use log::{error, info, warn, LevelFilter};
 
 fn setup_logging() -> Result<()> {
     // Configure logging
@@ -26,9 +84,19 @@ fn setup_logging() -> Result<()> {
     Ok(())
 }
 
-fn initialize_application() -> Result<()> {
+/// Loads the shared config. On failure the app runs on defaults and the error
+/// is handed to the GUI so settings stay read-only rather than overwriting the file.
+/// Safe mode takes the same path without touching Bluetooth or config.toml.
+fn initialize_application(safe_mode: bool) -> Result<(ConfigService, Option<AppError>)> {
     println!("CHECKING_RUST_MAIN_EXECUTION");
     info!("Starting RedTooth Manager...");
+    info!("Optional features: {}", capabilities::summary());
+
+    if safe_mode {
+        warn!("Starting in safe mode: Bluetooth, audio, plugins and settings are not loaded");
+        let reason = AppError::config("Safe mode; config.toml is left as it is");
+        return Ok((ConfigService::new(config::Config::default()), Some(reason)));
+    }
     
     // Initialize Bluetooth
     match bluetooth::init() {
@@ -40,18 +108,24 @@ fn initialize_application() -> Result<()> {
     }
     
     // Load configuration
-    match config::Config::load() {
-        Ok(config) => info!("Configuration loaded with {} devices", config.devices.len()),
-        Err(e) => error!("Failed to load configuration: {}", e),
-    }
+    let (config, config_error) = match ConfigService::load() {
+        Ok(service) => {
+            info!("Configuration loaded with {} devices", service.read(|c| c.devices.len()));
+            (service, None)
+        }
+        Err(e) => {
+            error!("Failed to load configuration: {}", e);
+            (ConfigService::new(config::Config::default()), Some(e))
+        }
+    };
     
     // Initialize registry
-    match registry::Registry::new() {
+    match registry::Registry::open(config.read(|c| c.registry.busy_timeout())) {
         Ok(_) => info!("Registry initialized successfully"),
         Err(e) => error!("Failed to initialize registry: {}", e),
     }
     
-    Ok(())
+    Ok((config, config_error))
 }
 
 fn main() -> Result<()> {
@@ -59,13 +133,51 @@ fn main() -> Result<()> {
     if let Err(e) = setup_logging() {
         eprintln!("Failed to setup logging: {}", e);
     }
+
+    let cli = cli::Cli::parse();
+
+    // Subcommands run headless and exit without touching Bluetooth
+    if let Some(command) = cli.command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     
+    #[cfg(feature = "tui")]
+    let tui = cli.tui;
+    #[cfg(not(feature = "tui"))]
+    let tui = false;
+    // Only GUI sessions count towards safe mode; that's where it can undo settings
+    let crashes = if tui { 0 } else { crash_guard::begin() };
+    let safe_mode = !tui && (cli.safe_mode || crashes >= crash_guard::SAFE_MODE_AFTER);
+
     // Initialize application components
-    if let Err(e) = initialize_application() {
-        error!("Application initialization failed: {}", e);
-        // Continue anyway - some components might still work
+    let (config, config_error) = match initialize_application(safe_mode) {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Application initialization failed: {}", e);
+            // Continue anyway - some components might still work
+            (ConfigService::new(config::Config::default()), Some(e))
+        }
+    };
+
+    let policy = config.policy();
+    if policy.is_active() {
+        info!("Managed policy in effect");
     }
+    bluetooth::set_blocked_devices(policy.blocked_addresses());
+    #[cfg(feature = "obex")]
+    obex::set_transfers_allowed(policy.allow_file_transfer);
     
+    if cli.kiosk {
+        info!("Kiosk mode: device actions and config changes are disabled");
+    }
+    #[cfg(feature = "tui")]
+    if tui {
+        return tui::run(config, cli.kiosk);
+    }
     info!("Starting GUI...");
     
     let options = eframe::NativeOptions {
@@ -79,13 +191,17 @@ fn main() -> Result<()> {
     eframe::run_native(
         "RedTooth Manager",
         options,
-        Box::new(|cc| {
+        Box::new(move |cc| {
             // Set up GUI context
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
-            Box::new(BluetoothApp::new(cc))
+            let safe_mode = safe_mode.then_some(crashes);
+            Box::new(BluetoothApp::new(cc, config, config_error, cli.kiosk, cli.profile, safe_mode))
         }),
     ).map_err(|e| {
         error!("GUI runtime error: {}", e);
         AppError::Gui(format!("GUI runtime error: {}", e))
-    })
+    })?;
+    // Surviving safe mode says nothing about the settings it didn't load
+    crash_guard::end_clean(!safe_mode);
+    Ok(())
 }
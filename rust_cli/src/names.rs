@@ -0,0 +1,105 @@
+//! Device names as displayed and searched. Names arrive as whatever bytes the
+//! device advertises: decomposed accents, emoji, bidi controls and right-to-left
+//! scripts that reorder the text around them. The raw name is kept on the
+//! device and shown in Details; everything else goes through here.
+
+use unicode_normalization::UnicodeNormalization;
+
+/// First Strong Isolate / Pop Directional Isolate
+const FSI: char = '\u{2068}';
+const PDI: char = '\u{2069}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameStyle {
+    pub strip_emoji: bool,
+    /// Wrap names containing right-to-left text in an isolate, so they can't
+    /// reorder the status or address printed after them
+    pub isolate_rtl: bool,
+}
+
+impl Default for NameStyle {
+    fn default() -> Self {
+        NameStyle { strip_emoji: false, isolate_rtl: true }
+    }
+}
+
+/// Embedding, override and isolate controls and directional marks; a name has
+/// no business changing the direction of what surrounds it.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Pictographs and the joiners, selectors and tags that build emoji sequences.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2300}'..='\u{23FF}'
+            | '\u{2600}'..='\u{27BF}'
+            | '\u{2B00}'..='\u{2BFF}'
+            | '\u{1F000}'..='\u{1FAFF}'
+            | '\u{200D}'
+            | '\u{20E3}'
+            | '\u{FE0E}'
+            | '\u{FE0F}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// Hebrew, Arabic, Syriac, Thaana, N'Ko and the other right-to-left blocks.
+fn is_rtl(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}' | '\u{10800}'..='\u{10FFF}' | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
+/// Control and bidi characters dropped, NFC, and whitespace runs collapsed.
+fn clean(raw: &str, strip_emoji: bool) -> String {
+    // Filtered first, so marks the dropped characters separated from their base still compose
+    let kept: String = raw
+        .chars()
+        .filter(|c| c.is_whitespace() || !(c.is_control() || is_bidi_control(*c) || strip_emoji && is_emoji(*c)))
+        .nfc()
+        .collect();
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The name to show. A name that is nothing but emoji keeps them, since an
+/// empty label helps nobody.
+pub fn display(raw: &str, style: NameStyle) -> String {
+    let mut name = clean(raw, style.strip_emoji);
+    if name.is_empty() && style.strip_emoji {
+        name = clean(raw, false);
+    }
+    if style.isolate_rtl && name.chars().any(is_rtl) {
+        format!("{}{}{}", FSI, name, PDI)
+    } else {
+        name
+    }
+}
+
+/// Form both a name and a query go through before matching, so "Café" typed
+/// with a precomposed é finds a device advertising e + combining accent.
+pub fn search_key(text: &str) -> String {
+    clean(text, false).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn displayed_names_are_stable(name: String, strip_emoji: bool) {
+            let style = NameStyle { strip_emoji, isolate_rtl: false };
+            let shown = display(&name, style);
+            let no_controls = !shown.chars().any(|c| c.is_control() || ('\u{2066}'..='\u{2069}').contains(&c));
+            prop_assert!(no_controls);
+            prop_assert_eq!(display(&shown, style), shown.clone());
+            if !strip_emoji {
+                prop_assert_eq!(search_key(&shown), search_key(&name));
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use log::error;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Incoming files are saved here unless the user picks another path.
+pub const RECEIVE_DIR: &str = "received";
+
+/// Cleared by a managed policy that disallows file transfer.
+static TRANSFERS_ALLOWED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_transfers_allowed(allowed: bool) {
+    TRANSFERS_ALLOWED.store(allowed, Ordering::SeqCst);
+}
+
+pub fn transfers_allowed() -> bool {
+    TRANSFERS_ALLOWED.load(Ordering::SeqCst)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+    Pending,
+    InProgress,
+    Complete,
+    Failed,
+    Cancelled,
+}
+
+impl TransferState {
+    fn from_raw(value: c_int) -> Self {
+        match value {
+            ffi::OBEX_PENDING => TransferState::Pending,
+            ffi::OBEX_IN_PROGRESS => TransferState::InProgress,
+            ffi::OBEX_COMPLETE => TransferState::Complete,
+            ffi::OBEX_CANCELLED => TransferState::Cancelled,
+            ffi::OBEX_FAILED => TransferState::Failed,
+            other => {
+                error!("Unknown OBEX transfer state {}, treating as failed", other);
+                TransferState::Failed
+            }
+        }
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(self, TransferState::Complete | TransferState::Failed | TransferState::Cancelled)
+    }
+}
+
+/// One Object Push transfer, outgoing or incoming, as tracked by the GUI.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub id: u32,
+    pub address: u64,
+    pub file_name: String,
+    pub incoming: bool,
+    pub state: TransferState,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+impl Transfer {
+    pub fn progress(&self) -> f32 {
+        if self.bytes_total == 0 {
+            0.0
+        } else {
+            self.bytes_done as f32 / self.bytes_total as f32
+        }
+    }
+}
+
+// ---- FFI Callbacks ----
+
+pub(crate) extern "C" fn on_progress(transfer_id: u32, state: c_int, bytes_done: u64, bytes_total: u64) {
+    let state = TransferState::from_raw(state);
+    if state.is_finished() {
+        println!("CLI: OBEX transfer {} {:?}", transfer_id, state);
+    }
+    bluetooth::send_event(BluetoothEvent::ObexProgress { id: transfer_id, state, bytes_done, bytes_total });
+}
+
+pub(crate) extern "C" fn on_incoming(transfer_id: u32, address: u64, file_name: *const c_char, size: u64) {
+    let file_name = unsafe {
+        if file_name.is_null() {
+            String::from("unnamed")
+        } else {
+            CStr::from_ptr(file_name).to_string_lossy().into_owned()
+        }
+    };
+
+    // CLI ECHO
+    println!("CLI: Incoming file '{}' ({} bytes) from {:X}", file_name, size, address);
+    if !transfers_allowed() || bluetooth::is_blocked(address) {
+        println!("CLI: Rejecting incoming file (blocked by policy)");
+        let _ = reject(transfer_id);
+        return;
+    }
+
+    bluetooth::send_event(BluetoothEvent::ObexIncoming(Transfer {
+        id: transfer_id,
+        address,
+        file_name,
+        incoming: true,
+        state: TransferState::Pending,
+        bytes_done: 0,
+        bytes_total: size,
+    }));
+}
+
+// ---- Public API ----
+
+pub fn send_file(address: u64, path: &Path) -> Result<Transfer> {
+    println!("CLI: Action -> Send {} to {:X}", path.display(), address);
+    if !transfers_allowed() {
+        return Err(AppError::bluetooth("File transfer is disabled by your organization's policy"));
+    }
+    let c_path = CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|_| AppError::bluetooth("File path contains a NUL byte"))?;
+    let mut id = 0;
+    match ffi_call!(bt_obex_send_file(address, c_path.as_ptr(), &mut id)) {
+        ffi::FfiErrorCode::Success => Ok(Transfer {
+            id,
+            address,
+            file_name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            incoming: false,
+            state: TransferState::Pending,
+            bytes_done: 0,
+            bytes_total: 0,
+        }),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+/// Accepts an incoming push, saving it under `RECEIVE_DIR`. The core doesn't
+/// receive pushes yet, so for now this only reports `NotSupported`.
+pub fn accept(transfer: &Transfer) -> Result<()> {
+    println!("CLI: Action -> Accept '{}'", transfer.file_name);
+    std::fs::create_dir_all(RECEIVE_DIR)?;
+    // Only keep the final path component; the name comes from the remote device
+    let name = Path::new(&transfer.file_name)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("transfer-{}", transfer.id));
+    let save_path = Path::new(RECEIVE_DIR).join(name);
+    let c_path = CString::new(save_path.to_string_lossy().as_bytes())
+        .map_err(|_| AppError::bluetooth("File name contains a NUL byte"))?;
+    match ffi_call!(bt_obex_accept(transfer.id, c_path.as_ptr())) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(bluetooth::last_error())),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+pub fn reject(transfer_id: u32) -> Result<()> {
+    println!("CLI: Action -> Reject transfer {}", transfer_id);
+    match ffi_call!(bt_obex_reject(transfer_id)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(bluetooth::last_error())),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+pub fn cancel(transfer_id: u32) -> Result<()> {
+    println!("CLI: Action -> Cancel transfer {}", transfer_id);
+    match ffi_call!(bt_obex_cancel(transfer_id)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
@@ -0,0 +1,117 @@
+//! Multi-step operations that are still running, kept on disk so that ones cut
+//! short by the app closing can be offered for resuming at the next start.
+
+use crate::paths;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Operation {
+    /// Connecting several devices at once; only the ones still to go are kept
+    ConnectDevices { addresses: Vec<u64> },
+    /// Connecting an audio profile's sinks to start it
+    StartProfile { name: String },
+}
+
+impl Operation {
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::ConnectDevices { addresses } => format!("Connecting {} device(s)", addresses.len()),
+            Operation::StartProfile { name } => format!("Starting audio profile '{}'", name),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Saved {
+    operations: Vec<Operation>,
+}
+
+fn path() -> PathBuf {
+    paths::user_file("pending_operations.toml")
+}
+
+/// Operations left unfinished by the last session. The file is removed, so
+/// they are offered once.
+pub fn take_interrupted() -> Vec<Operation> {
+    let Ok(text) = fs::read_to_string(path()) else {
+        return Vec::new();
+    };
+    let _ = fs::remove_file(path());
+    match toml::from_str::<Saved>(&text) {
+        Ok(saved) => saved.operations,
+        Err(e) => {
+            warn!("Ignoring unreadable pending operations: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// The running operations, written through to disk on every change.
+#[derive(Default)]
+pub struct OperationTracker {
+    running: Vec<Operation>,
+}
+
+impl OperationTracker {
+    /// Starts tracking `operation`. Devices join those already being connected;
+    /// a profile start replaces the one running.
+    pub fn begin(&mut self, operation: Operation) {
+        let same_kind = self.running.iter_mut().find(|op| std::mem::discriminant(*op) == std::mem::discriminant(&operation));
+        match (same_kind, operation) {
+            (Some(Operation::ConnectDevices { addresses }), Operation::ConnectDevices { addresses: more }) => {
+                for address in more {
+                    if !addresses.contains(&address) {
+                        addresses.push(address);
+                    }
+                }
+            }
+            (Some(running), operation) => *running = operation,
+            (None, operation) => self.running.push(operation),
+        }
+        self.save();
+    }
+
+    /// A device connected or gave up: it is no longer waited for.
+    pub fn device_done(&mut self, address: u64) {
+        let before = self.running.clone();
+        for op in &mut self.running {
+            if let Operation::ConnectDevices { addresses } = op {
+                addresses.retain(|a| *a != address);
+            }
+        }
+        self.running.retain(|op| !matches!(op, Operation::ConnectDevices { addresses } if addresses.is_empty()));
+        if self.running != before {
+            self.save();
+        }
+    }
+
+    /// The profile start finished, whether it succeeded or not.
+    pub fn profile_done(&mut self) {
+        let count = self.running.len();
+        self.running.retain(|op| !matches!(op, Operation::StartProfile { .. }));
+        if self.running.len() != count {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let result = if self.running.is_empty() {
+            match fs::remove_file(path()) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            }
+        } else {
+            toml::to_string(&Saved { operations: self.running.clone() })
+                .map_err(|e| e.to_string())
+                .and_then(|text| fs::write(path(), text).map_err(|e| e.to_string()))
+        };
+        match result {
+            Ok(()) => info!("{} operation(s) pending", self.running.len()),
+            Err(e) => warn!("Failed to record pending operations: {}", e),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::bluetooth;
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A tethered PAN link and its most recent throughput sample.
+#[derive(Debug, Clone)]
+pub struct PanLink {
+    pub connected: bool,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Bytes per second over the last poll interval
+    pub rx_rate: f64,
+    pub tx_rate: f64,
+    last_poll: Instant,
+}
+
+impl PanLink {
+    fn new() -> Self {
+        PanLink {
+            connected: true,
+            rx_bytes: 0,
+            tx_bytes: 0,
+            rx_rate: 0.0,
+            tx_rate: 0.0,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Refreshes counters and rates from the core, at most once per poll interval.
+    pub fn poll(&mut self, address: u64) {
+        let elapsed = self.last_poll.elapsed();
+        if elapsed < POLL_INTERVAL {
+            return;
+        }
+        let mut status = ffi::PanLinkStatus::default();
+        if ffi_call!(bt_pan_get_status(address, &mut status)) != ffi::FfiErrorCode::Success {
+            return;
+        }
+        let secs = elapsed.as_secs_f64();
+        self.rx_rate = status.rx_bytes.saturating_sub(self.rx_bytes) as f64 / secs;
+        self.tx_rate = status.tx_bytes.saturating_sub(self.tx_bytes) as f64 / secs;
+        self.rx_bytes = status.rx_bytes;
+        self.tx_bytes = status.tx_bytes;
+        self.connected = status.connected;
+        self.last_poll = Instant::now();
+    }
+}
+
+/// Human-readable byte rate, e.g. "1.2 MB/s".
+pub fn format_rate(bytes_per_sec: f64) -> String {
+    if bytes_per_sec >= 1_000_000.0 {
+        format!("{:.1} MB/s", bytes_per_sec / 1_000_000.0)
+    } else {
+        format!("{:.0} KB/s", bytes_per_sec / 1_000.0)
+    }
+}
+
+/// Whether the device advertises the NAP (internet sharing) service.
+pub fn has_nap(address: u64) -> bool {
+    ffi_call!(bt_pan_has_nap(address))
+}
+
+pub fn connect(address: u64) -> Result<PanLink> {
+    println!("CLI: Action -> Connect Network via {:X}", address);
+    match ffi_call!(bt_pan_connect(address)) {
+        ffi::FfiErrorCode::Success => Ok(PanLink::new()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+pub fn disconnect(address: u64) -> Result<()> {
+    println!("CLI: Action -> Disconnect Network via {:X}", address);
+    match ffi_call!(bt_pan_disconnect(address)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
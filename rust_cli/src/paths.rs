@@ -0,0 +1,59 @@
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+lazy_static::lazy_static! {
+    static ref USER_DIR: PathBuf = resolve_user_dir();
+}
+
+/// Per-user data (config, backups, registry, reports): `%APPDATA%\RedTooth` on
+/// Windows, `~/.config/redtooth` elsewhere. Falls back to the working directory
+/// if neither can be determined or created.
+pub fn user_dir() -> &'static Path {
+    &USER_DIR
+}
+
+fn resolve_user_dir() -> PathBuf {
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|d| PathBuf::from(d).join("RedTooth"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .map(|d| d.join("redtooth"))
+    };
+    match dir {
+        Some(dir) => match fs::create_dir_all(&dir) {
+            Ok(()) => dir,
+            Err(e) => {
+                warn!("Cannot create {:?} ({}); keeping data in the working directory", dir, e);
+                PathBuf::from(".")
+            }
+        },
+        None => PathBuf::from("."),
+    }
+}
+
+/// Machine-wide data shared by every user, next to the managed policy file:
+/// `%ProgramData%\RedTooth` on Windows, `/etc/redtooth` elsewhere.
+pub fn machine_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("RedTooth"))
+    } else {
+        Some(PathBuf::from("/etc/redtooth"))
+    }
+}
+
+/// Path of a per-user file or directory. Older versions kept everything in the
+/// working directory, so a leftover of the same name there is moved over once.
+pub fn user_file(name: &str) -> PathBuf {
+    let path = user_dir().join(name);
+    let legacy = Path::new(name);
+    if !path.exists() && legacy.exists() && user_dir() != Path::new(".") {
+        match fs::rename(legacy, &path) {
+            Ok(()) => info!("Moved {:?} to {:?}", legacy, path),
+            Err(e) => warn!("Failed to move {:?} to {:?}: {}", legacy, path, e),
+        }
+    }
+    path
+}
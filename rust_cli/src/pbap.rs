@@ -0,0 +1,273 @@
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Folder {
+    Contacts,
+    CallHistory,
+}
+
+impl Folder {
+    fn to_raw(self) -> c_int {
+        match self {
+            Folder::Contacts => ffi::PBAP_PHONEBOOK,
+            Folder::CallHistory => ffi::PBAP_CALL_HISTORY,
+        }
+    }
+
+    fn from_raw(value: c_int) -> Self {
+        if value == ffi::PBAP_CALL_HISTORY {
+            Folder::CallHistory
+        } else {
+            Folder::Contacts
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Received,
+    Dialed,
+    Missed,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Contact {
+    pub name: String,
+    pub numbers: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    pub name: String,
+    pub number: String,
+    pub kind: Option<CallKind>,
+    /// As sent by the phone, e.g. `20240131T081500`
+    pub time: String,
+}
+
+/// Contacts and call history pulled from one phone. Kept in memory only.
+#[derive(Debug, Clone, Default)]
+pub struct PhoneBook {
+    pub contacts: Vec<Contact>,
+    pub calls: Vec<CallRecord>,
+}
+
+// ---- vCard Parsing ----
+
+/// One vCard as (property name, parameters, value) triples.
+type VCard = Vec<(String, String, String)>;
+
+/// Splits a vCard 2.1/3.0 stream into cards, unfolding continuation lines and
+/// decoding QUOTED-PRINTABLE values.
+fn parse_vcards(text: &str) -> Vec<VCard> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let folded = line.strip_prefix(' ').or_else(|| line.strip_prefix('\t'));
+        match (lines.last_mut(), folded) {
+            // vCard 2.1 quoted-printable soft break: "=" at the end, continued unindented
+            (Some(prev), _) if is_soft_break(prev) => {
+                prev.pop();
+                prev.push_str(line);
+            }
+            (Some(prev), Some(rest)) => prev.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut cards = Vec::new();
+    let mut current: Option<VCard> = None;
+    for line in lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = key.split_once(';').unwrap_or((key, ""));
+        match (name.to_ascii_uppercase().as_str(), &mut current) {
+            ("BEGIN", _) => current = Some(Vec::new()),
+            ("END", Some(_)) => cards.extend(current.take()),
+            (name, Some(card)) => {
+                let params = params.to_ascii_uppercase();
+                let value = if params.contains("QUOTED-PRINTABLE") {
+                    decode_quoted_printable(value)
+                } else {
+                    value.to_string()
+                };
+                card.push((name.to_string(), params, value.trim().to_string()))
+            }
+            _ => {}
+        }
+    }
+    cards
+}
+
+fn is_soft_break(line: &str) -> bool {
+    line.ends_with('=')
+        && line
+            .split_once(':')
+            .is_some_and(|(key, _)| key.to_ascii_uppercase().contains("QUOTED-PRINTABLE"))
+}
+
+/// `=XX` escapes are bytes of the (UTF-8) value; anything else is literal.
+fn decode_quoted_printable(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'=' {
+            if let Some(byte) = value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn property<'a>(card: &'a VCard, name: &str) -> Option<&'a (String, String, String)> {
+    card.iter().find(|(n, _, _)| n == name)
+}
+
+/// Display name from FN, falling back to the structured N field.
+fn display_name(card: &VCard) -> String {
+    if let Some((_, _, name)) = property(card, "FN").filter(|(_, _, v)| !v.is_empty()) {
+        return name.clone();
+    }
+    property(card, "N")
+        .map(|(_, _, n)| {
+            // N is Family;Given;Middle;Prefix;Suffix
+            let parts: Vec<&str> = n.split(';').collect();
+            [parts.get(1), parts.first()]
+                .into_iter()
+                .flatten()
+                .filter(|p| !p.is_empty())
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default()
+}
+
+pub fn parse_contacts(text: &str) -> Vec<Contact> {
+    parse_vcards(text)
+        .iter()
+        .map(|card| Contact {
+            name: display_name(card),
+            numbers: card.iter().filter(|(n, _, _)| n == "TEL").map(|(_, _, v)| v.clone()).collect(),
+        })
+        // The phone's own card (0.vcf) is often empty
+        .filter(|c| !c.name.is_empty() || !c.numbers.is_empty())
+        .collect()
+}
+
+pub fn parse_call_history(text: &str) -> Vec<CallRecord> {
+    parse_vcards(text)
+        .iter()
+        .map(|card| {
+            let call = property(card, "X-IRMC-CALL-DATETIME");
+            let kind = call.and_then(|(_, params, _)| {
+                if params.contains("MISSED") {
+                    Some(CallKind::Missed)
+                } else if params.contains("RECEIVED") {
+                    Some(CallKind::Received)
+                } else if params.contains("DIALED") {
+                    Some(CallKind::Dialed)
+                } else {
+                    None
+                }
+            });
+            CallRecord {
+                name: display_name(card),
+                number: property(card, "TEL").map(|(_, _, v)| v.clone()).unwrap_or_default(),
+                kind,
+                time: call.map(|(_, _, v)| v.clone()).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+// ---- FFI ----
+
+extern "C" fn on_pbap_result(address: u64, folder: c_int, result: ffi::FfiErrorCode, vcards: *const c_char) {
+    let folder = Folder::from_raw(folder);
+    let data = if result == ffi::FfiErrorCode::Success && !vcards.is_null() {
+        Ok(unsafe { CStr::from_ptr(vcards) }.to_string_lossy().into_owned())
+    } else {
+        Err(format!("Phone book download failed ({:?})", result))
+    };
+
+    // CLI ECHO
+    println!("CLI: PBAP {:?} from {:X}: {}", folder, address, if data.is_ok() { "ok" } else { "failed" });
+
+    bluetooth::send_event(BluetoothEvent::PhoneBookPulled { address, folder, data });
+}
+
+/// Starts a read-only download; the result arrives as a `PhoneBookPulled` event.
+/// Callers are responsible for checking the user's per-device consent first.
+pub fn pull(address: u64, folder: Folder) -> Result<()> {
+    println!("CLI: Action -> Pull {:?} from {:X}", folder, address);
+    match ffi_call!(bt_pbap_pull(address, folder.to_raw(), on_pbap_result)) {
+        ffi::FfiErrorCode::Success => Ok(()),
+        ffi::FfiErrorCode::NotSupported => Err(AppError::NotSupported(bluetooth::last_error())),
+        _ => Err(AppError::bluetooth(&bluetooth::last_error())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folded_lines_are_joined() {
+        let text = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Alexandra\r\n  Montgomery\r\nTEL;TYPE=CELL:+44 20\r\n\t7946 0000\r\nEND:VCARD\r\n";
+        let contacts = parse_contacts(text);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].name, "Alexandra Montgomery");
+        assert_eq!(contacts[0].numbers, ["+44 207946 0000"]);
+    }
+
+    #[test]
+    fn quoted_printable_is_decoded() {
+        let text = "BEGIN:VCARD\nVERSION:2.1\nFN;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:J=C3=BCrgen M=C3=\n=BCller\nTEL:123\nEND:VCARD\n";
+        let contacts = parse_contacts(text);
+        assert_eq!(contacts[0].name, "Jürgen Müller");
+        assert_eq!(contacts[0].numbers, ["123"]);
+    }
+
+    #[test]
+    fn malformed_escapes_stay_literal() {
+        assert_eq!(decode_quoted_printable("100=ZZ="), "100=ZZ=");
+    }
+
+    #[test]
+    fn missing_fn_falls_back_to_n() {
+        let text = "BEGIN:VCARD\nVERSION:3.0\nN:Lovelace;Ada;;;\nTEL:555\nEND:VCARD\nBEGIN:VCARD\nVERSION:3.0\nFN:\nN:;;;;\nEND:VCARD\n";
+        let contacts = parse_contacts(text);
+        // The second card has no name and no number, like a phone's empty own card
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].name, "Ada Lovelace");
+    }
+
+    #[test]
+    fn call_history_reads_kind_and_time() {
+        let text = "\
+BEGIN:VCARD\nVERSION:2.1\nN:Doe;Jane\nTEL:+15550100\nX-IRMC-CALL-DATETIME;MISSED:20240131T081500\nEND:VCARD\n\
+BEGIN:VCARD\nVERSION:2.1\nFN:Bob\nTEL:+15550101\nX-IRMC-CALL-DATETIME;TYPE=DIALED:20240130T190000\nEND:VCARD\n\
+BEGIN:VCARD\nVERSION:2.1\nTEL:+15550102\nX-IRMC-CALL-DATETIME;RECEIVED:20240129T120000\nEND:VCARD\n\
+BEGIN:VCARD\nVERSION:2.1\nTEL:+15550103\nEND:VCARD\n";
+        let calls = parse_call_history(text);
+        assert_eq!(calls.len(), 4);
+        assert_eq!((calls[0].name.as_str(), calls[0].kind), ("Jane Doe", Some(CallKind::Missed)));
+        assert_eq!(calls[0].time, "20240131T081500");
+        assert_eq!(calls[1].kind, Some(CallKind::Dialed));
+        assert_eq!((calls[2].name.as_str(), calls[2].kind), ("", Some(CallKind::Received)));
+        assert_eq!(calls[2].number, "+15550102");
+        assert_eq!((calls[3].kind, calls[3].time.as_str()), (None, ""));
+    }
+}
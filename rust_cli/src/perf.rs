@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Frames averaged for the overlay (about two seconds at 60 Hz)
+const FRAME_WINDOW: usize = 120;
+
+/// GUI performance counters for the debug overlay.
+#[derive(Debug, Default)]
+pub struct PerfStats {
+    frame_times: VecDeque<Duration>,
+    /// Events drained from the Bluetooth channel on the latest frame
+    pub events_last_frame: usize,
+    /// Most events drained in a single frame this session
+    pub events_peak: usize,
+    cache_hits: u64,
+    cache_misses: u64,
+}
+
+impl PerfStats {
+    /// Time spent in one `update` call.
+    pub fn frame(&mut self, duration: Duration) {
+        if self.frame_times.len() == FRAME_WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(duration);
+    }
+
+    pub fn events(&mut self, count: usize) {
+        self.events_last_frame = count;
+        self.events_peak = self.events_peak.max(count);
+    }
+
+    /// A registry lookup answered from a GUI-side cache (`true`) or by a query.
+    pub fn cache_lookup(&mut self, hit: bool) {
+        if hit {
+            self.cache_hits += 1;
+        } else {
+            self.cache_misses += 1;
+        }
+    }
+
+    pub fn average_frame(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32
+    }
+
+    pub fn worst_frame(&self) -> Duration {
+        self.frame_times.iter().max().copied().unwrap_or_default()
+    }
+
+    /// Share of lookups served from cache; `None` before the first lookup.
+    pub fn cache_hit_rate(&self) -> Option<f32> {
+        let total = self.cache_hits + self.cache_misses;
+        (total > 0).then(|| self.cache_hits as f32 / total as f32)
+    }
+}
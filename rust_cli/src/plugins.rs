@@ -0,0 +1,215 @@
+use crate::bluetooth::{self, BluetoothDevice, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::paths;
+use eframe::egui;
+use log::{info, warn};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+mod sony;
+
+/// Something a plugin can do to a device, shown as a button in its Details window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginAction {
+    pub id: String,
+    pub label: String,
+    pub hint: Option<String>,
+}
+
+/// Noise control modes offered as a three-way switch on the device card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseMode {
+    NoiseCancelling,
+    Off,
+    Ambient,
+}
+
+impl NoiseMode {
+    pub const ALL: [NoiseMode; 3] = [NoiseMode::NoiseCancelling, NoiseMode::Off, NoiseMode::Ambient];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NoiseMode::NoiseCancelling => "ANC",
+            NoiseMode::Off => "Off",
+            NoiseMode::Ambient => "Ambient",
+        }
+    }
+}
+
+/// Vendor-specific features for the devices a plugin recognises.
+///
+/// Plugins are registered at startup, either built in (see `sony`) or loaded from manifests
+/// in the `plugins` directory. Actions that talk to the device must not block:
+/// do the work on a worker thread and report with `BluetoothEvent::PluginFinished`.
+pub trait VendorPlugin: Send {
+    fn name(&self) -> &str;
+
+    fn matches(&self, device: &BluetoothDevice) -> bool;
+
+    fn actions(&self, device: &BluetoothDevice) -> Vec<PluginAction>;
+
+    fn run(&self, device: &BluetoothDevice, action: &str) -> Result<()>;
+
+    /// Extra controls below the plugin's action buttons.
+    fn panel(&mut self, _ui: &mut egui::Ui, _device: &BluetoothDevice) {}
+
+    /// Whether `set_noise_control` works for this device.
+    fn supports_noise_control(&self, _device: &BluetoothDevice) -> bool {
+        false
+    }
+
+    /// Switches noise control; the outcome arrives as `BluetoothEvent::NoiseControlSet`.
+    fn set_noise_control(&self, _device: &BluetoothDevice, _mode: NoiseMode) -> Result<()> {
+        Err(AppError::Unknown(format!("{} has no noise control", self.name())))
+    }
+}
+
+/// Manufacturer part of an address (the OUI), as six hex digits.
+pub fn oui(address: u64) -> String {
+    format!("{:06X}", (address >> 24) & 0xFF_FFFF)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ManifestAction {
+    label: String,
+    command: String,
+    #[serde(default)]
+    channel: u8,
+    #[serde(default)]
+    hint: Option<String>,
+}
+
+/// A plugin described by a TOML file: which devices it is for and the AT
+/// commands behind each action. Covers vendors whose features are reachable
+/// over RFCOMM without writing any code.
+#[derive(Debug, Clone, Deserialize)]
+struct ManifestPlugin {
+    name: String,
+    /// Address prefixes, e.g. "AC800A"
+    #[serde(default)]
+    ouis: Vec<String>,
+    /// Case-insensitive substrings of the device name, e.g. "WH-1000"
+    #[serde(default)]
+    name_contains: Vec<String>,
+    #[serde(default)]
+    actions: Vec<ManifestAction>,
+}
+
+impl ManifestPlugin {
+    fn parse(text: &str) -> Result<Self> {
+        let plugin: ManifestPlugin = toml::from_str(text).map_err(|e| AppError::Parse(e.to_string()))?;
+        if plugin.name.trim().is_empty() {
+            return Err(AppError::Parse(String::from("plugin has no name")));
+        }
+        if plugin.ouis.is_empty() && plugin.name_contains.is_empty() {
+            return Err(AppError::Parse(String::from("plugin matches no devices; set ouis or name_contains")));
+        }
+        Ok(plugin)
+    }
+}
+
+impl VendorPlugin for ManifestPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, device: &BluetoothDevice) -> bool {
+        let oui = oui(device.address);
+        let name = device.name.to_lowercase();
+        self.ouis.iter().any(|o| o.replace([':', '-'], "").eq_ignore_ascii_case(&oui))
+            || self.name_contains.iter().any(|n| !n.is_empty() && name.contains(&n.to_lowercase()))
+    }
+
+    fn actions(&self, _device: &BluetoothDevice) -> Vec<PluginAction> {
+        self.actions
+            .iter()
+            .enumerate()
+            .map(|(i, a)| PluginAction { id: i.to_string(), label: a.label.clone(), hint: a.hint.clone() })
+            .collect()
+    }
+
+    fn run(&self, device: &BluetoothDevice, action: &str) -> Result<()> {
+        let manifest = action
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| self.actions.get(i))
+            .ok_or_else(|| AppError::Unknown(format!("{} has no action '{}'", self.name, action)))?;
+        let (address, plugin, label) = (device.address, self.name.clone(), manifest.label.clone());
+        let (command, channel) = (manifest.command.clone(), manifest.channel);
+        thread::spawn(move || {
+            let result = bluetooth::send_at_command(address, channel, &command).map_err(|e| e.to_string());
+            bluetooth::send_event(BluetoothEvent::PluginFinished { address, plugin, action: label, result });
+        });
+        Ok(())
+    }
+}
+
+/// Every plugin registered this session.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn VendorPlugin>>,
+}
+
+impl PluginRegistry {
+    /// Registers the built-in plugins, then loads manifests from the per-user and
+    /// machine-wide `plugins` directories.
+    /// Returns the registry and one message per manifest that failed to load.
+    pub fn load() -> (Self, Vec<String>) {
+        let mut registry = PluginRegistry::default();
+        registry.register(Box::new(sony::SonyMdr));
+        let mut errors = Vec::new();
+        let mut dirs = vec![paths::user_dir().join("plugins")];
+        if let Some(machine) = paths::machine_dir() {
+            dirs.push(machine.join("plugins"));
+        }
+        for dir in dirs {
+            registry.load_dir(&dir, &mut errors);
+        }
+        (registry, errors)
+    }
+
+    fn load_dir(&mut self, dir: &Path, errors: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            match fs::read_to_string(&path).map_err(AppError::from).and_then(|text| ManifestPlugin::parse(&text)) {
+                Ok(plugin) => {
+                    info!("Loaded plugin '{}' from {:?}", plugin.name, path);
+                    self.register(Box::new(plugin));
+                }
+                Err(e) => {
+                    warn!("Skipping plugin {:?}: {}", path, e);
+                    errors.push(format!("{}: {}", path.display(), e));
+                }
+            }
+        }
+    }
+
+    pub fn register(&mut self, plugin: Box<dyn VendorPlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(|p| p.name())
+    }
+
+    /// The first plugin that can switch noise control on `device`.
+    pub fn noise_control(&self, device: &BluetoothDevice) -> Option<&dyn VendorPlugin> {
+        self.plugins
+            .iter()
+            .find(|p| p.matches(device) && p.supports_noise_control(device))
+            .map(|p| p.as_ref())
+    }
+
+    /// Plugins that recognise `device`, in registration order.
+    pub fn matching(&mut self, device: &BluetoothDevice) -> impl Iterator<Item = &mut Box<dyn VendorPlugin>> {
+        let device = device.clone();
+        self.plugins.iter_mut().filter(move |p| p.matches(&device))
+    }
+}
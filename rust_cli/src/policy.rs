@@ -0,0 +1,177 @@
+use crate::address_list;
+use crate::config::{self, Config};
+use crate::error::{AppError, Result};
+use crate::paths;
+use log::{error, info, warn};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// Machine-wide restrictions deployed by IT, e.g.
+///
+/// ```toml
+/// allow_file_transfer = false
+/// blocked_devices = ["AA:BB:CC:DD:EE:FF"]
+///
+/// [settings.audio]
+/// recording_max_minutes = 0
+/// ```
+///
+/// Everything under `settings` is merged over the user's config and locked:
+/// edits from the GUI or CLI are refused or reverted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    pub allow_file_transfer: bool,
+    pub blocked_devices: Vec<String>,
+    pub settings: toml::value::Table,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy { allow_file_transfer: true, blocked_devices: Vec::new(), settings: toml::value::Table::new() }
+    }
+}
+
+/// `%ProgramData%\RedTooth\policy.toml` on Windows, `/etc/redtooth/policy.toml` elsewhere.
+pub fn policy_path() -> Option<PathBuf> {
+    paths::machine_dir().map(|dir| dir.join("policy.toml"))
+}
+
+impl Policy {
+    /// Loads the policy file if one is deployed. A broken file is logged and ignored
+    /// rather than keeping the app from starting.
+    pub fn load() -> Self {
+        let Some(path) = policy_path().filter(|p| p.exists()) else {
+            return Policy::default();
+        };
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| toml::from_str::<Policy>(&content).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(policy) => {
+                info!("Loaded policy from {:?}", path);
+                policy
+            }
+            Err(e) => {
+                error!("Ignoring unreadable policy file {:?}: {}", path, e);
+                Policy::default()
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.allow_file_transfer || !self.blocked_devices.is_empty() || !self.settings.is_empty()
+    }
+
+    /// True if `key` (dotted, e.g. `audio.keep_awake`) or anything under it is set by policy.
+    pub fn is_locked(&self, key: &str) -> bool {
+        let mut parts = key.split('.');
+        let Some(first) = parts.next() else {
+            return false;
+        };
+        parts.try_fold(self.settings.get(first), |value, part| Some(value?.get(part))).flatten().is_some()
+    }
+
+    pub fn blocked_addresses(&self) -> Vec<u64> {
+        self.blocked_devices
+            .iter()
+            .filter_map(|entry| {
+                let address = config::parse_address(entry);
+                if address.is_none() {
+                    warn!("Ignoring invalid blocked address '{}' in policy", entry);
+                }
+                address
+            })
+            .collect()
+    }
+
+    /// Rewrites `blocked_devices` in the deployed policy file, creating it if needed.
+    /// Other keys are kept, but comments in the file are lost. Needs write access
+    /// to the machine data directory.
+    pub fn save_blocked_devices(addresses: &[u64]) -> Result<PathBuf> {
+        let path = policy_path().ok_or_else(|| AppError::config("No machine data directory on this system"))?;
+        let mut root = match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str::<toml::Value>(&content)
+                .map_err(|e| AppError::config(&format!("Policy file is not valid TOML: {}", e)))?,
+            Err(_) => toml::Value::Table(toml::value::Table::new()),
+        };
+        let list = addresses.iter().map(|a| toml::Value::String(address_list::format_mac(*a))).collect();
+        if let Some(table) = root.as_table_mut() {
+            table.insert(String::from("blocked_devices"), toml::Value::Array(list));
+        }
+        let content = toml::to_string(&root).map_err(|e| AppError::config(&format!("Serialization failed: {}", e)))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, content)?;
+        info!("Wrote {} blocked devices to {:?}", addresses.len(), path);
+        Ok(path)
+    }
+
+    /// Overwrites locked settings in `config` with the policy's values.
+    pub fn enforce(&self, config: &mut Config) {
+        if self.settings.is_empty() {
+            return;
+        }
+        let Ok(mut value) = toml::Value::try_from(&*config) else {
+            return;
+        };
+        merge(&mut value, &self.settings);
+        match value.try_into::<Config>() {
+            Ok(enforced) => *config = enforced,
+            Err(e) => error!("Policy settings don't match the config schema, not applied: {}", e),
+        }
+    }
+
+    /// Undoes [`enforce`](Self::enforce) for saving: every locked setting in
+    /// `config` is put back to its value in `own` (the user's file), or removed
+    /// if the user never set it. The policy's values only live in memory.
+    pub fn withdraw(&self, config: &mut Config, own: &Config) {
+        if self.settings.is_empty() {
+            return;
+        }
+        let (Ok(mut value), Ok(own)) = (toml::Value::try_from(&*config), toml::Value::try_from(own)) else {
+            return;
+        };
+        unmerge(&mut value, Some(&own), &self.settings);
+        match value.try_into::<Config>() {
+            Ok(withdrawn) => *config = withdrawn,
+            Err(e) => error!("Couldn't separate policy settings from the config: {}", e),
+        }
+    }
+}
+
+fn unmerge(target: &mut toml::Value, own: Option<&toml::Value>, overlay: &toml::value::Table) {
+    let Some(table) = target.as_table_mut() else {
+        return;
+    };
+    for (key, value) in overlay {
+        let own_value = own.and_then(|o| o.get(key));
+        match (table.get_mut(key), value) {
+            (Some(existing @ toml::Value::Table(_)), toml::Value::Table(child)) => unmerge(existing, own_value, child),
+            _ => match own_value {
+                Some(v) => {
+                    table.insert(key.clone(), v.clone());
+                }
+                None => {
+                    table.remove(key);
+                }
+            },
+        }
+    }
+}
+
+fn merge(target: &mut toml::Value, overlay: &toml::value::Table) {
+    let Some(table) = target.as_table_mut() else {
+        return;
+    };
+    for (key, value) in overlay {
+        match (table.get_mut(key), value) {
+            (Some(existing @ toml::Value::Table(_)), toml::Value::Table(child)) => merge(existing, child),
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use log::{info, warn};
+
+/// Keeps the system from sleeping while held. Released on drop.
+///
+/// `SetThreadExecutionState` is per-thread, so the lock must be created and
+/// dropped on the same thread (the GUI thread).
+pub struct WakeLock {
+    /// False if the OS refused; kept anyway so callers don't retry every frame
+    held: bool,
+}
+
+impl WakeLock {
+    pub fn acquire() -> Self {
+        let held = set_sleep_inhibited(true);
+        if held {
+            info!("Wake lock acquired");
+        } else {
+            warn!("Failed to acquire wake lock; the system may sleep during streaming");
+        }
+        WakeLock { held }
+    }
+}
+
+impl Drop for WakeLock {
+    fn drop(&mut self) {
+        if self.held {
+            set_sleep_inhibited(false);
+            info!("Wake lock released");
+        }
+    }
+}
+
+#[cfg(windows)]
+fn set_sleep_inhibited(inhibit: bool) -> bool {
+    use winapi::um::winbase::SetThreadExecutionState;
+    use winapi::um::winnt::{ES_CONTINUOUS, ES_SYSTEM_REQUIRED};
+
+    let flags = if inhibit { ES_CONTINUOUS | ES_SYSTEM_REQUIRED } else { ES_CONTINUOUS };
+    unsafe { SetThreadExecutionState(flags) != 0 }
+}
+
+#[cfg(not(windows))]
+fn set_sleep_inhibited(_inhibit: bool) -> bool {
+    false
+}
+
+/// Locks the workstation, as Win+L does. False if Windows refused.
+#[cfg(windows)]
+pub fn lock_workstation() -> bool {
+    unsafe { winapi::um::winuser::LockWorkStation() != 0 }
+}
+
+#[cfg(not(windows))]
+pub fn lock_workstation() -> bool {
+    false
+}
+
+/// Turns the display on, showing the lock screen if the PC is locked.
+#[cfg(windows)]
+pub fn wake_display() -> bool {
+    use winapi::um::winbase::SetThreadExecutionState;
+    use winapi::um::winnt::ES_DISPLAY_REQUIRED;
+
+    // Without ES_CONTINUOUS this only resets the display idle timer once
+    unsafe { SetThreadExecutionState(ES_DISPLAY_REQUIRED) != 0 }
+}
+
+#[cfg(not(windows))]
+pub fn wake_display() -> bool {
+    false
+}
+
+/// True when running on battery, `None` if the power source can't be determined
+/// (desktops without a battery report AC).
+#[cfg(windows)]
+pub fn on_battery() -> Option<bool> {
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = Default::default();
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return None;
+    }
+    // ACLineStatus: 0 offline, 1 online, 255 unknown
+    match status.ACLineStatus {
+        0 => Some(true),
+        1 => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(windows))]
+pub fn on_battery() -> Option<bool> {
+    None
+}
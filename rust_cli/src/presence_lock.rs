@@ -0,0 +1,57 @@
+use crate::settings::PresenceLockSettings;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockAction {
+    Lock,
+    Wake,
+}
+
+/// Locks the PC when the user's phone leaves, judged by its signal in scan results.
+///
+/// The phone counts as near while it reports at or above the threshold. Weak
+/// reports and silence both count as away, so nothing happens until the phone
+/// has been seen near at least once; without a running scan the two can't be
+/// told apart, so the caller resets while scanning is off.
+#[derive(Debug, Default)]
+pub struct PresenceLock {
+    last_near: Option<Instant>,
+    locked: bool,
+}
+
+impl PresenceLock {
+    /// Feeds one scan result of the phone. Returns `Wake` when it comes back after a lock.
+    pub fn sighting(&mut self, rssi: i32, settings: &PresenceLockSettings) -> Option<LockAction> {
+        // The core reports 0 when RSSI is unavailable
+        if rssi == 0 || rssi < settings.min_rssi {
+            return None;
+        }
+        self.last_near = Some(Instant::now());
+        (std::mem::take(&mut self.locked) && settings.wake_on_return).then_some(LockAction::Wake)
+    }
+
+    /// Returns `Lock` once when the phone has been away for `away_secs`.
+    pub fn poll(&mut self, settings: &PresenceLockSettings) -> Option<LockAction> {
+        let near = self.last_near?;
+        if self.locked || near.elapsed() < Duration::from_secs(settings.away_secs as u64) {
+            return None;
+        }
+        self.locked = true;
+        Some(LockAction::Lock)
+    }
+
+    /// The phone has been heard near at least once, so its absence can lock the PC.
+    pub fn armed(&self) -> bool {
+        self.last_near.is_some()
+    }
+
+    /// Seconds until the PC locks if the phone stays away; `None` while it is near or unseen.
+    pub fn countdown(&self, settings: &PresenceLockSettings) -> Option<u64> {
+        let away = self.last_near?.elapsed().as_secs();
+        (!self.locked && away >= 3).then(|| (settings.away_secs as u64).saturating_sub(away))
+    }
+
+    pub fn reset(&mut self) {
+        *self = PresenceLock::default();
+    }
+}
@@ -0,0 +1,171 @@
+//! "Report problem": a GitHub issue prefilled with what a maintainer asks for
+//! first. Everything goes through `sanitize`, and the user sees and can edit the
+//! text before anything leaves the machine.
+
+use crate::adapter::Adapter;
+use crate::bluetooth;
+use crate::capabilities;
+use crate::ffi;
+use crate::paths;
+use crate::selftest::Check;
+use crate::trace;
+use chrono::{DateTime, Local};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::fmt::Write;
+
+pub const ISSUES_URL: &str = "https://github.com/cryogenicdeadfrost/Project_RedTooth/issues/new";
+/// GitHub rejects longer new-issue links; the full text goes to the clipboard instead
+const MAX_URL_LEN: usize = 7000;
+/// FFI calls included, counted back from the newest
+const TRACE_CALLS: usize = 25;
+const RECENT_ERRORS: usize = 10;
+
+/// What the GUI knows that the report can't look up itself.
+pub struct ReportContext<'a> {
+    pub adapter: Option<&'a Adapter>,
+    pub adapters: usize,
+    pub checks: Option<&'a [Check]>,
+    /// Newest last, as in the notification history
+    pub errors: Vec<(DateTime<Local>, String)>,
+}
+
+/// The issue body, already sanitized.
+pub fn build(context: &ReportContext) -> String {
+    let mut out = String::from("### What happened\n\n<!-- What did you do, and what did you expect instead? -->\n\n");
+
+    out.push_str("### Environment\n\n");
+    let _ = writeln!(out, "- RedTooth {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "- Core API v{}, app expects v{}", bluetooth::core_api_version(), ffi::BT_API_VERSION);
+    let _ = writeln!(out, "- {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(out, "- Features: {}", capabilities::summary());
+
+    out.push_str("\n### Adapter\n\n");
+    match context.adapter {
+        Some(adapter) => {
+            let _ = writeln!(out, "- {}, Bluetooth {}", adapter.manufacturer_label(), adapter.core_version());
+            let _ = writeln!(out, "- {}", adapter.firmware());
+        }
+        None => out.push_str("- Not reported\n"),
+    }
+    let _ = writeln!(out, "- Radio: {:?}, {} adapter(s)", bluetooth::radio_state(), context.adapters);
+
+    if let Some(failed) = context.checks.map(|c| c.iter().filter(|c| !c.passed).collect::<Vec<_>>()) {
+        if !failed.is_empty() {
+            out.push_str("\n### Failed self-test checks\n\n");
+            for check in failed {
+                let _ = writeln!(out, "- {}: {}", check.name, check.detail);
+            }
+        }
+    }
+
+    if !context.errors.is_empty() {
+        out.push_str("\n### Recent errors\n\n");
+        let skip = context.errors.len().saturating_sub(RECENT_ERRORS);
+        for (at, message) in &context.errors[skip..] {
+            let _ = writeln!(out, "- {} {}", at.format("%H:%M:%S"), message);
+        }
+    }
+
+    let entries = trace::entries();
+    if !entries.is_empty() {
+        out.push_str("\n### Core calls\n\n```\n");
+        for entry in &entries[entries.len().saturating_sub(TRACE_CALLS)..] {
+            let _ = writeln!(
+                out,
+                "{}{} {}({}) -> {} [{} µs]",
+                if entry.failed() { "! " } else { "  " },
+                entry.at.format("%H:%M:%S%.3f"),
+                entry.function,
+                entry.args.join(", "),
+                entry.result,
+                entry.duration.as_micros()
+            );
+        }
+        out.push_str("```\n");
+    }
+
+    sanitize(&out)
+}
+
+/// Masks device addresses and anything naming the user or the machine. Device
+/// names aren't recognisable as such and are left for the user to check.
+pub fn sanitize(text: &str) -> String {
+    let mut text = mask_addresses(text);
+    if let Some(dir) = paths::user_dir().to_str().filter(|d| d.len() > 1) {
+        text = text.replace(dir, "<user dir>");
+    }
+    for var in ["USERPROFILE", "HOME"] {
+        if let Some(home) = std::env::var(var).ok().filter(|h| h.len() > 1) {
+            text = text.replace(&home, "<home>");
+        }
+    }
+    for (var, placeholder) in [("COMPUTERNAME", "<computer>"), ("USERNAME", "<user>"), ("USER", "<user>")] {
+        // Short names would also mask unrelated words
+        if let Some(value) = std::env::var(var).ok().filter(|v| v.len() >= 3) {
+            text = text.replace(&value, placeholder);
+        }
+    }
+    text
+}
+
+/// Replaces `AABBCCDDEEFF`, `AA:BB:CC:DD:EE:FF` and the trace's unpadded `{:X}`
+/// form with `<address>`. Only whole words are masked, and they need a digit, so
+/// ordinary words and identifiers survive.
+fn mask_addresses(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            word.push(c);
+        } else {
+            mask_word(&word, &mut out);
+            word.clear();
+            out.push(c);
+        }
+    }
+    mask_word(&word, &mut out);
+    out
+}
+
+fn mask_word(word: &str, out: &mut String) {
+    // "AABBCCDDEEFF:" at the end of an error message's subject
+    let core = word.trim_matches(':');
+    let digits = core.chars().filter(|c| *c != ':').count();
+    let colons = core.len() - digits;
+    let address = (9..=12).contains(&digits)
+        && core.chars().all(|c| c.is_ascii_hexdigit() || c == ':')
+        && core.chars().any(|c| c.is_ascii_digit())
+        && (colons == 0 || (colons == 5 && digits == 12));
+    if address {
+        let start = word.len() - word.trim_start_matches(':').len();
+        out.push_str(&word[..start]);
+        out.push_str("<address>");
+        out.push_str(&word[start + core.len()..]);
+    } else {
+        out.push_str(word);
+    }
+}
+
+/// New-issue link with title and body filled in. Returns whether the body had to
+/// be shortened to fit.
+pub fn issue_url(title: &str, body: &str) -> (String, bool) {
+    let prefix = format!("{}?title={}&body=", ISSUES_URL, utf8_percent_encode(title, NON_ALPHANUMERIC));
+    let full = utf8_percent_encode(body, NON_ALPHANUMERIC).to_string();
+    if prefix.len() + full.len() <= MAX_URL_LEN {
+        return (format!("{}{}", prefix, full), false);
+    }
+
+    const NOTE: &str = "\n\n_Shortened to fit the link; the full report was copied to the clipboard._";
+    let mut encoded = String::new();
+    let budget = MAX_URL_LEN - prefix.len() - utf8_percent_encode(NOTE, NON_ALPHANUMERIC).to_string().len();
+    for c in body.chars() {
+        let mut buf = [0u8; 4];
+        let piece = utf8_percent_encode(c.encode_utf8(&mut buf), NON_ALPHANUMERIC).to_string();
+        if encoded.len() + piece.len() > budget {
+            break;
+        }
+        encoded.push_str(&piece);
+    }
+    encoded.extend(utf8_percent_encode(NOTE, NON_ALPHANUMERIC));
+    (format!("{}{}", prefix, encoded), true)
+}
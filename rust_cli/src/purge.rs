@@ -0,0 +1,104 @@
+use crate::backup;
+use crate::config::Config;
+use crate::paths;
+use crate::policy::Policy;
+use crate::shared_store::{self, SharedStore};
+use log::{info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Per-user files holding settings; kept when purging with `keep_config`
+const CONFIG_FILES: &[&str] = &["config.toml", "config_backups", "config_known_good.toml"];
+/// Other per-user data: device history, notes, presence and usage data
+const USER_DATA: &[&str] = &["registry.db", "registry.db-journal", "registry.db.bak", "backups", "reports", "session.toml", "pending_operations.toml"];
+/// Written to the working directory: core debug logs, GATT logs, history exports
+const WORKING_DIR_DATA: &[&str] = &["bt_debug_mgr.txt", "bt_debug_mgr_v2.txt", "gatt_logs", "device_export.csv"];
+
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    pub removed: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+    /// Devices this user had published to the machine-wide store
+    pub shared_removed: usize,
+}
+
+/// Existing files and folders a purge would delete. Recordings and received
+/// files are the user's own documents and are left alone. Backup zips hold a
+/// copy of the registry, so those in a custom backup folder go too, but not
+/// the folder or anything else in it.
+pub fn targets(keep_config: bool) -> Vec<PathBuf> {
+    let user_dir = paths::user_dir();
+    let mut names: Vec<&str> = USER_DATA.to_vec();
+    if !keep_config {
+        names.extend(CONFIG_FILES);
+    }
+    names
+        .into_iter()
+        .map(|name| user_dir.join(name))
+        .chain(WORKING_DIR_DATA.iter().map(PathBuf::from))
+        .filter(|path| path.exists())
+        .chain(custom_backups())
+        .collect()
+}
+
+/// RedTooth's zips in the configured backup folder, if it isn't the default
+/// `backups` already listed in `USER_DATA`.
+pub fn custom_backups() -> Vec<PathBuf> {
+    let mut config = Config::load().unwrap_or_default();
+    Policy::load().enforce(&mut config);
+    let dir = backup::folder(&config.backup);
+    if dir == paths::user_file("backups") {
+        return Vec::new();
+    }
+    backup::list(&dir)
+}
+
+/// Deletes everything in `targets`, overwriting file contents with zeros first,
+/// and withdraws the user's devices from the shared store. Overwriting can't
+/// guarantee the old blocks are gone on SSDs or copy-on-write file systems.
+///
+/// The registry must be closed first; Windows refuses to delete open files.
+pub fn purge(keep_config: bool) -> PurgeReport {
+    let mut report = PurgeReport::default();
+    for path in targets(keep_config) {
+        match shred(&path) {
+            Ok(()) => {
+                info!("Purged {:?}", path);
+                report.removed.push(path);
+            }
+            Err(e) => {
+                warn!("Failed to purge {:?}: {}", path, e);
+                report.failed.push((path, e.to_string()));
+            }
+        }
+    }
+    if shared_store::exists() {
+        match SharedStore::open().and_then(|store| store.remove_mine()) {
+            Ok(count) => report.shared_removed = count,
+            Err(e) => report.failed.push((PathBuf::from("shared_devices.db"), e.to_string())),
+        }
+    }
+    report
+}
+
+fn shred(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            shred(&entry?.path())?;
+        }
+        return fs::remove_dir(path);
+    }
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 64 * 1024];
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
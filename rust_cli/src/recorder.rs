@@ -0,0 +1,292 @@
+use crate::audio;
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::error::{AppError, Result};
+use crate::ffi;
+use crate::trace::ffi_call;
+use log::{error, info, warn};
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Recording stops once free space on the target drive falls below this.
+const MIN_FREE_BYTES: u64 = 500 * 1024 * 1024;
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+pub const RECORDINGS_DIR: &str = "recordings";
+
+// ---- WAV Writer ----
+
+/// Streams 16-bit PCM into a RIFF/WAVE file, patching the size fields on `finish`.
+pub struct WavWriter {
+    file: BufWriter<File>,
+    path: PathBuf,
+    channels: u16,
+    sample_rate: u32,
+    data_bytes: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, channels: u16, sample_rate: u32) -> Result<Self> {
+        let mut writer = WavWriter {
+            file: BufWriter::new(File::create(path)?),
+            path: path.to_path_buf(),
+            channels,
+            sample_rate,
+            data_bytes: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let block_align = self.channels * 2;
+        let byte_rate = self.sample_rate * block_align as u32;
+        let f = &mut self.file;
+        f.write_all(b"RIFF")?;
+        f.write_all(&(36 + self.data_bytes).to_le_bytes())?;
+        f.write_all(b"WAVEfmt ")?;
+        f.write_all(&16u32.to_le_bytes())?; // fmt chunk size
+        f.write_all(&1u16.to_le_bytes())?; // PCM
+        f.write_all(&self.channels.to_le_bytes())?;
+        f.write_all(&self.sample_rate.to_le_bytes())?;
+        f.write_all(&byte_rate.to_le_bytes())?;
+        f.write_all(&block_align.to_le_bytes())?;
+        f.write_all(&16u16.to_le_bytes())?; // bits per sample
+        f.write_all(b"data")?;
+        f.write_all(&self.data_bytes.to_le_bytes())?;
+        Ok(())
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<()> {
+        let bytes = (samples.len() * 2) as u32;
+        // RIFF sizes are 32-bit; refuse to wrap into a corrupt file
+        if self.data_bytes.checked_add(bytes + 36).is_none() {
+            return Err(AppError::audio("WAV file size limit reached"));
+        }
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_bytes += bytes;
+        Ok(())
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames = self.data_bytes as u64 / (self.channels as u64 * 2);
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.file.flush()?;
+        Ok(self.path)
+    }
+}
+
+// ---- Tap Plumbing ----
+
+struct TapChunk {
+    samples: Vec<i16>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+enum RecorderMessage {
+    Chunk(TapChunk),
+    Stop,
+}
+
+// Same pattern as the Bluetooth event sender: the C callback only needs the Sender.
+lazy_static::lazy_static! {
+    static ref TAP_SENDER: Mutex<Option<Sender<RecorderMessage>>> = Mutex::new(None);
+}
+
+extern "C" fn on_audio_tap(samples: *const i16, frames: u32, channels: u16, sample_rate: u32) {
+    if samples.is_null() || frames == 0 || channels == 0 {
+        return;
+    }
+    let len = frames as usize * channels as usize;
+    let samples = unsafe { std::slice::from_raw_parts(samples, len) }.to_vec();
+
+    if let Ok(guard) = TAP_SENDER.lock() {
+        if let Some(sender) = &*guard {
+            let _ = sender.send(RecorderMessage::Chunk(TapChunk { samples, channels, sample_rate }));
+        }
+    }
+}
+
+/// Free bytes available to the user on the volume holding `dir`, where the OS can tell us.
+#[cfg(windows)]
+pub fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::shared::ntdef::ULARGE_INTEGER;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut free: ULARGE_INTEGER = Default::default();
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, std::ptr::null_mut(), std::ptr::null_mut())
+    };
+    if ok == 0 {
+        None
+    } else {
+        Some(unsafe { *free.QuadPart() })
+    }
+}
+
+#[cfg(not(windows))]
+pub fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+// ---- Public API ----
+
+/// Whether the core can tap the broadcast mix at all.
+pub fn supported() -> bool {
+    ffi_call!(audio_tap_supported())
+}
+
+/// Handle to an in-progress recording. Dropping it stops the recording as `stop` does.
+pub struct Recording {
+    pub path: PathBuf,
+    pub started: Instant,
+    stop_tx: Sender<RecorderMessage>,
+}
+
+impl Recording {
+    /// Installs the audio tap and starts writing to `<dir>/broadcast-<timestamp>.wav`.
+    /// `max_duration` of `None` records until stopped or the disk fills up.
+    pub fn start(dir: &Path, max_duration: Option<Duration>) -> Result<Self> {
+        if !supported() {
+            return Err(AppError::NotSupported("Recording the broadcast is not supported yet".into()));
+        }
+        fs::create_dir_all(dir)?;
+        if available_space(dir).is_some_and(|free| free < MIN_FREE_BYTES) {
+            return Err(AppError::audio("Not enough free disk space to record"));
+        }
+
+        let path = dir.join(format!(
+            "broadcast-{}.wav",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let (tx, rx) = mpsc::channel();
+        {
+            let mut guard = TAP_SENDER.lock().unwrap();
+            *guard = Some(tx.clone());
+        }
+
+        // Chunks queue in the channel until the writer thread starts
+        match ffi_call!(audio_set_tap(Some(on_audio_tap as ffi::OnAudioTapCallback))) {
+            ffi::FfiErrorCode::Success => {}
+            code => {
+                if let Ok(mut guard) = TAP_SENDER.lock() {
+                    *guard = None;
+                }
+                return Err(match code {
+                    ffi::FfiErrorCode::NotSupported => AppError::NotSupported(audio::last_error()),
+                    _ => AppError::audio("Failed to install audio tap"),
+                });
+            }
+        }
+
+        let writer_path = path.clone();
+        let writer_dir = dir.to_path_buf();
+        thread::spawn(move || {
+            let result = run_writer(rx, &writer_path, &writer_dir, max_duration);
+            uninstall_tap();
+            bluetooth::send_event(BluetoothEvent::RecordingFinished {
+                path: writer_path.display().to_string(),
+                result,
+            });
+        });
+
+        println!("CLI: Recording broadcast to {}", path.display());
+        Ok(Recording { path, started: Instant::now(), stop_tx: tx })
+    }
+
+    pub fn stop(&self) {
+        println!("CLI: Action -> Stop Recording");
+        let _ = self.stop_tx.send(RecorderMessage::Stop);
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        // The tap's Sender clone would otherwise keep the writer waiting for chunks
+        uninstall_tap();
+        let _ = self.stop_tx.send(RecorderMessage::Stop);
+    }
+}
+
+fn uninstall_tap() {
+    ffi_call!(audio_set_tap(None));
+    if let Ok(mut guard) = TAP_SENDER.lock() {
+        *guard = None;
+    }
+}
+
+/// Writer thread body. Returns a human-readable reason the recording ended, or
+/// why no file was saved.
+fn run_writer(
+    rx: Receiver<RecorderMessage>,
+    path: &Path,
+    dir: &Path,
+    max_duration: Option<Duration>,
+) -> std::result::Result<String, String> {
+    let mut writer: Option<WavWriter> = None;
+    let mut last_disk_check = Instant::now();
+
+    let reason = loop {
+        let chunk = match rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(RecorderMessage::Chunk(chunk)) => chunk,
+            Ok(RecorderMessage::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                break "stopped".to_string();
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+        };
+
+        if writer.is_none() {
+            match WavWriter::create(path, chunk.channels, chunk.sample_rate) {
+                Ok(w) => writer = Some(w),
+                Err(e) => break format!("could not create file: {}", e),
+            }
+        }
+        let Some(w) = writer.as_mut() else {
+            break "writer unavailable".to_string();
+        };
+        if w.channels != chunk.channels || w.sample_rate != chunk.sample_rate {
+            warn!("Mix format changed mid-recording; dropping chunk");
+            continue;
+        }
+        if let Err(e) = w.write_samples(&chunk.samples) {
+            break format!("write failed: {}", e);
+        }
+        if max_duration.is_some_and(|max| w.duration() >= max) {
+            break "duration limit reached".to_string();
+        }
+        if last_disk_check.elapsed() >= DISK_CHECK_INTERVAL {
+            last_disk_check = Instant::now();
+            if available_space(dir).is_some_and(|free| free < MIN_FREE_BYTES) {
+                break "low disk space".to_string();
+            }
+        }
+    };
+
+    match writer.map(WavWriter::finish) {
+        Some(Ok(path)) => {
+            info!("Recording saved to {:?} ({})", path, reason);
+            Ok(reason)
+        }
+        Some(Err(e)) => {
+            error!("Failed to finalize recording: {}", e);
+            Err(format!("could not finish the file: {}", e))
+        }
+        None => {
+            info!("Recording ended before any audio arrived ({})", reason);
+            Err(format!("no audio was recorded ({})", reason))
+        }
+    }
+}
@@ -1,25 +1,183 @@
+use crate::adapter::Adapter;
+use crate::bluetooth;
 use crate::error::{AppError, Result};
-use rusqlite::{params, Connection};
-use std::path::Path;
+use crate::paths;
+use rusqlite::{params, Connection, Params};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use chrono::{NaiveDate, NaiveDateTime};
 use log::{info, warn, error};
 
+mod row;
+
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+/// The adapter events are attributed to: the one the device was connected through
+/// (`?4`) if it had a preference, else the one most recently recorded by `record_adapter`
+const EVENT_ADAPTER: &str = "COALESCE(?4, (SELECT address FROM adapters ORDER BY last_seen DESC LIMIT 1))";
+const OPEN_ATTEMPTS: u32 = 3;
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(250);
+/// How often raw RSSI samples are rolled up into hourly rows
+pub const ROLLUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Raw RSSI samples younger than this are kept as they are
+const RAW_RSSI_HOURS: u32 = 24;
+
+/// What happened in a `device_events` row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Connected,
+    ConnectFailed,
+    Disconnected,
+    DisconnectFailed,
+    /// Audio broadcast to the device; the row carries the duration
+    AudioStreamed,
+    /// Unknown device lingered in the watched area; the row carries how long
+    WatchAlert,
+}
+
+impl EventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Connected => "connected",
+            EventKind::ConnectFailed => "connect_failed",
+            EventKind::Disconnected => "disconnected",
+            EventKind::DisconnectFailed => "disconnect_failed",
+            EventKind::AudioStreamed => "audio_streamed",
+            EventKind::WatchAlert => "watch_alert",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "connected" => Some(EventKind::Connected),
+            "connect_failed" => Some(EventKind::ConnectFailed),
+            "disconnected" => Some(EventKind::Disconnected),
+            "disconnect_failed" => Some(EventKind::DisconnectFailed),
+            "audio_streamed" => Some(EventKind::AudioStreamed),
+            "watch_alert" => Some(EventKind::WatchAlert),
+            _ => None,
+        }
+    }
+}
+
+/// One `device_history` row, as read by `Registry::query`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceSummary {
+    pub address: u64,
+    pub name: String,
+    pub last_seen: String,
+    pub connection_count: i32,
+}
+
+/// Connect attempts of one device through one local adapter, from `Registry::adapter_reliability`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdapterReliability {
+    pub adapter: u64,
+    pub adapter_name: Option<String>,
+    pub connects: u32,
+    pub failures: u32,
+}
+
+impl AdapterReliability {
+    pub fn success_rate(&self) -> f32 {
+        let attempts = self.connects + self.failures;
+        if attempts == 0 {
+            0.0
+        } else {
+            self.connects as f32 / attempts as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceEvent {
+    pub address: u64,
+    pub kind: EventKind,
+    pub duration_secs: u64,
+    /// Why an operation failed, when known
+    pub detail: Option<String>,
+    /// UTC
+    pub at: NaiveDateTime,
+    /// Address of the local adapter in use at the time; unknown for older rows
+    pub adapter: Option<u64>,
+}
+
+/// Tables shown with their row counts in the Data settings
+const TABLES: [&str; 7] =
+    ["device_history", "device_notes", "device_events", "rssi_samples", "rssi_hourly", "seen_devices", "adapters"];
+
+/// Size of registry.db on disk and rows per table.
+#[derive(Debug, Clone, Default)]
+pub struct RegistryStats {
+    pub bytes: u64,
+    pub rows: Vec<(&'static str, u64)>,
+}
+
 pub struct Registry {
     conn: Connection,
 }
 
 impl Registry {
     pub fn new() -> Result<Self> {
-        let path = Path::new("registry.db");
+        Self::open(DEFAULT_BUSY_TIMEOUT)
+    }
+
+    /// Opens registry.db, waiting up to `busy_timeout` per statement for other
+    /// processes to release it. Setup is retried with backoff if the database
+    /// stays locked, since it has to write the schema.
+    pub fn open(busy_timeout: Duration) -> Result<Self> {
+        let mut delay = OPEN_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            match Self::try_open(busy_timeout) {
+                Err(e) if e.is_database_locked() && attempt < OPEN_ATTEMPTS => {
+                    warn!("Registry is locked by another process, retrying in {:?}", delay);
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn path() -> PathBuf {
+        paths::user_file("registry.db")
+    }
+
+    fn try_open(busy_timeout: Duration) -> Result<Self> {
+        let path = Self::path();
         info!("Opening registry database at {:?}", path);
         
-        let conn = match Connection::open(path) {
+        let conn = match Connection::open(&path) {
             Ok(conn) => conn,
             Err(e) => {
                 error!("Failed to open registry database: {}", e);
                 return Err(AppError::Database(e));
             }
         };
-        
+        conn.busy_timeout(busy_timeout)?;
+        Self::with_schema(conn)
+    }
+
+    /// An empty registry that only lives in memory.
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> Result<Self> {
+        Self::with_schema(Connection::open_in_memory()?)
+    }
+
+    /// For tests that need rows with chosen timestamps.
+    #[cfg(test)]
+    pub(crate) fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Creates the tables `conn` is missing and adds columns added since.
+    fn with_schema(conn: Connection) -> Result<Self> {
         // Create table if it doesn't exist
         match conn.execute(
             "CREATE TABLE IF NOT EXISTS device_history (
@@ -48,9 +206,430 @@ impl Registry {
             Err(e) => warn!("Failed to create index (non-critical): {}", e),
         }
         
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS device_notes (
+                address INTEGER PRIMARY KEY,
+                note TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        ) {
+            Ok(_) => info!("Notes table created/verified"),
+            Err(e) => {
+                error!("Failed to create notes table: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+        
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS device_events (
+                id INTEGER PRIMARY KEY,
+                address INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                duration_secs INTEGER NOT NULL DEFAULT 0,
+                at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        ) {
+            Ok(_) => info!("Events table created/verified"),
+            Err(e) => {
+                error!("Failed to create events table: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+
+        // Added after the table first shipped; only fails when the column is already there
+        let has_detail = conn.prepare("SELECT detail FROM device_events LIMIT 0").is_ok();
+        if !has_detail {
+            if let Err(e) = conn.execute("ALTER TABLE device_events ADD COLUMN detail TEXT", []) {
+                error!("Failed to add event detail column: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+
+        let has_adapter = conn.prepare("SELECT adapter FROM device_events LIMIT 0").is_ok();
+        if !has_adapter {
+            if let Err(e) = conn.execute("ALTER TABLE device_events ADD COLUMN adapter INTEGER", []) {
+                error!("Failed to add event adapter column: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+
+        if let Err(e) = conn.execute("CREATE INDEX IF NOT EXISTS idx_events_at ON device_events(at)", []) {
+            warn!("Failed to create events index (non-critical): {}", e);
+        }
+
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS rssi_samples (
+                id INTEGER PRIMARY KEY,
+                address INTEGER NOT NULL,
+                rssi INTEGER NOT NULL,
+                at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        ) {
+            Ok(_) => info!("RSSI table created/verified"),
+            Err(e) => {
+                error!("Failed to create RSSI table: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+
+        // Samples past RAW_RSSI_HOURS, one row per device and hour (UTC, "YYYY-MM-DD HH:00:00")
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS rssi_hourly (
+                address INTEGER NOT NULL,
+                hour DATETIME NOT NULL,
+                min_rssi INTEGER NOT NULL,
+                avg_rssi REAL NOT NULL,
+                max_rssi INTEGER NOT NULL,
+                samples INTEGER NOT NULL,
+                PRIMARY KEY (address, hour)
+            )",
+            [],
+        ) {
+            Ok(_) => info!("Hourly RSSI table created/verified"),
+            Err(e) => {
+                error!("Failed to create hourly RSSI table: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+
+        // Every device a scan has turned up, connected or not
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS seen_devices (
+                address INTEGER PRIMARY KEY,
+                first_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_seen DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        ) {
+            Ok(_) => info!("Sightings table created/verified"),
+            Err(e) => {
+                error!("Failed to create sightings table: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+        
+        // Local adapters this profile has used, so history can be lined up with adapter changes
+        match conn.execute(
+            "CREATE TABLE IF NOT EXISTS adapters (
+                address INTEGER PRIMARY KEY,
+                name TEXT,
+                manufacturer INTEGER NOT NULL,
+                hci_version INTEGER NOT NULL,
+                hci_revision INTEGER NOT NULL,
+                lmp_version INTEGER NOT NULL,
+                lmp_subversion INTEGER NOT NULL,
+                first_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_seen DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        ) {
+            Ok(_) => info!("Adapters table created/verified"),
+            Err(e) => {
+                error!("Failed to create adapters table: {}", e);
+                return Err(AppError::Database(e));
+            }
+        }
+        
         Ok(Registry { conn })
     }
 
+    /// Notes that `adapter` is in use now. Name and firmware are refreshed,
+    /// since both can change without the address changing.
+    pub fn record_adapter(&self, adapter: &Adapter) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO adapters (address, name, manufacturer, hci_version, hci_revision, lmp_version, lmp_subversion)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(address) DO UPDATE SET
+                name = ?2, manufacturer = ?3, hci_version = ?4, hci_revision = ?5,
+                lmp_version = ?6, lmp_subversion = ?7, last_seen = CURRENT_TIMESTAMP",
+            params![
+                adapter.address as i64,
+                adapter.name,
+                adapter.manufacturer,
+                adapter.hci_version,
+                adapter.hci_revision,
+                adapter.lmp_version,
+                adapter.lmp_subversion
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every adapter recorded, most recently used first, with first and last use (UTC).
+    pub fn adapters(&self) -> Result<Vec<(Adapter, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT address, name, manufacturer, hci_version, hci_revision, lmp_version, lmp_subversion, first_seen, last_seen
+             FROM adapters ORDER BY last_seen DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                Adapter {
+                    address: row.get::<_, i64>(0)? as u64,
+                    name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    manufacturer: row.get(2)?,
+                    hci_version: row.get(3)?,
+                    hci_revision: row.get(4)?,
+                    lmp_version: row.get(5)?,
+                    lmp_subversion: row.get(6)?,
+                },
+                row.get(7)?,
+                row.get(8)?,
+            ))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Writes a row inside a transaction that is rolled back, so a read-only
+    /// or locked database shows up without changing anything.
+    pub fn check_writable(&self) -> Result<()> {
+        let result = self.conn.execute_batch(
+            "BEGIN IMMEDIATE;
+             INSERT INTO device_notes (address, note) VALUES (-1, 'self-test');",
+        );
+        let _ = self.conn.execute_batch("ROLLBACK");
+        result.map_err(AppError::Database)
+    }
+
+    /// Runs `f` in one transaction, so composite updates such as a connect
+    /// (history row, event and sighting) land together or not at all. Rolled back
+    /// if `f` fails; must not be nested.
+    pub fn transaction<T>(&self, f: impl FnOnce(&Registry) -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let value = f(self)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Appends to the event log used by usage reports.
+    pub fn record_event(&self, address: u64, kind: EventKind, duration: Option<Duration>) -> Result<()> {
+        let duration_secs = duration.map_or(0, |d| d.as_secs()) as i64;
+        match self.conn.execute(
+            &format!(
+                "INSERT INTO device_events (address, kind, duration_secs, adapter) VALUES (?1, ?2, ?3, {})",
+                EVENT_ADAPTER
+            ),
+            params![address as i64, kind.as_str(), duration_secs, bluetooth::connected_via(address).map(|a| a as i64)],
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to record {} event: {}", kind.as_str(), e);
+                Err(AppError::Database(e))
+            }
+        }
+    }
+
+    /// Like `record_event`, with the reason an operation failed.
+    pub fn record_failure(&self, address: u64, kind: EventKind, detail: &str) -> Result<()> {
+        match self.conn.execute(
+            &format!("INSERT INTO device_events (address, kind, detail, adapter) VALUES (?1, ?2, ?3, {})", EVENT_ADAPTER),
+            params![address as i64, kind.as_str(), detail, bluetooth::connected_via(address).map(|a| a as i64)],
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                error!("Failed to record {} event: {}", kind.as_str(), e);
+                Err(AppError::Database(e))
+            }
+        }
+    }
+
+    pub fn record_rssi(&self, address: u64, rssi: i32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO rssi_samples (address, rssi) VALUES (?1, ?2)",
+            params![address as i64, rssi],
+        )?;
+        Ok(())
+    }
+
+    /// Notes that a scan found the device. Returns true if this PC has never
+    /// seen or connected to it before.
+    pub fn record_sighting(&self, address: u64) -> Result<bool> {
+        let known: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM seen_devices WHERE address = ?1)
+                 OR EXISTS(SELECT 1 FROM device_history WHERE address = ?1)",
+            params![address as i64],
+            |row| row.get(0),
+        )?;
+        self.conn.execute(
+            "INSERT INTO seen_devices (address) VALUES (?1)
+             ON CONFLICT(address) DO UPDATE SET last_seen = CURRENT_TIMESTAMP",
+            params![address as i64],
+        )?;
+        Ok(!known)
+    }
+
+    pub fn stats(&self) -> Result<RegistryStats> {
+        let mut rows = Vec::with_capacity(TABLES.len());
+        for table in TABLES {
+            let count: i64 = self.conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))?;
+            rows.push((table, count as u64));
+        }
+        let bytes = fs::metadata(Self::path()).map(|m| m.len()).unwrap_or(0);
+        Ok(RegistryStats { bytes, rows })
+    }
+
+    /// Rebuilds the file to give space freed by pruning back to the disk. Can take
+    /// a while on a large database and holds a write lock throughout, so run it on
+    /// its own connection off the GUI thread.
+    pub fn compact(&self) -> Result<()> {
+        info!("Compacting registry database");
+        self.conn.execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    /// Writes a consistent copy of the database to `path`, which must not exist,
+    /// while this connection stays usable.
+    pub fn snapshot(&self, path: &Path) -> Result<()> {
+        self.conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+        Ok(())
+    }
+
+    /// Rolls raw RSSI samples older than a day into per-hour min/avg/max rows and
+    /// deletes them. An hour already rolled up is merged with the new samples.
+    /// Returns the number of raw samples removed.
+    pub fn rollup_rssi(&mut self) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        // Whole hours only, so a bucket isn't split between raw and rolled-up rows
+        let cutoff: String = tx.query_row(
+            "SELECT strftime('%Y-%m-%d %H:00:00', 'now', ?1)",
+            params![format!("-{} hours", RAW_RSSI_HOURS)],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "INSERT INTO rssi_hourly (address, hour, min_rssi, avg_rssi, max_rssi, samples)
+             SELECT address, strftime('%Y-%m-%d %H:00:00', at), MIN(rssi), AVG(rssi), MAX(rssi), COUNT(*)
+             FROM rssi_samples WHERE at < ?1 GROUP BY 1, 2
+             ON CONFLICT(address, hour) DO UPDATE SET
+                 min_rssi = MIN(min_rssi, excluded.min_rssi),
+                 max_rssi = MAX(max_rssi, excluded.max_rssi),
+                 avg_rssi = (avg_rssi * samples + excluded.avg_rssi * excluded.samples) / (samples + excluded.samples),
+                 samples = samples + excluded.samples",
+            params![cutoff],
+        )?;
+        let removed = tx.execute("DELETE FROM rssi_samples WHERE at < ?1", params![cutoff])?;
+        tx.commit()?;
+        info!("Rolled up {} RSSI samples older than {}", removed, cutoff);
+        Ok(removed)
+    }
+
+    /// Mean RSSI and sample count per device for `from <= at < to` (UTC dates),
+    /// from raw samples and hourly rollups alike.
+    pub fn rssi_averages(&self, from: NaiveDate, to: NaiveDate) -> Result<HashMap<u64, (f32, u32)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT address, SUM(total) * 1.0 / SUM(n), SUM(n) FROM (
+                 SELECT address, SUM(rssi) AS total, COUNT(*) AS n
+                 FROM rssi_samples WHERE at >= ?1 AND at < ?2 GROUP BY address
+                 UNION ALL
+                 SELECT address, SUM(avg_rssi * samples), SUM(samples)
+                 FROM rssi_hourly WHERE hour >= ?1 AND hour < ?2 GROUP BY address
+             ) GROUP BY address",
+        )?;
+        let rows = stmt.query_map(params![from.to_string(), to.to_string()], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as u64,
+                (row.get::<_, f64>(1)? as f32, row.get::<_, u32>(2)?),
+            ))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Events with `from <= at < to` (UTC dates), oldest first.
+    pub fn events_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<DeviceEvent>> {
+        self.query_events(
+            "SELECT address, kind, duration_secs, detail, at, adapter FROM device_events WHERE at >= ?1 AND at < ?2 ORDER BY at",
+            params![from.to_string(), to.to_string()],
+        )
+    }
+
+    /// Seconds the device spent connected after `since` (UTC), from its connect and
+    /// disconnect events, plus the start of a session the log still has open.
+    /// Whether an open session is still going is up to the caller; it is not counted.
+    pub fn connected_secs(&self, address: u64, since: NaiveDateTime) -> Result<(u64, Option<NaiveDateTime>)> {
+        // Starts from the last connect or disconnect before `since`, for a session spanning it
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT kind, at FROM device_events
+             WHERE address = ?1 AND kind IN ('connected', 'disconnected') AND at >= COALESCE(
+                 (SELECT MAX(at) FROM device_events
+                  WHERE address = ?1 AND kind IN ('connected', 'disconnected') AND at < ?2), ?2)
+             ORDER BY at, id",
+        )?;
+        let since_text = since.format("%Y-%m-%d %H:%M:%S").to_string();
+        let rows = stmt.query_map(params![address as i64, since_text], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut total = 0;
+        let mut open: Option<NaiveDateTime> = None;
+        for row in rows {
+            let (kind, at) = row?;
+            let Ok(at) = NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S") else {
+                continue;
+            };
+            match EventKind::parse(&kind) {
+                // A connect without a disconnect before it means the app missed the drop;
+                // that session's length is unknown, so it starts over
+                Some(EventKind::Connected) => open = Some(at),
+                Some(EventKind::Disconnected) => {
+                    if let Some(start) = open.take() {
+                        total += (at - start.max(since)).num_seconds().max(0) as u64;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok((total, open))
+    }
+
+    /// Connects and failed connects of a device per local adapter, most used first,
+    /// for comparing e.g. the built-in radio with a USB dongle.
+    pub fn adapter_reliability(&self, address: u64) -> Result<Vec<AdapterReliability>> {
+        self.query(
+            "SELECT e.adapter AS adapter, a.name AS adapter_name,
+                    SUM(e.kind = 'connected') AS connects, SUM(e.kind = 'connect_failed') AS failures
+             FROM device_events e LEFT JOIN adapters a ON a.address = e.adapter
+             WHERE e.address = ?1 AND e.adapter IS NOT NULL AND e.kind IN ('connected', 'connect_failed')
+             GROUP BY e.adapter ORDER BY COUNT(*) DESC",
+            params![address as i64],
+        )
+    }
+
+    /// The device's latest `limit` events, newest first.
+    pub fn recent_events(&self, address: u64, limit: usize) -> Result<Vec<DeviceEvent>> {
+        self.query_events(
+            "SELECT address, kind, duration_secs, detail, at, adapter FROM device_events WHERE address = ?1 ORDER BY id DESC LIMIT ?2",
+            params![address as i64, limit as i64],
+        )
+    }
+
+    fn query_events(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<DeviceEvent>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, i64>(0)? as u64,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+            ))
+        })?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (address, kind, duration_secs, detail, at, adapter) = row?;
+            let (Some(kind), Ok(at)) = (EventKind::parse(&kind), NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S"))
+            else {
+                warn!("Skipping unreadable event row ({}, {})", kind, at);
+                continue;
+            };
+            let adapter = adapter.map(|a| a as u64);
+            events.push(DeviceEvent { address, kind, duration_secs: duration_secs.max(0) as u64, detail, at, adapter });
+        }
+        Ok(events)
+    }
+
     pub fn log_device(&self, address: u64, name: &str) -> Result<()> {
         info!("Logging device to registry: {} ({})", name, address);
         
@@ -72,74 +651,48 @@ impl Registry {
         }
     }
     
-    pub fn get_device_history(&self, address: u64) -> Result<Option<(String, String, i32)>> {
-        match self.conn.query_row(
-            "SELECT name, last_seen, connection_count FROM device_history WHERE address = ?1",
+    pub fn get_device_history(&self, address: u64) -> Result<Option<DeviceSummary>> {
+        let device = self.query_one(
+            "SELECT address, name, last_seen, connection_count FROM device_history WHERE address = ?1",
             params![address as i64],
-            |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, i32>(2)?,
-                ))
-            },
-        ) {
-            Ok(result) => {
-                info!("Retrieved history for device: {}", address);
-                Ok(Some(result))
-            }
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                info!("No history found for device: {}", address);
-                Ok(None)
-            }
-            Err(e) => {
-                error!("Failed to get device history: {}", e);
-                Err(AppError::Database(e))
-            }
+        )?;
+        match &device {
+            Some(_) => info!("Retrieved history for device: {}", address),
+            None => info!("No history found for device: {}", address),
         }
+        Ok(device)
     }
     
-    pub fn get_all_devices(&self) -> Result<Vec<(u64, String, String, i32)>> {
-        let mut stmt = match self.conn.prepare(
-            "SELECT address, name, last_seen, connection_count FROM device_history ORDER BY last_seen DESC"
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => {
-                error!("Failed to prepare query for all devices: {}", e);
-                return Err(AppError::Database(e));
-            }
-        };
-        
-        let device_iter = match stmt.query_map([], |row| {
-            Ok((
-                row.get::<_, i64>(0)? as u64,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, i32>(3)?,
-            ))
-        }) {
-            Ok(iter) => iter,
-            Err(e) => {
-                error!("Failed to execute query for all devices: {}", e);
-                return Err(AppError::Database(e));
-            }
-        };
-        
-        let mut devices = Vec::new();
-        for device in device_iter {
-            match device {
-                Ok(device) => devices.push(device),
-                Err(e) => {
-                    error!("Failed to parse device row: {}", e);
-                    return Err(AppError::Database(e));
-                }
-            }
-        }
-        
+    pub fn get_all_devices(&self) -> Result<Vec<DeviceSummary>> {
+        let devices: Vec<DeviceSummary> = self.query(
+            "SELECT address, name, last_seen, connection_count FROM device_history ORDER BY last_seen DESC",
+            [],
+        )?;
         info!("Retrieved {} devices from registry", devices.len());
         Ok(devices)
     }
     
+    /// Runs a cached statement and maps each row onto `T` by column name, so
+    /// `SELECT address, name ...` fills the `address` and `name` fields.
+    pub fn query<T: DeserializeOwned>(&self, sql: &str, params: impl Params) -> Result<Vec<T>> {
+        let mut stmt = self.conn.prepare_cached(sql)?;
+        let rows = stmt.query_map(params, row::from_row)?;
+        rows.collect::<rusqlite::Result<_>>().map_err(|e| {
+            error!("Failed to map rows of '{}': {}", sql, e);
+            AppError::Database(e)
+        })
+    }
+    
+    /// Like `query`, for statements expected to return at most one row.
+    pub fn query_one<T: DeserializeOwned>(&self, sql: &str, params: impl Params) -> Result<Option<T>> {
+        let mut stmt = self.conn.prepare_cached(sql)?;
+        let mut rows = stmt.query(params)?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+    
     pub fn cleanup_old_entries(&self, days_old: i32) -> Result<usize> {
         info!("Cleaning up registry entries older than {} days", days_old);
         
@@ -149,6 +702,30 @@ impl Registry {
         ) {
             Ok(deleted) => {
                 info!("Cleaned up {} old registry entries", deleted);
+                if let Err(e) = self.conn.execute(
+                    "DELETE FROM device_events WHERE julianday('now') - julianday(at) > ?1",
+                    params![days_old],
+                ) {
+                    warn!("Failed to prune event log: {}", e);
+                }
+                if let Err(e) = self.conn.execute(
+                    "DELETE FROM rssi_samples WHERE julianday('now') - julianday(at) > ?1",
+                    params![days_old],
+                ) {
+                    warn!("Failed to prune RSSI samples: {}", e);
+                }
+                if let Err(e) = self.conn.execute(
+                    "DELETE FROM rssi_hourly WHERE julianday('now') - julianday(hour) > ?1",
+                    params![days_old],
+                ) {
+                    warn!("Failed to prune hourly RSSI: {}", e);
+                }
+                if let Err(e) = self.conn.execute(
+                    "DELETE FROM seen_devices WHERE julianday('now') - julianday(last_seen) > ?1",
+                    params![days_old],
+                ) {
+                    warn!("Failed to prune sightings: {}", e);
+                }
                 Ok(deleted as usize)
             }
             Err(e) => {
@@ -157,4 +734,187 @@ impl Registry {
             }
         }
     }
+    
+    /// Stores a free-text note for a device. An empty note deletes it.
+    pub fn set_note(&self, address: u64, note: &str) -> Result<()> {
+        let result = if note.trim().is_empty() {
+            self.conn.execute(
+                "DELETE FROM device_notes WHERE address = ?1",
+                params![address as i64],
+            )
+        } else {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO device_notes (address, note, updated_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+                params![address as i64, note],
+            )
+        };
+        
+        match result {
+            Ok(_) => {
+                info!("Saved note for device: {}", address);
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to save note: {}", e);
+                Err(AppError::Database(e))
+            }
+        }
+    }
+    
+    pub fn get_all_notes(&self) -> Result<HashMap<u64, String>> {
+        let mut stmt = self.conn.prepare("SELECT address, note FROM device_notes")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)? as u64, row.get::<_, String>(1)?))
+        })?;
+        
+        let mut notes = HashMap::new();
+        for row in rows {
+            let (address, note) = row?;
+            notes.insert(address, note);
+        }
+        Ok(notes)
+    }
+    
+    /// Writes device history joined with notes to a CSV file, including notes
+    /// on devices that were never logged.
+    pub fn export_csv(&self, path: &Path) -> Result<usize> {
+        let rows: Vec<ExportRow> = self.query(
+            "SELECT h.address, h.name, h.last_seen, h.connection_count, n.note
+             FROM device_history h LEFT JOIN device_notes n ON n.address = h.address
+             UNION ALL
+             SELECT n.address, NULL, NULL, 0, n.note
+             FROM device_notes n WHERE n.address NOT IN (SELECT address FROM device_history)
+             ORDER BY 3 DESC",
+            [],
+        )?;
+
+        let mut out = String::from("address,name,last_seen,connection_count,note\n");
+        for row in &rows {
+            out.push_str(&format!(
+                "{:012X},{},{},{},{}\n",
+                row.address,
+                csv_field(row.name.as_deref().unwrap_or("")),
+                csv_field(row.last_seen.as_deref().unwrap_or("")),
+                row.connection_count,
+                csv_field(row.note.as_deref().unwrap_or(""))
+            ));
+        }
+        
+        fs::write(path, out)?;
+        info!("Exported {} devices to {:?}", rows.len(), path);
+        Ok(rows.len())
+    }
+}
+
+/// One line of `Registry::export_csv`; devices only known from a note have no history.
+#[derive(Deserialize)]
+struct ExportRow {
+    address: u64,
+    name: Option<String>,
+    last_seen: Option<String>,
+    connection_count: i32,
+    note: Option<String>,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICE: u64 = 0xA1;
+
+    fn sample(registry: &Registry, rssi: i32, at: &str) {
+        registry
+            .conn
+            .execute("INSERT INTO rssi_samples (address, rssi, at) VALUES (?1, ?2, ?3)", params![DEVICE as i64, rssi, at])
+            .unwrap();
+    }
+
+    fn hourly(registry: &Registry) -> Vec<(String, i32, f64, i32, u32)> {
+        let mut stmt = registry
+            .conn
+            .prepare("SELECT hour, min_rssi, avg_rssi, max_rssi, samples FROM rssi_hourly ORDER BY hour")
+            .unwrap();
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+            .unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn rollup_buckets_old_samples_by_hour() {
+        let mut registry = Registry::in_memory().unwrap();
+        sample(&registry, -60, "2024-01-01 10:05:00");
+        sample(&registry, -70, "2024-01-01 10:55:59");
+        sample(&registry, -50, "2024-01-01 11:00:00");
+        // Recent, so kept as it is
+        registry.record_rssi(DEVICE, -40).unwrap();
+
+        assert_eq!(registry.rollup_rssi().unwrap(), 3);
+        assert_eq!(
+            hourly(&registry),
+            [
+                ("2024-01-01 10:00:00".to_string(), -70, -65.0, -60, 2),
+                ("2024-01-01 11:00:00".to_string(), -50, -50.0, -50, 1),
+            ]
+        );
+        let left: Vec<i32> = registry
+            .conn
+            .prepare("SELECT rssi FROM rssi_samples")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(left, [-40]);
+    }
+
+    #[test]
+    fn rollup_merges_into_existing_hours() {
+        let mut registry = Registry::in_memory().unwrap();
+        sample(&registry, -60, "2024-01-01 10:05:00");
+        sample(&registry, -70, "2024-01-01 10:15:00");
+        registry.rollup_rssi().unwrap();
+
+        // Late arrivals for an hour already rolled up
+        sample(&registry, -80, "2024-01-01 10:45:00");
+        sample(&registry, -50, "2024-01-01 10:50:00");
+        assert_eq!(registry.rollup_rssi().unwrap(), 2);
+        assert_eq!(hourly(&registry), [("2024-01-01 10:00:00".to_string(), -80, -65.0, -50, 4)]);
+
+        // Nothing left to do
+        assert_eq!(registry.rollup_rssi().unwrap(), 0);
+        assert_eq!(hourly(&registry).len(), 1);
+
+        let means = registry
+            .rssi_averages(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 2).unwrap())
+            .unwrap();
+        assert_eq!(means.get(&DEVICE), Some(&(-65.0, 4)));
+    }
+
+    #[test]
+    fn export_includes_notes_on_devices_never_logged() {
+        let registry = Registry::in_memory().unwrap();
+        registry.log_device(0xA1, "Headset, left").unwrap();
+        registry.set_note(0xA1, "Charges slowly").unwrap();
+        registry.set_note(0xB2, "Borrowed from \"Sam\"").unwrap();
+
+        let path = std::env::temp_dir().join(format!("redtooth_export_{}.csv", std::process::id()));
+        assert_eq!(registry.export_csv(&path).unwrap(), 2);
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "address,name,last_seen,connection_count,note");
+        assert!(lines[1].starts_with("0000000000A1,\"Headset, left\","), "{}", lines[1]);
+        assert!(lines[1].ends_with(",Charges slowly"), "{}", lines[1]);
+        assert_eq!(lines[2], "0000000000B2,,,0,\"Borrowed from \"\"Sam\"\"\"");
+    }
 }
@@ -0,0 +1,161 @@
+//! Maps query rows onto `Deserialize` types by column name.
+//!
+//! Columns become struct fields, so `SELECT address, name AS label` fills
+//! `address` and `label`. Integers deserialize into any integer type they fit,
+//! and into `bool`; NULL only into `Option` or `()`.
+
+use rusqlite::types::Value;
+use rusqlite::Row;
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// Reads one row into `T`.
+pub fn from_row<T: DeserializeOwned>(row: &Row) -> rusqlite::Result<T> {
+    let columns = row.as_ref().column_names();
+    let mut fields = Vec::with_capacity(columns.len());
+    for (i, name) in columns.into_iter().enumerate() {
+        fields.push((name.to_string(), Column(row.get::<_, Value>(i)?)));
+    }
+    T::deserialize(MapDeserializer::new(fields.into_iter()))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Null, Box::new(e)))
+}
+
+struct Column(Value);
+
+impl<'de> IntoDeserializer<'de, Error> for Column {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Column {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Real(f) => visitor.visit_f64(f),
+            Value::Text(s) => visitor.visit_string(s),
+            Value::Blob(b) => visitor.visit_byte_buf(b),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Integer(i) => visitor.visit_bool(i != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // Unit variants stored as text, e.g. `#[serde(rename_all = "snake_case")]` kinds
+        match self.0 {
+            Value::Text(s) => visitor.visit_enum(s.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Device {
+        address: u64,
+        label: String,
+        note: Option<String>,
+        trusted: bool,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind {
+        Connected,
+        ConnectFailed,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Event {
+        kind: Kind,
+    }
+
+    fn read<T: DeserializeOwned>(sql: &str) -> rusqlite::Result<T> {
+        Connection::open_in_memory()?.query_row(sql, [], |row| from_row(row))
+    }
+
+    #[test]
+    fn fills_fields_by_column_name() {
+        let device: Device = read("SELECT 1 AS trusted, 'Headset' AS label, NULL AS note, 161 AS address").unwrap();
+        assert_eq!(device, Device { address: 161, label: String::from("Headset"), note: None, trusted: true });
+    }
+
+    #[test]
+    fn null_only_fits_option() {
+        let device: Device = read("SELECT 161 AS address, 'Headset' AS label, 'left' AS note, 0 AS trusted").unwrap();
+        assert_eq!(device.note.as_deref(), Some("left"));
+        assert!(!device.trusted);
+        assert!(read::<Device>("SELECT 161 AS address, NULL AS label, NULL AS note, 0 AS trusted").is_err());
+    }
+
+    #[test]
+    fn extra_columns_are_ignored_and_missing_ones_fail() {
+        assert!(read::<Device>("SELECT 161 AS address, 'Headset' AS label, NULL AS note, 1 AS trusted, 5 AS other").is_ok());
+        assert!(read::<Device>("SELECT 161 AS address, 'Headset' AS name, NULL AS note, 1 AS trusted").is_err());
+    }
+
+    #[test]
+    fn type_mismatches_fail() {
+        assert!(read::<Device>("SELECT 'A1' AS address, 'Headset' AS label, NULL AS note, 1 AS trusted").is_err());
+        assert!(read::<Device>("SELECT -1 AS address, 'Headset' AS label, NULL AS note, 1 AS trusted").is_err());
+        assert!(read::<Device>("SELECT 1.5 AS address, 'Headset' AS label, NULL AS note, 1 AS trusted").is_err());
+        assert!(read::<Device>("SELECT 161 AS address, 42 AS label, NULL AS note, 1 AS trusted").is_err());
+        assert!(read::<Device>("SELECT 161 AS address, 'Headset' AS label, NULL AS note, 'yes' AS trusted").is_err());
+    }
+
+    #[test]
+    fn integers_must_fit() {
+        #[derive(Debug, Deserialize)]
+        struct Small {
+            #[allow(dead_code)]
+            value: u8,
+        }
+        assert!(read::<Small>("SELECT 255 AS value").is_ok());
+        assert!(read::<Small>("SELECT 256 AS value").is_err());
+    }
+
+    #[test]
+    fn unit_variants_come_from_text() {
+        assert_eq!(read::<Event>("SELECT 'connect_failed' AS kind").unwrap().kind, Kind::ConnectFailed);
+        assert_eq!(read::<Event>("SELECT 'connected' AS kind").unwrap().kind, Kind::Connected);
+        assert!(read::<Event>("SELECT 'exploded' AS kind").is_err());
+        assert!(read::<Event>("SELECT 1 AS kind").is_err());
+    }
+}
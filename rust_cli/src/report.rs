@@ -0,0 +1,246 @@
+use crate::error::{AppError, Result};
+use crate::paths;
+use crate::registry::{EventKind, Registry};
+use chrono::{Datelike, Local, NaiveDate};
+use log::info;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reports are written next to the registry and never leave the machine.
+pub fn report_dir() -> PathBuf {
+    paths::user_file("reports")
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DeviceUsage {
+    pub name: String,
+    pub connects: u32,
+    pub failures: u32,
+    pub audio_secs: u64,
+}
+
+/// Connection attempts bucketed by week of the month (days 1-7, 8-14, …).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WeekTrend {
+    pub connects: u32,
+    pub failures: u32,
+}
+
+impl WeekTrend {
+    pub fn failure_rate(&self) -> f32 {
+        let attempts = self.connects + self.failures;
+        if attempts == 0 {
+            0.0
+        } else {
+            self.failures as f32 / attempts as f32
+        }
+    }
+}
+
+/// Connection attempts through one local adapter.
+#[derive(Debug, Default, Clone)]
+pub struct AdapterUsage {
+    pub name: String,
+    pub connects: u32,
+    pub failures: u32,
+}
+
+/// One month of the registry's event log, summarised.
+#[derive(Debug, Clone)]
+pub struct UsageReport {
+    pub year: i32,
+    pub month: u32,
+    pub devices: Vec<DeviceUsage>,
+    pub weeks: Vec<WeekTrend>,
+    /// Most used first; events recorded before adapters were tracked are left out
+    pub adapters: Vec<AdapterUsage>,
+}
+
+impl UsageReport {
+    pub fn build(registry: &Registry, year: i32, month: u32) -> Result<Self> {
+        let from = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| AppError::Parse(format!("Invalid month {}-{}", year, month)))?;
+        let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        let to = NaiveDate::from_ymd_opt(next_year, next_month, 1)
+            .ok_or_else(|| AppError::Parse(format!("Invalid month {}-{}", year, month)))?;
+
+        let names: HashMap<u64, String> = registry
+            .get_all_devices()?
+            .into_iter()
+            .map(|d| (d.address, d.name))
+            .collect();
+        let adapter_names: HashMap<u64, String> = registry
+            .adapters()?
+            .into_iter()
+            .map(|(a, _, _)| (a.address, a.name))
+            .collect();
+
+        let mut devices: BTreeMap<u64, DeviceUsage> = BTreeMap::new();
+        let mut weeks = vec![WeekTrend::default(); 5];
+        let mut adapters: BTreeMap<u64, AdapterUsage> = BTreeMap::new();
+        for event in registry.events_between(from, to)? {
+            if let (Some(adapter), EventKind::Connected | EventKind::ConnectFailed) = (event.adapter, event.kind) {
+                let usage = adapters.entry(adapter).or_insert_with(|| AdapterUsage {
+                    name: adapter_names
+                        .get(&adapter)
+                        .filter(|n| !n.is_empty())
+                        .cloned()
+                        .unwrap_or_else(|| format!("{:X}", adapter)),
+                    ..Default::default()
+                });
+                match event.kind {
+                    EventKind::Connected => usage.connects += 1,
+                    _ => usage.failures += 1,
+                }
+            }
+
+            let usage = devices.entry(event.address).or_insert_with(|| DeviceUsage {
+                name: names
+                    .get(&event.address)
+                    .filter(|n| !n.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("{:X}", event.address)),
+                ..Default::default()
+            });
+            let week = &mut weeks[(event.at.day0() / 7) as usize];
+            match event.kind {
+                EventKind::Connected => {
+                    usage.connects += 1;
+                    week.connects += 1;
+                }
+                EventKind::ConnectFailed => {
+                    usage.failures += 1;
+                    week.failures += 1;
+                }
+                EventKind::AudioStreamed => usage.audio_secs += event.duration_secs,
+                EventKind::Disconnected | EventKind::DisconnectFailed | EventKind::WatchAlert => {}
+            }
+        }
+
+        let mut devices: Vec<DeviceUsage> = devices.into_values().collect();
+        devices.sort_by_key(|d| std::cmp::Reverse(d.connects + d.failures));
+        let mut adapters: Vec<AdapterUsage> = adapters.into_values().collect();
+        adapters.sort_by_key(|a| std::cmp::Reverse(a.connects + a.failures));
+        Ok(UsageReport { year, month, devices, weeks, adapters })
+    }
+
+    /// Self-contained page; bars are plain CSS so it opens offline in any browser.
+    pub fn to_html(&self) -> String {
+        let title = format!("RedTooth usage — {}-{:02}", self.year, self.month);
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title>\n<style>\n\
+             body {{ font-family: sans-serif; margin: 2em; }}\n\
+             table {{ border-collapse: collapse; }}\n\
+             td, th {{ padding: 4px 12px; text-align: left; border-bottom: 1px solid #ddd; }}\n\
+             .bar {{ background: #c44; height: 12px; }}\n\
+             </style></head><body>\n<h1>{0}</h1>\n",
+            title
+        );
+
+        let connects: u32 = self.devices.iter().map(|d| d.connects).sum();
+        let failures: u32 = self.devices.iter().map(|d| d.failures).sum();
+        let audio_secs: u64 = self.devices.iter().map(|d| d.audio_secs).sum();
+        out.push_str(&format!(
+            "<p>{} connections, {} failed attempts, {} of audio across {} devices.</p>\n",
+            connects,
+            failures,
+            format_hours(audio_secs),
+            self.devices.len()
+        ));
+
+        out.push_str("<h2>Devices</h2>\n");
+        if self.devices.is_empty() {
+            out.push_str("<p>No activity recorded this month.</p>\n");
+        } else {
+            out.push_str("<table><tr><th>Device</th><th>Connections</th><th>Failures</th><th>Audio</th></tr>\n");
+            for device in &self.devices {
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape_html(&device.name),
+                    device.connects,
+                    device.failures,
+                    format_hours(device.audio_secs)
+                ));
+            }
+            out.push_str("</table>\n");
+        }
+
+        out.push_str("<h2>Connection failures by week</h2>\n<table><tr><th>Days</th><th>Failure rate</th><th></th></tr>\n");
+        for (i, week) in self.weeks.iter().enumerate() {
+            let rate = week.failure_rate();
+            out.push_str(&format!(
+                "<tr><td>{}–{}</td><td>{:.0}% ({} of {})</td><td><div class=\"bar\" style=\"width: {:.0}px\"></div></td></tr>\n",
+                i * 7 + 1,
+                ((i + 1) * 7).min(31),
+                rate * 100.0,
+                week.failures,
+                week.connects + week.failures,
+                rate * 200.0
+            ));
+        }
+        out.push_str("</table>\n");
+
+        if !self.adapters.is_empty() {
+            out.push_str("<h2>Adapters</h2>\n<table><tr><th>Adapter</th><th>Connections</th><th>Failures</th><th>Failure rate</th></tr>\n");
+            for adapter in &self.adapters {
+                let attempts = adapter.connects + adapter.failures;
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.0}%</td></tr>\n",
+                    escape_html(&adapter.name),
+                    adapter.connects,
+                    adapter.failures,
+                    adapter.failures as f32 / attempts.max(1) as f32 * 100.0
+                ));
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(file_name(self.year, self.month));
+        fs::write(&path, self.to_html())?;
+        info!("Wrote usage report {:?}", path);
+        Ok(path)
+    }
+}
+
+/// (year, month) of the calendar month before today.
+pub fn previous_month() -> (i32, u32) {
+    let today = Local::now().date_naive();
+    if today.month() == 1 {
+        (today.year() - 1, 12)
+    } else {
+        (today.year(), today.month() - 1)
+    }
+}
+
+/// Writes last month's report unless it already exists. Returns the path when one was written.
+pub fn write_previous_month(registry: &Registry) -> Result<Option<PathBuf>> {
+    let (year, month) = previous_month();
+    if report_dir().join(file_name(year, month)).exists() {
+        return Ok(None);
+    }
+    UsageReport::build(registry, year, month)?
+        .write(&report_dir())
+        .map(Some)
+}
+
+fn file_name(year: i32, month: u32) -> String {
+    format!("usage-{}-{:02}.html", year, month)
+}
+
+fn format_hours(secs: u64) -> String {
+    format!("{:.1} h", secs as f64 / 3600.0)
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
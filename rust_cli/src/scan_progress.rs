@@ -0,0 +1,57 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Discovery rate is averaged over this window
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// What the current scan has turned up, so a working scan can be told apart
+/// from one that is silently dead.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    started: Option<Instant>,
+    unique: HashSet<u64>,
+    // Discovery reports within the rate window, oldest first
+    recent: VecDeque<Instant>,
+}
+
+impl ScanProgress {
+    pub fn start(&mut self) {
+        *self = ScanProgress { started: Some(Instant::now()), ..Default::default() };
+    }
+
+    pub fn stop(&mut self) {
+        self.started = None;
+    }
+
+    /// Counts a discovery report; ignored while no scan is running.
+    pub fn record(&mut self, address: u64) {
+        if self.started.is_none() {
+            return;
+        }
+        let now = Instant::now();
+        self.unique.insert(address);
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) > RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.started.map(|t| t.elapsed())
+    }
+
+    /// Distinct devices reported since the scan started.
+    pub fn unique_count(&self) -> usize {
+        self.unique.len()
+    }
+
+    /// Discovery reports per second over the last few seconds.
+    pub fn rate(&self) -> f32 {
+        let Some(elapsed) = self.elapsed() else {
+            return 0.0;
+        };
+        let window = elapsed.min(RATE_WINDOW).as_secs_f32().max(1.0);
+        let count = self.recent.iter().filter(|t| t.elapsed() <= RATE_WINDOW).count();
+        count as f32 / window
+    }
+}
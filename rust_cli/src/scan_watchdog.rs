@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+/// Plain restarts tried before the whole stack is brought back up
+const RESTARTS_BEFORE_REINIT: u32 = 2;
+/// Re-initializations tried before the watchdog gives up until the next discovery
+const MAX_REINITS: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// Stop and start the scan again
+    RestartScan,
+    /// Re-initialize the Bluetooth core, then start scanning
+    Reinitialize,
+    /// Nothing helped; surface it to the user
+    GiveUp,
+}
+
+/// Notices a scan that claims to run but never reports anything.
+///
+/// The core's `bt_start_scan` sometimes succeeds without a single discovery
+/// callback following. Any `DeviceFound` counts as activity and resets the
+/// escalation, so a room with genuinely no devices only costs a few restarts.
+#[derive(Debug)]
+pub struct ScanWatchdog {
+    last_activity: Instant,
+    restarts: u32,
+    reinits: u32,
+    gave_up: bool,
+}
+
+impl Default for ScanWatchdog {
+    fn default() -> Self {
+        ScanWatchdog { last_activity: Instant::now(), restarts: 0, reinits: 0, gave_up: false }
+    }
+}
+
+impl ScanWatchdog {
+    /// A discovery arrived; the scan is healthy.
+    pub fn activity(&mut self) {
+        *self = ScanWatchdog::default();
+    }
+
+    /// Every recovery step failed and the scan is still silent.
+    pub fn stuck(&self) -> bool {
+        self.gave_up
+    }
+
+    /// Number of recovery attempts since the last discovery.
+    pub fn attempts(&self) -> u32 {
+        self.restarts + self.reinits
+    }
+
+    /// Returns the next recovery step once the scan has been silent for `limit`.
+    /// Each step grants the scan another full `limit` before escalating.
+    pub fn check(&mut self, scanning: bool, limit: Duration) -> Option<Recovery> {
+        if !scanning {
+            self.last_activity = Instant::now();
+            return None;
+        }
+        if self.gave_up || self.last_activity.elapsed() < limit {
+            return None;
+        }
+        self.last_activity = Instant::now();
+
+        if self.restarts < RESTARTS_BEFORE_REINIT {
+            self.restarts += 1;
+            Some(Recovery::RestartScan)
+        } else if self.reinits < MAX_REINITS {
+            self.reinits += 1;
+            self.restarts = 0;
+            Some(Recovery::Reinitialize)
+        } else {
+            self.gave_up = true;
+            Some(Recovery::GiveUp)
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{Datelike, Duration, Local, NaiveDateTime, NaiveTime, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Quiet hours during which auto-connect and notifications are suppressed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DndSchedule {
+    pub enabled: bool,
+    /// Local start time, "HH:MM"
+    pub start: String,
+    /// Local end time, "HH:MM". May be earlier than `start` for overnight windows.
+    pub end: String,
+    /// Treat all of Saturday and Sunday as quiet
+    pub weekends: bool,
+}
+
+impl Default for DndSchedule {
+    fn default() -> Self {
+        DndSchedule {
+            enabled: false,
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+            weekends: false,
+        }
+    }
+}
+
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+impl DndSchedule {
+    /// Returns false when either time fails to parse, so a typo never silences the app forever.
+    pub fn is_valid(&self) -> bool {
+        parse_time(&self.start).is_some() && parse_time(&self.end).is_some()
+    }
+
+    pub fn is_active_at(&self, now: NaiveDateTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.weekends && matches!(now.weekday(), Weekday::Sat | Weekday::Sun) {
+            return true;
+        }
+
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let time = now.time();
+        if start <= end {
+            time >= start && time < end
+        } else {
+            // Window wraps past midnight, e.g. 22:00–07:00
+            time >= start || time < end
+        }
+    }
+
+    pub fn is_active_now(&self) -> bool {
+        self.is_active_at(Local::now().naive_local())
+    }
+}
+
+/// Day names accepted in `ProfileSchedule::days`, Monday first
+pub const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// How late a schedule entry may still run, e.g. when the PC wakes from sleep
+/// or RedTooth starts a few minutes after the trigger time
+pub const CATCH_UP_MINUTES: i64 = 15;
+
+/// Starts an audio profile at a set time on chosen days, e.g. "Work" at 09:00
+/// on weekdays and "Living room" at 18:00 every day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ProfileSchedule {
+    pub enabled: bool,
+    /// Name of the audio profile to start
+    pub profile: String,
+    /// Local time, "HH:MM"
+    pub at: String,
+    /// Entries of `WEEKDAYS`; empty means every day
+    pub days: Vec<String>,
+}
+
+impl Default for ProfileSchedule {
+    fn default() -> Self {
+        ProfileSchedule {
+            enabled: true,
+            profile: String::new(),
+            at: "09:00".to_string(),
+            days: WEEKDAYS[..5].iter().map(|d| d.to_string()).collect(),
+        }
+    }
+}
+
+impl ProfileSchedule {
+    pub fn is_valid(&self) -> bool {
+        parse_time(&self.at).is_some() && self.days.iter().all(|d| d.parse::<Weekday>().is_ok())
+    }
+
+    pub fn runs_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.iter().any(|d| d.parse::<Weekday>().ok() == Some(day))
+    }
+
+    /// True when a trigger time on one of the entry's days fell within `(since, now]`
+    /// and is at most `CATCH_UP_MINUTES` old.
+    pub fn is_due(&self, since: NaiveDateTime, now: NaiveDateTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Some(at) = parse_time(&self.at) else {
+            return false;
+        };
+        // Yesterday's trigger matters when `since` lies before midnight
+        let today = now.date().and_time(at);
+        [today, today - Duration::days(1)].into_iter().any(|trigger| {
+            trigger > since
+                && trigger <= now
+                && now - trigger <= Duration::minutes(CATCH_UP_MINUTES)
+                && self.runs_on(trigger.weekday())
+        })
+    }
+}
@@ -0,0 +1,235 @@
+use crate::bluetooth::{self, BluetoothDevice, BluetoothEvent};
+use crate::config;
+use crate::error::{AppError, Result};
+use crate::paths;
+use chrono::{Datelike, Local};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Output lines kept per run
+const OUTPUT_LEN: usize = 500;
+/// `sleep` checks for Stop this often
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+/// Something a running script asked for or reported. Work that needs the GUI's
+/// state, such as starting an audio profile, is done there.
+#[derive(Debug, Clone)]
+pub enum ScriptEvent {
+    Output { run: u64, line: String },
+    Finished { run: u64, result: std::result::Result<(), String> },
+    StartProfile { run: u64, name: String },
+    StopAudio { run: u64 },
+    Notify { run: u64, message: String },
+}
+
+#[derive(Debug)]
+pub struct ScriptRun {
+    pub id: u64,
+    pub name: String,
+    pub output: Vec<String>,
+    /// `None` while running
+    pub result: Option<std::result::Result<(), String>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ScriptRun {
+    pub fn is_running(&self) -> bool {
+        self.result.is_none()
+    }
+}
+
+/// Rhai scripts for automations the built-in settings can't express.
+///
+/// Each run gets its own engine on a worker thread. Scripts see the device list
+/// as of the last frame and can connect, disconnect, switch audio profiles,
+/// notify and sleep; `print` goes to the run's output.
+#[derive(Default)]
+pub struct Scripts {
+    devices: Arc<Mutex<Arc<[BluetoothDevice]>>>,
+    runs: Vec<ScriptRun>,
+    next_id: u64,
+}
+
+impl Scripts {
+    /// Makes the current device list visible to running scripts.
+    pub fn publish(&mut self, devices: Arc<[BluetoothDevice]>) {
+        if let Ok(mut guard) = self.devices.lock() {
+            *guard = devices;
+        }
+    }
+
+    pub fn start(&mut self, name: &str, source: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        println!("CLI: Action -> Run script '{}' ({})", name, id);
+        let stop = Arc::new(AtomicBool::new(false));
+        self.runs.push(ScriptRun { id, name: name.to_string(), output: Vec::new(), result: None, stop: stop.clone() });
+
+        let devices = self.devices.clone();
+        thread::spawn(move || {
+            let engine = build_engine(id, devices, stop);
+            let result = engine.run(&source).map_err(|e| match *e {
+                EvalAltResult::ErrorTerminated(..) => String::from("Stopped"),
+                e => e.to_string(),
+            });
+            bluetooth::send_event(BluetoothEvent::Script(ScriptEvent::Finished { run: id, result }));
+        });
+        id
+    }
+
+    pub fn stop(&self, id: u64) {
+        if let Some(run) = self.runs.iter().find(|r| r.id == id) {
+            run.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Applies output and completion events; the GUI handles the rest.
+    pub fn record(&mut self, event: &ScriptEvent) {
+        match event {
+            ScriptEvent::Output { run, line } => {
+                if let Some(run) = self.runs.iter_mut().find(|r| r.id == *run) {
+                    if run.output.len() == OUTPUT_LEN {
+                        run.output.remove(0);
+                    }
+                    run.output.push(line.clone());
+                }
+            }
+            ScriptEvent::Finished { run, result } => {
+                if let Some(run) = self.runs.iter_mut().find(|r| r.id == *run) {
+                    run.result = Some(result.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn runs(&self) -> &[ScriptRun] {
+        &self.runs
+    }
+
+    /// Forgets finished runs.
+    pub fn clear_finished(&mut self) {
+        self.runs.retain(ScriptRun::is_running);
+    }
+
+    pub fn name_of(&self, id: u64) -> &str {
+        self.runs.iter().find(|r| r.id == id).map_or("script", |r| r.name.as_str())
+    }
+}
+
+fn emit(event: ScriptEvent) {
+    bluetooth::send_event(BluetoothEvent::Script(event));
+}
+
+/// An address ("AA:BB:CC:DD:EE:FF" or "AABBCCDDEEFF") or the name of a device in the list.
+fn resolve(devices: &Mutex<Arc<[BluetoothDevice]>>, target: &str) -> std::result::Result<u64, Box<EvalAltResult>> {
+    if let Some(address) = config::parse_address(target) {
+        return Ok(address);
+    }
+    let devices = devices.lock().map(|d| d.clone()).unwrap_or_else(|_| Arc::from(Vec::new()));
+    devices
+        .iter()
+        .find(|d| d.name.eq_ignore_ascii_case(target))
+        .map(|d| d.address)
+        .ok_or_else(|| format!("No device called '{}'", target).into())
+}
+
+fn device_map(device: &BluetoothDevice) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("address".into(), config::address_key(device.address).into());
+    map.insert("name".into(), device.name.clone().into());
+    map.insert("connected".into(), device.state.is_connected().into());
+    map.insert("rssi".into(), (device.rssi as i64).into());
+    map.insert("class".into(), format!("{:?}", device.major_class()).into());
+    map.into()
+}
+
+fn build_engine(run: u64, devices: Arc<Mutex<Arc<[BluetoothDevice]>>>, stop: Arc<AtomicBool>) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.on_print(move |line| emit(ScriptEvent::Output { run, line: line.to_string() }));
+    engine.on_debug(move |line, _, pos| emit(ScriptEvent::Output { run, line: format!("[{}] {}", pos, line) }));
+    let flag = stop.clone();
+    engine.on_progress(move |_| flag.load(Ordering::Relaxed).then_some(Dynamic::UNIT));
+
+    let list = devices.clone();
+    engine.register_fn("devices", move || -> Array {
+        list.lock().map(|d| d.iter().map(device_map).collect()).unwrap_or_default()
+    });
+    let list = devices.clone();
+    engine.register_fn("connect", move |target: &str| -> std::result::Result<(), Box<EvalAltResult>> {
+        let address = resolve(&list, target)?;
+        bluetooth::connect(address).map_err(|e| e.to_string().into())
+    });
+    let list = devices;
+    engine.register_fn("disconnect", move |target: &str| -> std::result::Result<(), Box<EvalAltResult>> {
+        let address = resolve(&list, target)?;
+        bluetooth::disconnect(address).map_err(|e| e.to_string().into())
+    });
+    engine.register_fn("start_profile", move |name: &str| emit(ScriptEvent::StartProfile { run, name: name.to_string() }));
+    engine.register_fn("stop_audio", move || emit(ScriptEvent::StopAudio { run }));
+    engine.register_fn("notify", move |message: &str| {
+        emit(ScriptEvent::Notify { run, message: message.to_string() })
+    });
+    engine.register_fn("sleep", move |ms: i64| {
+        let mut left = Duration::from_millis(ms.max(0) as u64);
+        while !left.is_zero() && !stop.load(Ordering::Relaxed) {
+            let slice = left.min(SLEEP_SLICE);
+            thread::sleep(slice);
+            left -= slice;
+        }
+    });
+    engine.register_fn("now", || Local::now().format("%H:%M").to_string());
+    engine.register_fn("weekday", || Local::now().weekday().to_string());
+
+    engine
+}
+
+/// Scripts live as `.rhai` files in the `scripts` folder of the user data directory.
+pub fn scripts_dir() -> PathBuf {
+    paths::user_file("scripts")
+}
+
+/// Script names, without the extension, sorted.
+pub fn list() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(scripts_dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("rhai"))
+        .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+fn script_path(name: &str) -> Result<PathBuf> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\', ':']) || name.starts_with('.') {
+        return Err(AppError::Parse(format!("'{}' is not a valid script name", name)));
+    }
+    Ok(scripts_dir().join(format!("{}.rhai", name)))
+}
+
+pub fn load(name: &str) -> Result<String> {
+    Ok(fs::read_to_string(script_path(name)?)?)
+}
+
+pub fn save(name: &str, source: &str) -> Result<()> {
+    let path = script_path(name)?;
+    fs::create_dir_all(scripts_dir())?;
+    fs::write(path, source)?;
+    Ok(())
+}
+
+/// Checks a script for syntax errors without running it.
+pub fn check(source: &str) -> std::result::Result<(), String> {
+    Engine::new().compile(source).map(|_| ()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,132 @@
+//! The main search box. One query is matched against the devices in range,
+//! devices known from the registry or config but not in range, saved names,
+//! notes and tags; the GUI shows each kind as its own group.
+
+use crate::bluetooth::BluetoothDevice;
+use crate::config::Config;
+use crate::names;
+use crate::registry::DeviceSummary;
+use std::collections::{HashMap, HashSet};
+
+/// Offline devices listed before the rest are summarized as a count
+pub const MAX_OFFLINE_RESULTS: usize = 20;
+
+pub struct Query {
+    key: String,
+}
+
+impl Query {
+    pub fn new(text: &str) -> Self {
+        Query { key: names::search_key(text) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.key.is_empty()
+    }
+
+    fn hits(&self, text: &str) -> bool {
+        names::search_key(text).contains(&self.key)
+    }
+}
+
+/// What a device matched on, tried in this order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Address,
+    /// The name it is saved under in My Devices
+    SavedName(String),
+    Note(String),
+    Tag(String),
+}
+
+impl Field {
+    /// Shown next to a result that didn't match on its name; `None` for the name.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            Field::Name => None,
+            Field::Address => Some(String::from("address")),
+            Field::SavedName(name) => Some(format!("saved as \"{}\"", name)),
+            Field::Note(note) => Some(format!("note: {}", note.lines().next().unwrap_or(""))),
+            Field::Tag(tag) => Some(format!("#{}", tag)),
+        }
+    }
+}
+
+/// Saved names, notes and tags to search besides the device's own name.
+pub struct Sources<'a> {
+    pub config: Option<&'a Config>,
+    pub notes: &'a HashMap<u64, String>,
+}
+
+impl Sources<'_> {
+    pub fn match_device(&self, query: &Query, address: u64, name: &str) -> Option<Field> {
+        if query.hits(name) {
+            return Some(Field::Name);
+        }
+        if format!("{:X}", address).to_lowercase().contains(&query.key) {
+            return Some(Field::Address);
+        }
+        if let Some(config) = self.config {
+            let saved = config.devices.iter().find(|(saved, a)| **a == address && query.hits(saved));
+            if let Some((saved, _)) = saved {
+                return Some(Field::SavedName(saved.clone()));
+            }
+        }
+        if let Some(note) = self.notes.get(&address).filter(|n| query.hits(n)) {
+            return Some(Field::Note(note.clone()));
+        }
+        let tags = self.config.map(|c| c.tags_for(address)).unwrap_or(&[]);
+        tags.iter().find(|t| query.hits(t)).map(|t| Field::Tag(t.clone()))
+    }
+
+    /// Matching devices that aren't in `live`: those in the registry's history,
+    /// most recently seen first, then saved devices it has no record of.
+    pub fn offline(&self, query: &Query, live: &[BluetoothDevice], known: &[DeviceSummary]) -> Vec<OfflineHit> {
+        let mut listed: HashSet<u64> = live.iter().map(|d| d.address).collect();
+        let mut hits = Vec::new();
+        for device in known {
+            if !listed.insert(device.address) {
+                continue;
+            }
+            if let Some(field) = self.match_device(query, device.address, &device.name) {
+                hits.push(OfflineHit {
+                    address: device.address,
+                    name: device.name.clone(),
+                    field,
+                    last_seen: Some(device.last_seen.clone()),
+                });
+            }
+        }
+        if let Some(config) = self.config {
+            for (name, address) in config.ordered_devices() {
+                if !listed.insert(address) {
+                    continue;
+                }
+                if let Some(field) = self.match_device(query, address, &name) {
+                    hits.push(OfflineHit { address, name, field, last_seen: None });
+                }
+            }
+        }
+        hits
+    }
+
+    /// Tags whose own name matches.
+    pub fn tags(&self, query: &Query) -> Vec<String> {
+        let Some(config) = self.config else {
+            return Vec::new();
+        };
+        config.all_tags().into_iter().filter(|t| query.hits(t)).collect()
+    }
+}
+
+/// A device that matched but isn't in range.
+#[derive(Debug, Clone)]
+pub struct OfflineHit {
+    pub address: u64,
+    /// Raw name, as in the registry or config
+    pub name: String,
+    pub field: Field,
+    /// As stored by the registry; `None` for devices only saved in config
+    pub last_seen: Option<String>,
+}
@@ -0,0 +1,90 @@
+use crate::bluetooth::{self, RadioState};
+use crate::config::Config;
+use crate::ffi;
+use crate::registry::Registry;
+
+/// One line of the self-test: what was checked, how it went, and what to do about a failure.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    /// Shown only when the check failed
+    pub hint: &'static str,
+}
+
+impl Check {
+    fn new(name: &'static str, passed: bool, detail: impl Into<String>, hint: &'static str) -> Self {
+        Check { name, passed, detail: detail.into(), hint }
+    }
+}
+
+/// Runs every check in dependency order. Cheap enough for startup: nothing
+/// here scans or connects.
+pub fn run() -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    // bt_core is linked statically, so getting this far means it loaded;
+    // the version call proves it also answers
+    let version = bluetooth::core_api_version();
+    checks.push(Check::new(
+        "Core library",
+        true,
+        "bt_core is linked in and responding",
+        "",
+    ));
+    checks.push(Check::new(
+        "API version",
+        version == ffi::BT_API_VERSION,
+        format!("core v{}, app expects v{}", version, ffi::BT_API_VERSION),
+        "The core library and the app were built from different versions. Rebuild both from the same checkout.",
+    ));
+
+    let radio = bluetooth::radio_state();
+    checks.push(Check::new(
+        "Adapter present",
+        radio != RadioState::Absent,
+        if radio == RadioState::Absent { "No Bluetooth adapter found" } else { "Found" },
+        "Plug in a Bluetooth adapter, or check that it isn't disabled in Device Manager.",
+    ));
+    checks.push(Check::new(
+        "Radio powered",
+        radio == RadioState::On,
+        match radio {
+            RadioState::On => "On",
+            RadioState::Off => "Off",
+            RadioState::Absent => "No adapter",
+        },
+        "Turn Bluetooth on in Windows Settings → Bluetooth & devices, or with the keyboard's wireless key.",
+    ));
+
+    let permitted = bluetooth::check_permission();
+    checks.push(Check::new(
+        "Permission granted",
+        permitted,
+        if permitted { "Radio access allowed" } else { "Access to the radio was denied" },
+        "Allow apps to use Bluetooth under Windows Settings → Privacy & security, or ask your administrator.",
+    ));
+
+    let database = Registry::new().and_then(|registry| registry.check_writable());
+    checks.push(Check::new(
+        "Database writable",
+        database.is_ok(),
+        database.err().map_or_else(|| String::from("registry.db accepts writes"), |e| e.to_string()),
+        "Close other RedTooth windows, and check that registry.db in %APPDATA%\\RedTooth isn't read-only.",
+    ));
+
+    let config = Config::load_strict().and_then(|config| config.validate());
+    checks.push(Check::new(
+        "Config valid",
+        config.is_ok(),
+        config.err().map_or_else(|| String::from("config.toml parses and validates"), |e| e.to_string()),
+        "Fix the setting named above in config.toml, or restore one of its backups from %APPDATA%\\RedTooth.",
+    ));
+
+    checks
+}
+
+pub fn all_passed(checks: &[Check]) -> bool {
+    checks.iter().all(|c| c.passed)
+}
@@ -0,0 +1,387 @@
+use crate::audio::EngineSettings;
+use crate::schedule::DndSchedule;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+// Config sections. Every struct is `#[serde(default)]` so a missing key falls
+// back to its own default rather than the whole section being reset.
+// Within a section, plain values must come before nested tables (TOML rule).
+
+/// How connection state and signal strength are rendered in the device list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusStyle {
+    /// Green/red colouring
+    #[default]
+    Color,
+    /// ✔/✖ shapes and outlined RSSI bars that don't rely on colour
+    Symbols,
+}
+
+/// How much each device in the list shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CardDensity {
+    /// One line per device: name, status, signal and connect button
+    Compact,
+    #[default]
+    Comfortable,
+    /// The full card plus device class, pairing and live audio statistics
+    Detailed,
+}
+
+/// What happens when a sink in the broadcast runs low on battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LowBatteryAction {
+    Nothing,
+    /// Offer to move its audio to another connected headset or speaker
+    #[default]
+    Suggest,
+    /// Move its audio without asking
+    Move,
+}
+
+impl LowBatteryAction {
+    pub const ALL: [LowBatteryAction; 3] = [LowBatteryAction::Nothing, LowBatteryAction::Suggest, LowBatteryAction::Move];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LowBatteryAction::Nothing => "Nothing",
+            LowBatteryAction::Suggest => "Suggest moving its audio",
+            LowBatteryAction::Move => "Move its audio",
+        }
+    }
+}
+
+/// What a mouse gesture on a device card does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CardAction {
+    #[default]
+    Nothing,
+    ToggleConnection,
+    OpenDetails,
+    /// Adds the device to, or removes it from, the multi-selection
+    Select,
+}
+
+impl CardAction {
+    pub const ALL: [CardAction; 4] =
+        [CardAction::Nothing, CardAction::ToggleConnection, CardAction::OpenDetails, CardAction::Select];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CardAction::Nothing => "Nothing",
+            CardAction::ToggleConnection => "Connect / disconnect",
+            CardAction::OpenDetails => "Open details",
+            CardAction::Select => "Select",
+        }
+    }
+}
+
+/// Bindings for gestures on the empty parts of a device card.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct GestureSettings {
+    pub double_click: CardAction,
+    pub middle_click: CardAction,
+    pub shift_click: CardAction,
+}
+
+impl GestureSettings {
+    /// Each gesture with its name, in the order settings and the shortcut list show them.
+    pub fn bindings_mut(&mut self) -> [(&'static str, &mut CardAction); 3] {
+        [
+            ("Double-click", &mut self.double_click),
+            ("Middle-click", &mut self.middle_click),
+            ("Shift+click", &mut self.shift_click),
+        ]
+    }
+
+    pub fn bindings(&self) -> [(&'static str, CardAction); 3] {
+        self.clone().bindings_mut().map(|(name, action)| (name, *action))
+    }
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        GestureSettings {
+            double_click: CardAction::ToggleConnection,
+            middle_click: CardAction::OpenDetails,
+            shift_click: CardAction::Select,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Opt out of low-power scanning on battery
+    pub full_power_on_battery: bool,
+    /// Restart the scan after this long without a single discovery; 0 disables the watchdog
+    pub watchdog_secs: u32,
+    pub tuning: ScanTuning,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        ScanSettings { full_power_on_battery: false, watchdog_secs: 60, tuning: ScanTuning::default() }
+    }
+}
+
+/// Advanced radio tuning for the classic inquiry, pushed to the core when it changes.
+/// Longer inquiries find slow responders; a longer gap leaves the radio to audio and input devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ScanTuning {
+    /// Inquiry length in 1.28 s units
+    pub inquiry_length: u8,
+    /// Idle time between inquiries
+    pub cycle_gap_ms: u32,
+}
+
+impl ScanTuning {
+    pub const INQUIRY_LENGTH: RangeInclusive<u8> = 1..=48;
+    pub const CYCLE_GAP_MS: RangeInclusive<u32> = 500..=60_000;
+
+    /// Within the bounds the core accepts, for values edited by hand in config.toml.
+    pub fn clamped(self) -> Self {
+        ScanTuning {
+            inquiry_length: self.inquiry_length.clamp(*Self::INQUIRY_LENGTH.start(), *Self::INQUIRY_LENGTH.end()),
+            cycle_gap_ms: self.cycle_gap_ms.clamp(*Self::CYCLE_GAP_MS.start(), *Self::CYCLE_GAP_MS.end()),
+        }
+    }
+}
+
+impl Default for ScanTuning {
+    fn default() -> Self {
+        ScanTuning { inquiry_length: 4, cycle_gap_ms: 1000 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct UiSettings {
+    pub status_style: StatusStyle,
+    /// Summons the quick connect overlay from anywhere, e.g. "Ctrl+Alt+B"; empty disables it
+    pub quick_connect_hotkey: String,
+    pub card_density: CardDensity,
+    /// Frame time, queue depth and cache counters drawn over the main window
+    pub perf_overlay: bool,
+    /// Device list sections per location, with unlocated devices last
+    pub group_by_location: bool,
+    /// Raw RFCOMM AT console in the Details window, for developers
+    pub at_console: bool,
+    /// 🛠 toolbar button for the call trace, HCI capture and manual core calls
+    pub developer_console: bool,
+    /// Drop emoji from device names in lists; Details still shows the name as advertised
+    pub strip_name_emoji: bool,
+    /// Keep right-to-left names from reordering the text around them
+    pub isolate_rtl_names: bool,
+    pub gestures: GestureSettings,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        UiSettings {
+            status_style: StatusStyle::default(),
+            quick_connect_hotkey: String::from("Ctrl+Alt+B"),
+            card_density: CardDensity::default(),
+            perf_overlay: false,
+            group_by_location: false,
+            at_console: false,
+            developer_console: false,
+            strip_name_emoji: false,
+            isolate_rtl_names: true,
+            gestures: GestureSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub pause_during_calls: bool,
+    pub keep_awake: bool,
+    /// Conference room mode: connecting a headset or speaker disconnects the one before it
+    pub exclusive_output: bool,
+    /// Cross-fade when the stream moves to another sink; 0 cuts over
+    pub crossfade_ms: u32,
+    /// Battery percentage below which a sink counts as running low
+    pub low_battery_percent: u8,
+    pub low_battery: LowBatteryAction,
+    /// 0 = no limit
+    pub recording_max_minutes: u32,
+    pub engine: EngineSettings,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            pause_during_calls: false,
+            keep_awake: false,
+            exclusive_output: false,
+            crossfade_ms: 300,
+            low_battery_percent: 10,
+            low_battery: LowBatteryAction::Suggest,
+            recording_max_minutes: 0,
+            engine: EngineSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RegistrySettings {
+    /// Sightings older than this are pruned at startup; 0 keeps history forever
+    pub history_retention_days: u32,
+    /// Write last month's usage report to `reports/` at startup if it's missing
+    pub monthly_report: bool,
+    /// Weekly check for devices whose connects or signal are getting worse
+    pub health_alerts: bool,
+    /// Also show devices published to the machine-wide store shared by all users
+    pub shared_store: bool,
+    /// How long a registry call waits for another process (second instance,
+    /// backup tool) to release registry.db before giving up
+    pub busy_timeout_ms: u32,
+}
+
+impl RegistrySettings {
+    pub fn busy_timeout(&self) -> Duration {
+        Duration::from_millis(u64::from(self.busy_timeout_ms))
+    }
+}
+
+impl Default for RegistrySettings {
+    fn default() -> Self {
+        RegistrySettings {
+            history_retention_days: 90,
+            monthly_report: false,
+            health_alerts: true,
+            shared_store: false,
+            busy_timeout_ms: 2000,
+        }
+    }
+}
+
+/// Scheduled zips of config.toml and registry.db.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Folder the zips go to, e.g. one a cloud drive syncs; empty for `backups` in the data folder
+    pub folder: String,
+    pub interval_hours: u32,
+    /// Zips kept; older ones are deleted after each backup
+    pub keep: u32,
+}
+
+impl BackupSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.interval_hours) * 3600)
+    }
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings { enabled: false, folder: String::new(), interval_hours: 24, keep: 7 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Transient banners for events like failover or incoming files
+    pub show_banners: bool,
+    /// Banner when a device never seen on this PC shows up in a scan
+    pub new_devices: bool,
+    pub dnd: DndSchedule,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings { show_banners: true, new_devices: false, dnd: DndSchedule::default() }
+    }
+}
+
+/// Presence monitor: alerts when a device that isn't allowed lingers nearby.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub enabled: bool,
+    /// Only devices at least this strong (dBm) count as in the area
+    pub min_rssi: i32,
+    /// How long a device must stay in the area before it raises an alert
+    pub dwell_secs: u32,
+    /// Hex addresses allowed besides saved devices
+    pub allowlist: Vec<String>,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        WatchSettings { enabled: false, min_rssi: -70, dwell_secs: 10, allowlist: Vec::new() }
+    }
+}
+
+/// Locks the PC when the chosen phone walks away. Only enabled through the
+/// setup dialog, which explains the limits first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PresenceLockSettings {
+    pub enabled: bool,
+    /// Hex address of the phone
+    pub device: String,
+    /// Weaker reports (dBm) count as away
+    pub min_rssi: i32,
+    /// How long the phone must be away before the PC locks
+    pub away_secs: u32,
+    /// Turn the display on when the phone comes back; never unlocks
+    pub wake_on_return: bool,
+}
+
+impl Default for PresenceLockSettings {
+    fn default() -> Self {
+        PresenceLockSettings { enabled: false, device: String::new(), min_rssi: -80, away_secs: 30, wake_on_return: false }
+    }
+}
+
+/// Safeguards around disruptive actions. Each confirmation can be turned off from its own dialog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ConfirmSettings {
+    pub disconnect_all: bool,
+    /// Disconnecting a keyboard or mouse, which can leave the PC without input
+    pub disconnect_input: bool,
+    pub unpair: bool,
+    /// Disconnect All leaves connected keyboards and mice alone unless overridden in its dialog
+    pub keep_input_devices: bool,
+}
+
+impl Default for ConfirmSettings {
+    fn default() -> Self {
+        ConfirmSettings { disconnect_all: true, disconnect_input: true, unpair: true, keep_input_devices: true }
+    }
+}
+
+/// Automation scripts (`.rhai` files in the `scripts` folder).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(default)]
+pub struct ScriptSettings {
+    /// Script names run when the GUI starts, in order
+    pub startup: Vec<String>,
+}
+
+/// Settings that moved, as (old dotted path, new dotted path). Values found at an
+/// old path are moved to the new one on load unless the new one is already set.
+pub const DEPRECATIONS: &[(&str, &str)] = &[
+    ("status_style", "ui.status_style"),
+    ("pause_audio_during_calls", "audio.pause_during_calls"),
+    ("keep_awake_while_streaming", "audio.keep_awake"),
+    ("recording_max_minutes", "audio.recording_max_minutes"),
+    ("audio_engine", "audio.engine"),
+    ("full_power_on_battery", "scan.full_power_on_battery"),
+    ("dnd", "notifications.dnd"),
+];
@@ -0,0 +1,98 @@
+use crate::config::{self, Config};
+use crate::error::{AppError, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use qrcode::{EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+
+/// Share codes start with this, so pasted text is recognisable and the format can change later
+const CODE_PREFIX: &str = "RT1.";
+
+/// A saved device and the settings that travel with it. Personal data such as
+/// notes, history or PBAP consent is never included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DevicePreset {
+    pub name: String,
+    /// `AABBCCDDEEFF`, as in config.toml
+    pub address: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub auto_connect: bool,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+impl DevicePreset {
+    /// The preset for a device saved under `name`, if there is one.
+    pub fn from_config(config: &Config, name: &str) -> Option<Self> {
+        let address = *config.devices.get(name)?;
+        Some(DevicePreset {
+            name: name.to_string(),
+            address: config::address_key(address),
+            tags: config.tags_for(address).to_vec(),
+            auto_connect: config.auto_connect.iter().any(|n| n == name),
+            location: config.location_for(address).map(str::to_string),
+        })
+    }
+
+    pub fn address(&self) -> Result<u64> {
+        config::parse_address(&self.address)
+            .ok_or_else(|| AppError::Parse(format!("'{}' is not a Bluetooth address", self.address)))
+    }
+
+    /// Short text form: the prefix followed by the preset as URL-safe base64 TOML.
+    pub fn to_code(&self) -> String {
+        let toml = toml::to_string(self).unwrap_or_default();
+        format!("{}{}", CODE_PREFIX, URL_SAFE_NO_PAD.encode(toml))
+    }
+
+    /// Accepts a code with surrounding whitespace, as it usually arrives from chat or email.
+    pub fn from_code(code: &str) -> Result<Self> {
+        let payload = code
+            .trim()
+            .strip_prefix(CODE_PREFIX)
+            .ok_or_else(|| AppError::Parse(String::from("Not a RedTooth share code")))?;
+        let bytes = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| AppError::Parse(String::from("Share code is damaged or incomplete")))?;
+        let text = String::from_utf8(bytes)
+            .map_err(|_| AppError::Parse(String::from("Share code is damaged or incomplete")))?;
+        let preset: DevicePreset =
+            toml::from_str(&text).map_err(|e| AppError::Parse(format!("Share code has unexpected content: {}", e)))?;
+        preset.address()?;
+        if preset.name.trim().is_empty() {
+            return Err(AppError::Parse(String::from("Share code has no device name")));
+        }
+        Ok(preset)
+    }
+
+    /// Saves the device, its tags, location and auto-connect choice. An existing device
+    /// with the same name is replaced. Returns the address.
+    pub fn apply(&self, config: &mut Config) -> Result<u64> {
+        let address = self.address()?;
+        config.add_device(self.name.clone(), address);
+        for tag in &self.tags {
+            config.add_tag(address, tag);
+        }
+        if self.auto_connect {
+            config.add_auto_connect(self.name.clone());
+        }
+        if let Some(location) = &self.location {
+            config.set_location(address, location);
+        }
+        Ok(address)
+    }
+
+    /// QR modules of the share code, row by row (`true` = dark).
+    pub fn qr_modules(&self) -> Result<Vec<Vec<bool>>> {
+        let code = QrCode::with_error_correction_level(self.to_code(), EcLevel::M)
+            .map_err(|e| AppError::Unknown(format!("Could not build QR code: {}", e)))?;
+        let width = code.width();
+        let colors = code.to_colors();
+        Ok(colors
+            .chunks(width)
+            .map(|row| row.iter().map(|c| *c == qrcode::Color::Dark).collect())
+            .collect())
+    }
+}
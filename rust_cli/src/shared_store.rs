@@ -0,0 +1,97 @@
+use crate::error::{AppError, Result};
+use crate::paths;
+use log::{error, info};
+use rusqlite::{params, Connection};
+use std::fs;
+
+fn store_path(dir: &std::path::Path) -> std::path::PathBuf {
+    dir.join("shared_devices.db")
+}
+
+/// Whether the machine-wide store has been created on this PC.
+pub fn exists() -> bool {
+    paths::machine_dir().is_some_and(|dir| store_path(&dir).exists())
+}
+
+fn current_user() -> String {
+    std::env::var("USERNAME").or_else(|_| std::env::var("USER")).unwrap_or_default()
+}
+
+/// A device published to the machine-wide store.
+#[derive(Debug, Clone)]
+pub struct SharedDevice {
+    pub address: u64,
+    pub name: String,
+    /// Windows user name of whoever published it
+    pub added_by: String,
+}
+
+/// Known devices shared by every user of the machine, e.g. the meeting room
+/// speaker. Only names and addresses live here; history stays per-user.
+///
+/// The database sits in the machine data directory, which IT has to make
+/// writable for users (ProgramData subfolders are creator-owned by default).
+pub struct SharedStore {
+    conn: Connection,
+}
+
+impl SharedStore {
+    pub fn open() -> Result<Self> {
+        let dir = paths::machine_dir().ok_or_else(|| AppError::config("No machine data directory on this system"))?;
+        fs::create_dir_all(&dir)?;
+        let path = store_path(&dir);
+        info!("Opening shared device store at {:?}", path);
+
+        let conn = Connection::open(&path)?;
+        if let Err(e) = conn.execute(
+            "CREATE TABLE IF NOT EXISTS known_devices (
+                address INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                added_by TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        ) {
+            error!("Failed to create shared device table: {}", e);
+            return Err(AppError::Database(e));
+        }
+        Ok(SharedStore { conn })
+    }
+
+    pub fn devices(&self) -> Result<Vec<SharedDevice>> {
+        let mut stmt = self.conn.prepare("SELECT address, name, added_by FROM known_devices ORDER BY name")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SharedDevice {
+                address: row.get::<_, i64>(0)? as u64,
+                name: row.get(1)?,
+                added_by: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Adds or renames a device for everyone on this machine.
+    pub fn publish(&self, address: u64, name: &str) -> Result<()> {
+        let user = current_user();
+        info!("Publishing {:X} as '{}' to the shared store", address, name);
+        self.conn.execute(
+            "INSERT OR REPLACE INTO known_devices (address, name, added_by, updated_at)
+             VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)",
+            params![address as i64, name, user],
+        )?;
+        Ok(())
+    }
+
+    /// Removes everything the current user published. Returns how many devices were removed.
+    pub fn remove_mine(&self) -> Result<usize> {
+        let removed = self.conn.execute("DELETE FROM known_devices WHERE added_by = ?1", params![current_user()])?;
+        info!("Removed {} devices published by this user from the shared store", removed);
+        Ok(removed)
+    }
+
+    pub fn remove(&self, address: u64) -> Result<()> {
+        info!("Removing {:X} from the shared store", address);
+        self.conn.execute("DELETE FROM known_devices WHERE address = ?1", params![address as i64])?;
+        Ok(())
+    }
+}
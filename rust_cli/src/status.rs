@@ -0,0 +1,51 @@
+/// Traffic-light level, ordered from best to worst so the overall state is the maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Working normally
+    Ok,
+    /// Usable with something missing, e.g. history not being saved
+    Degraded,
+    /// Not working
+    Down,
+}
+
+/// One subsystem's line in the status breakdown.
+#[derive(Debug, Clone)]
+pub struct Subsystem {
+    pub name: &'static str,
+    pub level: Level,
+    pub detail: String,
+}
+
+impl Subsystem {
+    pub fn new(name: &'static str, level: Level, detail: impl Into<String>) -> Self {
+        Subsystem { name, level, detail: detail.into() }
+    }
+}
+
+/// Aggregated app health shown in the status bar.
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    pub subsystems: Vec<Subsystem>,
+}
+
+impl Status {
+    pub fn push(&mut self, subsystem: Subsystem) {
+        self.subsystems.push(subsystem);
+    }
+
+    /// Worst level of any subsystem; `Ok` when there are none.
+    pub fn overall(&self) -> Level {
+        self.subsystems.iter().map(|s| s.level).max().unwrap_or(Level::Ok)
+    }
+
+    /// One line for the status bar: "All systems normal", or the subsystems needing attention.
+    pub fn summary(&self) -> String {
+        let problems: Vec<&str> = self.subsystems.iter().filter(|s| s.level != Level::Ok).map(|s| s.name).collect();
+        if problems.is_empty() {
+            String::from("All systems normal")
+        } else {
+            format!("Check {}", problems.join(", "))
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Toasts on screen at once; older ones move to the history early
+const MAX_VISIBLE: usize = 5;
+/// Entries kept in the history drawer
+const HISTORY_LEN: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// How long a toast stays up; errors linger so they aren't missed.
+    fn lifetime(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(5),
+            Severity::Warning => Duration::from_secs(10),
+            Severity::Error => Duration::from_secs(15),
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Warning => "⚠",
+            Severity::Error => "✖",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub id: u64,
+    pub severity: Severity,
+    pub message: String,
+    /// Extra context shown on request, e.g. a suggested fix or the items that failed
+    pub details: Option<String>,
+    pub at: DateTime<Local>,
+    shown: Instant,
+    /// Details are open; an expanded toast isn't dismissed while being read
+    pub expanded: bool,
+}
+
+/// Stacking, auto-dismissing notifications plus the history behind them.
+///
+/// Replaces the single modal error dialog, which blocked the window and lost
+/// every error but the last.
+#[derive(Debug, Default)]
+pub struct Toasts {
+    active: Vec<Toast>,
+    history: VecDeque<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>, details: Option<String>) {
+        let toast = Toast {
+            id: self.next_id,
+            severity,
+            message: message.into(),
+            details,
+            at: Local::now(),
+            shown: Instant::now(),
+            expanded: false,
+        };
+        self.next_id += 1;
+
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_back();
+        }
+        self.history.push_front(toast.clone());
+        self.active.push(toast);
+        if self.active.len() > MAX_VISIBLE {
+            self.active.remove(0);
+        }
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message, None);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message, None);
+    }
+
+    /// Drops toasts that have outlived their severity's lifetime.
+    pub fn expire(&mut self) {
+        self.active.retain(|t| t.expanded || t.shown.elapsed() < t.severity.lifetime());
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        self.active.retain(|t| t.id != id);
+    }
+
+    /// Toasts on screen, oldest first.
+    pub fn active_mut(&mut self) -> &mut [Toast] {
+        &mut self.active
+    }
+
+    /// Every toast this session, newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Toast> {
+        self.history.iter()
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+}
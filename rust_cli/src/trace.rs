@@ -0,0 +1,157 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of FFI calls kept for the developer console
+pub const TRACE_LEN: usize = 500;
+
+/// One call into the C++ core.
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    pub at: DateTime<Local>,
+    pub function: &'static str,
+    pub args: Vec<String>,
+    pub duration: Duration,
+    pub result: String,
+}
+
+impl TraceEntry {
+    /// True for `FfiErrorCode` results other than `Success`. Other return
+    /// types (bools, counts) aren't judged.
+    pub fn failed(&self) -> bool {
+        self.result != "Success" && self.result.starts_with(|c: char| c.is_ascii_uppercase())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref TRACE: Mutex<VecDeque<TraceEntry>> = Mutex::new(VecDeque::with_capacity(TRACE_LEN));
+}
+
+pub fn record(function: &'static str, args: Vec<String>, duration: Duration, result: String) {
+    if let Ok(mut trace) = TRACE.lock() {
+        if trace.len() == TRACE_LEN {
+            trace.pop_front();
+        }
+        trace.push_back(TraceEntry { at: Local::now(), function, args, duration, result });
+    }
+}
+
+/// Oldest first.
+pub fn entries() -> Vec<TraceEntry> {
+    TRACE.lock().map(|t| t.iter().cloned().collect()).unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut trace) = TRACE.lock() {
+        trace.clear();
+    }
+}
+
+// Argument formatting for `ffi_call!`. Method resolution picks the first impl
+// that applies: addresses (u64) in hex, then anything `Debug`, then a placeholder
+// for callbacks, which are plain fn items without `Debug`.
+
+pub struct Arg<'a, T>(pub &'a T);
+
+pub trait DescribeAddress {
+    fn describe(&self) -> String;
+}
+
+impl DescribeAddress for &&Arg<'_, u64> {
+    fn describe(&self) -> String {
+        format!("{:X}", self.0)
+    }
+}
+
+pub trait DescribeDebug {
+    fn describe(&self) -> String;
+}
+
+impl<T: Debug> DescribeDebug for &Arg<'_, T> {
+    fn describe(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+pub trait DescribeOther {
+    fn describe(&self) -> String;
+}
+
+impl<T> DescribeOther for Arg<'_, T> {
+    fn describe(&self) -> String {
+        String::from("<callback>")
+    }
+}
+
+/// Calls `ffi::$func(args…)` and records arguments, duration and return value.
+/// Each argument is evaluated once, before the call.
+macro_rules! ffi_call {
+    ($func:ident($($arg:expr),* $(,)?)) => {
+        $crate::trace::ffi_call!(@bind $func [] $($arg,)*)
+    };
+    (@bind $func:ident [$($bound:ident)*] $head:expr, $($rest:expr,)*) => {{
+        let arg = $head;
+        $crate::trace::ffi_call!(@bind $func [$($bound)* arg] $($rest,)*)
+    }};
+    (@bind $func:ident [$($bound:ident)*]) => {{
+        #[allow(unused_imports)]
+        use $crate::trace::{DescribeAddress as _, DescribeDebug as _, DescribeOther as _};
+        let start = std::time::Instant::now();
+        let result = unsafe { $crate::ffi::$func($($bound),*) };
+        $crate::trace::record(
+            stringify!($func),
+            vec![$((&&&$crate::trace::Arg(&$bound)).describe()),*],
+            start.elapsed(),
+            format!("{:?}", result),
+        );
+        result
+    }};
+}
+pub(crate) use ffi_call;
+
+/// Calls the developer console can make by hand. All take at most an address.
+pub const MANUAL_CALLS: &[&str] = &[
+    "bt_check_permission",
+    "bt_get_api_version",
+    "bt_get_radio_state",
+    "bt_stop_scan",
+    "bt_connect_device",
+    "bt_disconnect_device",
+    "bt_pan_has_nap",
+    "audio_play_test_tone",
+    "audio_endpoint_ready",
+    "audio_get_channel_count",
+    "audio_get_sink_stats",
+];
+
+/// Whether a manual call needs an address.
+pub fn takes_address(function: &str) -> bool {
+    !matches!(function, "bt_check_permission" | "bt_get_api_version" | "bt_get_radio_state" | "bt_stop_scan")
+}
+
+/// Runs one of `MANUAL_CALLS` and returns the raw result. Scanning, connecting
+/// and disconnecting go through the safe wrappers, so the policy blocklist and
+/// the link state machine apply as they do to the buttons.
+pub fn invoke(function: &str, address: u64) -> Option<String> {
+    let result = match function {
+        "bt_check_permission" => format!("{:?}", ffi_call!(bt_check_permission())),
+        "bt_get_api_version" => format!("{:?}", ffi_call!(bt_get_api_version())),
+        "bt_get_radio_state" => format!("{:?}", ffi_call!(bt_get_radio_state())),
+        "bt_stop_scan" => format!("{:?}", crate::bluetooth::stop_scan()),
+        "bt_connect_device" => format!("{:?}", crate::bluetooth::start_connect(address)),
+        "bt_disconnect_device" => format!("{:?}", crate::bluetooth::start_disconnect(address)),
+        "bt_pan_has_nap" => format!("{:?}", ffi_call!(bt_pan_has_nap(address))),
+        "audio_play_test_tone" => format!("{:?}", ffi_call!(audio_play_test_tone(address))),
+        "audio_endpoint_ready" => format!("{:?}", ffi_call!(audio_endpoint_ready(address))),
+        "audio_get_channel_count" => format!("{:?}", ffi_call!(audio_get_channel_count(address))),
+        "audio_get_sink_stats" => {
+            let mut stats = crate::ffi::AudioSinkStats::default();
+            let code = ffi_call!(audio_get_sink_stats(address, &mut stats));
+            format!("{:?} {:?}", code, stats)
+        }
+        _ => return None,
+    };
+    Some(result)
+}
@@ -0,0 +1,297 @@
+//! Terminal interface (`--tui`) for machines administered over SSH or a console
+//! session: the device list with connect, disconnect and scanning, driven by the
+//! same device store and Bluetooth calls as the GUI.
+
+use crate::bluetooth::{self, BluetoothEvent};
+use crate::config_service::ConfigService;
+use crate::device::{BluetoothDevice, ConnectionState};
+use crate::device_store::DeviceStore;
+use crate::error::{AppError, Result};
+use crate::names::{self, NameStyle};
+use crate::registry::{EventKind, Registry};
+use log::warn;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use std::io::Write;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// How long to wait for a key before checking for Bluetooth events again
+const TICK: Duration = Duration::from_millis(100);
+
+pub fn run(config: ConfigService, kiosk: bool) -> Result<()> {
+    let events = bluetooth::init()?;
+    if let Err(e) = bluetooth::set_scan_tuning(&config.read(|c| c.scan.tuning)) {
+        warn!("Scan tuning not applied: {}", e);
+    }
+    let registry = Registry::open(config.read(|c| c.registry.busy_timeout()))
+        .map_err(|e| warn!("Device history unavailable in the TUI: {}", e))
+        .ok();
+    // Events of devices without a preferred adapter are attributed to the most
+    // recently recorded one, as in the GUI
+    if let (Some(registry), Ok(adapter)) = (&registry, bluetooth::adapter_info()) {
+        if let Err(e) = registry.record_adapter(&adapter) {
+            warn!("Failed to record adapter: {}", e);
+        }
+    }
+    let mut app = TuiApp {
+        config,
+        registry,
+        kiosk,
+        devices: DeviceStore::default(),
+        table: TableState::default(),
+        scanning: false,
+        status: String::from(if kiosk { "Kiosk mode: read only" } else { "" }),
+        quit: false,
+    };
+    app.toggle_scan();
+
+    let console = console::take()?;
+    terminal::enable_raw_mode()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(console))?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+    let result = app.run(&mut terminal, &events);
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+    let _ = terminal.show_cursor();
+    drop(terminal);
+    console::restore();
+
+    if app.scanning {
+        let _ = bluetooth::stop_scan();
+    }
+    result
+}
+
+struct TuiApp {
+    config: ConfigService,
+    registry: Option<Registry>,
+    kiosk: bool,
+    devices: DeviceStore,
+    table: TableState,
+    scanning: bool,
+    /// Outcome of the last action, shown above the key help
+    status: String,
+    quit: bool,
+}
+
+impl TuiApp {
+    fn run<W: Write>(&mut self, terminal: &mut Terminal<CrosstermBackend<W>>, events: &Receiver<BluetoothEvent>) -> Result<()> {
+        while !self.quit {
+            for event in events.try_iter() {
+                self.handle(event);
+            }
+            let devices = self.devices.snapshot();
+            if self.table.selected().is_none() && !devices.is_empty() {
+                self.table.select(Some(0));
+            }
+            terminal.draw(|frame| self.draw(frame, &devices))?;
+
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.key(key.code, &devices);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, event: BluetoothEvent) {
+        match event {
+            BluetoothEvent::DeviceFound(dev) => {
+                self.devices.upsert(dev);
+            }
+            BluetoothEvent::ScanStarted => self.scanning = true,
+            BluetoothEvent::ScanStopped => self.scanning = false,
+            BluetoothEvent::Connected(addr) => {
+                self.devices.update(addr, |d| d.state = ConnectionState::Connected);
+                let name = self.label(addr);
+                if let Some(registry) = &self.registry {
+                    let recorded = registry.transaction(|r| {
+                        r.log_device(addr, &name)?;
+                        r.record_event(addr, EventKind::Connected, None)?;
+                        r.record_sighting(addr)
+                    });
+                    if let Err(e) = recorded {
+                        warn!("Failed to record connection to {:X}: {}", addr, e);
+                    }
+                }
+                self.status = format!("Connected to {}", name);
+            }
+            BluetoothEvent::Disconnected(addr) => {
+                self.devices.update(addr, |d| d.state = ConnectionState::Disconnected);
+                if let Some(registry) = &self.registry {
+                    let _ = registry.record_event(addr, EventKind::Disconnected, None);
+                }
+                self.status = format!("Disconnected from {}", self.label(addr));
+            }
+            BluetoothEvent::LinkState { address, state } => {
+                self.devices.update(address, |d| d.state = state);
+            }
+            BluetoothEvent::NameResolved(addr, name) => {
+                self.devices.update(addr, |d| d.name = name);
+            }
+            _ => {}
+        }
+    }
+
+    fn key(&mut self, code: KeyCode, devices: &[BluetoothDevice]) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            // Drawing clamps the selection to the last row
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Char('s') => self.toggle_scan(),
+            KeyCode::Enter | KeyCode::Char('c') => {
+                if let Some(device) = self.table.selected().and_then(|i| devices.get(i)) {
+                    self.toggle_connection(device);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_scan(&mut self) {
+        let result = if self.scanning { bluetooth::stop_scan() } else { bluetooth::start_scan() };
+        match result {
+            Ok(()) => self.scanning = !self.scanning,
+            Err(e) => self.status = format!("Scan failed: {}", e),
+        }
+    }
+
+    fn toggle_connection(&mut self, device: &BluetoothDevice) {
+        if self.kiosk {
+            self.status = String::from("Connecting is disabled in kiosk mode");
+            return;
+        }
+        let (address, label) = (device.address, self.label(device.address));
+        let (result, kind) = if device.state.is_connected() {
+            (bluetooth::disconnect(address), EventKind::DisconnectFailed)
+        } else if device.state.can_connect() {
+            (bluetooth::connect(address), EventKind::ConnectFailed)
+        } else {
+            return;
+        };
+        match result {
+            Ok(()) => self.status = format!("Working on {}…", label),
+            Err(e) => {
+                if !matches!(e, AppError::InvalidState { .. }) {
+                    if let Some(registry) = &self.registry {
+                        let _ = registry.record_failure(address, kind, &e.to_string());
+                    }
+                }
+                self.status = format!("{}: {}", label, e);
+            }
+        }
+    }
+
+    /// Name as reported, else the saved name, else the address; as in the GUI.
+    fn label(&self, address: u64) -> String {
+        if let Some(d) = self.devices.get(address).filter(|d| !d.name.is_empty()) {
+            return d.name.clone();
+        }
+        self.config
+            .read(|c| c.devices.iter().find(|(_, a)| **a == address).map(|(name, _)| name.clone()))
+            .unwrap_or_else(|| format!("{:X}", address))
+    }
+
+    fn draw(&mut self, frame: &mut Frame, devices: &[BluetoothDevice]) {
+        let [header, list, status, help] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+                .areas(frame.area());
+
+        let scan = if self.scanning { "scanning".green() } else { "not scanning".dark_gray() };
+        frame.render_widget(
+            Line::from(vec!["RedTooth ".bold(), format!("{} devices, ", devices.len()).into(), scan]),
+            header,
+        );
+
+        // Terminals apply their own bidi, if any, and may draw isolates as boxes
+        let style = NameStyle { strip_emoji: self.config.read(|c| c.ui.strip_name_emoji), isolate_rtl: false };
+        let rows = devices.iter().map(|d| {
+            let state = Cell::from(d.state.to_string()).style(state_style(&d.state));
+            let signal = if d.rssi == 0 { String::from("—") } else { format!("{} dBm", d.rssi) };
+            Row::new(vec![Cell::from(names::display(&self.label(d.address), style)), Cell::from(format!("{:012X}", d.address)), state, Cell::from(signal)])
+        });
+        let table = Table::new(
+            rows,
+            [Constraint::Fill(1), Constraint::Length(12), Constraint::Length(24), Constraint::Length(8)],
+        )
+        .header(Row::new(["Name", "Address", "State", "Signal"]).bold())
+        .block(Block::bordered().title(" Devices "))
+        .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(table, list, &mut self.table);
+
+        frame.render_widget(Line::from(self.status.as_str()), status);
+        let keys = if self.kiosk { "↑↓ select  s scan  q quit" } else { "↑↓ select  Enter connect/disconnect  s scan  q quit" };
+        frame.render_widget(Line::from(keys).dark_gray(), help);
+    }
+}
+
+fn state_style(state: &ConnectionState) -> Style {
+    match state {
+        ConnectionState::Connected => Style::new().fg(Color::Green),
+        ConnectionState::Failed(_) => Style::new().fg(Color::Red),
+        ConnectionState::Connecting | ConnectionState::Disconnecting => Style::new().fg(Color::Yellow),
+        ConnectionState::Disconnected => Style::new(),
+    }
+}
+
+/// The console trace (`println!`) and the log both write to the terminal the TUI
+/// draws on. On Windows they are sent to NUL while it runs and the TUI draws on
+/// the console directly.
+#[cfg(windows)]
+mod console {
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::os::windows::io::IntoRawHandle;
+    use std::sync::Mutex;
+    use winapi::um::processenv::{GetStdHandle, SetStdHandle};
+    use winapi::um::winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+    use winapi::um::winnt::HANDLE;
+
+    /// Original stdout and stderr, put back by `restore`
+    static SAVED: Mutex<Option<(usize, usize)>> = Mutex::new(None);
+
+    pub fn take() -> Result<File> {
+        let console = OpenOptions::new().read(true).write(true).open("CONOUT$")?;
+        let nul = OpenOptions::new().write(true).open("NUL")?.into_raw_handle() as HANDLE;
+        unsafe {
+            let saved = (GetStdHandle(STD_OUTPUT_HANDLE) as usize, GetStdHandle(STD_ERROR_HANDLE) as usize);
+            *SAVED.lock().unwrap() = Some(saved);
+            SetStdHandle(STD_OUTPUT_HANDLE, nul);
+            SetStdHandle(STD_ERROR_HANDLE, nul);
+        }
+        Ok(console)
+    }
+
+    pub fn restore() {
+        if let Some((stdout, stderr)) = SAVED.lock().unwrap().take() {
+            unsafe {
+                SetStdHandle(STD_OUTPUT_HANDLE, stdout as HANDLE);
+                SetStdHandle(STD_ERROR_HANDLE, stderr as HANDLE);
+            }
+        }
+    }
+}
+
+/// Elsewhere the trace is left alone and can scroll through the TUI.
+#[cfg(not(windows))]
+mod console {
+    use super::*;
+
+    pub fn take() -> Result<std::io::Stdout> {
+        Ok(std::io::stdout())
+    }
+
+    pub fn restore() {}
+}
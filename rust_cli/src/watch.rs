@@ -0,0 +1,72 @@
+use crate::bluetooth::BluetoothDevice;
+use crate::settings::WatchSettings;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// A device not reported for this long has left; its dwell time starts over
+const PRESENCE_GAP: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct WatchAlert {
+    pub address: u64,
+    pub name: String,
+    pub rssi: i32,
+    pub dwell: Duration,
+    pub at: DateTime<Local>,
+}
+
+struct Presence {
+    since: Instant,
+    last_seen: Instant,
+    alerted: bool,
+}
+
+/// Tracks how long each device has been in the watched area. Each visit
+/// raises at most one alert.
+#[derive(Default)]
+pub struct AreaWatch {
+    present: HashMap<u64, Presence>,
+}
+
+impl AreaWatch {
+    /// Feeds one scan result; `allowed` devices are never tracked.
+    pub fn observe(&mut self, dev: &BluetoothDevice, settings: &WatchSettings, allowed: bool) -> Option<WatchAlert> {
+        // Inquiry results report 0: no reading, so they neither start nor end a visit
+        if dev.rssi == 0 && settings.enabled && !allowed {
+            return None;
+        }
+        let in_area = settings.enabled && !allowed && dev.rssi >= settings.min_rssi;
+        if !in_area {
+            self.present.remove(&dev.address);
+            return None;
+        }
+
+        let now = Instant::now();
+        let presence = self
+            .present
+            .entry(dev.address)
+            .or_insert(Presence { since: now, last_seen: now, alerted: false });
+        if now.duration_since(presence.last_seen) > PRESENCE_GAP {
+            *presence = Presence { since: now, last_seen: now, alerted: false };
+        }
+        presence.last_seen = now;
+
+        let dwell = now.duration_since(presence.since);
+        if presence.alerted || dwell < Duration::from_secs(settings.dwell_secs as u64) {
+            return None;
+        }
+        presence.alerted = true;
+        Some(WatchAlert {
+            address: dev.address,
+            name: dev.name.clone(),
+            rssi: dev.rssi,
+            dwell,
+            at: Local::now(),
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.present.clear();
+    }
+}